@@ -7,6 +7,8 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::claude_messages::ClaudeMessage;
+
 use super::{
     storage::{self, CheckpointStorage},
     Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
@@ -66,19 +68,9 @@ impl CheckpointManager {
         messages.push(jsonl_message.clone());
 
         // Parse message to check for tool usage
-        if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&jsonl_message) {
-            if let Some(content) = msg.get("message").and_then(|m| m.get("content")) {
-                if let Some(content_array) = content.as_array() {
-                    for item in content_array {
-                        if item.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                            if let Some(tool_name) = item.get("name").and_then(|n| n.as_str()) {
-                                if let Some(input) = item.get("input") {
-                                    self.track_tool_operation(tool_name, input).await?;
-                                }
-                            }
-                        }
-                    }
-                }
+        if let Some(msg) = ClaudeMessage::parse(&jsonl_message) {
+            for tool_use in msg.tool_uses() {
+                self.track_tool_operation(tool_use.name, tool_use.input).await?;
             }
         }
 
@@ -312,84 +304,22 @@ impl CheckpointManager {
 
         // Iterate through messages in reverse to find the last user prompt
         for msg_str in messages.iter().rev() {
-            if let Ok(msg) = serde_json::from_str::<serde_json::Value>(msg_str) {
+            if let Some(msg) = ClaudeMessage::parse(msg_str) {
                 // Check for user message
-                if msg.get("type").and_then(|t| t.as_str()) == Some("user") {
-                    if let Some(content) = msg
-                        .get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_array())
-                    {
-                        for item in content {
-                            if item.get("type").and_then(|t| t.as_str()) == Some("text") {
-                                if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                    user_prompt = text.to_string();
-                                    break;
-                                }
-                            }
-                        }
+                if msg.is_user() {
+                    if let Some(text) = msg.body().and_then(|b| b.content.text()) {
+                        user_prompt = text;
                     }
                 }
 
-                // Extract model info
-                if let Some(model) = msg.get("model").and_then(|m| m.as_str()) {
+                // Extract model info (init or assistant messages)
+                if let Some(model) = msg.model() {
                     model_used = model.to_string();
                 }
 
-                // Also check for model in message.model (assistant messages)
-                if let Some(message) = msg.get("message") {
-                    if let Some(model) = message.get("model").and_then(|m| m.as_str()) {
-                        model_used = model.to_string();
-                    }
-                }
-
-                // Count tokens - check both top-level and nested usage
-                // First check for usage in message.usage (assistant messages)
-                if let Some(message) = msg.get("message") {
-                    if let Some(usage) = message.get("usage") {
-                        if let Some(input) = usage.get("input_tokens").and_then(|t| t.as_u64()) {
-                            total_tokens += input;
-                        }
-                        if let Some(output) = usage.get("output_tokens").and_then(|t| t.as_u64()) {
-                            total_tokens += output;
-                        }
-                        // Also count cache tokens
-                        if let Some(cache_creation) = usage
-                            .get("cache_creation_input_tokens")
-                            .and_then(|t| t.as_u64())
-                        {
-                            total_tokens += cache_creation;
-                        }
-                        if let Some(cache_read) = usage
-                            .get("cache_read_input_tokens")
-                            .and_then(|t| t.as_u64())
-                        {
-                            total_tokens += cache_read;
-                        }
-                    }
-                }
-
-                // Then check for top-level usage (result messages)
-                if let Some(usage) = msg.get("usage") {
-                    if let Some(input) = usage.get("input_tokens").and_then(|t| t.as_u64()) {
-                        total_tokens += input;
-                    }
-                    if let Some(output) = usage.get("output_tokens").and_then(|t| t.as_u64()) {
-                        total_tokens += output;
-                    }
-                    // Also count cache tokens
-                    if let Some(cache_creation) = usage
-                        .get("cache_creation_input_tokens")
-                        .and_then(|t| t.as_u64())
-                    {
-                        total_tokens += cache_creation;
-                    }
-                    if let Some(cache_read) = usage
-                        .get("cache_read_input_tokens")
-                        .and_then(|t| t.as_u64())
-                    {
-                        total_tokens += cache_read;
-                    }
+                // Count tokens from message.usage (assistant) or top-level usage (result)
+                if let Some(usage) = msg.usage() {
+                    total_tokens += usage.total();
                 }
             }
         }
@@ -691,56 +621,28 @@ impl CheckpointManager {
             CheckpointStrategy::Manual => false,
             CheckpointStrategy::PerPrompt => {
                 // Check if message is a user prompt
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    msg.get("type").and_then(|t| t.as_str()) == Some("user")
-                } else {
-                    false
-                }
+                ClaudeMessage::parse(message)
+                    .map(|msg| msg.is_user())
+                    .unwrap_or(false)
             }
             CheckpointStrategy::PerToolUse => {
                 // Check if message contains tool use
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    if let Some(content) = msg
-                        .get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_array())
-                    {
-                        content.iter().any(|item| {
-                            item.get("type").and_then(|t| t.as_str()) == Some("tool_use")
-                        })
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
+                ClaudeMessage::parse(message)
+                    .map(|msg| !msg.tool_uses().is_empty())
+                    .unwrap_or(false)
             }
             CheckpointStrategy::Smart => {
                 // Smart strategy: checkpoint after destructive operations
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    if let Some(content) = msg
-                        .get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_array())
-                    {
-                        content.iter().any(|item| {
-                            if item.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                                let tool_name =
-                                    item.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                                matches!(
-                                    tool_name.to_lowercase().as_str(),
-                                    "write" | "edit" | "multiedit" | "bash" | "rm" | "delete"
-                                )
-                            } else {
-                                false
-                            }
+                ClaudeMessage::parse(message)
+                    .map(|msg| {
+                        msg.tool_uses().iter().any(|tool_use| {
+                            matches!(
+                                tool_use.name.to_lowercase().as_str(),
+                                "write" | "edit" | "multiedit" | "bash" | "rm" | "delete"
+                            )
                         })
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
+                    })
+                    .unwrap_or(false)
             }
         }
     }
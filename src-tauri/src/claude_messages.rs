@@ -0,0 +1,395 @@
+//! Typed model of the messages Claude Code writes to its session JSONL files
+//! and emits on stdout with `--output-format stream-json`.
+//!
+//! Both formats share the same shape: one JSON object per line, tagged by a
+//! `type` field. Parsing is deliberately lenient - every field is optional,
+//! unknown message types and content blocks fall back to `Unknown` variants,
+//! and message content is accepted either as a plain string or as an array
+//! of blocks - so a new Claude Code release doesn't break usage tracking,
+//! checkpoints or agent metrics.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A single line of Claude Code output
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ClaudeMessage {
+    System(SystemMessage),
+    User(ChatMessage),
+    Assistant(ChatMessage),
+    Result(ResultMessage),
+    /// Any other line type (`summary`, future additions, ...)
+    #[serde(other)]
+    Unknown,
+}
+
+/// `system` message; the `init` subtype carries the session id
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SystemMessage {
+    pub subtype: Option<String>,
+    #[serde(alias = "sessionId")]
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+    pub cwd: Option<String>,
+    pub timestamp: Option<String>,
+    pub tools: Vec<String>,
+    pub mcp_servers: Vec<McpServerInfo>,
+}
+
+/// MCP server entry reported in the `init` message
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct McpServerInfo {
+    pub name: String,
+    pub status: Option<String>,
+}
+
+/// `user` or `assistant` message
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ChatMessage {
+    pub message: Option<MessageBody>,
+    pub timestamp: Option<String>,
+    #[serde(alias = "sessionId")]
+    pub session_id: Option<String>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    pub cwd: Option<String>,
+    /// Cost recorded by older Claude Code versions
+    #[serde(rename = "costUSD")]
+    pub cost_usd: Option<f64>,
+    pub uuid: Option<String>,
+    #[serde(rename = "parentUuid")]
+    pub parent_uuid: Option<String>,
+    /// Set on the synthetic assistant message Claude Code writes when an API request fails
+    #[serde(rename = "isApiErrorMessage")]
+    pub is_api_error_message: Option<bool>,
+}
+
+/// The Anthropic API message wrapped by a `user`/`assistant` line
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MessageBody {
+    pub id: Option<String>,
+    pub role: Option<String>,
+    pub model: Option<String>,
+    pub content: MessageContent,
+    pub usage: Option<Usage>,
+    pub stop_reason: Option<String>,
+}
+
+/// Message content: either a plain string or a list of content blocks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+    /// Anything else; kept so a malformed content field doesn't drop the whole line
+    Other(Value),
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Blocks(Vec::new())
+    }
+}
+
+/// A content block inside a message
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        #[serde(default)]
+        text: String,
+    },
+    Thinking {
+        #[serde(default)]
+        thinking: String,
+    },
+    ToolUse {
+        #[serde(default)]
+        id: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    ToolResult {
+        #[serde(default)]
+        tool_use_id: String,
+        /// String or nested blocks, left as raw JSON
+        #[serde(default)]
+        content: Value,
+        #[serde(default)]
+        is_error: Option<bool>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Token usage as reported by the API
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    pub cache_creation_input_tokens: Option<u64>,
+    pub cache_read_input_tokens: Option<u64>,
+}
+
+/// Final `result` message of a stream-json run
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ResultMessage {
+    pub subtype: Option<String>,
+    #[serde(alias = "sessionId")]
+    pub session_id: Option<String>,
+    pub is_error: Option<bool>,
+    pub duration_ms: Option<u64>,
+    pub num_turns: Option<u64>,
+    pub result: Option<String>,
+    pub cost_usd: Option<f64>,
+    pub total_cost_usd: Option<f64>,
+    pub usage: Option<Usage>,
+    pub timestamp: Option<String>,
+}
+
+/// A tool invocation found in an assistant message
+#[derive(Debug, Clone, Copy)]
+pub struct ToolUse<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+    pub input: &'a Value,
+}
+
+impl ClaudeMessage {
+    /// Parse one line, returning `None` for blank or non-JSON lines
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        serde_json::from_str(line).ok()
+    }
+
+    pub fn is_user(&self) -> bool {
+        matches!(self, ClaudeMessage::User(_))
+    }
+
+    /// Whether this is the `system`/`init` message that starts a session
+    pub fn is_init(&self) -> bool {
+        matches!(self, ClaudeMessage::System(s) if s.subtype.as_deref() == Some("init"))
+    }
+
+    /// Session id of the `init` message, if this is one
+    pub fn init_session_id(&self) -> Option<&str> {
+        match self {
+            ClaudeMessage::System(s) if s.subtype.as_deref() == Some("init") => {
+                s.session_id.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            ClaudeMessage::System(s) => s.session_id.as_deref(),
+            ClaudeMessage::User(m) | ClaudeMessage::Assistant(m) => m.session_id.as_deref(),
+            ClaudeMessage::Result(r) => r.session_id.as_deref(),
+            ClaudeMessage::Unknown => None,
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<&str> {
+        match self {
+            ClaudeMessage::System(s) => s.timestamp.as_deref(),
+            ClaudeMessage::User(m) | ClaudeMessage::Assistant(m) => m.timestamp.as_deref(),
+            ClaudeMessage::Result(r) => r.timestamp.as_deref(),
+            ClaudeMessage::Unknown => None,
+        }
+    }
+
+    pub fn cwd(&self) -> Option<&str> {
+        match self {
+            ClaudeMessage::System(s) => s.cwd.as_deref(),
+            ClaudeMessage::User(m) | ClaudeMessage::Assistant(m) => m.cwd.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The wrapped API message of a `user`/`assistant` line
+    pub fn body(&self) -> Option<&MessageBody> {
+        match self {
+            ClaudeMessage::User(m) | ClaudeMessage::Assistant(m) => m.message.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Model from either the assistant message or the `init` message
+    pub fn model(&self) -> Option<&str> {
+        match self {
+            ClaudeMessage::System(s) => s.model.as_deref(),
+            _ => self.body().and_then(|b| b.model.as_deref()),
+        }
+    }
+
+    /// Token usage carried by this line: `message.usage` for chat messages,
+    /// top-level `usage` for results
+    pub fn usage(&self) -> Option<&Usage> {
+        match self {
+            ClaudeMessage::Result(r) => r.usage.as_ref(),
+            _ => self.body().and_then(|b| b.usage.as_ref()),
+        }
+    }
+
+    /// Cost in USD reported by Claude Code itself, if any
+    pub fn reported_cost(&self) -> Option<f64> {
+        match self {
+            ClaudeMessage::User(m) | ClaudeMessage::Assistant(m) => m.cost_usd,
+            ClaudeMessage::Result(r) => r.total_cost_usd.or(r.cost_usd),
+            _ => None,
+        }
+    }
+
+    /// Text of a failed API request (`API Error: 529 ...`), either from the
+    /// synthetic assistant message or from an error result
+    pub fn api_error(&self) -> Option<String> {
+        match self {
+            ClaudeMessage::Assistant(m) if m.is_api_error_message == Some(true) => {
+                m.message.as_ref().and_then(|b| b.content.text())
+            }
+            ClaudeMessage::Result(r) if r.is_error == Some(true) => r
+                .result
+                .as_ref()
+                .filter(|text| text.starts_with("API Error"))
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    /// All tool_use blocks in this message
+    pub fn tool_uses(&self) -> Vec<ToolUse<'_>> {
+        self.body()
+            .map(|b| b.content.tool_uses())
+            .unwrap_or_default()
+    }
+}
+
+impl MessageContent {
+    pub fn blocks(&self) -> &[ContentBlock] {
+        match self {
+            MessageContent::Blocks(blocks) => blocks,
+            _ => &[],
+        }
+    }
+
+    /// Plain text of the message: the string itself, or the text blocks joined
+    pub fn text(&self) -> Option<String> {
+        match self {
+            MessageContent::Text(text) => Some(text.clone()),
+            MessageContent::Blocks(blocks) => {
+                let texts: Vec<&str> = blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if texts.is_empty() {
+                    None
+                } else {
+                    Some(texts.join("\n"))
+                }
+            }
+            MessageContent::Other(_) => None,
+        }
+    }
+
+    pub fn tool_uses(&self) -> Vec<ToolUse<'_>> {
+        self.blocks()
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { id, name, input } => Some(ToolUse { id, name, input }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Usage {
+    pub fn input(&self) -> u64 {
+        self.input_tokens.unwrap_or(0)
+    }
+
+    pub fn output(&self) -> u64 {
+        self.output_tokens.unwrap_or(0)
+    }
+
+    pub fn cache_creation(&self) -> u64 {
+        self.cache_creation_input_tokens.unwrap_or(0)
+    }
+
+    pub fn cache_read(&self) -> u64 {
+        self.cache_read_input_tokens.unwrap_or(0)
+    }
+
+    /// Input + output + both cache counters
+    pub fn total(&self) -> u64 {
+        self.input() + self.output() + self.cache_creation() + self.cache_read()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stream_json_init() {
+        let msg = ClaudeMessage::parse(
+            r#"{"type":"system","subtype":"init","session_id":"abc","model":"claude-sonnet-4","tools":["Bash"],"mcp_servers":[{"name":"fs","status":"connected"}]}"#,
+        )
+        .unwrap();
+        assert!(msg.is_init());
+        assert_eq!(msg.init_session_id(), Some("abc"));
+        assert_eq!(msg.model(), Some("claude-sonnet-4"));
+    }
+
+    #[test]
+    fn parses_string_and_block_content() {
+        let user = ClaudeMessage::parse(
+            r#"{"type":"user","sessionId":"s1","message":{"role":"user","content":"hello"}}"#,
+        )
+        .unwrap();
+        assert_eq!(user.session_id(), Some("s1"));
+        assert_eq!(user.body().unwrap().content.text().as_deref(), Some("hello"));
+
+        let assistant = ClaudeMessage::parse(
+            r#"{"type":"assistant","requestId":"r1","message":{"id":"m1","model":"claude-opus-4","content":[{"type":"text","text":"ok"},{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"a.rs"}},{"type":"server_tool_use","id":"x"}],"usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":null}}}"#,
+        )
+        .unwrap();
+        let tools = assistant.tool_uses();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "Edit");
+        assert_eq!(assistant.usage().unwrap().total(), 15);
+    }
+
+    #[test]
+    fn tolerates_unknown_types() {
+        assert!(matches!(
+            ClaudeMessage::parse(r#"{"type":"summary","summary":"x"}"#),
+            Some(ClaudeMessage::Unknown)
+        ));
+        let result = ClaudeMessage::parse(
+            r#"{"type":"result","subtype":"success","total_cost_usd":0.5,"usage":{"input_tokens":1}}"#,
+        )
+        .unwrap();
+        assert_eq!(result.reported_cost(), Some(0.5));
+        assert!(ClaudeMessage::parse("not json").is_none());
+    }
+}
@@ -2,6 +2,8 @@ use serde::Serialize;
 use tauri::command;
 use std::env;
 
+use super::http_client::{self, SendWithRetry};
+
 #[derive(Debug, Serialize)]
 pub struct AppInfo {
     pub version: String,
@@ -62,11 +64,11 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
     // GitHub API endpoint for releases
     let url = "https://api.github.com/repos/xinhai-ai/claude-suite/releases/latest";
     
-    let client = reqwest::Client::new();
+    let client = http_client::client().map_err(|e| e.to_string())?;
     let response = client
         .get(url)
         .header("User-Agent", "Claude-Suite")
-        .send()
+        .send_with_retry()
         .await
         .map_err(|e| format!("Failed to fetch release info: {}", e))?;
     
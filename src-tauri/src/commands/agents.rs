@@ -6,7 +6,7 @@ use regex;
 use reqwest;
 use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader};
 use std::process::Stdio;
 use std::sync::{Arc, Mutex};
@@ -15,6 +15,11 @@ use tauri_plugin_shell::ShellExt;
 use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
 use tokio::process::Command;
 
+use super::http_client::{self, SendWithRetry};
+use super::pricing::load_pricing_config;
+use super::usage::get_api_base_url;
+use crate::claude_messages::ClaudeMessage;
+
 /// Finds the full path to the claude binary
 /// This is necessary because Windows apps may have a limited PATH environment
 fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
@@ -107,11 +112,11 @@ impl AgentRunMetrics {
         let mut end_time: Option<chrono::DateTime<chrono::Utc>> = None;
 
         for line in jsonl_content.lines() {
-            if let Ok(json) = serde_json::from_str::<JsonValue>(line) {
+            if let Some(msg) = ClaudeMessage::parse(line) {
                 message_count += 1;
 
                 // Track timestamps
-                if let Some(timestamp_str) = json.get("timestamp").and_then(|t| t.as_str()) {
+                if let Some(timestamp_str) = msg.timestamp() {
                     if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(timestamp_str) {
                         let utc_time = timestamp.with_timezone(&chrono::Utc);
                         if start_time.is_none() || utc_time < start_time.unwrap() {
@@ -123,23 +128,13 @@ impl AgentRunMetrics {
                     }
                 }
 
-                // Extract token usage - check both top-level and nested message.usage
-                let usage = json
-                    .get("usage")
-                    .or_else(|| json.get("message").and_then(|m| m.get("usage")));
-
-                if let Some(usage) = usage {
-                    if let Some(input_tokens) = usage.get("input_tokens").and_then(|t| t.as_i64()) {
-                        total_tokens += input_tokens;
-                    }
-                    if let Some(output_tokens) = usage.get("output_tokens").and_then(|t| t.as_i64())
-                    {
-                        total_tokens += output_tokens;
-                    }
+                // Extract token usage (message.usage or the result's top-level usage)
+                if let Some(usage) = msg.usage() {
+                    total_tokens += (usage.input() + usage.output()) as i64;
                 }
 
                 // Extract cost information
-                if let Some(cost) = json.get("cost").and_then(|c| c.as_f64()) {
+                if let Some(cost) = msg.reported_cost() {
                     cost_usd += cost;
                 }
             }
@@ -167,8 +162,8 @@ impl AgentRunMetrics {
     }
 }
 
-/// Read JSONL content from a session file
-pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<String, String> {
+/// Path of a session's JSONL file
+fn session_jsonl_path(session_id: &str, project_path: &str) -> Result<std::path::PathBuf, String> {
     let claude_dir = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude")
@@ -176,8 +171,14 @@ pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<
 
     // Encode project path to match Claude Code's directory naming
     let encoded_project = project_path.replace('/', "-");
-    let project_dir = claude_dir.join(&encoded_project);
-    let session_file = project_dir.join(format!("{}.jsonl", session_id));
+    Ok(claude_dir
+        .join(&encoded_project)
+        .join(format!("{}.jsonl", session_id)))
+}
+
+/// Read JSONL content from a session file
+pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<String, String> {
+    let session_file = session_jsonl_path(session_id, project_path)?;
 
     if !session_file.exists() {
         return Err(format!(
@@ -192,6 +193,145 @@ pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<
     }
 }
 
+/// Estimate a run's cost from its token usage when Claude didn't report one
+fn estimate_run_cost(jsonl_content: &str) -> f64 {
+    let pricing = load_pricing_config();
+    let api_base_url = get_api_base_url();
+    let mut seen = HashSet::new();
+
+    jsonl_content
+        .lines()
+        .filter_map(ClaudeMessage::parse)
+        .filter_map(|msg| match msg {
+            ClaudeMessage::Assistant(entry) => Some(entry),
+            _ => None,
+        })
+        .filter_map(|entry| {
+            let message = entry.message.as_ref()?;
+            let usage = message.usage.as_ref()?;
+            // Streamed content blocks repeat the message's usage
+            if let Some(id) = &message.id {
+                if !seen.insert(id.clone()) {
+                    return None;
+                }
+            }
+            let date = entry
+                .timestamp
+                .as_deref()
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.date_naive());
+            Some(pricing.cost(
+                message.model.as_deref().unwrap_or("unknown"),
+                &api_base_url,
+                date,
+                usage,
+                None,
+            ))
+        })
+        .sum()
+}
+
+/// Compute a finished run's metrics from its session file
+fn read_run_metrics(session_id: &str, project_path: &str) -> Option<AgentRunMetrics> {
+    // Runs that failed before a session started have no metrics
+    if session_id.is_empty() {
+        return None;
+    }
+    session_jsonl_path(session_id, project_path)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|content| {
+            let mut metrics = AgentRunMetrics::from_jsonl(&content);
+            if metrics.cost_usd.is_none() {
+                let estimate = estimate_run_cost(&content);
+                metrics.cost_usd = (estimate > 0.0).then_some(estimate);
+            }
+            metrics
+        })
+}
+
+/// Store a run's metrics, marking it as recorded even without any so it isn't
+/// looked at again
+fn store_run_metrics(
+    conn: &Connection,
+    run_id: i64,
+    metrics: Option<&AgentRunMetrics>,
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE agent_runs
+         SET duration_ms = ?1, total_tokens = ?2, cost_usd = ?3, message_count = ?4,
+             metrics_recorded_at = CURRENT_TIMESTAMP
+         WHERE id = ?5",
+        params![
+            metrics.and_then(|m| m.duration_ms),
+            metrics.and_then(|m| m.total_tokens),
+            metrics.and_then(|m| m.cost_usd),
+            metrics.and_then(|m| m.message_count),
+            run_id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Compute a finished run's metrics from its session file and store them on
+/// the run, so aggregations don't have to re-read the JSONL
+pub fn record_run_metrics(conn: &Connection, run_id: i64) -> Result<(), String> {
+    let (session_id, project_path): (String, String) = conn
+        .query_row(
+            "SELECT session_id, project_path FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let metrics = read_run_metrics(&session_id, &project_path);
+    store_run_metrics(conn, run_id, metrics.as_ref())
+}
+
+/// Store metrics of finished runs that completed before metrics were recorded
+///
+/// Session files are read without holding the database lock.
+fn backfill_run_metrics(db: &AgentDb) -> Result<(), String> {
+    let runs: Vec<(i64, String, String)> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, session_id, project_path FROM agent_runs
+                 WHERE {} AND metrics_recorded_at IS NULL",
+                FINISHED_RUNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let runs = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        runs
+    };
+
+    for (run_id, session_id, project_path) in runs {
+        let metrics = read_run_metrics(&session_id, &project_path);
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Err(e) = store_run_metrics(&conn, run_id, metrics.as_ref()) {
+            warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Record the metrics of runs that finished before they were stored, once in
+/// the background after startup; later runs record theirs when they finish
+pub fn start_run_metrics_backfill(app: AppHandle) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<AgentDb>();
+        if let Err(e) = backfill_run_metrics(&db) {
+            warn!("Failed to backfill agent run metrics: {}", e);
+        }
+    });
+}
+
 /// Get agent run with real-time metrics
 pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
     match read_session_jsonl(&run.session_id, &run.project_path).await {
@@ -306,6 +446,13 @@ pub fn get_database_path() -> std::path::PathBuf {
         [],
     );
 
+    // Metrics stored when a run finishes, see `record_run_metrics`
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN duration_ms INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN total_tokens INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN cost_usd REAL", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN message_count INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN metrics_recorded_at TEXT", []);
+
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
     let _ = conn.execute(
@@ -353,6 +500,21 @@ pub fn get_database_path() -> std::path::PathBuf {
         [],
     )?;
 
+    // Create usage index tables
+    super::usage_index::init_usage_index_tables(&conn)?;
+
+    // Create budget tables
+    super::budgets::init_budget_tables(&conn)?;
+
+    // Create relay failover tables
+    super::relay_failover::init_failover_tables(&conn)?;
+
+    // Create routing proxy request log
+    super::routing_proxy::init_routing_proxy_tables(&conn)?;
+
+    // Create model catalog cache
+    super::model_catalog::init_model_catalog_tables(&conn)?;
+
     Ok(conn)
 }
 
@@ -670,23 +832,272 @@ pub async fn get_agent_run_with_real_time_metrics(
     Ok(get_agent_run_with_metrics(run).await)
 }
 
-/// List agent runs with real-time metrics from JSONL
+/// Metrics stored for finished runs, keyed by run id
+fn stored_run_metrics(conn: &Connection) -> Result<HashMap<i64, AgentRunMetrics>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, duration_ms, total_tokens, cost_usd, message_count
+             FROM agent_runs WHERE metrics_recorded_at IS NOT NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let metrics = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                AgentRunMetrics {
+                    duration_ms: row.get(1)?,
+                    total_tokens: row.get(2)?,
+                    cost_usd: row.get(3)?,
+                    message_count: row.get(4)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(metrics)
+}
+
+/// List agent runs with metrics
+///
+/// Finished runs use the metrics stored when they completed and come without
+/// output; only runs still in progress are read from their JSONL.
 #[tauri::command]
 pub async fn list_agent_runs_with_metrics(
     db: State<'_, AgentDb>,
     agent_id: Option<i64>,
 ) -> Result<Vec<AgentRunWithMetrics>, String> {
+    let mut stored = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        stored_run_metrics(&conn)?
+    };
     let runs = list_agent_runs(db, agent_id).await?;
     let mut runs_with_metrics = Vec::new();
 
     for run in runs {
-        let run_with_metrics = get_agent_run_with_metrics(run).await;
+        let run_with_metrics = match run.id.and_then(|id| stored.remove(&id)) {
+            Some(metrics) => AgentRunWithMetrics {
+                run,
+                metrics: Some(metrics),
+                output: None,
+            },
+            None => get_agent_run_with_metrics(run).await,
+        };
         runs_with_metrics.push(run_with_metrics);
     }
 
     Ok(runs_with_metrics)
 }
 
+/// Aggregated metrics of a group of finished runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentRunStats {
+    pub run_count: i64,
+    pub completed_runs: i64,
+    pub failed_runs: i64,
+    pub cancelled_runs: i64,
+    /// Completed runs as a percentage of finished runs
+    pub success_rate: f64,
+    pub total_cost: f64,
+    pub avg_cost_per_run: Option<f64>,
+    pub total_tokens: i64,
+    pub avg_tokens_per_run: Option<f64>,
+    pub avg_duration_ms: Option<f64>,
+}
+
+/// Per-agent totals for the leaderboard
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentLeaderboardEntry {
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub agent_icon: String,
+    #[serde(flatten)]
+    pub stats: AgentRunStats,
+    pub last_run_at: String,
+}
+
+/// Runs and cost of one day
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentCostTrendPoint {
+    pub date: String,
+    pub run_count: i64,
+    pub total_cost: f64,
+    pub total_tokens: i64,
+}
+
+/// Per-model totals of agent runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentModelComparison {
+    pub model: String,
+    #[serde(flatten)]
+    pub stats: AgentRunStats,
+}
+
+const FINISHED_RUNS: &str = "status IN ('completed', 'failed', 'cancelled')";
+
+// Columns read by `AgentRunStats::from_row`
+const RUN_STATS_COLUMNS: &str = "COUNT(*), SUM(status = 'completed'), SUM(status = 'failed'),
+    SUM(status = 'cancelled'), COALESCE(SUM(cost_usd), 0) AS total_cost, AVG(cost_usd),
+    COALESCE(SUM(total_tokens), 0), AVG(total_tokens), AVG(duration_ms)";
+
+impl AgentRunStats {
+    fn from_row(row: &rusqlite::Row, offset: usize) -> rusqlite::Result<Self> {
+        let run_count: i64 = row.get(offset)?;
+        let completed_runs: i64 = row.get(offset + 1)?;
+        Ok(Self {
+            run_count,
+            completed_runs,
+            failed_runs: row.get(offset + 2)?,
+            cancelled_runs: row.get(offset + 3)?,
+            success_rate: if run_count > 0 {
+                completed_runs as f64 / run_count as f64 * 100.0
+            } else {
+                0.0
+            },
+            total_cost: row.get(offset + 4)?,
+            avg_cost_per_run: row.get(offset + 5)?,
+            total_tokens: row.get(offset + 6)?,
+            avg_tokens_per_run: row.get(offset + 7)?,
+            avg_duration_ms: row.get(offset + 8)?,
+        })
+    }
+}
+
+/// Get per-agent cost, token, duration and success totals of finished runs,
+/// most expensive first; `since` (YYYY-MM-DD) limits them to recent runs
+#[tauri::command]
+pub async fn get_agent_leaderboard(
+    db: State<'_, AgentDb>,
+    since: Option<String>,
+) -> Result<Vec<AgentLeaderboardEntry>, String> {
+    if let Some(since) = &since {
+        chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {}", since))?;
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_agent_leaderboard(&conn, since.as_deref())
+}
+
+fn query_agent_leaderboard(
+    conn: &Connection,
+    since: Option<&str>,
+) -> Result<Vec<AgentLeaderboardEntry>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT agent_id, MAX(agent_name), MAX(agent_icon), MAX(created_at), {}
+             FROM agent_runs
+             WHERE {} AND (?1 IS NULL OR date(created_at, 'localtime') >= ?1)
+             GROUP BY agent_id
+             ORDER BY total_cost DESC, agent_id",
+            RUN_STATS_COLUMNS, FINISHED_RUNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(params![since], |row| {
+            Ok(AgentLeaderboardEntry {
+                agent_id: row.get(0)?,
+                agent_name: row.get(1)?,
+                agent_icon: row.get(2)?,
+                last_run_at: row.get(3)?,
+                stats: AgentRunStats::from_row(row, 4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(entries)
+}
+
+/// Get daily runs and cost over the last `days` days (default 30), for one
+/// agent or all of them
+#[tauri::command]
+pub async fn get_agent_cost_trends(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    days: Option<u32>,
+) -> Result<Vec<AgentCostTrendPoint>, String> {
+    let days = days.unwrap_or(30).max(1);
+    let since = (chrono::Local::now().date_naive() - chrono::Duration::days(days as i64 - 1))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_agent_cost_trends(&conn, &since, agent_id)
+}
+
+fn query_agent_cost_trends(
+    conn: &Connection,
+    since: &str,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentCostTrendPoint>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT date(created_at, 'localtime') AS day, COUNT(*),
+                    COALESCE(SUM(cost_usd), 0), COALESCE(SUM(total_tokens), 0)
+             FROM agent_runs
+             WHERE {} AND day >= ?1 AND (?2 IS NULL OR agent_id = ?2)
+             GROUP BY day
+             ORDER BY day",
+            FINISHED_RUNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let points = stmt
+        .query_map(params![since, agent_id], |row| {
+            Ok(AgentCostTrendPoint {
+                date: row.get(0)?,
+                run_count: row.get(1)?,
+                total_cost: row.get(2)?,
+                total_tokens: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(points)
+}
+
+/// Compare finished runs per model, for one agent or all of them
+#[tauri::command]
+pub async fn get_agent_model_comparison(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentModelComparison>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_agent_model_comparison(&conn, agent_id)
+}
+
+fn query_agent_model_comparison(
+    conn: &Connection,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentModelComparison>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT model, {}
+             FROM agent_runs
+             WHERE {} AND (?1 IS NULL OR agent_id = ?1)
+             GROUP BY model
+             ORDER BY model",
+            RUN_STATS_COLUMNS, FINISHED_RUNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let models = stmt
+        .query_map(params![agent_id], |row| {
+            Ok(AgentModelComparison {
+                model: row.get(0)?,
+                stats: AgentRunStats::from_row(row, 1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(models)
+}
+
 /// Execute a CC agent with streaming output
 #[tauri::command]
 pub async fn execute_agent(
@@ -920,11 +1331,12 @@ async fn spawn_agent_sidecar(
                         let _ = registry_clone.append_live_output(run_id, &line);
 
                         // Extract session ID from JSONL output
-                        if let Ok(json) = serde_json::from_str::<JsonValue>(&line) {
-                            // Claude Code uses "session_id" (underscore), not "sessionId"
-                            if json.get("type").and_then(|t| t.as_str()) == Some("system") &&
-                               json.get("subtype").and_then(|s| s.as_str()) == Some("init") {
-                                if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
+                        if let Some(msg) = ClaudeMessage::parse(&line) {
+                            if let Some(api_error) = msg.api_error() {
+                                super::relay_failover::report_stream_error(&app_handle, &api_error);
+                            }
+                            if msg.is_init() {
+                                if let Some(sid) = msg.init_session_id() {
                                     if let Ok(mut current_session_id) = session_id_holder_clone.lock() {
                                         if current_session_id.is_none() {
                                             *current_session_id = Some(sid.to_string());
@@ -1012,6 +1424,9 @@ async fn spawn_agent_sidecar(
                         "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
                         params![run_id],
                     );
+                    if let Err(e) = record_run_metrics(&conn, run_id) {
+                        warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+                    }
                 }
 
                 let _ = app.emit("agent-complete", false);
@@ -1051,6 +1466,9 @@ async fn spawn_agent_sidecar(
                     error!("❌ Failed to update agent run {} with session ID: {}", run_id, e);
                 }
             }
+            if let Err(e) = record_run_metrics(&conn, run_id) {
+                warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+            }
         } else {
             error!("❌ Failed to open database to update session ID for run {}", run_id);
         }
@@ -1168,11 +1586,12 @@ async fn spawn_agent_system(
             let _ = registry_clone.append_live_output(run_id, &line);
 
             // Extract session ID from JSONL output
-            if let Ok(json) = serde_json::from_str::<JsonValue>(&line) {
-                // Claude Code uses "session_id" (underscore), not "sessionId"
-                if json.get("type").and_then(|t| t.as_str()) == Some("system") &&
-                   json.get("subtype").and_then(|s| s.as_str()) == Some("init") {
-                    if let Some(sid) = json.get("session_id").and_then(|s| s.as_str()) {
+            if let Some(msg) = ClaudeMessage::parse(&line) {
+                if let Some(api_error) = msg.api_error() {
+                    super::relay_failover::report_stream_error(&app_handle, &api_error);
+                }
+                if msg.is_init() {
+                    if let Some(sid) = msg.init_session_id() {
                         if let Ok(mut current_session_id) = session_id_clone.lock() {
                             if current_session_id.is_empty() {
                                 *current_session_id = sid.to_string();
@@ -1340,6 +1759,9 @@ async fn spawn_agent_system(
                         "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
                         params![run_id],
                     );
+                    if let Err(e) = record_run_metrics(&conn, run_id) {
+                        warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+                    }
                 }
 
                 let _ = app.emit("agent-complete", false);
@@ -1386,6 +1808,9 @@ async fn spawn_agent_system(
                     error!("❌ Failed to update agent run {} with session ID: {}", run_id, e);
                 }
             }
+            if let Err(e) = record_run_metrics(&conn, run_id) {
+                warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+            }
         } else {
             error!("❌ Failed to open database to update session ID for run {}", run_id);
         }
@@ -1514,6 +1939,11 @@ pub async fn kill_agent_session(
         "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
         params![run_id],
     ).map_err(|e| e.to_string())?;
+    if updated > 0 {
+        if let Err(e) = record_run_metrics(&conn, run_id) {
+            warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+        }
+    }
 
     // Emit cancellation event with run_id for proper isolation
     let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
@@ -1599,6 +2029,9 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
             ).map_err(|e| e.to_string())?;
 
             if updated > 0 {
+                if let Err(e) = record_run_metrics(&conn, run_id) {
+                    warn!("Failed to record metrics for agent run {}: {}", run_id, e);
+                }
                 cleaned_up.push(run_id);
                 info!(
                     "Marked agent run {} as completed (PID {} no longer running)",
@@ -2225,14 +2658,14 @@ struct GitHubApiResponse {
 pub async fn fetch_github_agents() -> Result<Vec<GitHubAgentFile>, String> {
     info!("Fetching agents from GitHub repository...");
 
-    let client = reqwest::Client::new();
+    let client = http_client::client().map_err(|e| e.to_string())?;
     let url = "https://api.github.com/repos/getAsterisk/claudia/contents/cc_agents";
 
     let response = client
         .get(url)
         .header("Accept", "application/vnd.github+json")
         .header("User-Agent", "Claude-Suite-App")
-        .send()
+        .send_with_retry()
         .await
         .map_err(|e| format!("Failed to fetch from GitHub: {}", e))?;
 
@@ -2271,12 +2704,12 @@ pub async fn fetch_github_agents() -> Result<Vec<GitHubAgentFile>, String> {
 pub async fn fetch_github_agent_content(download_url: String) -> Result<AgentExport, String> {
     info!("Fetching agent content from: {}", download_url);
 
-    let client = reqwest::Client::new();
+    let client = http_client::client().map_err(|e| e.to_string())?;
     let response = client
         .get(&download_url)
         .header("Accept", "application/json")
         .header("User-Agent", "Claude-Suite-App")
-        .send()
+        .send_with_retry()
         .await
         .map_err(|e| format!("Failed to download agent: {}", e))?;
 
@@ -2390,3 +2823,169 @@ pub async fn load_agent_session_history(
         Err(format!("Session file not found: {}", session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE agent_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                agent_id INTEGER NOT NULL,
+                agent_name TEXT NOT NULL,
+                agent_icon TEXT NOT NULL,
+                task TEXT NOT NULL DEFAULT '',
+                model TEXT NOT NULL,
+                project_path TEXT NOT NULL DEFAULT '',
+                session_id TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                duration_ms INTEGER,
+                total_tokens INTEGER,
+                cost_usd REAL,
+                message_count INTEGER,
+                metrics_recorded_at TEXT
+            )",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add_run(
+        conn: &Connection,
+        agent_id: i64,
+        model: &str,
+        status: &str,
+        created_at: &str,
+        cost_usd: Option<f64>,
+        total_tokens: Option<i64>,
+    ) {
+        conn.execute(
+            "INSERT INTO agent_runs
+                (agent_id, agent_name, agent_icon, model, status, created_at, cost_usd, total_tokens, duration_ms)
+             VALUES (?1, ?2, 'bot', ?3, ?4, ?5, ?6, ?7, 1000)",
+            params![
+                agent_id,
+                format!("agent-{}", agent_id),
+                model,
+                status,
+                created_at,
+                cost_usd,
+                total_tokens
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn leaderboard_ranks_agents_by_total_cost() {
+        let conn = runs_db();
+        add_run(&conn, 1, "sonnet", "completed", "2026-03-01 12:00:00", Some(0.5), Some(100));
+        add_run(&conn, 1, "sonnet", "failed", "2026-03-02 12:00:00", Some(0.25), Some(50));
+        add_run(&conn, 2, "opus", "completed", "2026-03-02 12:00:00", Some(2.0), Some(400));
+        add_run(&conn, 2, "opus", "cancelled", "2026-03-03 12:00:00", None, None);
+        // Still running, so not counted yet
+        add_run(&conn, 3, "opus", "running", "2026-03-03 12:00:00", Some(9.0), Some(900));
+
+        let entries = query_agent_leaderboard(&conn, None).unwrap();
+        let ids: Vec<i64> = entries.iter().map(|e| e.agent_id).collect();
+        assert_eq!(ids, vec![2, 1]);
+
+        let top = &entries[0];
+        assert_eq!(top.agent_name, "agent-2");
+        assert_eq!(top.last_run_at, "2026-03-03 12:00:00");
+        assert_eq!(top.stats.run_count, 2);
+        assert_eq!(top.stats.completed_runs, 1);
+        assert_eq!(top.stats.cancelled_runs, 1);
+        assert_eq!(top.stats.success_rate, 50.0);
+        assert_eq!(top.stats.total_cost, 2.0);
+        // Runs without metrics don't pull the averages down
+        assert_eq!(top.stats.avg_cost_per_run, Some(2.0));
+        assert_eq!(top.stats.avg_tokens_per_run, Some(400.0));
+
+        let second = &entries[1];
+        assert_eq!(second.stats.failed_runs, 1);
+        assert_eq!(second.stats.total_cost, 0.75);
+        assert_eq!(second.stats.total_tokens, 150);
+        assert_eq!(second.stats.avg_duration_ms, Some(1000.0));
+
+        // Agent 2 only ran the uncosted run since then
+        let recent = query_agent_leaderboard(&conn, Some("2026-03-03")).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].agent_id, 2);
+        assert_eq!(recent[0].stats.total_cost, 0.0);
+        assert_eq!(recent[0].stats.avg_cost_per_run, None);
+    }
+
+    #[test]
+    fn cost_trends_bucket_finished_runs_by_day() {
+        let conn = runs_db();
+        add_run(&conn, 1, "sonnet", "completed", "2026-03-01 12:00:00", Some(1.0), Some(10));
+        add_run(&conn, 1, "sonnet", "completed", "2026-03-02 12:00:00", Some(0.5), Some(20));
+        add_run(&conn, 2, "opus", "failed", "2026-03-02 12:00:00", Some(1.5), Some(30));
+        add_run(&conn, 2, "opus", "running", "2026-03-02 12:00:00", Some(4.0), Some(40));
+
+        let points = query_agent_cost_trends(&conn, "2026-03-02", None).unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].date, "2026-03-02");
+        assert_eq!(points[0].run_count, 2);
+        assert_eq!(points[0].total_cost, 2.0);
+        assert_eq!(points[0].total_tokens, 50);
+
+        let points = query_agent_cost_trends(&conn, "2026-01-01", Some(1)).unwrap();
+        let days: Vec<(&str, f64)> = points
+            .iter()
+            .map(|p| (p.date.as_str(), p.total_cost))
+            .collect();
+        assert_eq!(days, vec![("2026-03-01", 1.0), ("2026-03-02", 0.5)]);
+    }
+
+    #[test]
+    fn model_comparison_groups_runs_per_model() {
+        let conn = runs_db();
+        add_run(&conn, 1, "sonnet", "completed", "2026-03-01 12:00:00", Some(0.2), Some(100));
+        add_run(&conn, 1, "sonnet", "completed", "2026-03-01 13:00:00", Some(0.4), Some(300));
+        add_run(&conn, 1, "opus", "failed", "2026-03-01 14:00:00", Some(3.0), Some(500));
+        add_run(&conn, 2, "haiku", "completed", "2026-03-01 15:00:00", Some(0.1), Some(50));
+
+        let models = query_agent_model_comparison(&conn, Some(1)).unwrap();
+        let names: Vec<&str> = models.iter().map(|m| m.model.as_str()).collect();
+        assert_eq!(names, vec!["opus", "sonnet"]);
+
+        let sonnet = &models[1].stats;
+        assert_eq!(sonnet.run_count, 2);
+        assert_eq!(sonnet.success_rate, 100.0);
+        assert!((sonnet.total_cost - 0.6).abs() < 1e-9);
+        assert!((sonnet.avg_cost_per_run.unwrap() - 0.3).abs() < 1e-9);
+        assert_eq!(sonnet.avg_tokens_per_run, Some(200.0));
+        assert_eq!(models[0].stats.success_rate, 0.0);
+
+        assert_eq!(query_agent_model_comparison(&conn, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn backfill_marks_finished_runs_without_a_session() {
+        let conn = runs_db();
+        add_run(&conn, 1, "sonnet", "failed", "2026-03-01 12:00:00", None, None);
+        add_run(&conn, 1, "sonnet", "running", "2026-03-01 13:00:00", None, None);
+        let db = AgentDb(Mutex::new(conn));
+
+        backfill_run_metrics(&db).unwrap();
+
+        let conn = db.0.lock().unwrap();
+        let recorded: Vec<(String, bool)> = conn
+            .prepare("SELECT status, metrics_recorded_at IS NOT NULL FROM agent_runs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            recorded,
+            vec![("failed".to_string(), true), ("running".to_string(), false)]
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use log::{error, info};
+use rusqlite::{params, Connection, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use super::agents::AgentDb;
+use super::usage_index::lock_synced_index;
+use crate::i18n;
+
+// How often new usage entries are checked against the budgets
+const BUDGET_CHECK_INTERVAL_SECS: u64 = 60;
+
+fn default_thresholds() -> Vec<u32> {
+    vec![50, 80, 100]
+}
+
+/// A spend budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: Option<i64>,
+    pub name: String,
+    /// "daily", "weekly" or "monthly"
+    pub period: String,
+    /// "global", "project" or "api_base_url"
+    pub scope: String,
+    /// Project path or API base URL, depending on `scope`
+    pub scope_value: Option<String>,
+    /// Limit in the pricing currency
+    pub amount: f64,
+    /// Percentages of `amount` that trigger a notification
+    #[serde(default = "default_thresholds")]
+    pub thresholds: Vec<u32>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Current spend against a budget
+#[derive(Debug, Serialize)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub period_start: String,
+    pub period_end: String,
+    pub spent: f64,
+    pub remaining: f64,
+    pub percent_used: f64,
+    /// Spend at the end of the period if the current pace continues
+    pub projected_spend: f64,
+    /// Thresholds already crossed in this period
+    pub reached_thresholds: Vec<u32>,
+}
+
+/// Payload of the `budget-threshold-reached` event
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetAlert {
+    pub budget_id: i64,
+    pub budget_name: String,
+    pub threshold: u32,
+    pub spent: f64,
+    pub amount: f64,
+    pub period_start: String,
+}
+
+/// Create the budget tables
+pub fn init_budget_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_budgets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            period TEXT NOT NULL DEFAULT 'monthly',
+            scope TEXT NOT NULL DEFAULT 'global',
+            scope_value TEXT,
+            amount REAL NOT NULL,
+            thresholds TEXT NOT NULL DEFAULT '[50,80,100]',
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // One row per threshold already notified in a budget period
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_budget_alerts (
+            budget_id INTEGER NOT NULL,
+            period_start TEXT NOT NULL,
+            threshold INTEGER NOT NULL,
+            spent REAL NOT NULL,
+            notified_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (budget_id, period_start, threshold),
+            FOREIGN KEY (budget_id) REFERENCES usage_budgets(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS update_usage_budget_timestamp
+         AFTER UPDATE ON usage_budgets
+         FOR EACH ROW
+         BEGIN
+             UPDATE usage_budgets SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+         END",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn budget_from_row(row: &Row) -> SqliteResult<Budget> {
+    let thresholds: String = row.get(6)?;
+    Ok(Budget {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        period: row.get(2)?,
+        scope: row.get(3)?,
+        scope_value: row.get(4)?,
+        amount: row.get(5)?,
+        thresholds: serde_json::from_str(&thresholds).unwrap_or_else(|_| default_thresholds()),
+        enabled: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn load_budgets(conn: &Connection, enabled_only: bool) -> Result<Vec<Budget>, String> {
+    let sql = format!(
+        "SELECT id, name, period, scope, scope_value, amount, thresholds, enabled, created_at, updated_at
+         FROM usage_budgets {} ORDER BY id",
+        if enabled_only { "WHERE enabled = 1" } else { "" }
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let budgets = stmt
+        .query_map([], budget_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(budgets)
+}
+
+/// Check a budget and bring its thresholds into ascending order
+fn validate_budget(budget: &mut Budget) -> Result<(), String> {
+    if !matches!(budget.period.as_str(), "daily" | "weekly" | "monthly") {
+        return Err(format!("Invalid budget period: {}", budget.period));
+    }
+    match budget.scope.as_str() {
+        "global" => {}
+        "project" | "api_base_url" => {
+            if budget.scope_value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+                return Err(format!("Budget scope '{}' requires a value", budget.scope));
+            }
+        }
+        other => return Err(format!("Invalid budget scope: {}", other)),
+    }
+    if budget.amount <= 0.0 {
+        return Err("Budget amount must be positive".to_string());
+    }
+    if budget.thresholds.is_empty() {
+        return Err("Budget needs at least one threshold".to_string());
+    }
+    if budget.thresholds.contains(&0) {
+        return Err("Budget thresholds must be positive percentages".to_string());
+    }
+    budget.thresholds.sort_unstable();
+    budget.thresholds.dedup();
+    Ok(())
+}
+
+/// Whether an edit changes what the budget's alerts were measured against,
+/// so thresholds crossed in the current period may fire again
+fn resets_alerts(old: &Budget, new: &Budget) -> bool {
+    old.amount != new.amount
+        || old.thresholds != new.thresholds
+        || old.period != new.period
+        || old.scope != new.scope
+        || old.scope_value != new.scope_value
+}
+
+fn get_budget(conn: &Connection, id: i64) -> Result<Budget, String> {
+    conn.query_row(
+        "SELECT id, name, period, scope, scope_value, amount, thresholds, enabled, created_at, updated_at
+         FROM usage_budgets WHERE id = ?1",
+        params![id],
+        budget_from_row,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Budget {} not found", id),
+        e => e.to_string(),
+    })
+}
+
+/// Local start and end (exclusive) of the budget period containing `today`
+fn period_bounds(period: &str, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match period {
+        "daily" => (today, today + Duration::days(1)),
+        "weekly" => {
+            let start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+            (start, start + Duration::days(7))
+        }
+        _ => {
+            let start = today.with_day(1).unwrap_or(today);
+            let end = if start.month() == 12 {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+            } else {
+                NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+            }
+            .unwrap_or(start + Duration::days(31));
+            (start, end)
+        }
+    }
+}
+
+fn local_midnight_millis(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+        .map(|dt| dt.timestamp_millis())
+        .unwrap_or(0)
+}
+
+fn budget_status(conn: &Connection, budget: Budget) -> Result<BudgetStatus, String> {
+    let now = Local::now();
+    let (start, end) = period_bounds(&budget.period, now.date_naive());
+    let start_ms = local_midnight_millis(start);
+    let end_ms = local_midnight_millis(end);
+
+    let (scope_condition, scope_value) = match budget.scope.as_str() {
+        "project" => ("AND project_path = ?3", budget.scope_value.clone()),
+        "api_base_url" => (
+            "AND RTRIM(LOWER(api_base_url), '/') = RTRIM(LOWER(?3), '/')",
+            budget.scope_value.clone(),
+        ),
+        _ => ("AND ?3 IS NULL", None),
+    };
+
+    let spent: f64 = conn
+        .query_row(
+            &format!(
+                "SELECT COALESCE(SUM(cost), 0) FROM usage_entries
+                 WHERE timestamp_ms >= ?1 AND timestamp_ms < ?2 {}",
+                scope_condition
+            ),
+            params![start_ms, end_ms, scope_value],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let percent_used = spent / budget.amount * 100.0;
+    let elapsed = (now.timestamp_millis() - start_ms).max(1) as f64;
+    let projected_spend = spent * (end_ms - start_ms) as f64 / elapsed;
+
+    let mut reached_thresholds: Vec<u32> = budget
+        .thresholds
+        .iter()
+        .copied()
+        .filter(|threshold| percent_used >= *threshold as f64)
+        .collect();
+    reached_thresholds.sort_unstable();
+
+    Ok(BudgetStatus {
+        period_start: start.format("%Y-%m-%d").to_string(),
+        period_end: (end - Duration::days(1)).format("%Y-%m-%d").to_string(),
+        spent,
+        remaining: (budget.amount - spent).max(0.0),
+        percent_used,
+        projected_spend,
+        reached_thresholds,
+        budget,
+    })
+}
+
+/// Record newly crossed thresholds and return the alerts that still have to be sent
+fn collect_new_alerts(conn: &Connection) -> Result<Vec<BudgetAlert>, String> {
+    let mut alerts = Vec::new();
+
+    for budget in load_budgets(conn, true)? {
+        let status = budget_status(conn, budget)?;
+        let budget_id = status.budget.id.unwrap_or_default();
+
+        // Only the highest newly crossed threshold is worth a notification
+        let mut highest_new = None;
+        for threshold in &status.reached_thresholds {
+            let inserted = conn
+                .execute(
+                    "INSERT OR IGNORE INTO usage_budget_alerts (budget_id, period_start, threshold, spent)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![budget_id, status.period_start, threshold, status.spent],
+                )
+                .map_err(|e| e.to_string())?;
+            if inserted > 0 {
+                highest_new = Some(*threshold);
+            }
+        }
+
+        if let Some(threshold) = highest_new {
+            alerts.push(BudgetAlert {
+                budget_id,
+                budget_name: status.budget.name.clone(),
+                threshold,
+                spent: status.spent,
+                amount: status.budget.amount,
+                period_start: status.period_start.clone(),
+            });
+        }
+    }
+
+    Ok(alerts)
+}
+
+fn notify_budget_alert(app: &AppHandle, alert: &BudgetAlert) {
+    let title = i18n::t_with_args("budget.threshold_title", &[("name", alert.budget_name.as_str())]);
+    let body = i18n::t_with_args(
+        "budget.threshold_body",
+        &[
+            ("threshold", alert.threshold.to_string().as_str()),
+            ("spent", format!("{:.2}", alert.spent).as_str()),
+            ("amount", format!("{:.2}", alert.amount).as_str()),
+        ],
+    );
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        error!("Failed to show budget notification: {}", e);
+    }
+    let _ = app.emit("budget-threshold-reached", alert);
+}
+
+/// Sync new usage entries and notify about budget thresholds crossed since the last check
+pub fn check_budgets(app: &AppHandle) -> Result<(), String> {
+    let alerts = {
+        let db = app.state::<AgentDb>();
+        let conn = lock_synced_index(&db)?;
+        collect_new_alerts(&conn)?
+    };
+
+    for alert in &alerts {
+        info!(
+            "Budget '{}' reached {}% ({:.2} of {:.2})",
+            alert.budget_name, alert.threshold, alert.spent, alert.amount
+        );
+        notify_budget_alert(app, alert);
+    }
+
+    Ok(())
+}
+
+/// Periodically check the budgets in the background
+pub fn start_budget_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(BUDGET_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let app = app.clone();
+            let result = tauri::async_runtime::spawn_blocking(move || check_budgets(&app)).await;
+            if let Ok(Err(e)) = result {
+                error!("Budget check failed: {}", e);
+            }
+        }
+    });
+}
+
+/// List all budgets
+#[command]
+pub fn list_budgets(db: State<'_, AgentDb>) -> Result<Vec<Budget>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_budgets(&conn, false)
+}
+
+/// Create a budget
+#[command]
+pub fn create_budget(db: State<'_, AgentDb>, mut budget: Budget) -> Result<Budget, String> {
+    validate_budget(&mut budget)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO usage_budgets (name, period, scope, scope_value, amount, thresholds, enabled)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            budget.name,
+            budget.period,
+            budget.scope,
+            budget.scope_value,
+            budget.amount,
+            serde_json::to_string(&budget.thresholds).map_err(|e| e.to_string())?,
+            budget.enabled,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    get_budget(&conn, conn.last_insert_rowid())
+}
+
+/// Update a budget
+#[command]
+pub fn update_budget(db: State<'_, AgentDb>, id: i64, mut budget: Budget) -> Result<Budget, String> {
+    validate_budget(&mut budget)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let old = get_budget(&conn, id)?;
+
+    conn.execute(
+            "UPDATE usage_budgets
+             SET name = ?1, period = ?2, scope = ?3, scope_value = ?4, amount = ?5, thresholds = ?6, enabled = ?7
+             WHERE id = ?8",
+            params![
+                budget.name,
+                budget.period,
+                budget.scope,
+                budget.scope_value,
+                budget.amount,
+                serde_json::to_string(&budget.thresholds).map_err(|e| e.to_string())?,
+                budget.enabled,
+                id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+    // Renaming or toggling a budget must not repeat alerts already sent
+    if resets_alerts(&old, &budget) {
+        conn.execute("DELETE FROM usage_budget_alerts WHERE budget_id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+    }
+
+    get_budget(&conn, id)
+}
+
+/// Delete a budget
+#[command]
+pub fn delete_budget(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM usage_budget_alerts WHERE budget_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM usage_budgets WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get the current spend against every budget
+#[command]
+pub fn get_budget_status(db: State<'_, AgentDb>) -> Result<Vec<BudgetStatus>, String> {
+    let conn = lock_synced_index(&db)?;
+    load_budgets(&conn, false)?
+        .into_iter()
+        .map(|budget| budget_status(&conn, budget))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::init_usage_index_tables;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        init_budget_tables(&conn).unwrap();
+        conn
+    }
+
+    fn budget(thresholds: Vec<u32>) -> Budget {
+        Budget {
+            id: None,
+            name: "monthly".to_string(),
+            period: "monthly".to_string(),
+            scope: "global".to_string(),
+            scope_value: None,
+            amount: 10.0,
+            thresholds,
+            enabled: true,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn insert_budget(conn: &Connection, budget: &Budget) -> i64 {
+        conn.execute(
+            "INSERT INTO usage_budgets (name, period, scope, amount, thresholds) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                budget.name,
+                budget.period,
+                budget.scope,
+                budget.amount,
+                serde_json::to_string(&budget.thresholds).unwrap(),
+            ],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn spend(conn: &Connection, cost: f64) {
+        conn.execute(
+            "INSERT INTO usage_entries (file_path, timestamp, timestamp_ms, date, model, cost,
+                session_id, project_path, api_base_url)
+             VALUES ('a.jsonl', '', ?1, '', 'claude-sonnet-4', ?2, 's1', '/work', 'https://api.anthropic.com')",
+            params![Local::now().timestamp_millis(), cost],
+        )
+        .unwrap();
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn period_bounds_cover_the_current_period() {
+        assert_eq!(period_bounds("daily", date(2026, 2, 28)), (date(2026, 2, 28), date(2026, 3, 1)));
+        // 2026-03-04 is a Wednesday
+        assert_eq!(period_bounds("weekly", date(2026, 3, 4)), (date(2026, 3, 2), date(2026, 3, 9)));
+        assert_eq!(period_bounds("weekly", date(2026, 3, 1)), (date(2026, 2, 23), date(2026, 3, 2)));
+        assert_eq!(period_bounds("monthly", date(2026, 2, 14)), (date(2026, 2, 1), date(2026, 3, 1)));
+        assert_eq!(period_bounds("monthly", date(2026, 12, 31)), (date(2026, 12, 1), date(2027, 1, 1)));
+    }
+
+    #[test]
+    fn validates_and_orders_thresholds() {
+        let mut valid = budget(vec![100, 50, 80, 50]);
+        validate_budget(&mut valid).unwrap();
+        assert_eq!(valid.thresholds, vec![50, 80, 100]);
+        assert!(validate_budget(&mut budget(vec![])).is_err());
+        assert!(validate_budget(&mut budget(vec![0, 50])).is_err());
+    }
+
+    #[test]
+    fn alerts_fire_once_per_threshold_and_period() {
+        let conn = test_conn();
+        let id = insert_budget(&conn, &budget(vec![50, 80, 100]));
+        assert!(collect_new_alerts(&conn).unwrap().is_empty());
+
+        // Crossing two thresholds at once only reports the highest
+        spend(&conn, 8.5);
+        let alerts = collect_new_alerts(&conn).unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].budget_id, alerts[0].threshold), (id, 80));
+        assert!(collect_new_alerts(&conn).unwrap().is_empty());
+
+        spend(&conn, 2.0);
+        let alerts = collect_new_alerts(&conn).unwrap();
+        assert_eq!(alerts.iter().map(|a| a.threshold).collect::<Vec<_>>(), vec![100]);
+        assert!(collect_new_alerts(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_measurement_changes_reset_alerts() {
+        let old = budget(vec![50, 80, 100]);
+        let mut renamed = old.clone();
+        renamed.name = "team".to_string();
+        renamed.enabled = false;
+        assert!(!resets_alerts(&old, &renamed));
+
+        let mut raised = old.clone();
+        raised.amount = 20.0;
+        assert!(resets_alerts(&old, &raised));
+        let mut thresholds = old.clone();
+        thresholds.thresholds = vec![90];
+        assert!(resets_alerts(&old, &thresholds));
+        let mut scoped = old.clone();
+        scoped.scope = "project".to_string();
+        scoped.scope_value = Some("/work".to_string());
+        assert!(resets_alerts(&old, &scoped));
+        let mut weekly = old;
+        weekly.period = "weekly".to_string();
+        assert!(resets_alerts(&budget(vec![50, 80, 100]), &weekly));
+    }
+}
@@ -0,0 +1,433 @@
+use chrono::NaiveDate;
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::pricing::{load_pricing_config, PricingConfig};
+use super::usage::{parse_filter_date, UsageFilter};
+use super::usage_index::lock_synced_index;
+
+// Sessions below this hit ratio are flagged as reusing the cache poorly
+const DEFAULT_MIN_HIT_RATIO: f64 = 0.5;
+// Sessions that wrote fewer tokens to the cache aren't worth flagging
+const MIN_FLAGGED_CACHE_WRITES: u64 = 10_000;
+
+/// Prompt cache metrics of a group of usage entries
+///
+/// Amounts are in the pricing currency. Cache writes are billed at a premium
+/// over plain input and pay back through cheaper cache reads; write premium
+/// not covered by read savings is reported as `unrecovered_write_cost`.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CacheEfficiency {
+    input_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    /// Share of prompt tokens served from the cache
+    hit_ratio: f64,
+    /// Cost of all cache writes
+    write_cost: f64,
+    /// Extra paid for cache writes compared with uncached input
+    write_premium: f64,
+    /// Saved by cache reads compared with uncached input
+    read_savings: f64,
+    unrecovered_write_cost: f64,
+    /// `read_savings - write_premium`
+    net_savings: f64,
+}
+
+impl CacheEfficiency {
+    fn add(&mut self, other: &CacheEfficiency) {
+        self.input_tokens += other.input_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.write_cost += other.write_cost;
+        self.write_premium += other.write_premium;
+        self.read_savings += other.read_savings;
+        self.unrecovered_write_cost += other.unrecovered_write_cost;
+    }
+
+    fn finish(mut self) -> Self {
+        let prompt_tokens = self.input_tokens + self.cache_creation_tokens + self.cache_read_tokens;
+        self.hit_ratio = if prompt_tokens > 0 {
+            self.cache_read_tokens as f64 / prompt_tokens as f64
+        } else {
+            0.0
+        };
+        self.net_savings = self.read_savings - self.write_premium;
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupCacheEfficiency {
+    key: String,
+    #[serde(flatten)]
+    metrics: CacheEfficiency,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionCacheEfficiency {
+    session_id: String,
+    project_path: String,
+    #[serde(flatten)]
+    metrics: CacheEfficiency,
+    poor_reuse: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheEfficiencyReport {
+    totals: CacheEfficiency,
+    by_project: Vec<GroupCacheEfficiency>,
+    by_model: Vec<GroupCacheEfficiency>,
+    /// Sessions ordered by unrecovered write cost
+    by_session: Vec<SessionCacheEfficiency>,
+    /// Ids of sessions with poor cache reuse
+    flagged_sessions: Vec<String>,
+}
+
+/// Cache metrics of one session and model; the cache is per model, so this is
+/// the unit write premium is paid back in
+#[derive(Default)]
+struct CacheCell {
+    project_path: String,
+    metrics: CacheEfficiency,
+}
+
+fn accumulate_cells(
+    conn: &Connection,
+    filter: &UsageFilter,
+    pricing: &PricingConfig,
+) -> Result<HashMap<(String, String), CacheCell>, String> {
+    let (where_clause, values) = filter.where_clause();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT session_id, model, project_path, api_base_url, date,
+                    input_tokens, cache_creation_tokens, cache_read_tokens
+             FROM usage_entries WHERE {}",
+            where_clause
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query(params_from_iter(values.iter()))
+        .map_err(|e| e.to_string())?;
+
+    let mut cells: HashMap<(String, String), CacheCell> = HashMap::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let session_id: String = row.get(0).map_err(|e| e.to_string())?;
+        let model: String = row.get(1).map_err(|e| e.to_string())?;
+        let project_path: String = row.get(2).map_err(|e| e.to_string())?;
+        let api_base_url: String = row.get(3).map_err(|e| e.to_string())?;
+        let date: String = row.get(4).map_err(|e| e.to_string())?;
+        let input_tokens = row.get::<_, i64>(5).map_err(|e| e.to_string())? as u64;
+        let cache_creation_tokens = row.get::<_, i64>(6).map_err(|e| e.to_string())? as u64;
+        let cache_read_tokens = row.get::<_, i64>(7).map_err(|e| e.to_string())? as u64;
+
+        let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok();
+        let cell = cells.entry((session_id, model.clone())).or_default();
+        if cell.project_path.is_empty() {
+            cell.project_path = project_path;
+        }
+
+        let metrics = &mut cell.metrics;
+        metrics.input_tokens += input_tokens;
+        metrics.cache_creation_tokens += cache_creation_tokens;
+        metrics.cache_read_tokens += cache_read_tokens;
+
+        // Unknown models still count towards the token ratios
+        if let Some((price, multiplier)) = pricing.model_price(&model, &api_base_url, date) {
+            let per_token = multiplier / 1_000_000.0;
+            metrics.write_cost += cache_creation_tokens as f64 * price.cache_write * per_token;
+            metrics.write_premium +=
+                cache_creation_tokens as f64 * (price.cache_write - price.input) * per_token;
+            metrics.read_savings +=
+                cache_read_tokens as f64 * (price.input - price.cache_read) * per_token;
+        }
+    }
+
+    for cell in cells.values_mut() {
+        let metrics = &mut cell.metrics;
+        metrics.unrecovered_write_cost = (metrics.write_premium - metrics.read_savings).max(0.0);
+    }
+
+    Ok(cells)
+}
+
+fn into_groups(groups: HashMap<String, CacheEfficiency>) -> Vec<GroupCacheEfficiency> {
+    let mut groups: Vec<GroupCacheEfficiency> = groups
+        .into_iter()
+        .map(|(key, metrics)| GroupCacheEfficiency {
+            key,
+            metrics: metrics.finish(),
+        })
+        .collect();
+    groups.sort_by(|a, b| b.metrics.net_savings.total_cmp(&a.metrics.net_savings));
+    groups
+}
+
+fn build_report(
+    cells: HashMap<(String, String), CacheCell>,
+    min_hit_ratio: f64,
+) -> CacheEfficiencyReport {
+    let mut totals = CacheEfficiency::default();
+    let mut by_project: HashMap<String, CacheEfficiency> = HashMap::new();
+    let mut by_model: HashMap<String, CacheEfficiency> = HashMap::new();
+    let mut by_session: HashMap<String, CacheCell> = HashMap::new();
+
+    for ((session_id, model), cell) in cells {
+        totals.add(&cell.metrics);
+        by_project
+            .entry(cell.project_path.clone())
+            .or_default()
+            .add(&cell.metrics);
+        by_model.entry(model).or_default().add(&cell.metrics);
+
+        let session = by_session.entry(session_id).or_default();
+        if session.project_path.is_empty() {
+            session.project_path = cell.project_path;
+        }
+        session.metrics.add(&cell.metrics);
+    }
+
+    let mut sessions: Vec<SessionCacheEfficiency> = by_session
+        .into_iter()
+        .map(|(session_id, cell)| {
+            let metrics = cell.metrics.finish();
+            let poor_reuse = metrics.cache_creation_tokens >= MIN_FLAGGED_CACHE_WRITES
+                && (metrics.hit_ratio < min_hit_ratio || metrics.unrecovered_write_cost > 0.0);
+            SessionCacheEfficiency {
+                session_id,
+                project_path: cell.project_path,
+                metrics,
+                poor_reuse,
+            }
+        })
+        .collect();
+    sessions.sort_by(|a, b| {
+        b.metrics
+            .unrecovered_write_cost
+            .total_cmp(&a.metrics.unrecovered_write_cost)
+    });
+
+    let flagged_sessions = sessions
+        .iter()
+        .filter(|s| s.poor_reuse)
+        .map(|s| s.session_id.clone())
+        .collect();
+
+    CacheEfficiencyReport {
+        totals: totals.finish(),
+        by_project: into_groups(by_project),
+        by_model: into_groups(by_model),
+        by_session: sessions,
+        flagged_sessions,
+    }
+}
+
+/// Analyse prompt cache reuse per project, model and session
+#[command]
+pub fn get_cache_efficiency(
+    db: State<'_, AgentDb>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    project_path: Option<String>,
+    min_hit_ratio: Option<f64>,
+) -> Result<CacheEfficiencyReport, String> {
+    let filter = UsageFilter {
+        start_date: start_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "start"))
+            .transpose()?,
+        end_date: end_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "end"))
+            .transpose()?,
+        project_path,
+        ..Default::default()
+    };
+
+    let conn = lock_synced_index(&db)?;
+    let cells = accumulate_cells(&conn, &filter, &load_pricing_config())?;
+    Ok(build_report(cells, min_hit_ratio.unwrap_or(DEFAULT_MIN_HIT_RATIO)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::pricing::{ModelPrice, PricingOverride};
+    use crate::commands::usage_index::init_usage_index_tables;
+
+    const RELAY: &str = "https://relay.example.com/v1";
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_entry(
+        conn: &Connection,
+        session_id: &str,
+        model: &str,
+        api_base_url: &str,
+        (input, cache_creation, cache_read): (i64, i64, i64),
+    ) {
+        conn.execute(
+            "INSERT INTO usage_entries (file_path, timestamp, timestamp_ms, date, model, input_tokens,
+                cache_creation_tokens, cache_read_tokens, session_id, project_path, api_base_url)
+             VALUES ('a.jsonl', '', 0, '2026-03-01', ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                model,
+                input,
+                cache_creation,
+                cache_read,
+                session_id,
+                format!("/work/{}", session_id),
+                api_base_url
+            ],
+        )
+        .unwrap();
+    }
+
+    // List prices, plus a relay that bills its own sonnet prices at half rate
+    fn pricing() -> PricingConfig {
+        let mut config = PricingConfig::default();
+        config.overrides.push(PricingOverride {
+            api_base_url: "https://relay.example.com".to_string(),
+            multiplier: 0.5,
+            models: vec![ModelPrice {
+                pattern: "sonnet-4".to_string(),
+                input: 2.0,
+                output: 10.0,
+                cache_write: 4.0,
+                cache_read: 0.5,
+                effective_from: None,
+                effective_until: None,
+            }],
+        });
+        config
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn prices_cache_traffic_with_the_resolved_model_price() {
+        let conn = test_conn();
+        // Anthropic list price: input 3.00, cache write 3.75, cache read 0.30
+        add_entry(
+            &conn,
+            "s1",
+            "claude-sonnet-4",
+            "https://api.anthropic.com",
+            (100_000, 600_000, 1_000_000),
+        );
+        add_entry(
+            &conn,
+            "s1",
+            "claude-sonnet-4",
+            "https://api.anthropic.com",
+            (0, 400_000, 2_000_000),
+        );
+        // Relay price at half rate: input 2.00, cache write 4.00, cache read 0.50
+        add_entry(
+            &conn,
+            "s2",
+            "claude-sonnet-4",
+            RELAY,
+            (0, 1_000_000, 100_000),
+        );
+        // No price, only the token ratios count
+        add_entry(&conn, "s3", "gpt-4o", RELAY, (1_000, 0, 1_000));
+
+        let cells = accumulate_cells(&conn, &UsageFilter::default(), &pricing()).unwrap();
+        let report = build_report(cells, DEFAULT_MIN_HIT_RATIO);
+
+        let s1 = report
+            .by_session
+            .iter()
+            .find(|s| s.session_id == "s1")
+            .unwrap();
+        assert_close(s1.metrics.hit_ratio, 3_000_000.0 / 4_100_000.0);
+        assert_close(s1.metrics.write_cost, 3.75);
+        assert_close(s1.metrics.write_premium, 0.75);
+        assert_close(s1.metrics.read_savings, 8.1);
+        assert_close(s1.metrics.unrecovered_write_cost, 0.0);
+        assert_close(s1.metrics.net_savings, 7.35);
+        assert!(!s1.poor_reuse);
+
+        let s2 = &report.by_session[0];
+        assert_eq!(s2.session_id, "s2");
+        assert_eq!(s2.project_path, "/work/s2");
+        assert_close(s2.metrics.hit_ratio, 100_000.0 / 1_100_000.0);
+        assert_close(s2.metrics.write_cost, 2.0);
+        assert_close(s2.metrics.write_premium, 1.0);
+        assert_close(s2.metrics.read_savings, 0.075);
+        assert_close(s2.metrics.unrecovered_write_cost, 0.925);
+        assert!(s2.poor_reuse);
+
+        let s3 = report
+            .by_session
+            .iter()
+            .find(|s| s.session_id == "s3")
+            .unwrap();
+        assert_close(s3.metrics.hit_ratio, 0.5);
+        assert_close(s3.metrics.write_cost + s3.metrics.read_savings, 0.0);
+        assert!(!s3.poor_reuse);
+        assert_eq!(report.flagged_sessions, vec!["s2".to_string()]);
+
+        let totals = &report.totals;
+        assert_eq!(totals.cache_read_tokens, 3_101_000);
+        assert_close(totals.write_cost, 5.75);
+        assert_close(totals.read_savings, 8.175);
+        assert_close(totals.unrecovered_write_cost, 0.925);
+        assert_close(totals.net_savings, 6.425);
+
+        let models: Vec<&str> = report.by_model.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(models, vec!["claude-sonnet-4", "gpt-4o"]);
+        assert_close(report.by_model[0].metrics.net_savings, 6.425);
+    }
+
+    #[test]
+    fn write_premium_is_only_recovered_within_the_same_model() {
+        let conn = test_conn();
+        // Opus writes the cache and never reads it; sonnet reads its own cache
+        add_entry(
+            &conn,
+            "s1",
+            "claude-opus-4",
+            "https://api.anthropic.com",
+            (0, 1_000_000, 0),
+        );
+        add_entry(
+            &conn,
+            "s1",
+            "claude-sonnet-4",
+            "https://api.anthropic.com",
+            (0, 0, 10_000_000),
+        );
+
+        let cells = accumulate_cells(&conn, &UsageFilter::default(), &pricing()).unwrap();
+        let report = build_report(cells, DEFAULT_MIN_HIT_RATIO);
+
+        // Opus premium (18.75 - 15.00) isn't offset by the sonnet read savings
+        let session = &report.by_session[0];
+        assert_close(session.metrics.write_premium, 3.75);
+        assert_close(session.metrics.read_savings, 27.0);
+        assert_close(session.metrics.unrecovered_write_cost, 3.75);
+        assert!(session.poor_reuse);
+        assert_close(report.totals.hit_ratio, 10.0 / 11.0);
+    }
+}
@@ -12,6 +12,8 @@ use tokio::sync::Mutex;
 use tauri_plugin_shell::ShellExt;
 use regex;
 
+use crate::claude_messages::ClaudeMessage;
+
 /// Global state to track current Claude process
 pub struct ClaudeProcessState {
     pub current_process: Arc<Mutex<Option<Child>>>,
@@ -57,23 +59,6 @@ pub struct Session {
     pub message_timestamp: Option<String>,
 }
 
-/// Represents a message entry in the JSONL file
-#[derive(Debug, Deserialize)]
-struct JsonlEntry {
-    #[serde(rename = "type")]
-    #[allow(dead_code)]
-    entry_type: Option<String>,
-    message: Option<MessageContent>,
-    timestamp: Option<String>,
-}
-
-/// Represents the message content
-#[derive(Debug, Deserialize)]
-struct MessageContent {
-    role: Option<String>,
-    content: Option<String>,
-}
-
 /// Represents the settings from ~/.claude/settings.json
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeSettings {
@@ -164,8 +149,8 @@ fn get_project_path_from_sessions(project_dir: &PathBuf) -> Result<String, Strin
                     let reader = BufReader::new(file);
                     if let Some(Ok(first_line)) = reader.lines().next() {
                         // Parse the JSON and extract cwd
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&first_line) {
-                            if let Some(cwd) = json.get("cwd").and_then(|v| v.as_str()) {
+                        if let Some(msg) = ClaudeMessage::parse(&first_line) {
+                            if let Some(cwd) = msg.cwd() {
                                 return Ok(cwd.to_string());
                             }
                         }
@@ -199,10 +184,10 @@ fn extract_first_user_message(jsonl_path: &PathBuf) -> (Option<String>, Option<S
 
     for line in reader.lines() {
         if let Ok(line) = line {
-            if let Ok(entry) = serde_json::from_str::<JsonlEntry>(&line) {
+            if let Some(ClaudeMessage::User(entry)) = ClaudeMessage::parse(&line) {
                 if let Some(message) = entry.message {
                     if message.role.as_deref() == Some("user") {
-                        if let Some(content) = message.content {
+                        if let Some(content) = message.content.text() {
                             // Skip if it contains the caveat message
                             if content.contains("Caveat: The messages below were generated by the user while running local commands") {
                                 continue;
@@ -1423,9 +1408,12 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
             log::debug!("Claude stdout: {}", line);
             
             // Parse the line to check for init message with session ID
-            if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) {
-                if msg["type"] == "system" && msg["subtype"] == "init" {
-                    if let Some(claude_session_id) = msg["session_id"].as_str() {
+            if let Some(msg) = ClaudeMessage::parse(&line) {
+                if let Some(api_error) = msg.api_error() {
+                    super::relay_failover::report_stream_error(&app_handle, &api_error);
+                }
+                if msg.is_init() {
+                    if let Some(claude_session_id) = msg.init_session_id() {
                         let mut session_id_guard = session_id_holder_clone.lock().unwrap();
                         if session_id_guard.is_none() {
                             *session_id_guard = Some(claude_session_id.to_string());
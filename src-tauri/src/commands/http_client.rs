@@ -0,0 +1,554 @@
+//! Shared HTTP client for relay station adapters and GitHub lookups.
+//!
+//! Clients are built once from the `http_client` settings and reused, one per
+//! user agent. Idempotent requests sent through [`SendWithRetry`] are retried
+//! with exponential backoff on connection failures, timeouts and overloaded
+//! upstreams.
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::relay_stations::RelayStation;
+use super::secrets::STORE_LOCKED;
+
+const SETTINGS_KEY: &str = "http_client";
+const DEFAULT_USER_AGENT: &str = "Claude-Suite";
+// Backoff never waits longer than this between attempts
+const MAX_BACKOFF_MS: u64 = 10_000;
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_read_timeout_secs() -> u64 {
+    30
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_user_agent() -> String {
+    DEFAULT_USER_AGENT.to_string()
+}
+
+/// Settings shared by every outgoing request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HttpClientConfig {
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Longest wait for the next chunk of a response
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// Extra attempts for idempotent requests
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy; the system
+    /// proxy is used when unset
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Comma separated hosts that bypass the proxy
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// PEM files with extra trusted root certificates
+    #[serde(default)]
+    pub ca_cert_paths: Vec<String>,
+    /// Sent unless a station sets `adapter_config.user_agent`
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: default_read_timeout_secs(),
+            max_retries: default_max_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            proxy_url: None,
+            no_proxy: None,
+            ca_cert_paths: Vec::new(),
+            user_agent: default_user_agent(),
+        }
+    }
+}
+
+/// Why a request failed
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HttpError {
+    /// No connection or no response data within the configured timeout
+    Timeout {
+        url: String,
+        phase: TimeoutPhase,
+        timeout_secs: u64,
+    },
+    Connect {
+        url: String,
+        message: String,
+    },
+    Request {
+        url: String,
+        message: String,
+    },
+    /// The client settings are invalid
+    Config {
+        message: String,
+    },
+    /// The station's credentials are still encrypted in the locked secret store
+    Locked {
+        station: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutPhase {
+    Connect,
+    Read,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Timeout {
+                url,
+                phase: TimeoutPhase::Connect,
+                timeout_secs,
+            } => write!(f, "Connecting to {} timed out after {}s", url, timeout_secs),
+            HttpError::Timeout {
+                url, timeout_secs, ..
+            } => write!(f, "{} did not respond within {}s", url, timeout_secs),
+            HttpError::Connect { url, message } => {
+                write!(f, "Failed to connect to {}: {}", url, message)
+            }
+            HttpError::Request { url, message } => {
+                write!(f, "Request to {} failed: {}", url, message)
+            }
+            HttpError::Config { message } => write!(f, "Invalid HTTP client settings: {}", message),
+            HttpError::Locked { station } => write!(
+                f,
+                "{}, unlock it to send requests to '{}'",
+                STORE_LOCKED, station
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl HttpError {
+    fn from_reqwest(error: reqwest::Error, config: &HttpClientConfig) -> Self {
+        let url = error.url().map(|url| url.to_string()).unwrap_or_default();
+        // Connect timeouts report both flags
+        if error.is_timeout() && error.is_connect() {
+            HttpError::Timeout {
+                url,
+                phase: TimeoutPhase::Connect,
+                timeout_secs: config.connect_timeout_secs,
+            }
+        } else if error.is_timeout() {
+            HttpError::Timeout {
+                url,
+                phase: TimeoutPhase::Read,
+                timeout_secs: config.read_timeout_secs,
+            }
+        } else if error.is_connect() {
+            HttpError::Connect {
+                url,
+                message: error.to_string(),
+            }
+        } else {
+            HttpError::Request {
+                url,
+                message: error.to_string(),
+            }
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, HttpError::Timeout { .. })
+    }
+}
+
+#[derive(Default)]
+struct SharedClients {
+    config: HttpClientConfig,
+    /// Built clients, by user agent
+    clients: HashMap<String, reqwest::Client>,
+    /// Client of the routing proxy, built on first use
+    proxy: Option<reqwest::Client>,
+}
+
+static SHARED: Lazy<RwLock<SharedClients>> = Lazy::new(Default::default);
+
+/// Builder with the configured connect timeout, proxy and CA certificates
+fn client_builder(
+    config: &HttpClientConfig,
+    user_agent: &str,
+) -> Result<reqwest::ClientBuilder, HttpError> {
+    let invalid = |message: String| HttpError::Config { message };
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs));
+
+    if let Some(proxy_url) = config
+        .proxy_url
+        .as_deref()
+        .filter(|url| !url.trim().is_empty())
+    {
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| invalid(format!("proxy {}: {}", proxy_url, e)))?
+            .no_proxy(
+                config
+                    .no_proxy
+                    .as_deref()
+                    .and_then(reqwest::NoProxy::from_string),
+            );
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &config.ca_cert_paths {
+        let pem = std::fs::read(path).map_err(|e| invalid(format!("{}: {}", path, e)))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| invalid(format!("{}: {}", path, e)))?;
+        if certs.is_empty() {
+            return Err(invalid(format!("{}: no certificates found", path)));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    Ok(builder)
+}
+
+fn build_client(config: &HttpClientConfig, user_agent: &str) -> Result<reqwest::Client, HttpError> {
+    client_builder(config, user_agent)?
+        .read_timeout(Duration::from_secs(config.read_timeout_secs))
+        .build()
+        .map_err(|e| HttpError::Config {
+            message: e.to_string(),
+        })
+}
+
+fn client_with_user_agent(user_agent: Option<&str>) -> Result<reqwest::Client, HttpError> {
+    {
+        let shared = SHARED.read().map_err(|e| HttpError::Config {
+            message: e.to_string(),
+        })?;
+        let user_agent = user_agent.unwrap_or(&shared.config.user_agent);
+        if let Some(client) = shared.clients.get(user_agent) {
+            return Ok(client.clone());
+        }
+    }
+
+    let mut shared = SHARED.write().map_err(|e| HttpError::Config {
+        message: e.to_string(),
+    })?;
+    let user_agent = user_agent.unwrap_or(&shared.config.user_agent).to_string();
+    let client = build_client(&shared.config, &user_agent)?;
+    shared.clients.insert(user_agent, client.clone());
+    Ok(client)
+}
+
+/// The shared client with the configured user agent
+pub fn client() -> Result<reqwest::Client, HttpError> {
+    client_with_user_agent(None)
+}
+
+/// Client for requests relayed by the routing proxy
+///
+/// Model calls may take minutes before the first response byte, so unlike the
+/// shared client it has no read timeout; the caller of the proxy decides how
+/// long to wait.
+pub fn proxy_client() -> Result<reqwest::Client, HttpError> {
+    let mut shared = SHARED.write().map_err(|e| HttpError::Config {
+        message: e.to_string(),
+    })?;
+    if let Some(client) = &shared.proxy {
+        return Ok(client.clone());
+    }
+    let client = client_builder(&shared.config, &shared.config.user_agent)?
+        .build()
+        .map_err(|e| HttpError::Config {
+            message: e.to_string(),
+        })?;
+    shared.proxy = Some(client.clone());
+    Ok(client)
+}
+
+/// The shared client with the station's `adapter_config.user_agent`, if set
+pub fn station_client(station: &RelayStation) -> Result<reqwest::Client, HttpError> {
+    // Never send the reference itself as a token
+    if station.credentials_locked() {
+        return Err(HttpError::Locked {
+            station: station.name.clone(),
+        });
+    }
+    let user_agent = station
+        .adapter_config
+        .as_ref()
+        .and_then(|config| config.get("user_agent"))
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    client_with_user_agent(user_agent)
+}
+
+fn current_config() -> HttpClientConfig {
+    SHARED
+        .read()
+        .map(|shared| shared.config.clone())
+        .unwrap_or_default()
+}
+
+pub(crate) fn is_idempotent(method: &reqwest::Method) -> bool {
+    matches!(
+        *method,
+        reqwest::Method::GET
+            | reqwest::Method::HEAD
+            | reqwest::Method::OPTIONS
+            | reqwest::Method::PUT
+            | reqwest::Method::DELETE
+    )
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+fn backoff(config: &HttpClientConfig, attempt: u32) -> Duration {
+    let delay = config
+        .retry_backoff_ms
+        .saturating_mul(1u64 << attempt.min(16));
+    Duration::from_millis(delay.min(MAX_BACKOFF_MS))
+}
+
+/// Sending with the shared retry policy
+#[async_trait::async_trait]
+pub trait SendWithRetry {
+    /// Send the request, retrying idempotent ones on connection failures,
+    /// timeouts and 429/502/503/504 responses
+    async fn send_with_retry(self) -> Result<reqwest::Response, HttpError>;
+}
+
+#[async_trait::async_trait]
+impl SendWithRetry for reqwest::RequestBuilder {
+    async fn send_with_retry(self) -> Result<reqwest::Response, HttpError> {
+        let config = current_config();
+        let (client, request) = self.build_split();
+        let request = request.map_err(|e| HttpError::from_reqwest(e, &config))?;
+        let max_retries = if is_idempotent(request.method()) {
+            config.max_retries
+        } else {
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            // Streaming bodies cannot be cloned and are sent once
+            let Some(attempt_request) = request.try_clone() else {
+                return client
+                    .execute(request)
+                    .await
+                    .map_err(|e| HttpError::from_reqwest(e, &config));
+            };
+            let retryable = match client.execute(attempt_request).await {
+                Ok(response) if attempt < max_retries && is_retryable_status(response.status()) => {
+                    format!("status {}", response.status())
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < max_retries && (e.is_timeout() || e.is_connect()) => {
+                    HttpError::from_reqwest(e, &config).to_string()
+                }
+                Err(e) => return Err(HttpError::from_reqwest(e, &config)),
+            };
+            let delay = backoff(&config, attempt);
+            attempt += 1;
+            warn!(
+                "{} {} failed ({}), retry {}/{} in {}ms",
+                request.method(),
+                request.url(),
+                retryable,
+                attempt,
+                max_retries,
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn load_config(conn: &Connection) -> HttpClientConfig {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn store_config(conn: &Connection, config: &HttpClientConfig) -> Result<(), String> {
+    let value = serde_json::to_string(config).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2",
+        params![SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save HTTP client settings: {}", e))?;
+    Ok(())
+}
+
+/// Replace the shared clients; the settings are checked by building the
+/// default client first
+pub fn apply_config(config: HttpClientConfig) -> Result<(), HttpError> {
+    if config.user_agent.trim().is_empty() {
+        return Err(HttpError::Config {
+            message: "User agent must not be empty".to_string(),
+        });
+    }
+    let client = build_client(&config, &config.user_agent)?;
+    let mut shared = SHARED.write().map_err(|e| HttpError::Config {
+        message: e.to_string(),
+    })?;
+    shared.clients.clear();
+    shared.clients.insert(config.user_agent.clone(), client);
+    shared.proxy = None;
+    shared.config = config;
+    Ok(())
+}
+
+/// Load the saved client settings
+pub fn init_http_client(app: &AppHandle) {
+    let config = {
+        let db = app.state::<AgentDb>();
+        let conn = match db.0.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Failed to load HTTP client settings: {}", e);
+                return;
+            }
+        };
+        load_config(&conn)
+    };
+    if let Err(e) = apply_config(config) {
+        // Fall back to the defaults rather than leaving requests without timeouts
+        warn!("{}, using defaults", e);
+        if let Err(e) = apply_config(HttpClientConfig::default()) {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// Get the HTTP client settings
+#[command]
+pub fn get_http_client_config(db: State<'_, AgentDb>) -> Result<HttpClientConfig, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_config(&conn))
+}
+
+/// Save the HTTP client settings and rebuild the shared clients
+#[command]
+pub fn save_http_client_config(
+    db: State<'_, AgentDb>,
+    config: HttpClientConfig,
+) -> Result<HttpClientConfig, String> {
+    apply_config(config.clone()).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store_config(&conn, &config)?;
+    info!("HTTP client settings updated");
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let config = HttpClientConfig::default();
+        assert_eq!(backoff(&config, 0), Duration::from_millis(500));
+        assert_eq!(backoff(&config, 2), Duration::from_millis(2000));
+        assert_eq!(backoff(&config, 40), Duration::from_millis(MAX_BACKOFF_MS));
+    }
+
+    #[test]
+    fn only_idempotent_methods_are_retried() {
+        assert!(is_idempotent(&reqwest::Method::GET));
+        assert!(is_idempotent(&reqwest::Method::PUT));
+        assert!(!is_idempotent(&reqwest::Method::POST));
+        assert!(!is_idempotent(&reqwest::Method::PATCH));
+    }
+
+    #[test]
+    fn rejects_unreadable_ca_certificates() {
+        let config = HttpClientConfig {
+            ca_cert_paths: vec!["/nonexistent/ca.pem".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_client(&config, DEFAULT_USER_AGENT),
+            Err(HttpError::Config { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn proxy_clients_wait_past_the_read_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 1024];
+                    let _ = socket.read(&mut request).await;
+                    tokio::time::sleep(Duration::from_millis(1500)).await;
+                    let _ = socket
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                        )
+                        .await;
+                });
+            }
+        });
+
+        let config = HttpClientConfig {
+            read_timeout_secs: 1,
+            ..Default::default()
+        };
+        let error = build_client(&config, DEFAULT_USER_AGENT)
+            .unwrap()
+            .post(&url)
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.is_timeout() && !error.is_connect());
+
+        let proxy = client_builder(&config, DEFAULT_USER_AGENT)
+            .unwrap()
+            .build()
+            .unwrap();
+        let response = proxy.post(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+}
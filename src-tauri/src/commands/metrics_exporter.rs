@@ -0,0 +1,595 @@
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager, State};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use super::agents::AgentDb;
+use super::relay_stations::RelayStationManager;
+use super::usage_index::lock_synced_index;
+use crate::process::ProcessRegistryState;
+
+const SETTINGS_KEY: &str = "metrics_exporter";
+const DEFAULT_PORT: u16 = 9464;
+const METRICS_PATH: &str = "/metrics";
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+// Scrape requests are a single line plus a few headers
+const MAX_REQUEST_BYTES: usize = 8192;
+// Clients that don't finish sending their request in time are dropped
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings of the local metrics endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricsExporterConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsExporterStatus {
+    pub config: MetricsExporterConfig,
+    /// Address being served, when running
+    pub address: Option<String>,
+}
+
+struct RunningExporter {
+    address: String,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+/// The running endpoint, if any
+#[derive(Default)]
+pub struct MetricsExporterState(Mutex<Option<RunningExporter>>);
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for MetricsExporterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+fn load_config(conn: &Connection) -> MetricsExporterConfig {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+/// Metric families in OpenMetrics text exposition format
+#[derive(Default)]
+struct MetricsWriter {
+    out: String,
+}
+
+impl MetricsWriter {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push_str("# EOF\n");
+        self.out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_usage_metrics(metrics: &mut MetricsWriter, conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT model, project_path, api_base_url, SUM(input_tokens), SUM(output_tokens),
+                    SUM(cache_creation_tokens), SUM(cache_read_tokens), SUM(cost), COUNT(*)
+             FROM usage_entries
+             GROUP BY model, project_path, api_base_url
+             ORDER BY model, project_path, api_base_url",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                [
+                    ("input", row.get::<_, i64>(3)?),
+                    ("output", row.get::<_, i64>(4)?),
+                    ("cache_creation", row.get::<_, i64>(5)?),
+                    ("cache_read", row.get::<_, i64>(6)?),
+                ],
+                row.get::<_, f64>(7)?,
+                row.get::<_, i64>(8)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    metrics.family(
+        "claude_suite_usage_tokens",
+        "counter",
+        "Tokens used by Claude sessions",
+    );
+    for (model, project, api_base_url, tokens, _, _) in &rows {
+        for (kind, count) in tokens {
+            metrics.sample(
+                "claude_suite_usage_tokens_total",
+                &[
+                    ("model", model.as_str()),
+                    ("project", project.as_str()),
+                    ("api_base_url", api_base_url.as_str()),
+                    ("type", *kind),
+                ],
+                *count as f64,
+            );
+        }
+    }
+
+    metrics.family(
+        "claude_suite_usage_cost",
+        "counter",
+        "Cost of Claude sessions in the pricing currency",
+    );
+    for (model, project, api_base_url, _, cost, _) in &rows {
+        metrics.sample(
+            "claude_suite_usage_cost_total",
+            &[
+                ("model", model.as_str()),
+                ("project", project.as_str()),
+                ("api_base_url", api_base_url.as_str()),
+            ],
+            *cost,
+        );
+    }
+
+    metrics.family(
+        "claude_suite_usage_messages",
+        "counter",
+        "Assistant messages of Claude sessions",
+    );
+    for (model, project, api_base_url, _, _, messages) in &rows {
+        metrics.sample(
+            "claude_suite_usage_messages_total",
+            &[
+                ("model", model.as_str()),
+                ("project", project.as_str()),
+                ("api_base_url", api_base_url.as_str()),
+            ],
+            *messages as f64,
+        );
+    }
+
+    Ok(())
+}
+
+fn write_agent_run_metrics(metrics: &mut MetricsWriter, conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT status, COUNT(*) FROM agent_runs GROUP BY status ORDER BY status")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    metrics.family("claude_suite_agent_runs", "gauge", "Agent runs by status");
+    for (status, count) in rows {
+        metrics.sample(
+            "claude_suite_agent_runs",
+            &[("status", status.as_str())],
+            count as f64,
+        );
+    }
+    Ok(())
+}
+
+fn write_process_metrics(metrics: &mut MetricsWriter, app: &AppHandle) -> Result<(), String> {
+    let registry = app.state::<ProcessRegistryState>();
+    let agents = registry.0.get_running_agent_processes()?.len();
+    let sessions = registry.0.get_running_claude_sessions()?.len();
+
+    metrics.family(
+        "claude_suite_running_processes",
+        "gauge",
+        "Claude processes currently running",
+    );
+    metrics.sample(
+        "claude_suite_running_processes",
+        &[("kind", "agent_run")],
+        agents as f64,
+    );
+    metrics.sample(
+        "claude_suite_running_processes",
+        &[("kind", "claude_session")],
+        sessions as f64,
+    );
+    Ok(())
+}
+
+fn write_relay_metrics(metrics: &mut MetricsWriter, app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<Option<RelayStationManager>>>();
+    let health = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        match manager.as_ref() {
+            Some(manager) => manager.list_station_health().map_err(|e| e.to_string())?,
+            None => Vec::new(),
+        }
+    };
+
+    metrics.family(
+        "claude_suite_relay_station_up",
+        "gauge",
+        "Whether the last connection test of a relay station succeeded",
+    );
+    for station in &health {
+        metrics.sample(
+            "claude_suite_relay_station_up",
+            &[
+                ("station", station.station_name.as_str()),
+                ("station_id", station.station_id.as_str()),
+            ],
+            if station.success { 1.0 } else { 0.0 },
+        );
+    }
+
+    metrics.family(
+        "claude_suite_relay_station_enabled",
+        "gauge",
+        "Whether a relay station is enabled",
+    );
+    for station in &health {
+        metrics.sample(
+            "claude_suite_relay_station_enabled",
+            &[
+                ("station", station.station_name.as_str()),
+                ("station_id", station.station_id.as_str()),
+            ],
+            if station.enabled { 1.0 } else { 0.0 },
+        );
+    }
+
+    metrics.family(
+        "claude_suite_relay_station_response_time_seconds",
+        "gauge",
+        "Response time of the last connection test of a relay station",
+    );
+    for station in &health {
+        if let Some(response_time) = station.response_time {
+            metrics.sample(
+                "claude_suite_relay_station_response_time_seconds",
+                &[
+                    ("station", station.station_name.as_str()),
+                    ("station_id", station.station_id.as_str()),
+                ],
+                response_time as f64 / 1000.0,
+            );
+        }
+    }
+
+    metrics.family(
+        "claude_suite_relay_station_last_check_timestamp_seconds",
+        "gauge",
+        "When a relay station was last tested",
+    );
+    for station in &health {
+        metrics.sample(
+            "claude_suite_relay_station_last_check_timestamp_seconds",
+            &[
+                ("station", station.station_name.as_str()),
+                ("station_id", station.station_id.as_str()),
+            ],
+            station.checked_at as f64,
+        );
+    }
+    Ok(())
+}
+
+/// Render all metrics for one scrape
+fn render_metrics(app: &AppHandle) -> Result<String, String> {
+    let mut metrics = MetricsWriter::default();
+    {
+        let db = app.state::<AgentDb>();
+        let conn = lock_synced_index(&db)?;
+        write_usage_metrics(&mut metrics, &conn)?;
+        write_agent_run_metrics(&mut metrics, &conn)?;
+    }
+    write_process_metrics(&mut metrics, app)?;
+    write_relay_metrics(&mut metrics, app)?;
+    Ok(metrics.finish())
+}
+
+/// Read the request head, bounded in size and in time
+async fn read_request<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    timeout: Duration,
+) -> std::io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    let read_head = async {
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    tokio::time::timeout(timeout, read_head)
+        .await
+        .map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::TimedOut, "request not received in time")
+        })??;
+    Ok(request)
+}
+
+/// Answer one HTTP request; only `GET /metrics` is served
+async fn handle_connection(
+    app: AppHandle,
+    mut stream: tokio::net::TcpStream,
+) -> std::io::Result<()> {
+    let request = read_request(&mut stream, REQUEST_READ_TIMEOUT).await?;
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.lines().next().unwrap_or("").split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let (status, content_type, body) = if method != "GET" {
+        (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n".to_string(),
+        )
+    } else if path.split('?').next() != Some(METRICS_PATH) {
+        ("404 Not Found", "text/plain", "Not found\n".to_string())
+    } else {
+        match tauri::async_runtime::spawn_blocking(move || render_metrics(&app)).await {
+            Ok(Ok(body)) => ("200 OK", CONTENT_TYPE, body),
+            Ok(Err(e)) => {
+                error!("Failed to render metrics: {}", e);
+                (
+                    "500 Internal Server Error",
+                    "text/plain",
+                    format!("{}\n", e),
+                )
+            }
+            Err(e) => (
+                "500 Internal Server Error",
+                "text/plain",
+                format!("{}\n", e),
+            ),
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Start, move or stop the endpoint as `config` requires
+fn apply_config(app: &AppHandle, config: &MetricsExporterConfig) -> Result<(), String> {
+    let state = app.state::<MetricsExporterState>();
+    let mut running = state.0.lock().map_err(|e| e.to_string())?;
+
+    // Never listen beyond loopback
+    let address = format!("127.0.0.1:{}", config.port);
+    if config.enabled
+        && running
+            .as_ref()
+            .is_some_and(|exporter| exporter.address == address)
+    {
+        return Ok(());
+    }
+
+    // Bind before stopping the running endpoint, so a taken port is reported
+    // to the caller and the old endpoint keeps serving
+    let listener = if config.enabled {
+        let listener = std::net::TcpListener::bind(&address)
+            .map_err(|e| format!("Failed to bind {}: {}", address, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Some(listener)
+    } else {
+        None
+    };
+
+    if let Some(exporter) = running.take() {
+        exporter.task.abort();
+        info!("Stopped metrics endpoint on {}", exporter.address);
+    }
+    let Some(listener) = listener else {
+        return Ok(());
+    };
+
+    let app_handle = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to start metrics endpoint: {}", e);
+                return;
+            }
+        };
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let app = app_handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = handle_connection(app, stream).await {
+                            warn!("Metrics request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept metrics connection: {}", e),
+            }
+        }
+    });
+
+    info!("Serving metrics on http://{}{}", address, METRICS_PATH);
+    *running = Some(RunningExporter { address, task });
+    Ok(())
+}
+
+/// Start the metrics endpoint if it is enabled in the settings
+pub fn start_metrics_exporter(app: &AppHandle) {
+    let config = {
+        let db = app.state::<AgentDb>();
+        let conn = match db.0.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Failed to load metrics endpoint settings: {}", e);
+                return;
+            }
+        };
+        load_config(&conn)
+    };
+    if let Err(e) = apply_config(app, &config) {
+        error!("{}", e);
+    }
+}
+
+/// Get the metrics endpoint settings and whether it is running
+#[command]
+pub fn get_metrics_exporter_status(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<MetricsExporterStatus, String> {
+    let config = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_config(&conn)
+    };
+    let state = app.state::<MetricsExporterState>();
+    let running = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(MetricsExporterStatus {
+        config,
+        address: running
+            .as_ref()
+            .map(|exporter| format!("http://{}{}", exporter.address, METRICS_PATH)),
+    })
+}
+
+/// Save the metrics endpoint settings and start or stop it accordingly
+#[command]
+pub fn save_metrics_exporter_config(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    config: MetricsExporterConfig,
+) -> Result<(), String> {
+    if config.port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+
+    apply_config(&app, &config)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2",
+        params![SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save metrics endpoint settings: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writer_emits_openmetrics_text() {
+        let mut metrics = MetricsWriter::default();
+        metrics.family("claude_suite_agent_runs", "gauge", "Agent runs by status");
+        metrics.sample("claude_suite_agent_runs", &[("status", "completed")], 3.0);
+        metrics.sample("claude_suite_uptime", &[], 1.5);
+        assert_eq!(
+            metrics.finish(),
+            "# TYPE claude_suite_agent_runs gauge\n\
+             # HELP claude_suite_agent_runs Agent runs by status\n\
+             claude_suite_agent_runs{status=\"completed\"} 3\n\
+             claude_suite_uptime 1.5\n\
+             # EOF\n"
+        );
+    }
+
+    #[test]
+    fn writer_escapes_label_values() {
+        let mut metrics = MetricsWriter::default();
+        metrics.sample(
+            "claude_suite_usage_messages_total",
+            &[("project", "C:\\work\\\"app\"\nx"), ("model", "opus")],
+            2.0,
+        );
+        assert_eq!(
+            metrics.finish(),
+            "claude_suite_usage_messages_total{project=\"C:\\\\work\\\\\\\"app\\\"\\nx\",model=\"opus\"} 2\n# EOF\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_the_request_head() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let request = read_request(&mut server, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(request.starts_with(b"GET /metrics HTTP/1.1\r\n"));
+    }
+
+    #[tokio::test]
+    async fn drops_clients_that_stall() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        // The connection stays open, but the head is never completed
+        client
+            .write_all(b"GET /metrics HTTP/1.1\r\n")
+            .await
+            .unwrap();
+        let err = read_request(&mut server, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        drop(client);
+    }
+}
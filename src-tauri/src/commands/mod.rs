@@ -3,9 +3,30 @@ pub mod agents;
 pub mod claude;
 pub mod mcp;
 pub mod usage;
+pub mod usage_index;
+pub mod usage_export;
+pub mod usage_breakdown;
+pub mod cache_analytics;
+pub mod tool_usage;
+pub mod usage_forecast;
+pub mod pricing;
+pub mod budgets;
+pub mod plan_limits;
+pub mod metrics_exporter;
 pub mod storage;
 pub mod slash_commands;
 pub mod clipboard;
 pub mod provider;
 pub mod relay_stations;
 pub mod relay_adapters;
+pub mod relay_reconciliation;
+pub mod relay_health;
+pub mod relay_failover;
+pub mod routing_proxy;
+pub mod protocol_translation;
+pub mod secrets;
+pub mod relay_export;
+pub mod relay_capabilities;
+pub mod http_client;
+pub mod relay_balance;
+pub mod model_catalog;
@@ -0,0 +1,456 @@
+use chrono::Utc;
+use log::warn;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::http_client::{self, SendWithRetry};
+use super::provider::{find_provider_config, ProviderConfig};
+use super::relay_failover::{resolve_member, FailoverMember, FailoverMemberKind};
+use super::relay_stations::{create_adapter, RelayStationManager};
+
+// Catalogs older than this are refetched before a model is validated
+const CATALOG_MAX_AGE_SECS: i64 = 24 * 3600;
+const FETCH_TIMEOUT_SECS: u64 = 15;
+const MAX_SUGGESTIONS: usize = 3;
+// Edit distance up to which a model id counts as a likely typo
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// Models available on a relay station or provider, with user-defined aliases
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelCatalog {
+    #[serde(flatten)]
+    pub member: FailoverMember,
+    /// Sorted model ids
+    pub models: Vec<String>,
+    /// Alias to model id, e.g. `sonnet` to a station-specific id
+    pub aliases: BTreeMap<String, String>,
+    /// When the models were last fetched, as a Unix timestamp
+    pub fetched_at: Option<i64>,
+}
+
+impl ModelCatalog {
+    fn empty(member: &FailoverMember) -> Self {
+        Self {
+            member: member.clone(),
+            models: Vec::new(),
+            aliases: BTreeMap::new(),
+            fetched_at: None,
+        }
+    }
+}
+
+/// Create the model catalog table
+pub fn init_model_catalog_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS model_catalogs (
+            member_kind TEXT NOT NULL,
+            member_id TEXT NOT NULL,
+            models TEXT NOT NULL DEFAULT '[]',
+            aliases TEXT NOT NULL DEFAULT '{}',
+            fetched_at INTEGER,
+            PRIMARY KEY (member_kind, member_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn catalog_from_row(row: &Row) -> SqliteResult<ModelCatalog> {
+    let kind: String = row.get(0)?;
+    let models: String = row.get(2)?;
+    let aliases: String = row.get(3)?;
+    Ok(ModelCatalog {
+        member: FailoverMember {
+            kind: if kind == "provider" {
+                FailoverMemberKind::Provider
+            } else {
+                FailoverMemberKind::Station
+            },
+            id: row.get(1)?,
+        },
+        models: serde_json::from_str(&models).unwrap_or_default(),
+        aliases: serde_json::from_str(&aliases).unwrap_or_default(),
+        fetched_at: row.get(4)?,
+    })
+}
+
+fn load_catalog(
+    conn: &Connection,
+    member: &FailoverMember,
+) -> Result<Option<ModelCatalog>, String> {
+    conn.query_row(
+        "SELECT member_kind, member_id, models, aliases, fetched_at
+         FROM model_catalogs WHERE member_kind = ?1 AND member_id = ?2",
+        params![member.kind.as_str(), member.id],
+        catalog_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn store_models(
+    conn: &Connection,
+    member: &FailoverMember,
+    models: &[String],
+    fetched_at: i64,
+) -> Result<(), String> {
+    let models = serde_json::to_string(models).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO model_catalogs (member_kind, member_id, models, fetched_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(member_kind, member_id) DO UPDATE SET models = ?3, fetched_at = ?4",
+        params![member.kind.as_str(), member.id, models, fetched_at],
+    )
+    .map_err(|e| format!("Failed to save model catalog: {}", e))?;
+    Ok(())
+}
+
+fn store_aliases(
+    conn: &Connection,
+    member: &FailoverMember,
+    aliases: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let aliases = serde_json::to_string(aliases).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO model_catalogs (member_kind, member_id, aliases)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(member_kind, member_id) DO UPDATE SET aliases = ?3",
+        params![member.kind.as_str(), member.id, aliases],
+    )
+    .map_err(|e| format!("Failed to save model aliases: {}", e))?;
+    Ok(())
+}
+
+/// Model ids of an OpenAI or Anthropic `/v1/models` response
+fn parse_model_list(data: &serde_json::Value) -> Vec<String> {
+    data["data"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|model| model.as_str().or_else(|| model["id"].as_str()))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn fetch_v1_models(config: &ProviderConfig) -> Result<Vec<String>, String> {
+    let client = http_client::client().map_err(|e| e.to_string())?;
+    let mut request = client
+        .get(format!(
+            "{}/v1/models",
+            config.base_url.trim_end_matches('/')
+        ))
+        // Anthropic pages its model list
+        .query(&[("limit", "1000")])
+        .header("anthropic-version", "2023-06-01")
+        .timeout(Duration::from_secs(FETCH_TIMEOUT_SECS));
+    if let Some(token) = &config.auth_token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(key) = &config.api_key {
+        request = request.header("x-api-key", key);
+    }
+
+    let response = request.send_with_retry().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to list models: {}", response.status()));
+    }
+    let data: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    let models = parse_model_list(&data);
+    if models.is_empty() {
+        return Err("The model list is empty".to_string());
+    }
+    Ok(models)
+}
+
+/// Fetch a member's models: through the station adapter, falling back to
+/// `/v1/models` with the applied token
+async fn fetch_models(app: &AppHandle, member: &FailoverMember) -> Result<Vec<String>, String> {
+    let mut errors = Vec::new();
+
+    if member.kind == FailoverMemberKind::Station {
+        let station = {
+            let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+            let manager_lock = state.lock().map_err(|e| e.to_string())?;
+            let manager = manager_lock
+                .as_ref()
+                .ok_or("Relay station manager not initialized")?;
+            manager
+                .get_station(&member.id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Station not found: {}", member.id))?
+        };
+        let adapter = create_adapter(&station.adapter);
+        match tokio::time::timeout(
+            Duration::from_secs(FETCH_TIMEOUT_SECS),
+            adapter.list_models(&station),
+        )
+        .await
+        {
+            Ok(Ok(models)) if !models.is_empty() => return Ok(models),
+            Ok(Ok(_)) => errors.push("The model list is empty".to_string()),
+            Ok(Err(e)) => errors.push(e.to_string()),
+            Err(_) => errors.push("Timed out".to_string()),
+        }
+    }
+
+    match resolve_member(app, member) {
+        Some(config) => match fetch_v1_models(&config).await {
+            Ok(models) => return Ok(models),
+            Err(e) => errors.push(e),
+        },
+        None if member.kind == FailoverMemberKind::Station => {
+            errors.push("No configuration has been applied from this station".to_string())
+        }
+        None => errors.push(format!("Provider not found: {}", member.id)),
+    }
+    Err(errors.join("; "))
+}
+
+/// Fetch and store a member's models, keeping its aliases
+async fn refresh_catalog(app: &AppHandle, member: &FailoverMember) -> Result<ModelCatalog, String> {
+    let mut models = fetch_models(app, member).await?;
+    models.sort();
+    models.dedup();
+
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store_models(&conn, member, &models, Utc::now().timestamp())?;
+    Ok(load_catalog(&conn, member)?.unwrap_or_else(|| ModelCatalog::empty(member)))
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Model ids that look like what was meant by `model`
+fn suggestions(models: &[String], model: &str) -> Vec<String> {
+    let needle = model.to_lowercase();
+    let mut scored: Vec<(usize, &String)> = models
+        .iter()
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let distance = edit_distance(&needle, &lower);
+            if lower.contains(&needle) {
+                Some((0, candidate))
+            } else if distance <= MAX_SUGGESTION_DISTANCE {
+                Some((distance, candidate))
+            } else {
+                None
+            }
+        })
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, model)| model.clone())
+        .collect()
+}
+
+/// Map an alias to its model id and check the id against the catalog; an
+/// unknown or empty catalog accepts any model
+pub(crate) fn resolve_model(catalog: Option<&ModelCatalog>, model: &str) -> Result<String, String> {
+    let model = model.trim();
+    let Some(catalog) = catalog else {
+        return Ok(model.to_string());
+    };
+    let resolved = catalog
+        .aliases
+        .get(model)
+        .map(String::as_str)
+        .unwrap_or(model);
+    if catalog.models.is_empty() || catalog.models.iter().any(|m| m == resolved) {
+        return Ok(resolved.to_string());
+    }
+
+    let suggestions = suggestions(&catalog.models, resolved);
+    if suggestions.is_empty() {
+        Err(format!("Model '{}' is not available", resolved))
+    } else {
+        Err(format!(
+            "Model '{}' is not available. Did you mean: {}?",
+            resolved,
+            suggestions.join(", ")
+        ))
+    }
+}
+
+/// Resolve and validate a model for a station or provider, refreshing a
+/// missing or outdated catalog first. Models of a member whose catalog
+/// cannot be fetched are left unchecked.
+pub(crate) async fn validate_member_model(
+    app: &AppHandle,
+    member: &FailoverMember,
+    model: &str,
+) -> Result<String, String> {
+    let cached = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_catalog(&conn, member)?
+    };
+
+    let now = Utc::now().timestamp();
+    let is_stale = cached
+        .as_ref()
+        .and_then(|c| c.fetched_at)
+        .is_none_or(|fetched_at| now - fetched_at > CATALOG_MAX_AGE_SECS);
+    // Configurations applied from a station are not saved providers
+    let is_known =
+        member.kind == FailoverMemberKind::Station || find_provider_config(&member.id).is_ok();
+
+    let catalog = if is_stale && is_known {
+        match refresh_catalog(app, member).await {
+            Ok(catalog) => Some(catalog),
+            Err(e) => {
+                warn!("Failed to refresh model catalog of {}: {}", member.key(), e);
+                cached
+            }
+        }
+    } else {
+        cached
+    };
+    resolve_model(catalog.as_ref(), model)
+}
+
+/// Get every cached model catalog
+#[command]
+pub fn get_model_catalogs(db: State<'_, AgentDb>) -> Result<Vec<ModelCatalog>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT member_kind, member_id, models, aliases, fetched_at
+             FROM model_catalogs ORDER BY member_kind, member_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let catalogs = stmt
+        .query_map([], catalog_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(catalogs)
+}
+
+/// Fetch the models of a station or provider now
+#[command]
+pub async fn refresh_model_catalog(
+    app: AppHandle,
+    member: FailoverMember,
+) -> Result<ModelCatalog, String> {
+    refresh_catalog(&app, &member).await
+}
+
+/// Replace the model aliases of a station or provider
+#[command]
+pub fn save_model_aliases(
+    db: State<'_, AgentDb>,
+    member: FailoverMember,
+    aliases: BTreeMap<String, String>,
+) -> Result<ModelCatalog, String> {
+    let aliases: BTreeMap<String, String> = aliases
+        .into_iter()
+        .map(|(alias, model)| (alias.trim().to_string(), model.trim().to_string()))
+        .collect();
+    if aliases
+        .iter()
+        .any(|(alias, model)| alias.is_empty() || model.is_empty())
+    {
+        return Err("Aliases and model ids must not be empty".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let catalog = load_catalog(&conn, &member)?;
+    if let Some(catalog) = catalog.as_ref().filter(|c| !c.models.is_empty()) {
+        if let Some((alias, model)) = aliases
+            .iter()
+            .find(|(_, model)| !catalog.models.contains(model))
+        {
+            return Err(format!(
+                "Alias '{}' points to '{}', which is not available",
+                alias, model
+            ));
+        }
+    }
+    store_aliases(&conn, &member, &aliases)?;
+    Ok(load_catalog(&conn, &member)?.unwrap_or_else(|| ModelCatalog::empty(&member)))
+}
+
+/// Resolve an alias and check that a station or provider offers the model,
+/// returning the model id to use
+#[command]
+pub async fn validate_model(
+    app: AppHandle,
+    member: FailoverMember,
+    model: String,
+) -> Result<String, String> {
+    validate_member_model(&app, &member, &model).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> ModelCatalog {
+        ModelCatalog {
+            member: FailoverMember {
+                kind: FailoverMemberKind::Station,
+                id: "s".to_string(),
+            },
+            models: vec![
+                "claude-3-5-haiku-20241022".to_string(),
+                "claude-sonnet-4-20250514".to_string(),
+            ],
+            aliases: BTreeMap::from([(
+                "sonnet".to_string(),
+                "claude-sonnet-4-20250514".to_string(),
+            )]),
+            fetched_at: Some(0),
+        }
+    }
+
+    #[test]
+    fn resolves_aliases_and_known_models() {
+        let catalog = catalog();
+        assert_eq!(
+            resolve_model(Some(&catalog), "sonnet").unwrap(),
+            "claude-sonnet-4-20250514"
+        );
+        assert_eq!(
+            resolve_model(Some(&catalog), " claude-3-5-haiku-20241022 ").unwrap(),
+            "claude-3-5-haiku-20241022"
+        );
+        assert_eq!(resolve_model(None, "anything").unwrap(), "anything");
+    }
+
+    #[test]
+    fn suggests_models_for_typos() {
+        let error = resolve_model(Some(&catalog()), "claude-sonet-4-20250514").unwrap_err();
+        assert!(error.contains("Did you mean: claude-sonnet-4-20250514"));
+        assert!(resolve_model(Some(&catalog()), "gpt-4o")
+            .unwrap_err()
+            .ends_with("is not available"));
+    }
+
+    #[test]
+    fn parses_openai_and_plain_model_lists() {
+        let data = serde_json::json!({ "data": [{ "id": "a", "object": "model" }, "b"] });
+        assert_eq!(parse_model_list(&data), vec!["a", "b"]);
+    }
+}
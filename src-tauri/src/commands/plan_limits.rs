@@ -0,0 +1,446 @@
+use chrono::{DateTime, Duration, Local};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::usage_index::lock_synced_index;
+
+/// Length of a Claude Code usage window when no plan is configured
+pub const DEFAULT_WINDOW_HOURS: f64 = 5.0;
+
+const HOUR_MS: i64 = 3_600_000;
+const WEEK_MS: i64 = 7 * 24 * HOUR_MS;
+
+/// Usage caps of a subscription plan
+///
+/// Tokens are input plus output tokens; cache reads and writes don't count
+/// towards the caps. Messages are assistant responses.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanProfile {
+    pub id: String,
+    pub name: String,
+    /// Length of the rolling usage window
+    #[serde(default = "default_window_hours")]
+    pub window_hours: f64,
+    #[serde(default)]
+    pub window_token_limit: Option<u64>,
+    #[serde(default)]
+    pub window_message_limit: Option<u64>,
+    #[serde(default)]
+    pub weekly_token_limit: Option<u64>,
+    #[serde(default)]
+    pub weekly_message_limit: Option<u64>,
+}
+
+/// The user-editable plan file (`~/.claude/plan_limits.json`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanLimitsConfig {
+    /// Id of the profile matching the user's subscription; no caps apply when unset
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    #[serde(default = "default_plan_profiles")]
+    pub profiles: Vec<PlanProfile>,
+}
+
+/// Usage inside one rolling window
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanWindowStatus {
+    /// "session" (the short window) or "weekly"
+    pub kind: String,
+    pub window_start: String,
+    pub resets_at: String,
+    pub tokens_used: u64,
+    pub token_limit: Option<u64>,
+    pub messages_used: u64,
+    pub message_limit: Option<u64>,
+    /// Highest usage percentage across the window's caps
+    pub percent_used: f64,
+    /// When a cap is hit at the current burn rate, if before the reset
+    pub projected_exhaustion: Option<String>,
+}
+
+/// Plan usage across all windows
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanUsageStatus {
+    pub profile: Option<PlanProfile>,
+    /// Counted tokens per minute over the last hour
+    pub token_burn_rate: f64,
+    /// Messages per minute over the last hour
+    pub message_burn_rate: f64,
+    pub windows: Vec<PlanWindowStatus>,
+    /// Earliest projected exhaustion across all windows
+    pub estimated_depletion_time: Option<String>,
+    /// Earliest reset across all windows
+    pub next_reset_time: Option<String>,
+}
+
+fn default_window_hours() -> f64 {
+    DEFAULT_WINDOW_HOURS
+}
+
+fn profile(
+    id: &str,
+    name: &str,
+    window_token_limit: u64,
+    window_message_limit: u64,
+) -> PlanProfile {
+    PlanProfile {
+        id: id.to_string(),
+        name: name.to_string(),
+        window_hours: DEFAULT_WINDOW_HOURS,
+        window_token_limit: Some(window_token_limit),
+        window_message_limit: Some(window_message_limit),
+        weekly_token_limit: None,
+        weekly_message_limit: None,
+    }
+}
+
+/// Built-in profiles. Anthropic doesn't publish exact caps, so these are
+/// community estimates meant to be adjusted in the plan file.
+pub fn default_plan_profiles() -> Vec<PlanProfile> {
+    vec![
+        profile("pro", "Pro", 19_000, 45),
+        profile("max5", "Max 5x", 88_000, 225),
+        profile("max20", "Max 20x", 220_000, 900),
+    ]
+}
+
+impl Default for PlanLimitsConfig {
+    fn default() -> Self {
+        Self {
+            active_profile: None,
+            profiles: default_plan_profiles(),
+        }
+    }
+}
+
+impl PlanLimitsConfig {
+    pub fn active(&self) -> Option<&PlanProfile> {
+        let id = self.active_profile.as_deref()?;
+        self.profiles.iter().find(|p| p.id == id)
+    }
+
+    /// Length of the short usage window in hours
+    pub fn window_hours(&self) -> f64 {
+        self.active()
+            .map(|p| p.window_hours)
+            .filter(|hours| *hours > 0.0)
+            .unwrap_or(DEFAULT_WINDOW_HOURS)
+    }
+}
+
+fn get_plan_limits_path() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude")
+        .join("plan_limits.json"))
+}
+
+/// Load the plan config, falling back to the defaults when the file is
+/// missing or invalid
+pub fn load_plan_limits_config() -> PlanLimitsConfig {
+    let Ok(path) = get_plan_limits_path() else {
+        return PlanLimitsConfig::default();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return PlanLimitsConfig::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Invalid plan file {:?}: {}; using defaults", path, e);
+        PlanLimitsConfig::default()
+    })
+}
+
+/// Start of the window containing `now_ms`, if any
+///
+/// A window opens with the first message after the previous one closed and
+/// lasts `length_ms`. Session windows start at the top of the hour.
+///
+/// Only the last two window lengths are scanned: the current window opened
+/// within the last one, and the one before settles whether an earlier window
+/// still covered its first messages.
+fn current_window_start(
+    conn: &Connection,
+    length_ms: i64,
+    round_to_hour: bool,
+    now_ms: i64,
+) -> Result<Option<i64>, String> {
+    let since_ms = now_ms - 2 * length_ms - if round_to_hour { HOUR_MS } else { 0 };
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp_ms FROM usage_entries
+             WHERE timestamp_ms >= ?1 AND timestamp_ms <= ?2
+             ORDER BY timestamp_ms",
+        )
+        .map_err(|e| e.to_string())?;
+    let timestamps = stmt
+        .query_map(params![since_ms, now_ms], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?;
+
+    let mut start: Option<i64> = None;
+    for timestamp in timestamps {
+        let timestamp = timestamp.map_err(|e| e.to_string())?;
+        match start {
+            Some(s) if timestamp < s + length_ms => {}
+            _ => {
+                start = Some(if round_to_hour {
+                    timestamp - timestamp.rem_euclid(HOUR_MS)
+                } else {
+                    timestamp
+                });
+            }
+        }
+    }
+
+    Ok(start.filter(|s| now_ms < s + length_ms))
+}
+
+/// Counted tokens and messages since `since_ms`
+fn usage_since(conn: &Connection, since_ms: i64) -> Result<(u64, u64), String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(input_tokens + output_tokens), 0), COUNT(*)
+         FROM usage_entries WHERE timestamp_ms >= ?1",
+        params![since_ms],
+        |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// When `used` reaches `limit` at `rate` per minute, or None if it doesn't before `resets_at`
+fn project_exhaustion(
+    used: u64,
+    limit: Option<u64>,
+    rate: f64,
+    now: DateTime<Local>,
+    resets_at: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    let limit = limit?;
+    if used >= limit {
+        return Some(now);
+    }
+    if rate <= 0.0 {
+        return None;
+    }
+    let minutes = (limit - used) as f64 / rate;
+    let exhaustion = now + Duration::seconds((minutes * 60.0) as i64);
+    (exhaustion < resets_at).then_some(exhaustion)
+}
+
+fn percent(used: u64, limit: Option<u64>) -> f64 {
+    match limit {
+        Some(limit) if limit > 0 => used as f64 / limit as f64 * 100.0,
+        _ => 0.0,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn window_status(
+    conn: &Connection,
+    kind: &str,
+    length_ms: i64,
+    round_to_hour: bool,
+    token_limit: Option<u64>,
+    message_limit: Option<u64>,
+    rates: (f64, f64),
+    now: DateTime<Local>,
+) -> Result<Option<PlanWindowStatus>, String> {
+    let now_ms = now.timestamp_millis();
+    let Some(start_ms) = current_window_start(conn, length_ms, round_to_hour, now_ms)? else {
+        return Ok(None);
+    };
+    let (Some(start), Some(resets_at)) = (
+        DateTime::from_timestamp_millis(start_ms).map(|dt| dt.with_timezone(&Local)),
+        DateTime::from_timestamp_millis(start_ms + length_ms).map(|dt| dt.with_timezone(&Local)),
+    ) else {
+        return Ok(None);
+    };
+
+    let (tokens_used, messages_used) = usage_since(conn, start_ms)?;
+    let projected_exhaustion = [
+        project_exhaustion(tokens_used, token_limit, rates.0, now, resets_at),
+        project_exhaustion(messages_used, message_limit, rates.1, now, resets_at),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+
+    Ok(Some(PlanWindowStatus {
+        kind: kind.to_string(),
+        window_start: start.to_rfc3339(),
+        resets_at: resets_at.to_rfc3339(),
+        tokens_used,
+        token_limit,
+        messages_used,
+        message_limit,
+        percent_used: percent(tokens_used, token_limit).max(percent(messages_used, message_limit)),
+        projected_exhaustion: projected_exhaustion.map(|t| t.to_rfc3339()),
+    }))
+}
+
+/// Compute the rolling plan windows from the indexed usage entries
+pub(crate) fn compute_plan_usage(
+    conn: &Connection,
+    config: &PlanLimitsConfig,
+    now: DateTime<Local>,
+) -> Result<PlanUsageStatus, String> {
+    let profile = config.active().cloned();
+
+    // Burn rate over the last hour
+    let (recent_tokens, recent_messages) = usage_since(conn, (now - Duration::hours(1)).timestamp_millis())?;
+    let rates = (recent_tokens as f64 / 60.0, recent_messages as f64 / 60.0);
+
+    let window_ms = (config.window_hours() * HOUR_MS as f64) as i64;
+    let mut windows = Vec::new();
+    windows.extend(window_status(
+        conn,
+        "session",
+        window_ms,
+        true,
+        profile.as_ref().and_then(|p| p.window_token_limit),
+        profile.as_ref().and_then(|p| p.window_message_limit),
+        rates,
+        now,
+    )?);
+    if profile
+        .as_ref()
+        .is_some_and(|p| p.weekly_token_limit.is_some() || p.weekly_message_limit.is_some())
+    {
+        windows.extend(window_status(
+            conn,
+            "weekly",
+            WEEK_MS,
+            false,
+            profile.as_ref().and_then(|p| p.weekly_token_limit),
+            profile.as_ref().and_then(|p| p.weekly_message_limit),
+            rates,
+            now,
+        )?);
+    }
+
+    // RFC 3339 strings in the same offset sort chronologically
+    let estimated_depletion_time = windows
+        .iter()
+        .filter_map(|w| w.projected_exhaustion.clone())
+        .min();
+    let next_reset_time = windows.iter().map(|w| w.resets_at.clone()).min();
+
+    Ok(PlanUsageStatus {
+        profile,
+        token_burn_rate: rates.0,
+        message_burn_rate: rates.1,
+        windows,
+        estimated_depletion_time,
+        next_reset_time,
+    })
+}
+
+/// Get the plan profiles and the active one
+#[command]
+pub fn get_plan_limits_config() -> Result<PlanLimitsConfig, String> {
+    Ok(load_plan_limits_config())
+}
+
+/// Save the plan profiles and the active one
+#[command]
+pub fn save_plan_limits_config(config: PlanLimitsConfig) -> Result<(), String> {
+    if let Some(id) = &config.active_profile {
+        if !config.profiles.iter().any(|p| &p.id == id) {
+            return Err(format!("Unknown plan profile: {}", id));
+        }
+    }
+
+    let path = get_plan_limits_path()?;
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write plan file: {}", e))
+}
+
+/// Get usage of the current plan windows
+#[command]
+pub fn get_plan_usage(db: State<'_, AgentDb>) -> Result<PlanUsageStatus, String> {
+    let conn = lock_synced_index(&db)?;
+    compute_plan_usage(&conn, &load_plan_limits_config(), Local::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_MS: i64 = 5 * HOUR_MS;
+    // 2026-01-05T10:00:00Z
+    const T0: i64 = 1_767_607_200_000;
+
+    fn db_with(timestamps: &[i64]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE usage_entries (timestamp_ms INTEGER NOT NULL)", [])
+            .unwrap();
+        for timestamp in timestamps {
+            conn.execute("INSERT INTO usage_entries (timestamp_ms) VALUES (?1)", params![timestamp])
+                .unwrap();
+        }
+        conn
+    }
+
+    fn minutes(n: i64) -> i64 {
+        n * 60_000
+    }
+
+    #[test]
+    fn session_window_starts_at_the_hour_of_its_first_message() {
+        let conn = db_with(&[T0 + minutes(30), T0 + minutes(90)]);
+        let start = current_window_start(&conn, WINDOW_MS, true, T0 + minutes(120)).unwrap();
+        assert_eq!(start, Some(T0));
+
+        // Messages after `now` don't open a window yet
+        let conn = db_with(&[T0 + minutes(30)]);
+        assert_eq!(current_window_start(&conn, WINDOW_MS, true, T0).unwrap(), None);
+    }
+
+    #[test]
+    fn window_rolls_over_after_its_length() {
+        let conn = db_with(&[T0 + minutes(30), T0 + minutes(290), T0 + minutes(305)]);
+
+        // 10:00-15:00 has closed and nothing was sent since 15:00
+        assert_eq!(
+            current_window_start(&conn, WINDOW_MS, true, T0 + minutes(301)).unwrap(),
+            None
+        );
+        // The 15:05 message opens the next window at 15:00, the 14:50 one
+        // still belonged to the first
+        assert_eq!(
+            current_window_start(&conn, WINDOW_MS, true, T0 + minutes(310)).unwrap(),
+            Some(T0 + minutes(300))
+        );
+        // Without rounding the first window lasts until 15:30 and takes both
+        assert_eq!(
+            current_window_start(&conn, WINDOW_MS, false, T0 + minutes(310)).unwrap(),
+            Some(T0 + minutes(30))
+        );
+    }
+
+    #[test]
+    fn earlier_window_decides_where_the_current_one_opens() {
+        // The first window (9:00-14:00) covers the 13:00 message, so the
+        // current one opens at 14:30 although 13:00 is within the last five hours
+        let conn = db_with(&[
+            T0 - minutes(60),
+            T0 + minutes(180),
+            T0 + minutes(270),
+        ]);
+        assert_eq!(
+            current_window_start(&conn, WINDOW_MS, false, T0 + minutes(400)).unwrap(),
+            Some(T0 + minutes(270))
+        );
+    }
+
+    #[test]
+    fn history_before_the_scanned_range_is_ignored() {
+        let conn = db_with(&[T0 - 10 * WEEK_MS, T0 - minutes(30)]);
+        assert_eq!(
+            current_window_start(&conn, WEEK_MS, false, T0).unwrap(),
+            Some(T0 - minutes(30))
+        );
+    }
+}
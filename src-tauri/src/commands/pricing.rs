@@ -0,0 +1,377 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::usage_index::sync_usage_index;
+use crate::claude_messages::Usage;
+
+/// Price of one model (USD per million tokens)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    /// Model name pattern. `*` and `?` are wildcards; a pattern without
+    /// wildcards matches any model name containing it. Case-insensitive.
+    pub pattern: String,
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    /// First day (inclusive) this price applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<NaiveDate>,
+    /// Last day (inclusive) this price applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_until: Option<NaiveDate>,
+}
+
+/// Prices for requests sent through a specific API base URL (e.g. a relay station)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PricingOverride {
+    /// Matched as a prefix of the entry's api_base_url, ignoring case and trailing slashes
+    pub api_base_url: String,
+    /// Factor applied to costs of this endpoint, e.g. a station's rate ratio
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f64,
+    /// Model prices checked before the global table
+    #[serde(default)]
+    pub models: Vec<ModelPrice>,
+}
+
+/// The user-editable pricing file (`~/.claude/pricing.json` or `~/.claude/pricing.yaml`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PricingConfig {
+    /// Currency all costs are shown in
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// USD -> `currency` conversion rate
+    #[serde(default = "default_multiplier")]
+    pub currency_multiplier: f64,
+    /// Global model prices; the first matching entry wins
+    #[serde(default = "default_model_prices")]
+    pub models: Vec<ModelPrice>,
+    #[serde(default)]
+    pub overrides: Vec<PricingOverride>,
+}
+
+/// Pricing config together with where it was loaded from
+#[derive(Debug, Serialize)]
+pub struct PricingConfigInfo {
+    pub path: String,
+    pub from_file: bool,
+    pub config: PricingConfig,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+fn price(pattern: &str, input: f64, output: f64, cache_write: f64, cache_read: f64) -> ModelPrice {
+    ModelPrice {
+        pattern: pattern.to_string(),
+        input,
+        output,
+        cache_write,
+        cache_read,
+        effective_from: None,
+        effective_until: None,
+    }
+}
+
+/// Built-in Anthropic list prices
+pub fn default_model_prices() -> Vec<ModelPrice> {
+    vec![
+        // Claude 4
+        price("opus-4", 15.0, 75.0, 18.75, 1.50),
+        price("sonnet-4", 3.0, 15.0, 3.75, 0.30),
+        // Claude 3.7 / 3.5
+        price("sonnet-3.7", 3.0, 15.0, 3.75, 0.30),
+        price("3-7-sonnet", 3.0, 15.0, 3.75, 0.30),
+        price("sonnet-3.5", 3.0, 15.0, 3.75, 0.30),
+        price("3-5-sonnet", 3.0, 15.0, 3.75, 0.30),
+        price("haiku-3.5", 0.80, 4.0, 1.0, 0.08),
+        price("3-5-haiku", 0.80, 4.0, 1.0, 0.08),
+    ]
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            currency: default_currency(),
+            currency_multiplier: default_multiplier(),
+            models: default_model_prices(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl ModelPrice {
+    fn matches(&self, model: &str, date: Option<NaiveDate>) -> bool {
+        if let Some(date) = date {
+            if self.effective_from.is_some_and(|from| date < from)
+                || self.effective_until.is_some_and(|until| date > until)
+            {
+                return false;
+            }
+        }
+
+        let pattern = self.pattern.to_lowercase();
+        let model = model.to_lowercase();
+        if pattern.contains('*') || pattern.contains('?') {
+            glob::Pattern::new(&pattern)
+                .map(|p| p.matches(&model))
+                .unwrap_or(false)
+        } else {
+            model.contains(&pattern)
+        }
+    }
+
+    fn cost(&self, usage: &Usage) -> f64 {
+        // Prices are per million tokens
+        (usage.input() as f64 * self.input
+            + usage.output() as f64 * self.output
+            + usage.cache_creation() as f64 * self.cache_write
+            + usage.cache_read() as f64 * self.cache_read)
+            / 1_000_000.0
+    }
+}
+
+fn normalize_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_lowercase()
+}
+
+impl PricingConfig {
+    fn find_override(&self, api_base_url: &str) -> Option<&PricingOverride> {
+        let url = normalize_url(api_base_url);
+        self.overrides
+            .iter()
+            .find(|o| url.starts_with(&normalize_url(&o.api_base_url)))
+    }
+
+    /// Price table matching `model`, whether it came from the endpoint
+    /// override, and the multiplier of this endpoint
+    fn resolve_price(
+        &self,
+        model: &str,
+        api_base_url: &str,
+        date: Option<NaiveDate>,
+    ) -> (Option<(&ModelPrice, bool)>, f64) {
+        let endpoint = self.find_override(api_base_url);
+        let multiplier = endpoint.map(|o| o.multiplier).unwrap_or(1.0) * self.currency_multiplier;
+
+        let price = endpoint
+            .and_then(|o| o.models.iter().find(|p| p.matches(model, date)))
+            .map(|price| (price, true))
+            .or_else(|| {
+                self.models
+                    .iter()
+                    .find(|p| p.matches(model, date))
+                    .map(|price| (price, false))
+            });
+        (price, multiplier)
+    }
+
+    /// Per-token price table for `model`, with the multiplier turning its
+    /// USD amounts into the configured currency for this endpoint
+    pub fn model_price(
+        &self,
+        model: &str,
+        api_base_url: &str,
+        date: Option<NaiveDate>,
+    ) -> Option<(&ModelPrice, f64)> {
+        let (price, multiplier) = self.resolve_price(model, api_base_url, date);
+        price.map(|(price, _)| (price, multiplier))
+    }
+
+    /// Cost of one usage entry in the configured currency
+    ///
+    /// Endpoint-specific prices win over the cost Claude Code reported itself,
+    /// which in turn wins over the global price table. Unknown models cost 0
+    /// rather than a guessed price.
+    pub fn cost(
+        &self,
+        model: &str,
+        api_base_url: &str,
+        date: Option<NaiveDate>,
+        usage: &Usage,
+        reported_cost: Option<f64>,
+    ) -> f64 {
+        let (price, multiplier) = self.resolve_price(model, api_base_url, date);
+        let base_cost = match (price, reported_cost) {
+            (Some((price, true)), _) | (Some((price, false)), None) => price.cost(usage),
+            (_, Some(reported)) => reported,
+            (None, None) => 0.0,
+        };
+
+        base_cost * multiplier
+    }
+
+    /// Stable hash used to detect pricing changes that require recomputing costs
+    pub fn fingerprint(&self) -> String {
+        let serialized = serde_json::to_string(self).unwrap_or_default();
+        format!("{:x}", Sha256::digest(serialized.as_bytes()))
+    }
+}
+
+fn get_pricing_dir() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude"))
+}
+
+/// Existing pricing file, preferring JSON over YAML
+fn find_pricing_file() -> Result<Option<PathBuf>, String> {
+    let dir = get_pricing_dir()?;
+    Ok(["pricing.json", "pricing.yaml", "pricing.yml"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists()))
+}
+
+fn parse_pricing_file(path: &PathBuf) -> Result<PricingConfig, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read pricing file {:?}: {}", path, e))?;
+    let is_json = path.extension().and_then(|e| e.to_str()) == Some("json");
+    if is_json {
+        serde_json::from_str(&content).map_err(|e| format!("Invalid pricing file {:?}: {}", path, e))
+    } else {
+        serde_yaml::from_str(&content).map_err(|e| format!("Invalid pricing file {:?}: {}", path, e))
+    }
+}
+
+/// Load the pricing config, falling back to the built-in defaults when the
+/// file is missing or invalid
+pub fn load_pricing_config() -> PricingConfig {
+    match find_pricing_file() {
+        Ok(Some(path)) => parse_pricing_file(&path).unwrap_or_else(|e| {
+            log::warn!("{}; using built-in prices", e);
+            PricingConfig::default()
+        }),
+        _ => PricingConfig::default(),
+    }
+}
+
+/// Get the active pricing config
+#[command]
+pub fn get_pricing_config() -> Result<PricingConfigInfo, String> {
+    let existing = find_pricing_file()?;
+    let config = match &existing {
+        Some(path) => parse_pricing_file(path)?,
+        None => PricingConfig::default(),
+    };
+
+    Ok(PricingConfigInfo {
+        from_file: existing.is_some(),
+        path: existing
+            .unwrap_or(get_pricing_dir()?.join("pricing.json"))
+            .to_string_lossy()
+            .to_string(),
+        config,
+    })
+}
+
+/// Save the pricing config and recompute the cost of all indexed usage
+#[command]
+pub fn save_pricing_config(db: State<'_, AgentDb>, config: PricingConfig) -> Result<(), String> {
+    // Keep the format of an existing file
+    let path = match find_pricing_file()? {
+        Some(path) => path,
+        None => get_pricing_dir()?.join("pricing.json"),
+    };
+
+    let content = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?
+    } else {
+        serde_yaml::to_string(&config).map_err(|e| e.to_string())?
+    };
+    fs::write(&path, content).map_err(|e| format!("Failed to write pricing file: {}", e))?;
+
+    sync_usage_index(&db)?;
+    Ok(())
+}
+
+/// Remove the pricing file so the built-in prices apply again
+#[command]
+pub fn reset_pricing_config(db: State<'_, AgentDb>) -> Result<(), String> {
+    if let Some(path) = find_pricing_file()? {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove pricing file: {}", e))?;
+    }
+
+    sync_usage_index(&db)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u64, output: u64) -> Usage {
+        Usage {
+            input_tokens: Some(input),
+            output_tokens: Some(output),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_builtin_models() {
+        let config = PricingConfig::default();
+        let cost = config.cost("claude-opus-4-20250514", "https://api.anthropic.com", None, &usage(1_000_000, 0), None);
+        assert_eq!(cost, 15.0);
+        let cost = config.cost("claude-3-5-haiku-20241022", "https://api.anthropic.com", None, &usage(0, 1_000_000), None);
+        assert_eq!(cost, 4.0);
+        assert_eq!(config.cost("gpt-4o", "https://api.anthropic.com", None, &usage(10, 10), None), 0.0);
+    }
+
+    #[test]
+    fn applies_effective_dates_and_overrides() {
+        let mut config = PricingConfig::default();
+        let mut old = price("claude-*-4*", 1.0, 1.0, 0.0, 0.0);
+        old.effective_until = NaiveDate::from_ymd_opt(2025, 1, 1);
+        config.models.insert(0, old);
+        config.currency_multiplier = 2.0;
+        config.overrides.push(PricingOverride {
+            api_base_url: "https://relay.example.com/".to_string(),
+            multiplier: 0.5,
+            models: vec![],
+        });
+
+        let tokens = usage(1_000_000, 0);
+        let before = NaiveDate::from_ymd_opt(2024, 12, 31);
+        let after = NaiveDate::from_ymd_opt(2025, 2, 1);
+        assert_eq!(config.cost("claude-sonnet-4", "https://api.anthropic.com", before, &tokens, None), 2.0);
+        assert_eq!(config.cost("claude-sonnet-4", "https://api.anthropic.com", after, &tokens, None), 6.0);
+        assert_eq!(config.cost("claude-sonnet-4", "https://relay.example.com/v1", after, &tokens, None), 3.0);
+        assert_eq!(config.cost("claude-sonnet-4", "https://api.anthropic.com", after, &tokens, Some(1.0)), 2.0);
+    }
+
+    #[test]
+    fn endpoint_prices_win_over_reported_cost() {
+        let mut config = PricingConfig::default();
+        config.overrides.push(PricingOverride {
+            api_base_url: "https://relay.example.com".to_string(),
+            multiplier: 0.5,
+            models: vec![price("claude-sonnet-4*", 1.0, 2.0, 0.0, 0.0)],
+        });
+
+        let tokens = usage(1_000_000, 1_000_000);
+        let relay = "https://relay.example.com/v1";
+        assert_eq!(config.cost("claude-sonnet-4-5", relay, None, &tokens, Some(10.0)), 1.5);
+        assert_eq!(config.cost("claude-opus-4-1", relay, None, &tokens, Some(10.0)), 5.0);
+        assert_eq!(config.cost("claude-opus-4-1", relay, None, &usage(1_000_000, 0), None), 7.5);
+
+        let (sonnet, multiplier) = config.model_price("claude-sonnet-4-5", relay, None).unwrap();
+        assert_eq!((sonnet.input, multiplier), (1.0, 0.5));
+        let (opus, _) = config.model_price("claude-opus-4-1", relay, None).unwrap();
+        assert_eq!(opus.input, 15.0);
+        assert!(config.model_price("gpt-4o", relay, None).is_none());
+    }
+}
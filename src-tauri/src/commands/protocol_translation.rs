@@ -0,0 +1,745 @@
+//! Translation between the Anthropic Messages API, which the Claude CLI
+//! speaks, and the OpenAI chat completions API that many relay stations
+//! expose instead.
+//!
+//! A station opts in through its `adapter_config`:
+//!
+//! ```json
+//! { "api_format": "openai", "model_map": { "claude-sonnet-4-20250514": "claude-sonnet-4" } }
+//! ```
+//!
+//! The routing proxy then sends `/v1/messages` requests to the station's
+//! `/v1/chat/completions` (or `openai_path`) and converts the response,
+//! streamed or not, back into Messages API JSON or SSE events.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use super::relay_stations::RelayStation;
+
+const DEFAULT_OPENAI_PATH: &str = "/v1/chat/completions";
+
+/// Wire format of a station's model API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiFormat {
+    #[default]
+    Anthropic,
+    Openai,
+}
+
+/// How requests to a station have to be translated
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StationTranslation {
+    pub api_format: ApiFormat,
+    /// Path of the chat completions endpoint, relative to the base URL
+    pub openai_path: String,
+    /// Anthropic model names to the station's model names
+    pub model_map: HashMap<String, String>,
+}
+
+impl StationTranslation {
+    /// Read the translation settings from a station's `adapter_config`
+    pub fn from_station(station: &RelayStation) -> Self {
+        let config = station.adapter_config.as_ref();
+        let get = |key: &str| config.and_then(|c| c.get(key)).cloned();
+        StationTranslation {
+            api_format: get("api_format")
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+            openai_path: get("openai_path")
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_else(|| DEFAULT_OPENAI_PATH.to_string()),
+            model_map: get("model_map")
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_openai(&self) -> bool {
+        self.api_format == ApiFormat::Openai
+    }
+
+    fn map_model(&self, model: &str) -> String {
+        self.model_map
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| model.to_string())
+    }
+}
+
+/// Text of a string or an array of text blocks; other blocks are dropped
+fn joined_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn image_part(block: &Value) -> Option<Value> {
+    let source = block.get("source")?;
+    let url = match source.get("type").and_then(|t| t.as_str()) {
+        Some("base64") => format!(
+            "data:{};base64,{}",
+            source.get("media_type")?.as_str()?,
+            source.get("data")?.as_str()?
+        ),
+        Some("url") => source.get("url")?.as_str()?.to_string(),
+        _ => return None,
+    };
+    Some(json!({ "type": "image_url", "image_url": { "url": url } }))
+}
+
+/// Convert one Anthropic message into one or more OpenAI messages; tool
+/// results become separate `tool` messages
+fn convert_message(message: &Value, out: &mut Vec<Value>) {
+    let role = message
+        .get("role")
+        .and_then(|r| r.as_str())
+        .unwrap_or("user");
+    let content = message.get("content").cloned().unwrap_or(Value::Null);
+    let blocks = match content {
+        Value::String(text) => {
+            out.push(json!({ "role": role, "content": text }));
+            return;
+        }
+        Value::Array(blocks) => blocks,
+        _ => return,
+    };
+
+    if role == "assistant" {
+        let text = joined_text(&Value::Array(blocks.clone()));
+        let tool_calls: Vec<Value> = blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+            .map(|b| {
+                json!({
+                    "id": b.get("id").cloned().unwrap_or(Value::Null),
+                    "type": "function",
+                    "function": {
+                        "name": b.get("name").cloned().unwrap_or(Value::Null),
+                        "arguments": b.get("input").unwrap_or(&json!({})).to_string(),
+                    }
+                })
+            })
+            .collect();
+        let mut converted = Map::new();
+        converted.insert("role".into(), json!("assistant"));
+        converted.insert(
+            "content".into(),
+            if text.is_empty() {
+                Value::Null
+            } else {
+                json!(text)
+            },
+        );
+        if !tool_calls.is_empty() {
+            converted.insert("tool_calls".into(), Value::Array(tool_calls));
+        }
+        out.push(Value::Object(converted));
+        return;
+    }
+
+    let mut parts = Vec::new();
+    for block in &blocks {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => parts.push(json!({
+                "type": "text",
+                "text": block.get("text").cloned().unwrap_or(json!("")),
+            })),
+            Some("image") => parts.extend(image_part(block)),
+            Some("tool_result") => {
+                let mut text = joined_text(block.get("content").unwrap_or(&Value::Null));
+                if block.get("is_error").and_then(|e| e.as_bool()) == Some(true) {
+                    text = format!("Error: {}", text);
+                }
+                out.push(json!({
+                    "role": "tool",
+                    "tool_call_id": block.get("tool_use_id").cloned().unwrap_or(Value::Null),
+                    "content": text,
+                }));
+            }
+            _ => {}
+        }
+    }
+    if !parts.is_empty() {
+        out.push(json!({ "role": role, "content": parts }));
+    }
+}
+
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    match choice.get("type").and_then(|t| t.as_str())? {
+        "auto" => Some(json!("auto")),
+        "any" => Some(json!("required")),
+        "none" => Some(json!("none")),
+        "tool" => Some(json!({
+            "type": "function",
+            "function": { "name": choice.get("name").cloned().unwrap_or(Value::Null) }
+        })),
+        _ => None,
+    }
+}
+
+/// Convert a Messages API request body into a chat completions request body
+pub fn anthropic_to_openai_request(
+    request: &Value,
+    translation: &StationTranslation,
+) -> Result<Value, String> {
+    let model = request
+        .get("model")
+        .and_then(|m| m.as_str())
+        .ok_or("Request has no model")?;
+
+    let mut messages = Vec::new();
+    if let Some(system) = request.get("system") {
+        let text = joined_text(system);
+        if !text.is_empty() {
+            messages.push(json!({ "role": "system", "content": text }));
+        }
+    }
+    for message in request
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .ok_or("Request has no messages")?
+    {
+        convert_message(message, &mut messages);
+    }
+
+    let mut body = Map::new();
+    body.insert("model".into(), json!(translation.map_model(model)));
+    body.insert("messages".into(), Value::Array(messages));
+    for key in ["max_tokens", "temperature", "top_p"] {
+        if let Some(value) = request.get(key) {
+            body.insert(key.into(), value.clone());
+        }
+    }
+    if let Some(stop) = request.get("stop_sequences") {
+        body.insert("stop".into(), stop.clone());
+    }
+    if let Some(tools) = request.get("tools").and_then(|t| t.as_array()) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.get("name").cloned().unwrap_or(Value::Null),
+                        "description": tool.get("description").cloned().unwrap_or(json!("")),
+                        "parameters": tool.get("input_schema").cloned().unwrap_or(json!({ "type": "object" })),
+                    }
+                })
+            })
+            .collect();
+        if !tools.is_empty() {
+            body.insert("tools".into(), Value::Array(tools));
+        }
+    }
+    if let Some(choice) = request.get("tool_choice").and_then(convert_tool_choice) {
+        body.insert("tool_choice".into(), choice);
+    }
+    if request.get("stream").and_then(|s| s.as_bool()) == Some(true) {
+        body.insert("stream".into(), json!(true));
+        body.insert("stream_options".into(), json!({ "include_usage": true }));
+    }
+    Ok(Value::Object(body))
+}
+
+fn stop_reason(finish_reason: &str) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        _ => "end_turn",
+    }
+}
+
+/// OpenAI prompt tokens include cached ones; Anthropic reports them apart
+fn anthropic_usage(usage: Option<&Value>) -> Value {
+    let get = |v: Option<&Value>| v.and_then(|v| v.as_u64()).unwrap_or(0);
+    let usage = usage.filter(|u| u.is_object());
+    let prompt = get(usage.and_then(|u| u.get("prompt_tokens")));
+    let cached = get(usage.and_then(|u| u.pointer("/prompt_tokens_details/cached_tokens")));
+    json!({
+        "input_tokens": prompt.saturating_sub(cached),
+        "output_tokens": get(usage.and_then(|u| u.get("completion_tokens"))),
+        "cache_read_input_tokens": cached,
+    })
+}
+
+fn message_id(openai_id: Option<&Value>) -> String {
+    match openai_id.and_then(|id| id.as_str()) {
+        Some(id) => format!("msg_{}", id),
+        None => format!("msg_{}", uuid::Uuid::new_v4().simple()),
+    }
+}
+
+/// Convert a chat completions response into a Messages API response
+pub fn openai_to_anthropic_response(response: &Value, model: &str) -> Value {
+    let choice = response.pointer("/choices/0");
+    let message = choice.and_then(|c| c.get("message"));
+
+    let mut content = Vec::new();
+    if let Some(text) = message
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .filter(|t| !t.is_empty())
+    {
+        content.push(json!({ "type": "text", "text": text }));
+    }
+    for call in message
+        .and_then(|m| m.get("tool_calls"))
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let arguments = call
+            .pointer("/function/arguments")
+            .and_then(|a| a.as_str())
+            .unwrap_or("{}");
+        content.push(json!({
+            "type": "tool_use",
+            "id": call.get("id").cloned().unwrap_or(Value::Null),
+            "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+            "input": serde_json::from_str::<Value>(arguments).unwrap_or_else(|_| json!({})),
+        }));
+    }
+
+    json!({
+        "id": message_id(response.get("id")),
+        "type": "message",
+        "role": "assistant",
+        "model": model,
+        "content": content,
+        "stop_reason": stop_reason(
+            choice
+                .and_then(|c| c.get("finish_reason"))
+                .and_then(|f| f.as_str())
+                .unwrap_or("stop")
+        ),
+        "stop_sequence": null,
+        "usage": anthropic_usage(response.get("usage")),
+    })
+}
+
+/// Convert an error response into the Messages API error shape
+pub fn openai_to_anthropic_error(status: u16, body: &[u8]) -> Value {
+    let error_type = match status {
+        400 | 422 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        529 => "overloaded_error",
+        _ => "api_error",
+    };
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .or_else(|| v.get("message"))
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| String::from_utf8_lossy(body).into_owned());
+    json!({ "type": "error", "error": { "type": error_type, "message": message } })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OpenBlock {
+    Text,
+    /// A tool_use block fed by the OpenAI tool call with this index
+    Tool(u64),
+}
+
+/// Turns a chat completions SSE stream into Messages API SSE events
+pub struct StreamTranslator {
+    model: String,
+    pending: Vec<u8>,
+    started: bool,
+    finished: bool,
+    next_index: usize,
+    open_block: Option<OpenBlock>,
+    stop_reason: &'static str,
+    usage: Option<Value>,
+}
+
+impl StreamTranslator {
+    pub fn new(model: &str) -> Self {
+        StreamTranslator {
+            model: model.to_string(),
+            pending: Vec::new(),
+            started: false,
+            finished: false,
+            next_index: 0,
+            open_block: None,
+            stop_reason: "end_turn",
+            usage: None,
+        }
+    }
+
+    fn event(out: &mut Vec<u8>, data: Value) {
+        let kind = data.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        out.extend_from_slice(format!("event: {}\ndata: {}\n\n", kind, data).as_bytes());
+    }
+
+    fn start(&mut self, out: &mut Vec<u8>, id: Option<&Value>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        Self::event(
+            out,
+            json!({
+                "type": "message_start",
+                "message": {
+                    "id": message_id(id),
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": { "input_tokens": 0, "output_tokens": 0 },
+                }
+            }),
+        );
+    }
+
+    fn close_block(&mut self, out: &mut Vec<u8>) {
+        if self.open_block.take().is_some() {
+            Self::event(
+                out,
+                json!({ "type": "content_block_stop", "index": self.next_index - 1 }),
+            );
+        }
+    }
+
+    fn open(&mut self, out: &mut Vec<u8>, block: OpenBlock, content_block: Value) {
+        self.close_block(out);
+        Self::event(
+            out,
+            json!({
+                "type": "content_block_start",
+                "index": self.next_index,
+                "content_block": content_block,
+            }),
+        );
+        self.open_block = Some(block);
+        self.next_index += 1;
+    }
+
+    fn delta(out: &mut Vec<u8>, index: usize, delta: Value) {
+        Self::event(
+            out,
+            json!({ "type": "content_block_delta", "index": index, "delta": delta }),
+        );
+    }
+
+    fn chunk(&mut self, chunk: &Value, out: &mut Vec<u8>) {
+        self.start(out, chunk.get("id"));
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            self.usage = Some(usage.clone());
+        }
+        let Some(choice) = chunk.pointer("/choices/0") else {
+            return;
+        };
+        let delta = choice.get("delta");
+
+        if let Some(text) = delta
+            .and_then(|d| d.get("content"))
+            .and_then(|c| c.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            if self.open_block != Some(OpenBlock::Text) {
+                self.open(out, OpenBlock::Text, json!({ "type": "text", "text": "" }));
+            }
+            Self::delta(
+                out,
+                self.next_index - 1,
+                json!({ "type": "text_delta", "text": text }),
+            );
+        }
+
+        for call in delta
+            .and_then(|d| d.get("tool_calls"))
+            .and_then(|t| t.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let index = call.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+            if self.open_block != Some(OpenBlock::Tool(index)) {
+                let id = call
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("toolu_{}", uuid::Uuid::new_v4().simple()));
+                self.open(
+                    out,
+                    OpenBlock::Tool(index),
+                    json!({
+                        "type": "tool_use",
+                        "id": id,
+                        "name": call.pointer("/function/name").cloned().unwrap_or(json!("")),
+                        "input": {},
+                    }),
+                );
+            }
+            if let Some(arguments) = call
+                .pointer("/function/arguments")
+                .and_then(|a| a.as_str())
+                .filter(|a| !a.is_empty())
+            {
+                Self::delta(
+                    out,
+                    self.next_index - 1,
+                    json!({ "type": "input_json_delta", "partial_json": arguments }),
+                );
+            }
+        }
+
+        if let Some(reason) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+            self.stop_reason = stop_reason(reason);
+        }
+    }
+
+    fn line(&mut self, line: &[u8], out: &mut Vec<u8>) {
+        let Some(data) = line.strip_prefix(b"data:") else {
+            return;
+        };
+        let data = data.trim_ascii();
+        if data == b"[DONE]" {
+            out.extend(self.finish());
+        } else if let Ok(chunk) = serde_json::from_slice::<Value>(data) {
+            self.chunk(&chunk, out);
+        }
+    }
+
+    /// Feed raw bytes of the OpenAI stream, returning the Anthropic events they complete
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.pending.extend_from_slice(bytes);
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            self.line(line.trim_ascii(), &mut out);
+        }
+        out
+    }
+
+    /// Close the message; a no-op if `[DONE]` was already seen
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.finished {
+            return out;
+        }
+        let rest = std::mem::take(&mut self.pending);
+        if !rest.trim_ascii().is_empty() {
+            self.line(rest.trim_ascii(), &mut out);
+            if self.finished {
+                return out;
+            }
+        }
+        self.finished = true;
+        self.start(&mut out, None);
+        self.close_block(&mut out);
+        Self::event(
+            &mut out,
+            json!({
+                "type": "message_delta",
+                "delta": { "stop_reason": self.stop_reason, "stop_sequence": null },
+                "usage": anthropic_usage(self.usage.as_ref()),
+            }),
+        );
+        Self::event(&mut out, json!({ "type": "message_stop" }));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn openai() -> StationTranslation {
+        StationTranslation {
+            api_format: ApiFormat::Openai,
+            openai_path: DEFAULT_OPENAI_PATH.to_string(),
+            model_map: HashMap::from([("claude-sonnet-4".to_string(), "sonnet".to_string())]),
+        }
+    }
+
+    /// Parse SSE output into its `data` payloads
+    fn events(sse: &[u8]) -> Vec<Value> {
+        String::from_utf8_lossy(sse)
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn translates_request_with_tools() {
+        let request = json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "system": [{ "type": "text", "text": "Be brief." }],
+            "stream": true,
+            "tools": [{ "name": "read", "description": "Read a file", "input_schema": { "type": "object" } }],
+            "tool_choice": { "type": "any" },
+            "messages": [
+                { "role": "user", "content": "Open a.txt" },
+                { "role": "assistant", "content": [
+                    { "type": "thinking", "thinking": "..." },
+                    { "type": "text", "text": "Reading." },
+                    { "type": "tool_use", "id": "toolu_1", "name": "read", "input": { "path": "a.txt" } }
+                ]},
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": [{ "type": "text", "text": "hello" }] },
+                    { "type": "text", "text": "Summarize it" }
+                ]}
+            ]
+        });
+
+        let body = anthropic_to_openai_request(&request, &openai()).unwrap();
+        assert_eq!(body["model"], "sonnet");
+        assert_eq!(body["tool_choice"], "required");
+        assert_eq!(body["stream_options"]["include_usage"], true);
+        assert_eq!(body["tools"][0]["function"]["name"], "read");
+
+        let messages = body["messages"].as_array().unwrap();
+        let roles: Vec<&str> = messages
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["system", "user", "assistant", "tool", "user"]);
+        assert_eq!(messages[0]["content"], "Be brief.");
+        assert_eq!(messages[2]["content"], "Reading.");
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            r#"{"path":"a.txt"}"#
+        );
+        assert_eq!(messages[3]["tool_call_id"], "toolu_1");
+        assert_eq!(messages[3]["content"], "hello");
+        assert_eq!(messages[4]["content"][0]["text"], "Summarize it");
+    }
+
+    #[test]
+    fn translates_response_and_error() {
+        let response = json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "message": {
+                    "content": "Let me look.",
+                    "tool_calls": [{ "id": "call_1", "function": { "name": "read", "arguments": "{\"path\":\"a\"}" } }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 100, "completion_tokens": 20, "prompt_tokens_details": { "cached_tokens": 40 } }
+        });
+        let message = openai_to_anthropic_response(&response, "claude-sonnet-4");
+        assert_eq!(message["id"], "msg_chatcmpl-1");
+        assert_eq!(message["stop_reason"], "tool_use");
+        assert_eq!(message["content"][1]["input"]["path"], "a");
+        assert_eq!(message["usage"]["input_tokens"], 60);
+        assert_eq!(message["usage"]["cache_read_input_tokens"], 40);
+
+        let error = openai_to_anthropic_error(429, br#"{"error":{"message":"slow down"}}"#);
+        assert_eq!(error["error"]["type"], "rate_limit_error");
+        assert_eq!(error["error"]["message"], "slow down");
+    }
+
+    #[tokio::test]
+    async fn translates_stream_from_mock_server() {
+        let stream = [
+            r#"{"id":"c1","choices":[{"delta":{"role":"assistant","content":"Hi"}}]}"#,
+            r#"{"id":"c1","choices":[{"delta":{"content":" there"}}]}"#,
+            r#"{"id":"c1","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","function":{"name":"read","arguments":""}}]}}]}"#,
+            r#"{"id":"c1","choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"path\":"}}]}}]}"#,
+            r#"{"id":"c1","choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a\"}"}}]},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"c1","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":7}}"#,
+        ]
+        .iter()
+        .map(|chunk| format!("data: {}\n\n", chunk))
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 65536];
+            let n = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..n]).to_string();
+            let head =
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n";
+            socket.write_all(head.as_bytes()).await.unwrap();
+            // Split mid-line to exercise buffering
+            let (first, second) = stream.split_at(stream.len() / 2);
+            socket.write_all(first.as_bytes()).await.unwrap();
+            socket.flush().await.unwrap();
+            socket.write_all(second.as_bytes()).await.unwrap();
+            request
+        });
+
+        let request = json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 64,
+            "stream": true,
+            "messages": [{ "role": "user", "content": "hi" }]
+        });
+        let body = anthropic_to_openai_request(&request, &openai()).unwrap();
+        let mut response = reqwest::Client::new()
+            .post(format!("http://{}{}", address, DEFAULT_OPENAI_PATH))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        let mut translator = StreamTranslator::new("claude-sonnet-4");
+        let mut sse = Vec::new();
+        while let Some(chunk) = response.chunk().await.unwrap() {
+            sse.extend(translator.push(&chunk));
+        }
+        sse.extend(translator.finish());
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("POST /v1/chat/completions"));
+        assert!(received.contains(r#""model":"sonnet""#));
+
+        let events = events(&sse);
+        let kinds: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        assert_eq!(events[0]["message"]["id"], "msg_c1");
+        assert_eq!(events[3]["delta"]["text"], " there");
+        assert_eq!(events[5]["content_block"]["name"], "read");
+        assert_eq!(events[5]["index"], 1);
+        let json: String = events[6..8]
+            .iter()
+            .map(|e| e["delta"]["partial_json"].as_str().unwrap())
+            .collect();
+        assert_eq!(json, r#"{"path":"a"}"#);
+        assert_eq!(events[9]["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events[9]["usage"]["output_tokens"], 7);
+        assert_eq!(events[9]["usage"]["input_tokens"], 12);
+    }
+}
@@ -3,8 +3,11 @@ use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
-use tauri::command;
+use tauri::{command, AppHandle};
 use crate::i18n;
+use super::model_catalog::validate_member_model;
+use super::relay_failover::{FailoverMember, FailoverMemberKind};
+use super::secrets::{delete_secret, is_masked, is_secret_ref, mask_optional, put_secret, resolve_secret_or_keep};
 
 #[command]
 pub fn set_backend_language(language: String) -> Result<String, String> {
@@ -163,14 +166,77 @@ fn load_providers_from_file() -> Result<Vec<ProviderConfig>, String> {
     let providers: Vec<ProviderConfig> = serde_json::from_str(&content)
         .map_err(|e| i18n::t_with_args("provider.parse_config_failed", &[("error", &e.to_string())]))?;
     
-    Ok(providers)
+    // 旧版本以明文保存的密钥自动迁移到加密存储
+    let has_plaintext = providers.iter().any(|p| {
+        [&p.auth_token, &p.api_key]
+            .into_iter()
+            .flatten()
+            .any(|v| !v.is_empty() && !is_secret_ref(v))
+    });
+    if has_plaintext {
+        if let Err(e) = save_providers_to_file(&providers) {
+            log::warn!("Failed to move provider credentials into the secret store: {}", e);
+        }
+    }
+    
+    Ok(providers.into_iter().map(resolve_provider_secrets).collect())
+}
+
+fn provider_secret_id(provider_id: &str, field: &str) -> String {
+    format!("provider:{}:{}", provider_id, field)
+}
+
+// 解密配置中引用的密钥；密钥存储未解锁时保留引用，列表仍可正常显示
+fn resolve_provider_secrets(mut provider: ProviderConfig) -> ProviderConfig {
+    for value in [&mut provider.auth_token, &mut provider.api_key].into_iter().flatten() {
+        *value = resolve_secret_or_keep(std::mem::take(value));
+    }
+    provider
+}
+
+// 将密钥写入加密存储，配置中只保留引用
+fn store_provider_secrets(provider: &ProviderConfig) -> Result<ProviderConfig, String> {
+    let mut stored = provider.clone();
+    for (field, name) in [(&mut stored.auth_token, "auth_token"), (&mut stored.api_key, "api_key")] {
+        let id = provider_secret_id(&provider.id, name);
+        match field {
+            Some(value) => *value = put_secret(&id, value)?,
+            None => delete_secret(&id)?,
+        }
+    }
+    Ok(stored)
+}
+
+impl ProviderConfig {
+    pub fn masked(mut self) -> Self {
+        mask_optional(&mut self.auth_token);
+        mask_optional(&mut self.api_key);
+        self
+    }
+
+    // 密钥仍是未解密的引用（密钥存储已锁定），不能用于请求
+    pub fn has_locked_secrets(&self) -> bool {
+        [&self.auth_token, &self.api_key].into_iter().flatten().any(|v| is_secret_ref(v))
+    }
+}
+
+impl CurrentConfig {
+    pub fn masked(mut self) -> Self {
+        mask_optional(&mut self.anthropic_auth_token);
+        mask_optional(&mut self.anthropic_api_key);
+        self
+    }
 }
 
 // 保存代理商配置到文件
 fn save_providers_to_file(providers: &Vec<ProviderConfig>) -> Result<(), String> {
     let config_path = get_providers_config_path()?;
     
-    let content = serde_json::to_string_pretty(providers)
+    let stored = providers
+        .iter()
+        .map(store_provider_secrets)
+        .collect::<Result<Vec<_>, _>>()?;
+    let content = serde_json::to_string_pretty(&stored)
         .map_err(|e| i18n::t_with_args("provider.serialize_config_failed", &[("error", &e.to_string())]))?;
     
     fs::write(&config_path, content)
@@ -181,24 +247,19 @@ fn save_providers_to_file(providers: &Vec<ProviderConfig>) -> Result<(), String>
 
 // CRUD 操作 - 获取所有代理商配置
 #[command]
-pub fn get_provider_presets() -> Result<Vec<ProviderConfig>, String> {
-    let config_path = get_providers_config_path()?;
+pub fn get_provider_presets(reveal: Option<bool>) -> Result<Vec<ProviderConfig>, String> {
+    let configs = load_providers_from_file()?;
     
-    if !config_path.exists() {
-        return Ok(vec![]);
+    if reveal.unwrap_or(false) {
+        Ok(configs)
+    } else {
+        Ok(configs.into_iter().map(ProviderConfig::masked).collect())
     }
-    
-    let content = fs::read_to_string(&config_path)
-        .map_err(|e| i18n::t_with_args("provider.read_config_failed", &[("error", &e.to_string())]))?;
-    
-    let configs: Vec<ProviderConfig> = serde_json::from_str(&content)
-        .map_err(|e| i18n::t_with_args("provider.invalid_config_format", &[("error", &e.to_string())]))?;
-    
-    Ok(configs)
 }
 
 #[command]
-pub fn add_provider_config(config: ProviderConfig) -> Result<String, String> {
+pub async fn add_provider_config(app: AppHandle, config: ProviderConfig) -> Result<String, String> {
+    validate_provider_model(&app, &config).await?;
     let mut providers = load_providers_from_file()?;
     
     // 检查ID是否已存在
@@ -214,7 +275,8 @@ pub fn add_provider_config(config: ProviderConfig) -> Result<String, String> {
 
 // CRUD 操作 - 更新代理商配置
 #[command]
-pub fn update_provider_config(config: ProviderConfig) -> Result<String, String> {
+pub async fn update_provider_config(app: AppHandle, config: ProviderConfig) -> Result<String, String> {
+    validate_provider_model(&app, &config).await?;
     let mut providers = load_providers_from_file()?;
     
     let index = providers.iter().position(|p| p.id == config.id)
@@ -236,23 +298,37 @@ pub fn delete_provider_config(id: String) -> Result<String, String> {
     
     let deleted_config = providers.remove(index);
     save_providers_to_file(&providers)?;
+    delete_secret(&provider_secret_id(&id, "auth_token"))?;
+    delete_secret(&provider_secret_id(&id, "api_key"))?;
     
     Ok(i18n::t_with_args("provider.delete_success", &[("name", &deleted_config.name)]))
 }
 
 // CRUD 操作 - 获取单个代理商配置
 #[command]
-pub fn get_provider_config(id: String) -> Result<ProviderConfig, String> {
+pub fn get_provider_config(id: String, reveal: Option<bool>) -> Result<ProviderConfig, String> {
+    let config = find_provider_config(&id)?;
+    Ok(if reveal.unwrap_or(false) { config } else { config.masked() })
+}
+
+// 获取单个代理商配置（密钥为明文，仅供后端使用）
+pub fn find_provider_config(id: &str) -> Result<ProviderConfig, String> {
     let providers = load_providers_from_file()?;
     
     providers.into_iter()
         .find(|p| p.id == id)
-        .ok_or_else(|| i18n::t_with_args("provider.config_not_found", &[("id", &id)]))
+        .ok_or_else(|| i18n::t_with_args("provider.config_not_found", &[("id", id)]))
 }
 
 #[command]
-pub fn get_current_provider_config() -> Result<CurrentConfig, String> {
-    Ok(CurrentConfig {
+pub fn get_current_provider_config(reveal: Option<bool>) -> Result<CurrentConfig, String> {
+    let config = current_provider_config();
+    Ok(if reveal.unwrap_or(false) { config } else { config.masked() })
+}
+
+// 读取当前生效的配置（密钥为明文，仅供后端使用）
+pub fn current_provider_config() -> CurrentConfig {
+    CurrentConfig {
         anthropic_base_url: get_settings_env("ANTHROPIC_BASE_URL")
             .or_else(|| env::var("ANTHROPIC_BASE_URL").ok()),
         anthropic_auth_token: get_settings_env("ANTHROPIC_AUTH_TOKEN")
@@ -261,11 +337,45 @@ pub fn get_current_provider_config() -> Result<CurrentConfig, String> {
             .or_else(|| env::var("ANTHROPIC_API_KEY").ok()),
         anthropic_model: get_settings_env("ANTHROPIC_MODEL")
             .or_else(|| env::var("ANTHROPIC_MODEL").ok()),
-    })
+    }
+}
+
+// 解析模型别名，并按模型目录校验模型；未填写模型时返回 None
+async fn validate_provider_model(app: &AppHandle, config: &ProviderConfig) -> Result<Option<String>, String> {
+    match config.model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        Some(model) => {
+            let member = FailoverMember { kind: FailoverMemberKind::Provider, id: config.id.clone() };
+            validate_member_model(app, &member, model).await.map(Some)
+        }
+        None => Ok(None),
+    }
 }
 
 #[command]
-pub async fn switch_provider_config(config: ProviderConfig) -> Result<String, String> {
+pub async fn switch_provider_config(app: AppHandle, mut config: ProviderConfig) -> Result<String, String> {
+    if let Some(model) = validate_provider_model(&app, &config).await? {
+        config.model = Some(model);
+    }
+
+    apply_provider_config(config).await
+}
+
+// 写入配置（不校验模型，供故障转移等后端流程使用）
+pub async fn apply_provider_config(mut config: ProviderConfig) -> Result<String, String> {
+    // 前端传回的脱敏密钥替换为已保存的明文
+    if [&config.auth_token, &config.api_key].into_iter().flatten().any(|v| is_masked(v)) {
+        let saved = find_provider_config(&config.id)?;
+        if config.auth_token.as_deref().is_some_and(is_masked) {
+            config.auth_token = saved.auth_token;
+        }
+        if config.api_key.as_deref().is_some_and(is_masked) {
+            config.api_key = saved.api_key;
+        }
+    }
+    if config.has_locked_secrets() {
+        return Err(i18n::t("provider.secret_store_locked"));
+    }
+    
     // 更新 Raw Settings 中的环境变量
     update_settings_env("ANTHROPIC_BASE_URL", Some(&config.base_url))?;
     
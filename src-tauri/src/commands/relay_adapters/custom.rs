@@ -72,4 +72,8 @@ impl StationAdapter for CustomAdapter {
     async fn get_user_groups(&self, _station: &RelayStation) -> Result<serde_json::Value> {
         Err(anyhow!("User groups not available for custom configurations"))
     }
+
+    async fn list_models(&self, _station: &RelayStation) -> Result<Vec<String>> {
+        Err(anyhow!("Model listing not available for custom configurations"))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,874 @@
+//! Declarative adapter: relay platforms described in YAML or JSON instead of Rust.
+//!
+//! Definitions live in `~/.claude/relay_adapters/*.{yaml,yml,json}` and a
+//! station of type `declarative` picks one through `adapter_config.definition`.
+//! Each `StationAdapter` method maps to a request template whose `{{var}}`
+//! placeholders are filled from the station and the call arguments, and
+//! JSONPath-style paths (`$.data.items[0].name`) map the response back.
+//!
+//! ```yaml
+//! id: my-relay
+//! name: My Relay
+//! headers:
+//!   Authorization: "Bearer {{token}}"
+//! user_info:
+//!   path: /api/user/self
+//!   response:
+//!     root: $.data
+//!     success: $.success
+//!     fields:
+//!       user_id: $.id
+//!       balance_remaining: { path: $.quota, scale: 0.000002 }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::commands::http_client::{station_client, SendWithRetry};
+use crate::commands::relay_stations::{
+    RelayStation, RelayStationToken, StationInfo, UserInfo, StationLogEntry,
+    LogPaginationResponse, TokenPaginationResponse, ConnectionTestResult, CreateTokenRequest, UpdateTokenRequest,
+    StationAdapter
+};
+
+const DEFINITIONS_DIR: &str = "relay_adapters";
+const DEFINITION_EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
+const TEST_TIMEOUT_SECS: u64 = 10;
+
+/// A relay platform described as request templates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdapterDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub station_info: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub user_info: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub logs: Option<EndpointTemplate>,
+    /// Falls back to `station_info`, then to the station URL
+    #[serde(default)]
+    pub test_connection: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub list_tokens: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub create_token: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub update_token: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub delete_token: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub toggle_token: Option<EndpointTemplate>,
+    #[serde(default)]
+    pub user_groups: Option<EndpointTemplate>,
+    /// Items are model ids, or objects mapped through an `id` field
+    #[serde(default)]
+    pub list_models: Option<EndpointTemplate>,
+}
+
+/// One HTTP request and how to read its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointTemplate {
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Appended to the station URL unless it is an absolute URL
+    pub path: String,
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// A string that is only a placeholder keeps the type of its value
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub response: ResponseMapping,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseMapping {
+    /// Object the fields are read from, the whole response by default
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Item list of paginated endpoints
+    #[serde(default)]
+    pub items: Option<String>,
+    /// Total item count of paginated endpoints, 0 (unknown) when unmapped
+    #[serde(default)]
+    pub total: Option<String>,
+    /// Flag that has to be true for the call to count as successful
+    #[serde(default)]
+    pub success: Option<String>,
+    /// Error message reported when `success` is false
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Target field name -> where to find it in the root or item
+    #[serde(default)]
+    pub fields: HashMap<String, FieldMapping>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldMapping {
+    Path(String),
+    Rule(FieldRule),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldRule {
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Translates values, keys are compared as strings (e.g. `"1": active`)
+    #[serde(default)]
+    pub map: HashMap<String, Value>,
+    /// Multiplies numbers, e.g. quota units to dollars
+    #[serde(default)]
+    pub scale: Option<f64>,
+    /// Used when the path is missing or null
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+/// Definition file as listed in the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdapterDefinitionSummary {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub path: String,
+    /// Adapter methods the definition implements
+    pub methods: Vec<String>,
+    /// Parse error, the definition cannot be used
+    pub error: Option<String>,
+}
+
+impl AdapterDefinition {
+    fn methods(&self) -> Vec<String> {
+        [
+            ("station_info", &self.station_info),
+            ("user_info", &self.user_info),
+            ("logs", &self.logs),
+            ("test_connection", &self.test_connection),
+            ("list_tokens", &self.list_tokens),
+            ("create_token", &self.create_token),
+            ("update_token", &self.update_token),
+            ("delete_token", &self.delete_token),
+            ("toggle_token", &self.toggle_token),
+            ("user_groups", &self.user_groups),
+            ("list_models", &self.list_models),
+        ]
+        .into_iter()
+        .filter(|(_, endpoint)| endpoint.is_some())
+        .map(|(name, _)| name.to_string())
+        .collect()
+    }
+
+    fn endpoint<'a>(&'a self, name: &str, endpoint: &'a Option<EndpointTemplate>) -> Result<&'a EndpointTemplate> {
+        endpoint
+            .as_ref()
+            .ok_or_else(|| anyhow!("Adapter '{}' does not define {}", self.name, name))
+    }
+}
+
+pub fn definitions_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+    Ok(home.join(".claude").join(DEFINITIONS_DIR))
+}
+
+pub fn parse_definition(content: &str) -> Result<AdapterDefinition> {
+    // YAML is a superset of JSON, so one parser covers both formats
+    let definition: AdapterDefinition = serde_yaml::from_str(content)?;
+    if definition.id.trim().is_empty() {
+        return Err(anyhow!("Adapter definition has an empty id"));
+    }
+    Ok(definition)
+}
+
+fn definition_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    path.extension()
+                        .and_then(|ext| ext.to_str())
+                        .map(|ext| DEFINITION_EXTENSIONS.contains(&ext))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn load_file(path: &Path) -> Result<AdapterDefinition> {
+    parse_definition(&fs::read_to_string(path)?)
+}
+
+/// Load the definition with the given id from the user directory
+pub fn find_definition(id: &str) -> Result<AdapterDefinition> {
+    let dir = definitions_dir()?;
+    for path in definition_files(&dir) {
+        match load_file(&path) {
+            Ok(definition) if definition.id == id => return Ok(definition),
+            Ok(_) => {}
+            Err(e) => warn!("Skipping adapter definition {:?}: {}", path, e),
+        }
+    }
+    Err(anyhow!("Adapter definition '{}' not found in {:?}", id, dir))
+}
+
+/// Select a value with a JSONPath-style path: `$`, `.key`, `[0]` and `['key']`
+pub fn select<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.trim();
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    let mut current = value;
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let end = index.find(']')?;
+            let key = index[..end].trim();
+            let quoted = key
+                .strip_prefix('\'')
+                .and_then(|k| k.strip_suffix('\''))
+                .or_else(|| key.strip_prefix('"').and_then(|k| k.strip_suffix('"')));
+            current = match quoted {
+                Some(name) => current.get(name)?,
+                None => current.get(key.parse::<usize>().ok()?)?,
+            };
+            rest = &index[end + 1..];
+        } else {
+            let segment = rest.strip_prefix('.').unwrap_or(rest);
+            let end = segment.find(['.', '[']).unwrap_or(segment.len());
+            if end > 0 {
+                current = current.get(&segment[..end])?;
+            }
+            rest = &segment[end..];
+        }
+    }
+    Some(current)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Fill `{{var}}` placeholders; unknown variables become empty strings
+pub fn render(template: &str, vars: &Value, encode: bool) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let text = select(vars, after[..end].trim()).map(value_text).unwrap_or_default();
+        if encode {
+            out.push_str(&urlencoding::encode(&text));
+        } else {
+            out.push_str(&text);
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_value(template: &Value, vars: &Value) -> Value {
+    match template {
+        Value::String(s) => {
+            let trimmed = s.trim();
+            let placeholder = trimmed
+                .strip_prefix("{{")
+                .and_then(|name| name.strip_suffix("}}"))
+                .filter(|name| !name.contains("{{") && !name.contains("}}"));
+            match placeholder {
+                Some(name) => select(vars, name.trim()).cloned().unwrap_or(Value::Null),
+                None => Value::String(render(s, vars, false)),
+            }
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| render_value(item, vars)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), render_value(value, vars)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+impl FieldMapping {
+    fn apply(&self, source: &Value) -> Option<Value> {
+        let present = |path: &str| select(source, path).filter(|v| !v.is_null()).cloned();
+        match self {
+            FieldMapping::Path(path) => present(path),
+            FieldMapping::Rule(rule) => {
+                let mut value = rule.path.as_deref().and_then(present);
+                if let Some(mapped) = value.as_ref().and_then(|v| rule.map.get(&value_text(v))) {
+                    value = Some(mapped.clone());
+                }
+                if let (Some(scale), Some(number)) = (rule.scale, value.as_ref().and_then(Value::as_f64)) {
+                    value = Some(json!(number * scale));
+                }
+                value.or_else(|| rule.default.clone())
+            }
+        }
+    }
+}
+
+/// Typed access to the mapped fields of one response object
+struct Mapped<'a> {
+    source: &'a Value,
+    fields: &'a HashMap<String, FieldMapping>,
+}
+
+impl<'a> Mapped<'a> {
+    fn new(source: &'a Value, mapping: &'a ResponseMapping) -> Self {
+        Self { source, fields: &mapping.fields }
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        self.fields.get(name)?.apply(self.source)
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        self.get(name).map(|v| value_text(&v))
+    }
+
+    fn i64(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Bool(b) => Some(b as i64),
+            _ => None,
+        }
+    }
+
+    fn f64(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Value::Bool(b) => Some(b),
+            Value::Number(n) => n.as_f64().map(|f| f != 0.0),
+            Value::String(s) => match s.trim() {
+                "true" | "1" => Some(true),
+                "false" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn raw(&self, key: &str) -> Option<HashMap<String, Value>> {
+        Some(HashMap::from([(key.to_string(), self.source.clone())]))
+    }
+}
+
+fn mapping_root<'a>(data: &'a Value, mapping: &ResponseMapping) -> Result<&'a Value> {
+    match mapping.root.as_deref() {
+        Some(path) => select(data, path).ok_or_else(|| anyhow!("Response has no '{}'", path)),
+        None => Ok(data),
+    }
+}
+
+fn mapping_items<'a>(data: &'a Value, mapping: &ResponseMapping) -> Result<&'a Vec<Value>> {
+    let path = mapping.items.as_deref().unwrap_or("$");
+    select(data, path)
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("Response has no item list at '{}'", path))
+}
+
+/// Reported total, or 0 when unknown. A page's length is no fallback: the
+/// station may serve fewer items than requested, so callers page until a
+/// page adds nothing new.
+fn mapping_total(data: &Value, mapping: &ResponseMapping) -> i64 {
+    mapping
+        .total
+        .as_deref()
+        .and_then(|path| select(data, path))
+        .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+        .unwrap_or(0)
+}
+
+/// Template variables shared by every request of a station
+fn station_vars(station: &RelayStation, extra: Value) -> Value {
+    let mut vars = json!({
+        "token": station.system_token,
+        "user_id": station.user_id.clone().unwrap_or_default(),
+        "station": {
+            "id": station.id,
+            "name": station.name,
+            "api_url": station.api_url,
+        },
+        "config": station.adapter_config,
+        "now": chrono::Utc::now().timestamp(),
+    });
+    if let (Some(vars), Value::Object(extra)) = (vars.as_object_mut(), extra) {
+        vars.extend(extra);
+    }
+    vars
+}
+
+fn station_definition(station: &RelayStation) -> Result<AdapterDefinition> {
+    let id = station
+        .adapter_config
+        .as_ref()
+        .and_then(|config| config.get("definition"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Station '{}' has no adapter definition selected", station.name))?;
+    find_definition(id)
+}
+
+async fn send(
+    definition: &AdapterDefinition,
+    endpoint: &EndpointTemplate,
+    station: &RelayStation,
+    vars: &Value,
+    timeout: Option<Duration>,
+) -> Result<(reqwest::StatusCode, Value)> {
+    let path = render(&endpoint.path, vars, true);
+    let url = if path.starts_with("http://") || path.starts_with("https://") {
+        path
+    } else {
+        format!("{}/{}", station.api_url.trim_end_matches('/'), path.trim_start_matches('/'))
+    };
+    let method = reqwest::Method::from_bytes(endpoint.method.to_uppercase().as_bytes())?;
+
+    let client = station_client(station)?;
+    let mut request = client.request(method, &url);
+    let mut headers = definition.headers.clone();
+    headers.extend(endpoint.headers.clone());
+    for (name, value) in headers {
+        let value = render(&value, vars, false);
+        // Headers built from unset variables are left out
+        if !value.trim().is_empty() {
+            request = request.header(name, value);
+        }
+    }
+    if !endpoint.query.is_empty() {
+        let query: Vec<(String, String)> = endpoint
+            .query
+            .iter()
+            .map(|(key, value)| (key.clone(), render(value, vars, false)))
+            .collect();
+        request = request.query(&query);
+    }
+    if let Some(body) = &endpoint.body {
+        request = request.json(&render_value(body, vars));
+    }
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+
+    let response = request.send_with_retry().await?;
+    let status = response.status();
+    let text = response.text().await?;
+    let data = if text.trim().is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+    };
+    Ok((status, data))
+}
+
+fn check_success(name: &str, status: reqwest::StatusCode, data: &Value, mapping: &ResponseMapping) -> Result<()> {
+    if !status.is_success() {
+        return Err(anyhow!("Failed to {}: {}", name, status));
+    }
+    if let Some(path) = mapping.success.as_deref() {
+        let ok = select(data, path)
+            .map(|v| v.as_bool().unwrap_or_else(|| !v.is_null() && v != &json!(0)))
+            .unwrap_or(false);
+        if !ok {
+            let message = mapping
+                .message
+                .as_deref()
+                .and_then(|p| select(data, p))
+                .map(value_text)
+                .unwrap_or_else(|| "request was not successful".to_string());
+            return Err(anyhow!("Failed to {}: {}", name, message));
+        }
+    }
+    Ok(())
+}
+
+async fn call(
+    definition: &AdapterDefinition,
+    name: &str,
+    endpoint: &EndpointTemplate,
+    station: &RelayStation,
+    vars: &Value,
+) -> Result<Value> {
+    let (status, data) = send(definition, endpoint, station, vars, None).await?;
+    check_success(name, status, &data, &endpoint.response)?;
+    Ok(data)
+}
+
+fn map_token(mapped: &Mapped, station: &RelayStation, fallback_id: Option<&str>, fallback_enabled: bool) -> RelayStationToken {
+    RelayStationToken {
+        id: mapped
+            .string("id")
+            .or_else(|| fallback_id.map(|id| id.to_string()))
+            .unwrap_or_default(),
+        station_id: station.id.clone(),
+        name: mapped.string("name").unwrap_or_default(),
+        token: mapped.string("token").unwrap_or_default(),
+        user_id: mapped.string("user_id"),
+        enabled: mapped.bool("enabled").unwrap_or(fallback_enabled),
+        expires_at: mapped.i64("expires_at"),
+        group: mapped.string("group"),
+        remain_quota: mapped.i64("remain_quota"),
+        unlimited_quota: mapped.bool("unlimited_quota"),
+        metadata: mapped.raw("raw"),
+        created_at: mapped.i64("created_at").unwrap_or(0),
+    }
+}
+
+/// Adapter driven by a definition file chosen per station
+pub struct DeclarativeAdapter;
+
+impl DeclarativeAdapter {
+    async fn token_call(
+        &self,
+        station: &RelayStation,
+        name: &str,
+        select_endpoint: fn(&AdapterDefinition) -> &Option<EndpointTemplate>,
+        extra: Value,
+        fallback_id: Option<&str>,
+        fallback_enabled: bool,
+    ) -> Result<RelayStationToken> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint(name, select_endpoint(&definition))?;
+        let data = call(&definition, name, endpoint, station, &station_vars(station, extra)).await?;
+        let root = mapping_root(&data, &endpoint.response)?;
+        Ok(map_token(&Mapped::new(root, &endpoint.response), station, fallback_id, fallback_enabled))
+    }
+}
+
+#[async_trait::async_trait]
+impl StationAdapter for DeclarativeAdapter {
+    async fn get_station_info(&self, station: &RelayStation) -> Result<StationInfo> {
+        let definition = station_definition(station)?;
+        let Some(endpoint) = &definition.station_info else {
+            return Ok(StationInfo {
+                name: station.name.clone(),
+                announcement: None,
+                api_url: station.api_url.clone(),
+                version: None,
+                metadata: Some(HashMap::from([(
+                    "adapter_definition".to_string(),
+                    Value::String(definition.id.clone()),
+                )])),
+                quota_per_unit: None,
+            });
+        };
+
+        let data = call(&definition, "get station info", endpoint, station, &station_vars(station, json!({}))).await?;
+        let mapped = Mapped::new(mapping_root(&data, &endpoint.response)?, &endpoint.response);
+        Ok(StationInfo {
+            name: mapped.string("name").unwrap_or_else(|| station.name.clone()),
+            announcement: mapped.string("announcement"),
+            api_url: station.api_url.clone(),
+            version: mapped.string("version"),
+            metadata: mapped.raw("response"),
+            quota_per_unit: mapped.i64("quota_per_unit"),
+        })
+    }
+
+    async fn get_user_info(&self, station: &RelayStation, user_id: &str) -> Result<UserInfo> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint("user_info", &definition.user_info)?;
+        let user_id = if user_id.is_empty() {
+            station.user_id.clone().unwrap_or_default()
+        } else {
+            user_id.to_string()
+        };
+
+        let vars = station_vars(station, json!({ "user_id": user_id }));
+        let data = call(&definition, "get user info", endpoint, station, &vars).await?;
+        let mapped = Mapped::new(mapping_root(&data, &endpoint.response)?, &endpoint.response);
+        Ok(UserInfo {
+            user_id: mapped.string("user_id").unwrap_or(user_id),
+            username: mapped.string("username"),
+            email: mapped.string("email").filter(|s| !s.is_empty()),
+            balance_remaining: mapped.f64("balance_remaining"),
+            amount_used: mapped.f64("amount_used"),
+            request_count: mapped.i64("request_count"),
+            status: mapped.string("status"),
+            metadata: mapped.raw("response"),
+        })
+    }
+
+    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>) -> Result<LogPaginationResponse> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint("logs", &definition.logs)?;
+        let page = page.unwrap_or(1).max(1);
+        let page_size = page_size.unwrap_or(10);
+
+        let vars = station_vars(station, json!({
+            "page": page,
+            "page0": page - 1,
+            "page_size": page_size,
+            "filters": filters.unwrap_or_else(|| json!({})),
+        }));
+        let data = call(&definition, "get logs", endpoint, station, &vars).await?;
+        let logs = mapping_items(&data, &endpoint.response)?;
+
+        let items = logs.iter().map(|log| {
+            let mapped = Mapped::new(log, &endpoint.response);
+            StationLogEntry {
+                id: mapped.string("id").unwrap_or_default(),
+                timestamp: mapped.i64("timestamp").unwrap_or(0),
+                level: mapped.string("level").unwrap_or_else(|| "info".to_string()),
+                message: mapped.string("message").unwrap_or_default(),
+                user_id: mapped.string("user_id"),
+                request_id: mapped.string("request_id"),
+                metadata: mapped.raw("raw"),
+                model_name: mapped.string("model_name"),
+                prompt_tokens: mapped.i64("prompt_tokens"),
+                completion_tokens: mapped.i64("completion_tokens"),
+                quota: mapped.i64("quota"),
+                token_name: mapped.string("token_name"),
+                use_time: mapped.i64("use_time"),
+                is_stream: mapped.bool("is_stream"),
+                channel: mapped.i64("channel"),
+                group: mapped.string("group"),
+            }
+        }).collect::<Vec<_>>();
+
+        Ok(LogPaginationResponse {
+            total: mapping_total(&data, &endpoint.response),
+            items,
+            page,
+            page_size,
+        })
+    }
+
+    async fn test_connection(&self, station: &RelayStation) -> Result<ConnectionTestResult> {
+        let definition = station_definition(station)?;
+        let fallback = EndpointTemplate {
+            method: default_method(),
+            path: String::new(),
+            query: BTreeMap::new(),
+            headers: HashMap::new(),
+            body: None,
+            response: ResponseMapping::default(),
+        };
+        let endpoint = definition
+            .test_connection
+            .as_ref()
+            .or(definition.station_info.as_ref())
+            .unwrap_or(&fallback);
+
+        let start_time = Instant::now();
+        let vars = station_vars(station, json!({}));
+        match send(&definition, endpoint, station, &vars, Some(Duration::from_secs(TEST_TIMEOUT_SECS))).await {
+            Ok((status, data)) => {
+                let response_time = start_time.elapsed().as_millis() as u64;
+                let result = check_success("connect", status, &data, &endpoint.response);
+                Ok(ConnectionTestResult {
+                    success: result.is_ok(),
+                    response_time: Some(response_time),
+                    message: match result {
+                        Ok(()) => "Connection successful".to_string(),
+                        Err(e) => e.to_string(),
+                    },
+                    status_code: Some(status.as_u16()),
+                    details: None,
+                })
+            }
+            Err(e) => Ok(ConnectionTestResult {
+                success: false,
+                response_time: None,
+                message: format!("Connection failed: {}", e),
+                status_code: None,
+                details: None,
+            }),
+        }
+    }
+
+    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint("list_tokens", &definition.list_tokens)?;
+        let page = page.unwrap_or(1).max(1);
+        let size = size.unwrap_or(10);
+
+        let vars = station_vars(station, json!({
+            "page": page,
+            "page0": page - 1,
+            "page_size": size,
+        }));
+        let data = call(&definition, "list tokens", endpoint, station, &vars).await?;
+        let items = mapping_items(&data, &endpoint.response)?
+            .iter()
+            .map(|token| map_token(&Mapped::new(token, &endpoint.response), station, None, true))
+            .collect::<Vec<_>>();
+
+        Ok(TokenPaginationResponse {
+            total: mapping_total(&data, &endpoint.response),
+            items,
+            page,
+            page_size: size,
+        })
+    }
+
+    async fn create_token(&self, station: &RelayStation, token_data: &CreateTokenRequest) -> Result<RelayStationToken> {
+        let extra = json!({ "request": token_data });
+        self.token_call(station, "create_token", |d| &d.create_token, extra, None, true).await
+    }
+
+    async fn update_token(&self, station: &RelayStation, token_id: &str, token_data: &UpdateTokenRequest) -> Result<RelayStationToken> {
+        let extra = json!({ "token_id": token_id, "request": token_data });
+        let enabled = token_data.enabled.unwrap_or(true);
+        self.token_call(station, "update_token", |d| &d.update_token, extra, Some(token_id), enabled).await
+    }
+
+    async fn delete_token(&self, station: &RelayStation, token_id: &str) -> Result<()> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint("delete_token", &definition.delete_token)?;
+        let vars = station_vars(station, json!({ "token_id": token_id }));
+        call(&definition, "delete token", endpoint, station, &vars).await?;
+        Ok(())
+    }
+
+    async fn toggle_token(&self, station: &RelayStation, token_id: &str, enabled: bool) -> Result<RelayStationToken> {
+        let extra = json!({ "token_id": token_id, "enabled": enabled });
+        self.token_call(station, "toggle_token", |d| &d.toggle_token, extra, Some(token_id), enabled).await
+    }
+
+    async fn get_user_groups(&self, station: &RelayStation) -> Result<serde_json::Value> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint("user_groups", &definition.user_groups)?;
+        let data = call(&definition, "get user groups", endpoint, station, &station_vars(station, json!({}))).await?;
+        mapping_root(&data, &endpoint.response).cloned()
+    }
+
+    async fn list_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        let definition = station_definition(station)?;
+        let endpoint = definition.endpoint("list_models", &definition.list_models)?;
+        let data = call(&definition, "list models", endpoint, station, &station_vars(station, json!({}))).await?;
+        Ok(mapping_items(&data, &endpoint.response)?
+            .iter()
+            .filter_map(|model| match model {
+                Value::String(id) => Some(id.clone()),
+                _ => Mapped::new(model, &endpoint.response).string("id"),
+            })
+            .collect())
+    }
+}
+
+/// List the adapter definitions found in the user directory
+#[tauri::command]
+pub async fn list_adapter_definitions() -> Result<Vec<AdapterDefinitionSummary>, String> {
+    let dir = definitions_dir().map_err(|e| e.to_string())?;
+    Ok(definition_files(&dir)
+        .into_iter()
+        .map(|path| {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match load_file(&path) {
+                Ok(definition) => AdapterDefinitionSummary {
+                    methods: definition.methods(),
+                    id: definition.id,
+                    name: definition.name,
+                    description: definition.description,
+                    path: path.to_string_lossy().to_string(),
+                    error: None,
+                },
+                Err(e) => AdapterDefinitionSummary {
+                    id: stem.clone(),
+                    name: stem,
+                    description: None,
+                    path: path.to_string_lossy().to_string(),
+                    methods: Vec::new(),
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_json_paths() {
+        let data = json!({ "data": { "items": [{ "id": 7, "a.b": "x" }] } });
+        assert_eq!(select(&data, "$.data.items[0].id"), Some(&json!(7)));
+        assert_eq!(select(&data, "data.items[0]['a.b']"), Some(&json!("x")));
+        assert_eq!(select(&data, "$"), Some(&data));
+        assert_eq!(select(&data, "$.data.items[1]"), None);
+    }
+
+    #[test]
+    fn renders_templates() {
+        let vars = json!({ "page": 2, "filters": { "model": "claude 3" }, "enabled": false });
+        assert_eq!(render("/logs?p={{page}}&m={{ filters.model }}", &vars, true), "/logs?p=2&m=claude%203");
+        assert_eq!(render("{{missing}}-x", &vars, false), "-x");
+        assert_eq!(
+            render_value(&json!({ "status": "{{enabled}}", "label": "page {{page}}" }), &vars),
+            json!({ "status": false, "label": "page 2" })
+        );
+    }
+
+    #[test]
+    fn maps_fields() {
+        let mapping: ResponseMapping = serde_yaml::from_str(
+            r#"
+fields:
+  id: $.id
+  enabled: { path: $.status, map: { "1": true, "2": false } }
+  remain_quota: { path: $.quota, scale: 2 }
+  group: { default: default }
+"#,
+        )
+        .unwrap();
+        let source = json!({ "id": 5, "status": 2, "quota": 21 });
+        let mapped = Mapped::new(&source, &mapping);
+        assert_eq!(mapped.string("id").as_deref(), Some("5"));
+        assert_eq!(mapped.bool("enabled"), Some(false));
+        assert_eq!(mapped.i64("remain_quota"), Some(42));
+        assert_eq!(mapped.string("group").as_deref(), Some("default"));
+        assert_eq!(mapped.string("name"), None);
+    }
+
+    #[test]
+    fn reads_totals_only_when_mapped() {
+        let data = json!({ "data": { "items": [1, 2], "total": "25" } });
+        let mapped: ResponseMapping = serde_yaml::from_str("items: $.data.items\ntotal: $.data.total").unwrap();
+        assert_eq!(mapping_total(&data, &mapped), 25);
+        // A short page is no proof of the last one
+        let unmapped: ResponseMapping = serde_yaml::from_str("items: $.data.items").unwrap();
+        assert_eq!(mapping_items(&data, &unmapped).unwrap().len(), 2);
+        assert_eq!(mapping_total(&data, &unmapped), 0);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod newapi;
+pub mod oneapi;
 pub mod yourapi;
 pub mod custom;
+pub mod declarative;
 
 pub use newapi::NewApiAdapter;
+pub use oneapi::OneApiAdapter;
 pub use yourapi::YourApiAdapter;
-pub use custom::CustomAdapter;
\ No newline at end of file
+pub use custom::CustomAdapter;
+pub use declarative::DeclarativeAdapter;
\ No newline at end of file
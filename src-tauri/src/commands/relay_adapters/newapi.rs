@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 use anyhow::{Result, anyhow};
-use reqwest;
 use chrono;
 
+use crate::commands::http_client::{station_client, SendWithRetry};
 use crate::commands::relay_stations::{
     RelayStation, RelayStationToken, StationInfo, UserInfo, StationLogEntry, 
     LogPaginationResponse, TokenPaginationResponse, ConnectionTestResult, CreateTokenRequest, UpdateTokenRequest,
@@ -15,12 +15,12 @@ pub struct NewApiAdapter;
 #[async_trait::async_trait]
 impl StationAdapter for NewApiAdapter {
     async fn get_station_info(&self, station: &RelayStation) -> Result<StationInfo> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1"); // Default to "1" if no user_id configured
         let response = client
             .get(&format!("{}/api/status", station.api_url))
             .header("New-API-User", user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -56,7 +56,7 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn get_user_info(&self, station: &RelayStation, user_id: &str) -> Result<UserInfo> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let actual_user_id = if user_id.is_empty() {
             station.user_id.as_deref().unwrap_or("1")
         } else {
@@ -67,7 +67,7 @@ impl StationAdapter for NewApiAdapter {
             .get(&format!("{}/api/user/self", station.api_url))
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", actual_user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -111,7 +111,7 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>) -> Result<LogPaginationResponse> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let page = page.unwrap_or(1);
         let page_size = page_size.unwrap_or(10);
         let user_id = station.user_id.as_deref().unwrap_or("1");
@@ -163,7 +163,7 @@ impl StationAdapter for NewApiAdapter {
             .get(&url)
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -248,14 +248,14 @@ impl StationAdapter for NewApiAdapter {
 
     async fn test_connection(&self, station: &RelayStation) -> Result<ConnectionTestResult> {
         let start_time = std::time::Instant::now();
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
         match client
             .get(&format!("{}/api/status", station.api_url))
             .header("New-API-User", user_id)
             .timeout(std::time::Duration::from_secs(10))
-            .send()
+            .send_with_retry()
             .await
         {
             Ok(response) => {
@@ -293,7 +293,7 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         let page = page.unwrap_or(1);
         let size = size.unwrap_or(10);
@@ -304,7 +304,7 @@ impl StationAdapter for NewApiAdapter {
             .get(&url)
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -376,7 +376,7 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn create_token(&self, station: &RelayStation, token_data: &CreateTokenRequest) -> Result<RelayStationToken> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
         let request_body = serde_json::json!({
@@ -396,7 +396,7 @@ impl StationAdapter for NewApiAdapter {
             .header("New-API-User", user_id)
             .header("Content-Type", "application/json")
             .json(&request_body)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -438,7 +438,7 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn update_token(&self, station: &RelayStation, token_id: &str, token_data: &UpdateTokenRequest) -> Result<RelayStationToken> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
         let mut request_body = serde_json::Map::new();
@@ -478,7 +478,7 @@ impl StationAdapter for NewApiAdapter {
             .header("New-API-User", user_id)
             .header("Content-Type", "application/json")
             .json(&request_body)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -533,14 +533,14 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn delete_token(&self, station: &RelayStation, token_id: &str) -> Result<()> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
         let response = client
             .delete(&format!("{}/api/token/{}", station.api_url, token_id))
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -551,7 +551,7 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn toggle_token(&self, station: &RelayStation, token_id: &str, enabled: bool) -> Result<RelayStationToken> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
         let request_body = serde_json::json!({
@@ -565,7 +565,7 @@ impl StationAdapter for NewApiAdapter {
             .header("New-API-User", user_id)
             .header("Content-Type", "application/json")
             .json(&request_body)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -621,14 +621,14 @@ impl StationAdapter for NewApiAdapter {
     }
 
     async fn get_user_groups(&self, station: &RelayStation) -> Result<serde_json::Value> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
         let response = client
             .get(&format!("{}/api/user/self/groups", station.api_url))
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
@@ -638,4 +638,30 @@ impl StationAdapter for NewApiAdapter {
             Err(anyhow!("API request failed with status: {}", response.status()))
         }
     }
+
+    async fn list_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        let client = station_client(station)?;
+        let user_id = station.user_id.as_deref().unwrap_or("1");
+
+        // Models enabled for the user's groups
+        let response = client
+            .get(format!("{}/api/user/models", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .header("New-API-User", user_id)
+            .send_with_retry()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to list models: {}", response.status()));
+        }
+        let data: serde_json::Value = response.json().await?;
+        if data["success"].as_bool() == Some(false) {
+            return Err(anyhow!("Failed to list models: {}", data["message"].as_str().unwrap_or("Unknown error")));
+        }
+        let models = data["data"].as_array().ok_or_else(|| anyhow!("Invalid response format"))?;
+        Ok(models.iter()
+            .filter_map(|model| model.as_str())
+            .map(|model| model.to_string())
+            .collect())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,687 @@
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use reqwest;
+use chrono;
+
+use crate::commands::http_client::{station_client, SendWithRetry};
+use crate::commands::relay_stations::{
+    RelayStation, RelayStationToken, StationInfo, UserInfo, StationLogEntry,
+    LogPaginationResponse, TokenPaginationResponse, ConnectionTestResult, CreateTokenRequest, UpdateTokenRequest,
+    StationAdapter
+};
+
+// OneAPI serves a fixed number of items per page unless the deployment overrides it
+const ONEAPI_PAGE_SIZE: usize = 10;
+
+/// OneAPI adapter implementation
+///
+/// Unlike NewAPI, OneAPI authenticates with the access token alone (no
+/// `New-API-User` header), pages from 0 without returning totals, stores token
+/// keys without the `sk-` prefix and reports failures as `success: false`
+/// with HTTP 200.
+pub struct OneApiAdapter;
+
+/// Fail on HTTP errors and on `success: false` responses
+async fn read_response(response: reqwest::Response, action: &str) -> Result<serde_json::Value> {
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to {}: {}", action, response.status()));
+    }
+    let data: serde_json::Value = response.json().await?;
+    if data.get("success").and_then(|v| v.as_bool()) == Some(false) {
+        let message = data.get("message")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .unwrap_or("Unknown error");
+        return Err(anyhow!("Failed to {}: {}", action, message));
+    }
+    Ok(data)
+}
+
+/// Estimate the total from a page, as OneAPI does not report it
+fn estimate_total(page: usize, page_size: usize, items_len: usize, has_more_pages: bool) -> i64 {
+    if has_more_pages {
+        (page * page_size + 1) as i64
+    } else {
+        ((page - 1) * page_size + items_len) as i64
+    }
+}
+
+fn parse_token(station: &RelayStation, token: &serde_json::Value, fallback_id: Option<&str>) -> RelayStationToken {
+    let empty_map = serde_json::Map::new();
+    let token_obj = token.as_object().unwrap_or(&empty_map);
+    RelayStationToken {
+        id: token_obj.get("id")
+            .and_then(|v| v.as_i64())
+            .map(|id| id.to_string())
+            .or_else(|| fallback_id.map(|id| id.to_string()))
+            .unwrap_or_default(),
+        station_id: station.id.clone(),
+        name: token_obj.get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        // OneAPI stores keys without the prefix clients send
+        token: token_obj.get("key")
+            .and_then(|v| v.as_str())
+            .filter(|key| !key.is_empty())
+            .map(|key| if key.starts_with("sk-") { key.to_string() } else { format!("sk-{}", key) })
+            .unwrap_or_default(),
+        user_id: token_obj.get("user_id")
+            .and_then(|v| v.as_i64())
+            .map(|id| id.to_string()),
+        // 1 enabled, 2 disabled, 3 expired, 4 exhausted
+        enabled: token_obj.get("status")
+            .and_then(|v| v.as_i64())
+            .map(|s| s == 1)
+            .unwrap_or(false),
+        expires_at: token_obj.get("expired_time")
+            .and_then(|v| v.as_i64())
+            .filter(|&t| t != -1),
+        // OneAPI tokens have no group, they use the group of their user
+        group: None,
+        remain_quota: token_obj.get("remain_quota")
+            .and_then(|v| v.as_i64()),
+        unlimited_quota: token_obj.get("unlimited_quota")
+            .and_then(|v| v.as_bool()),
+        metadata: Some({
+            let mut map = HashMap::new();
+            map.insert("raw".to_string(), token.clone());
+            map.insert("used_quota".to_string(),
+                token_obj.get("used_quota").cloned().unwrap_or(serde_json::Value::Null));
+            map.insert("models".to_string(),
+                token_obj.get("models").cloned().unwrap_or(serde_json::Value::Null));
+            map.insert("accessed_time".to_string(),
+                token_obj.get("accessed_time").cloned().unwrap_or(serde_json::Value::Null));
+            map
+        }),
+        created_at: token_obj.get("created_time")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0),
+    }
+}
+
+impl OneApiAdapter {
+    async fn get_token(&self, station: &RelayStation, token_id: &str) -> Result<serde_json::Value> {
+        let client = station_client(station)?;
+        let response = client
+            .get(format!("{}/api/token/{}", station.api_url, token_id))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "get token").await?;
+        if data["data"].is_object() {
+            Ok(data["data"].clone())
+        } else {
+            Err(anyhow!("Invalid response format"))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl StationAdapter for OneApiAdapter {
+    async fn get_station_info(&self, station: &RelayStation) -> Result<StationInfo> {
+        let client = station_client(station)?;
+        let response = client
+            .get(format!("{}/api/status", station.api_url))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "get station info").await?;
+        let data_obj = data["data"].as_object().ok_or_else(|| anyhow!("Invalid response format"))?;
+
+        // The announcement is served separately and is optional
+        let announcement = match client.get(format!("{}/api/notice", station.api_url)).send_with_retry().await {
+            Ok(response) if response.status().is_success() => response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|notice| notice["data"].as_str().map(|s| s.to_string()))
+                .filter(|s| !s.is_empty()),
+            _ => None,
+        };
+
+        Ok(StationInfo {
+            name: data_obj.get("system_name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&station.name)
+                .to_string(),
+            announcement,
+            api_url: station.api_url.clone(),
+            version: data_obj.get("version")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            quota_per_unit: data_obj.get("quota_per_unit")
+                .and_then(|v| v.as_f64())
+                .map(|q| q as i64),
+            metadata: Some({
+                let mut map = HashMap::new();
+                map.insert("response".to_string(), data["data"].clone());
+                map
+            }),
+        })
+    }
+
+    async fn get_user_info(&self, station: &RelayStation, user_id: &str) -> Result<UserInfo> {
+        let client = station_client(station)?;
+        let response = client
+            .get(format!("{}/api/user/self", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "get user info").await?;
+        let user_data = data["data"].as_object().ok_or_else(|| anyhow!("Invalid response format"))?;
+
+        Ok(UserInfo {
+            user_id: user_data.get("id")
+                .and_then(|v| v.as_i64())
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| user_id.to_string()),
+            username: user_data.get("display_name")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .or_else(|| user_data.get("username").and_then(|v| v.as_str()))
+                .map(|s| s.to_string()),
+            email: user_data.get("email")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+            balance_remaining: user_data.get("quota")
+                .and_then(|v| v.as_i64())
+                .map(|q| q as f64 / 500000.0), // Convert to dollars (default quota_per_unit)
+            amount_used: user_data.get("used_quota")
+                .and_then(|v| v.as_i64())
+                .map(|q| q as f64 / 500000.0),
+            request_count: user_data.get("request_count")
+                .and_then(|v| v.as_i64()),
+            status: match user_data.get("status").and_then(|v| v.as_i64()) {
+                Some(1) => Some("active".to_string()),
+                Some(2) => Some("disabled".to_string()),
+                _ => Some("unknown".to_string()),
+            },
+            metadata: Some({
+                let mut map = HashMap::new();
+                map.insert("response".to_string(), data["data"].clone());
+                map
+            }),
+        })
+    }
+
+    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>) -> Result<LogPaginationResponse> {
+        let client = station_client(station)?;
+        let page = page.unwrap_or(1).max(1);
+        let page_size = page_size.unwrap_or(ONEAPI_PAGE_SIZE);
+
+        let mut start_timestamp = String::new();
+        let mut end_timestamp = String::new();
+        let mut model_name = String::new();
+        let mut token_name = String::new();
+
+        if let Some(filters_obj) = filters {
+            let timestamp = |key: &str| {
+                filters_obj.get(key)
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.is_empty())
+                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(&format!("{}:00+00:00", s)).ok())
+                    .map(|dt| dt.timestamp().to_string())
+            };
+            start_timestamp = timestamp("startTime").unwrap_or_default();
+            end_timestamp = timestamp("endTime").unwrap_or_default();
+
+            if let Some(model) = filters_obj.get("modelName").and_then(|v| v.as_str()) {
+                model_name = model.to_string();
+            }
+            if let Some(token) = filters_obj.get("tokenName").and_then(|v| v.as_str()) {
+                token_name = token.to_string();
+            }
+        }
+
+        // OneAPI pages from 0
+        let url = format!(
+            "{}/api/log/self/?p={}&page_size={}&type=0&token_name={}&model_name={}&start_timestamp={}&end_timestamp={}",
+            station.api_url,
+            page - 1,
+            page_size,
+            urlencoding::encode(&token_name),
+            urlencoding::encode(&model_name),
+            start_timestamp,
+            end_timestamp
+        );
+
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "get logs").await?;
+        // Logs come back as a plain array without a total
+        let empty_vec = vec![];
+        let logs = data["data"].as_array().unwrap_or(&empty_vec);
+
+        let items = logs.iter().map(|log| {
+            let empty_map = serde_json::Map::new();
+            let log_obj = log.as_object().unwrap_or(&empty_map);
+            let content = log_obj.get("content")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty());
+
+            StationLogEntry {
+                id: log_obj.get("id")
+                    .and_then(|v| v.as_i64())
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                timestamp: log_obj.get("created_at")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0),
+                level: match log_obj.get("type").and_then(|v| v.as_i64()) {
+                    Some(1) => "info".to_string(), // Top up
+                    Some(2) => "api".to_string(),  // API call
+                    Some(3) => "warn".to_string(), // Management
+                    Some(4) => "error".to_string(), // System
+                    _ => "info".to_string(),
+                },
+                message: content.map(|s| s.to_string()).unwrap_or_else(|| format!(
+                    "API调用 - 模型: {} | 提示: {} | 补全: {} | 花费: {}",
+                    log_obj.get("model_name").and_then(|v| v.as_str()).unwrap_or("unknown"),
+                    log_obj.get("prompt_tokens").and_then(|v| v.as_i64()).unwrap_or(0),
+                    log_obj.get("completion_tokens").and_then(|v| v.as_i64()).unwrap_or(0),
+                    log_obj.get("quota").and_then(|v| v.as_i64()).unwrap_or(0)
+                )),
+                user_id: log_obj.get("user_id")
+                    .and_then(|v| v.as_i64())
+                    .map(|id| id.to_string()),
+                request_id: log_obj.get("request_id")
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string()),
+                model_name: log_obj.get("model_name")
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string()),
+                prompt_tokens: log_obj.get("prompt_tokens").and_then(|v| v.as_i64()),
+                completion_tokens: log_obj.get("completion_tokens").and_then(|v| v.as_i64()),
+                quota: log_obj.get("quota").and_then(|v| v.as_i64()),
+                token_name: log_obj.get("token_name")
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string()),
+                // elapsed_time is in milliseconds
+                use_time: log_obj.get("elapsed_time")
+                    .and_then(|v| v.as_i64())
+                    .map(|ms| ms / 1000),
+                is_stream: log_obj.get("is_stream").and_then(|v| v.as_bool()),
+                channel: log_obj.get("channel").and_then(|v| v.as_i64()),
+                group: None,
+                metadata: Some({
+                    let mut map = HashMap::new();
+                    map.insert("raw".to_string(), log.clone());
+                    map
+                }),
+            }
+        }).collect::<Vec<_>>();
+
+        // OneAPI ignores page_size and serves its own fixed page size, so only
+        // a page that holds at least that many logs may have a next one
+        let served_size = items.len().max(ONEAPI_PAGE_SIZE);
+        let total = estimate_total(page, served_size, items.len(), items.len() >= ONEAPI_PAGE_SIZE);
+        Ok(LogPaginationResponse {
+            items,
+            page,
+            page_size: served_size,
+            total,
+        })
+    }
+
+    async fn test_connection(&self, station: &RelayStation) -> Result<ConnectionTestResult> {
+        let start_time = std::time::Instant::now();
+        let client = station_client(station)?;
+
+        match client
+            .get(format!("{}/api/status", station.api_url))
+            .timeout(std::time::Duration::from_secs(10))
+            .send_with_retry()
+            .await
+        {
+            Ok(response) => {
+                let response_time = start_time.elapsed().as_millis() as u64;
+                let status_code = response.status().as_u16();
+
+                if response.status().is_success() {
+                    Ok(ConnectionTestResult {
+                        success: true,
+                        response_time: Some(response_time),
+                        message: "Connection successful".to_string(),
+                        status_code: Some(status_code),
+                        details: None,
+                    })
+                } else {
+                    Ok(ConnectionTestResult {
+                        success: false,
+                        response_time: Some(response_time),
+                        message: format!("HTTP {}", status_code),
+                        status_code: Some(status_code),
+                        details: None,
+                    })
+                }
+            }
+            Err(e) => {
+                Ok(ConnectionTestResult {
+                    success: false,
+                    response_time: None,
+                    message: format!("Connection failed: {}", e),
+                    status_code: None,
+                    details: None,
+                })
+            }
+        }
+    }
+
+    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse> {
+        let client = station_client(station)?;
+        let page = page.unwrap_or(1).max(1);
+        let size = size.unwrap_or(ONEAPI_PAGE_SIZE);
+
+        // OneAPI pages from 0 and returns a plain array; one extra item tells
+        // whether there are more pages
+        let url = format!("{}/api/token/?p={}&size={}", station.api_url, page - 1, size + 1);
+
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "list tokens").await?;
+        let tokens = data["data"].as_array().ok_or_else(|| anyhow!("Invalid response format: data is not an array"))?;
+
+        let has_more_pages = tokens.len() > size;
+        let items = tokens.iter()
+            .take(size)
+            .map(|token| parse_token(station, token, None))
+            .collect::<Vec<_>>();
+
+        let total = estimate_total(page, size, items.len(), has_more_pages);
+        Ok(TokenPaginationResponse {
+            items,
+            page,
+            page_size: size,
+            total,
+        })
+    }
+
+    async fn create_token(&self, station: &RelayStation, token_data: &CreateTokenRequest) -> Result<RelayStationToken> {
+        let client = station_client(station)?;
+
+        let mut request_body = serde_json::json!({
+            "name": token_data.name,
+            "remain_quota": token_data.remain_quota.unwrap_or(500000),
+            "expired_time": token_data.expired_time.unwrap_or(-1),
+            "unlimited_quota": token_data.unlimited_quota.unwrap_or(true),
+            "subnet": token_data.allow_ips.as_deref().unwrap_or(""),
+        });
+        // OneAPI limits models with a comma separated `models` field
+        if token_data.model_limits_enabled.unwrap_or(false) {
+            request_body["models"] = serde_json::Value::String(token_data.model_limits.clone().unwrap_or_default());
+        }
+
+        let response = client
+            .post(format!("{}/api/token/", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "create token").await?;
+
+        // Newer OneAPI versions return the created token
+        if data["data"].is_object() {
+            return Ok(parse_token(station, &data["data"], None));
+        }
+
+        Ok(RelayStationToken {
+            id: "".to_string(), // Will be updated when token list is refreshed
+            station_id: station.id.clone(),
+            name: token_data.name.clone(),
+            token: "".to_string(),
+            user_id: None,
+            enabled: true,
+            expires_at: if token_data.expired_time.unwrap_or(-1) == -1 { None } else { token_data.expired_time },
+            group: None,
+            remain_quota: token_data.remain_quota,
+            unlimited_quota: token_data.unlimited_quota,
+            metadata: Some({
+                let mut map = HashMap::new();
+                map.insert("response".to_string(), data.clone());
+                map.insert("note".to_string(), serde_json::Value::String("Token created successfully, refresh to see details".to_string()));
+                map
+            }),
+            created_at: chrono::Utc::now().timestamp(),
+        })
+    }
+
+    async fn update_token(&self, station: &RelayStation, token_id: &str, token_data: &UpdateTokenRequest) -> Result<RelayStationToken> {
+        // OneAPI overwrites every editable field, so start from the current token
+        let mut request_body = self.get_token(station, token_id).await?;
+
+        if let Some(name) = &token_data.name {
+            request_body["name"] = serde_json::Value::String(name.clone());
+        }
+        if let Some(quota) = token_data.remain_quota {
+            request_body["remain_quota"] = serde_json::Value::Number(quota.into());
+        }
+        if let Some(expired) = token_data.expired_time {
+            request_body["expired_time"] = serde_json::Value::Number(expired.into());
+        }
+        if let Some(unlimited) = token_data.unlimited_quota {
+            request_body["unlimited_quota"] = serde_json::Value::Bool(unlimited);
+        }
+        match (token_data.model_limits_enabled, &token_data.model_limits) {
+            (Some(false), _) => request_body["models"] = serde_json::Value::String(String::new()),
+            (_, Some(limits)) => request_body["models"] = serde_json::Value::String(limits.clone()),
+            _ => {}
+        }
+        if let Some(ips) = &token_data.allow_ips {
+            request_body["subnet"] = serde_json::Value::String(ips.clone());
+        }
+
+        let client = station_client(station)?;
+        let response = client
+            .put(format!("{}/api/token/", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "update token").await?;
+        let token = if data["data"].is_object() { data["data"].clone() } else { request_body };
+
+        // Status changes go through the status-only update
+        match token_data.enabled {
+            Some(enabled) => self.toggle_token(station, token_id, enabled).await,
+            None => Ok(parse_token(station, &token, Some(token_id))),
+        }
+    }
+
+    async fn delete_token(&self, station: &RelayStation, token_id: &str) -> Result<()> {
+        let client = station_client(station)?;
+
+        let response = client
+            .delete(format!("{}/api/token/{}/", station.api_url, token_id))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        read_response(response, "delete token").await?;
+        Ok(())
+    }
+
+    async fn toggle_token(&self, station: &RelayStation, token_id: &str, enabled: bool) -> Result<RelayStationToken> {
+        let client = station_client(station)?;
+
+        let request_body = serde_json::json!({
+            "id": token_id.parse::<i64>().map_err(|e| anyhow!("Invalid token ID: {}", e))?,
+            "status": if enabled { 1 } else { 2 }
+        });
+
+        let response = client
+            .put(format!("{}/api/token/?status_only=true", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "toggle token").await?;
+        let token = if data["data"].is_object() {
+            data["data"].clone()
+        } else {
+            self.get_token(station, token_id).await?
+        };
+        Ok(parse_token(station, &token, Some(token_id)))
+    }
+
+    async fn get_user_groups(&self, station: &RelayStation) -> Result<serde_json::Value> {
+        let client = station_client(station)?;
+
+        // Only administrators may list groups on OneAPI
+        let response = client
+            .get(format!("{}/api/group/", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "get user groups").await?;
+        let groups = data["data"].as_array().ok_or_else(|| anyhow!("Invalid response format"))?;
+
+        // Same shape as NewAPI's /api/user/self/groups
+        let groups: serde_json::Map<String, serde_json::Value> = groups.iter()
+            .filter_map(|group| group.as_str())
+            .map(|group| (group.to_string(), serde_json::json!({ "desc": group })))
+            .collect();
+        Ok(serde_json::json!({ "success": true, "data": groups }))
+    }
+
+    async fn list_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        let client = station_client(station)?;
+        let response = client
+            .get(format!("{}/api/user/available_models", station.api_url))
+            .header("Authorization", format!("Bearer {}", station.system_token))
+            .send_with_retry()
+            .await?;
+
+        let data = read_response(response, "list models").await?;
+        let models = data["data"].as_array().ok_or_else(|| anyhow!("Invalid response format"))?;
+        Ok(models.iter()
+            .filter_map(|model| model.as_str())
+            .map(|model| model.to_string())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::relay_stations::{AuthMethod, RelayStationAdapter};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn station() -> RelayStation {
+        RelayStation {
+            id: "s1".to_string(),
+            name: "oneapi".to_string(),
+            description: None,
+            api_url: "https://oneapi.example.com".to_string(),
+            adapter: RelayStationAdapter::Oneapi,
+            auth_method: AuthMethod::BearerToken,
+            system_token: String::new(),
+            user_id: None,
+            adapter_config: None,
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    /// Serve one HTTP response on a local port and return its URL
+    async fn serve_once(status: &str, body: &str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body
+        );
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn estimates_totals_from_full_and_short_pages() {
+        // A full page may have a next one
+        assert_eq!(estimate_total(1, 10, 10, true), 11);
+        assert_eq!(estimate_total(3, 10, 10, true), 31);
+        // A short page is the last one
+        assert_eq!(estimate_total(1, 10, 4, false), 4);
+        assert_eq!(estimate_total(3, 10, 7, false), 27);
+        assert_eq!(estimate_total(2, 10, 0, false), 10);
+    }
+
+    #[test]
+    fn parses_token_keys_and_status() {
+        let token = parse_token(&station(), &serde_json::json!({
+            "id": 7,
+            "name": "ci",
+            "key": "abc123",
+            "status": 1,
+            "expired_time": -1,
+            "remain_quota": 500,
+            "unlimited_quota": false,
+        }), None);
+        assert_eq!(token.id, "7");
+        assert_eq!(token.token, "sk-abc123");
+        assert!(token.enabled);
+        assert_eq!(token.expires_at, None);
+        assert_eq!(token.remain_quota, Some(500));
+
+        let token = parse_token(&station(), &serde_json::json!({"key": "sk-abc123", "status": 2, "expired_time": 1700000000}), Some("9"));
+        assert_eq!(token.id, "9");
+        assert_eq!(token.token, "sk-abc123");
+        assert!(!token.enabled);
+        assert_eq!(token.expires_at, Some(1700000000));
+
+        // Expired and exhausted tokens are not usable
+        for status in [3, 4] {
+            assert!(!parse_token(&station(), &serde_json::json!({"status": status}), None).enabled);
+        }
+        assert_eq!(parse_token(&station(), &serde_json::json!({"key": ""}), None).token, "");
+    }
+
+    #[tokio::test]
+    async fn read_response_fails_on_unsuccessful_body() {
+        let url = serve_once("200 OK", r#"{"success":false,"message":"无权进行此操作"}"#).await;
+        let response = reqwest::Client::new().get(&url).send().await.unwrap();
+        let error = read_response(response, "list tokens").await.unwrap_err();
+        assert_eq!(error.to_string(), "Failed to list tokens: 无权进行此操作");
+
+        let url = serve_once("200 OK", r#"{"success":false,"message":""}"#).await;
+        let response = reqwest::Client::new().get(&url).send().await.unwrap();
+        let error = read_response(response, "get logs").await.unwrap_err();
+        assert_eq!(error.to_string(), "Failed to get logs: Unknown error");
+
+        let url = serve_once("200 OK", r#"{"success":true,"data":[1]}"#).await;
+        let response = reqwest::Client::new().get(&url).send().await.unwrap();
+        assert_eq!(read_response(response, "get logs").await.unwrap()["data"][0], 1);
+
+        let url = serve_once("401 Unauthorized", "{}").await;
+        let response = reqwest::Client::new().get(&url).send().await.unwrap();
+        let error = read_response(response, "get logs").await.unwrap_err();
+        assert!(error.to_string().starts_with("Failed to get logs: 401"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use anyhow::{Result, anyhow};
-use reqwest;
 
+use crate::commands::http_client::{station_client, SendWithRetry};
 use crate::commands::relay_stations::{
     RelayStation, RelayStationToken, StationInfo, UserInfo, 
     LogPaginationResponse, TokenPaginationResponse, ConnectionTestResult, CreateTokenRequest, UpdateTokenRequest,
@@ -62,9 +62,13 @@ impl StationAdapter for YourApiAdapter {
         self.newapi.get_user_groups(station).await
     }
 
+    async fn list_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        self.newapi.list_models(station).await
+    }
+
     // Override list_tokens for YourAPI format
     async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse> {
-        let client = reqwest::Client::new();
+        let client = station_client(station)?;
         let user_id = station.user_id.as_deref().unwrap_or("1");
         let page = page.unwrap_or(1); // Use 1-based pagination like frontend expects
         let size = size.unwrap_or(10);
@@ -78,7 +82,7 @@ impl StationAdapter for YourApiAdapter {
             .get(&url)
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
+            .send_with_retry()
             .await?;
 
         if response.status().is_success() {
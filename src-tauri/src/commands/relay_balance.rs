@@ -0,0 +1,554 @@
+use chrono::{Local, TimeZone, Utc};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use super::relay_stations::{
+    create_adapter, BalanceAlertSettings, QuotaSnapshot, RelayStation, RelayStationAdapter,
+    RelayStationManager, UserInfo,
+};
+use super::secrets::is_store_locked;
+use crate::i18n;
+
+const SNAPSHOT_INTERVAL_SECS: u64 = 3600;
+// History kept for consumption statistics
+const SNAPSHOT_HISTORY_DAYS: i64 = 90;
+const DEFAULT_TREND_DAYS: u32 = 30;
+// What NewAPI and OneAPI use unless configured otherwise
+const DEFAULT_QUOTA_PER_UNIT: f64 = 500_000.0;
+const TOKEN_PAGE_SIZE: usize = 100;
+const MAX_TOKEN_PAGES: usize = 10;
+// Shorter histories give no meaningful consumption rate
+const MIN_TREND_SPAN_SECS: i64 = 3600;
+
+/// Balance change of one day
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceDay {
+    /// Local date (YYYY-MM-DD)
+    pub date: String,
+    pub consumed: f64,
+    pub topped_up: f64,
+    /// Balance at the last snapshot of the day
+    pub balance: f64,
+}
+
+/// Balance history and consumption forecast of an account or token
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceTrend {
+    pub station_id: String,
+    pub station_name: String,
+    /// None for the account balance
+    pub token_id: Option<String>,
+    pub token_name: Option<String>,
+    pub balance: f64,
+    /// Mean consumption per day over the period, top-ups excluded
+    pub consumption_per_day: Option<f64>,
+    /// Days until the balance runs out at `consumption_per_day`
+    pub days_left: Option<f64>,
+    /// Unix timestamp the balance is predicted to run out
+    pub depletion_at: Option<i64>,
+    pub daily: Vec<BalanceDay>,
+    /// Snapshots in the period, oldest first
+    pub history: Vec<QuotaSnapshot>,
+}
+
+/// Payload of the `relay-balance-low` event
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceAlert {
+    pub station_id: String,
+    pub station_name: String,
+    pub balance: f64,
+    pub days_left: Option<f64>,
+    pub depletion_at: Option<i64>,
+}
+
+fn quota_to_currency(quota: i64, quota_per_unit: Option<i64>) -> f64 {
+    let per_unit = quota_per_unit
+        .filter(|q| *q > 0)
+        .map(|q| q as f64)
+        .unwrap_or(DEFAULT_QUOTA_PER_UNIT);
+    quota as f64 / per_unit
+}
+
+/// Raw quota field of the user info response, when the adapter kept it
+fn raw_quota(user_info: &UserInfo, field: &str) -> Option<i64> {
+    user_info
+        .metadata
+        .as_ref()?
+        .get("response")?
+        .get(field)?
+        .as_i64()
+}
+
+fn account_snapshot(
+    station: &RelayStation,
+    user_info: &UserInfo,
+    quota_per_unit: Option<i64>,
+    now: i64,
+) -> Option<QuotaSnapshot> {
+    let remain_quota = raw_quota(user_info, "quota");
+    // Adapters convert with a fixed rate; the station's own rate wins when known
+    let balance = remain_quota
+        .map(|q| quota_to_currency(q, quota_per_unit))
+        .or(user_info.balance_remaining)?;
+    let amount_used = raw_quota(user_info, "used_quota")
+        .map(|q| quota_to_currency(q, quota_per_unit))
+        .or(user_info.amount_used);
+    Some(QuotaSnapshot {
+        station_id: station.id.clone(),
+        token_id: None,
+        token_name: None,
+        remain_quota,
+        balance,
+        amount_used,
+        quota_per_unit,
+        captured_at: now,
+    })
+}
+
+/// Read the current balance of a station's account and limited tokens
+async fn capture_station(
+    station: &RelayStation,
+    user_info: bool,
+    tokens: bool,
+) -> Result<Vec<QuotaSnapshot>, String> {
+    let adapter = create_adapter(&station.adapter);
+    let now = Utc::now().timestamp();
+    let quota_per_unit = adapter
+        .get_station_info(station)
+        .await
+        .ok()
+        .and_then(|info| info.quota_per_unit);
+
+    let mut snapshots = Vec::new();
+    let mut errors = Vec::new();
+
+    if user_info {
+        let user_id = station.user_id.as_deref().unwrap_or("");
+        match adapter.get_user_info(station, user_id).await {
+            Ok(info) => snapshots.extend(account_snapshot(station, &info, quota_per_unit, now)),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if tokens {
+        for page in 1..=MAX_TOKEN_PAGES {
+            let response = match adapter
+                .list_tokens(station, Some(page), Some(TOKEN_PAGE_SIZE))
+                .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    errors.push(e.to_string());
+                    break;
+                }
+            };
+            let count = response.items.len();
+            snapshots.extend(
+                response
+                    .items
+                    .into_iter()
+                    .filter(|token| token.unlimited_quota != Some(true))
+                    .filter_map(|token| {
+                        let quota = token.remain_quota?;
+                        Some(QuotaSnapshot {
+                            station_id: station.id.clone(),
+                            token_id: Some(token.id),
+                            token_name: Some(token.name),
+                            remain_quota: Some(quota),
+                            balance: quota_to_currency(quota, quota_per_unit),
+                            amount_used: None,
+                            quota_per_unit,
+                            captured_at: now,
+                        })
+                    }),
+            );
+            if count < TOKEN_PAGE_SIZE || (page * TOKEN_PAGE_SIZE) as i64 >= response.total {
+                break;
+            }
+        }
+    }
+
+    if snapshots.is_empty() && !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(snapshots)
+}
+
+/// Consumption statistics of one account or token, from its snapshots
+fn balance_trend(station: &RelayStation, history: Vec<QuotaSnapshot>) -> Option<BalanceTrend> {
+    let first = history.first()?;
+    let last = history.last()?;
+
+    let mut consumed = 0.0;
+    let mut days: BTreeMap<String, BalanceDay> = BTreeMap::new();
+    for pair in history.windows(2) {
+        let [previous, current] = pair else { continue };
+        let change = current.balance - previous.balance;
+        let date = Local
+            .timestamp_opt(current.captured_at, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let day = days.entry(date.clone()).or_insert_with(|| BalanceDay {
+            date,
+            consumed: 0.0,
+            topped_up: 0.0,
+            balance: current.balance,
+        });
+        if change < 0.0 {
+            day.consumed -= change;
+            consumed -= change;
+        } else {
+            day.topped_up += change;
+        }
+        day.balance = current.balance;
+    }
+
+    let span = last.captured_at - first.captured_at;
+    let consumption_per_day =
+        (span >= MIN_TREND_SPAN_SECS).then(|| consumed / (span as f64 / 86400.0));
+    let days_left = consumption_per_day
+        .filter(|rate| *rate > 0.0)
+        .map(|rate| last.balance.max(0.0) / rate);
+
+    Some(BalanceTrend {
+        station_id: station.id.clone(),
+        station_name: station.name.clone(),
+        token_id: last.token_id.clone(),
+        token_name: last.token_name.clone(),
+        balance: last.balance,
+        consumption_per_day,
+        days_left,
+        depletion_at: days_left.map(|days| last.captured_at + (days * 86400.0) as i64),
+        daily: days.into_values().collect(),
+        history,
+    })
+}
+
+/// Split a station's snapshots into one trend per account or token, account first
+fn station_trends(station: &RelayStation, snapshots: Vec<QuotaSnapshot>) -> Vec<BalanceTrend> {
+    let mut series: BTreeMap<Option<String>, Vec<QuotaSnapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        series
+            .entry(snapshot.token_id.clone())
+            .or_default()
+            .push(snapshot);
+    }
+    series
+        .into_values()
+        .filter_map(|history| balance_trend(station, history))
+        .collect()
+}
+
+fn is_below_threshold(settings: &BalanceAlertSettings, trend: &BalanceTrend) -> bool {
+    settings.min_balance.is_some_and(|min| trend.balance < min)
+        || settings
+            .min_days_left
+            .is_some_and(|min| trend.days_left.is_some_and(|days| days < min))
+}
+
+fn notify_balance_alert(app: &AppHandle, alert: &BalanceAlert) {
+    let title = i18n::t_with_args(
+        "relay.balance_low_title",
+        &[("name", alert.station_name.as_str())],
+    );
+    let balance = format!("{:.2}", alert.balance);
+    let body = match alert.days_left {
+        Some(days) => i18n::t_with_args(
+            "relay.balance_depleting_body",
+            &[
+                ("balance", balance.as_str()),
+                ("days", format!("{:.1}", days).as_str()),
+            ],
+        ),
+        None => i18n::t_with_args("relay.balance_low_body", &[("balance", balance.as_str())]),
+    };
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        error!("Failed to show balance notification: {}", e);
+    }
+    let _ = app.emit("relay-balance-low", alert);
+}
+
+/// Snapshot one station and notify if its account balance fell below its thresholds
+async fn snapshot_station(
+    app: &AppHandle,
+    station: &RelayStation,
+) -> Result<Vec<QuotaSnapshot>, String> {
+    // Skip what the last capability probe found unsupported
+    let capabilities = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state.lock().map_err(|e| e.to_string())?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or("Relay station manager not initialized")?;
+        manager
+            .list_station_capabilities()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|c| c.station_id == station.id)
+    };
+    let user_info = capabilities.as_ref().is_none_or(|c| c.user_info);
+    let tokens = capabilities.as_ref().is_none_or(|c| c.tokens);
+
+    let snapshots = capture_station(station, user_info, tokens).await?;
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let alert = {
+        let manager_lock = state.lock().map_err(|e| e.to_string())?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or("Relay station manager not initialized")?;
+        manager
+            .record_quota_snapshots(&snapshots)
+            .map_err(|e| e.to_string())?;
+
+        let settings = manager
+            .list_balance_alerts()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|a| a.station_id == station.id && a.enabled);
+        let has_account = snapshots.iter().any(|s| s.token_id.is_none());
+        match settings {
+            Some(settings) if has_account => {
+                let since = Utc::now().timestamp() - DEFAULT_TREND_DAYS as i64 * 24 * 3600;
+                let history: Vec<QuotaSnapshot> = manager
+                    .list_quota_snapshots(&station.id, since)
+                    .map_err(|e| e.to_string())?
+                    .into_iter()
+                    .filter(|s| s.token_id.is_none())
+                    .collect();
+                let trend = balance_trend(station, history);
+                let below = trend
+                    .as_ref()
+                    .is_some_and(|t| is_below_threshold(&settings, t));
+                let was_below = manager
+                    .set_balance_alerted(&station.id, below)
+                    .map_err(|e| e.to_string())?;
+                // Notify once per drop below a threshold
+                trend.filter(|_| below && !was_below).map(|t| BalanceAlert {
+                    station_id: station.id.clone(),
+                    station_name: station.name.clone(),
+                    balance: t.balance,
+                    days_left: t.days_left,
+                    depletion_at: t.depletion_at,
+                })
+            }
+            _ => None,
+        }
+    };
+
+    if let Some(alert) = alert {
+        warn!(
+            "Relay station '{}' balance is low: {:.2}",
+            alert.station_name, alert.balance
+        );
+        notify_balance_alert(app, &alert);
+    }
+    Ok(snapshots)
+}
+
+fn load_stations(app: &AppHandle, station_id: Option<&str>) -> Result<Vec<RelayStation>, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    let Some(manager) = manager_lock.as_ref() else {
+        return Ok(Vec::new());
+    };
+    match station_id {
+        Some(id) => Ok(vec![manager
+            .get_station(id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Station not found: {}", id))?]),
+        None => manager.list_stations().map_err(|e| e.to_string()),
+    }
+}
+
+/// Snapshot every enabled station once
+async fn snapshot_stations(app: &AppHandle) -> Result<(), String> {
+    if is_store_locked() {
+        info!("Secret store is locked, skipping relay station balance snapshots");
+        return Ok(());
+    }
+    let stations = load_stations(app, None)?;
+    for station in stations.into_iter().filter(|s| s.enabled) {
+        // Custom configurations have no balance to read
+        if matches!(station.adapter, RelayStationAdapter::Custom) {
+            continue;
+        }
+        match snapshot_station(app, &station).await {
+            Ok(snapshots) => info!(
+                "Recorded {} balance snapshots of relay station '{}'",
+                snapshots.len(),
+                station.name
+            ),
+            Err(e) => warn!(
+                "Failed to read balance of relay station '{}': {}",
+                station.name, e
+            ),
+        }
+    }
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    if let Some(manager) = manager_lock.as_ref() {
+        let before = Utc::now().timestamp() - SNAPSHOT_HISTORY_DAYS * 24 * 3600;
+        manager
+            .prune_quota_snapshots(before)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Periodically record the balance of the enabled relay stations in the background
+pub fn start_relay_balance_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(SNAPSHOT_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = snapshot_stations(&app).await {
+                error!("Relay station balance snapshot failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Record the current balance of one station now
+#[tauri::command]
+pub async fn snapshot_relay_balance(
+    app: AppHandle,
+    station_id: String,
+) -> Result<Vec<QuotaSnapshot>, String> {
+    let station = load_stations(&app, Some(&station_id))?
+        .into_iter()
+        .next()
+        .ok_or("Relay station manager not initialized")?;
+    snapshot_station(&app, &station).await
+}
+
+/// Get balance history, daily consumption and the predicted run-out date of
+/// the accounts and tokens of one station, or of every station, over the
+/// last `days` days (default 30)
+#[tauri::command]
+pub async fn get_relay_balance_trends(
+    app: AppHandle,
+    station_id: Option<String>,
+    days: Option<u32>,
+) -> Result<Vec<BalanceTrend>, String> {
+    let days = days.unwrap_or(DEFAULT_TREND_DAYS).max(1);
+    let since = Utc::now().timestamp() - days as i64 * 24 * 3600;
+    let stations = load_stations(&app, station_id.as_deref())?;
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    let Some(manager) = manager_lock.as_ref() else {
+        return Ok(Vec::new());
+    };
+
+    let mut trends = Vec::new();
+    for station in &stations {
+        let snapshots = manager
+            .list_quota_snapshots(&station.id, since)
+            .map_err(|e| e.to_string())?;
+        trends.extend(station_trends(station, snapshots));
+    }
+    Ok(trends)
+}
+
+/// Get the low balance notification settings of every configured station
+#[tauri::command]
+pub async fn get_relay_balance_alerts(app: AppHandle) -> Result<Vec<BalanceAlertSettings>, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    match manager_lock.as_ref() {
+        Some(manager) => manager.list_balance_alerts().map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Save the low balance notification settings of a station
+#[tauri::command]
+pub async fn save_relay_balance_alert(
+    app: AppHandle,
+    settings: BalanceAlertSettings,
+) -> Result<BalanceAlertSettings, String> {
+    if settings.min_balance.is_some_and(|v| v < 0.0)
+        || settings.min_days_left.is_some_and(|v| v < 0.0)
+    {
+        return Err("Thresholds must not be negative".to_string());
+    }
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or("Relay station manager not initialized")?;
+    manager
+        .get_station(&settings.station_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Station not found: {}", settings.station_id))?;
+    manager
+        .save_balance_alert(&settings)
+        .map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::relay_stations::AuthMethod;
+
+    fn snapshot(balance: f64, captured_at: i64) -> QuotaSnapshot {
+        QuotaSnapshot {
+            station_id: "s".to_string(),
+            token_id: None,
+            token_name: None,
+            remain_quota: None,
+            balance,
+            amount_used: None,
+            quota_per_unit: None,
+            captured_at,
+        }
+    }
+
+    #[test]
+    fn consumption_rate_ignores_top_ups() {
+        let station = RelayStation {
+            id: "s".to_string(),
+            name: "Station".to_string(),
+            description: None,
+            api_url: "http://localhost".to_string(),
+            adapter: RelayStationAdapter::Newapi,
+            auth_method: AuthMethod::BearerToken,
+            system_token: String::new(),
+            user_id: None,
+            adapter_config: None,
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        };
+        let day = 86400;
+        // Spends 2 a day, with a net top-up of 8 on the second day
+        let history = vec![
+            snapshot(20.0, 0),
+            snapshot(18.0, day),
+            snapshot(26.0, 2 * day),
+            snapshot(24.0, 3 * day),
+        ];
+        let trend = balance_trend(&station, history).unwrap();
+        assert!((trend.consumption_per_day.unwrap() - 4.0 / 3.0).abs() < 1e-9);
+        assert!((trend.days_left.unwrap() - 18.0).abs() < 1e-9);
+        assert_eq!(trend.depletion_at, Some(3 * day + 18 * day));
+        assert_eq!(trend.daily.iter().map(|d| d.topped_up).sum::<f64>(), 8.0);
+    }
+
+    #[test]
+    fn converts_quota_with_the_station_rate() {
+        assert_eq!(quota_to_currency(1_000_000, Some(500_000)), 2.0);
+        assert_eq!(quota_to_currency(1_000_000, Some(0)), 2.0);
+        assert_eq!(quota_to_currency(250, Some(100)), 2.5);
+    }
+}
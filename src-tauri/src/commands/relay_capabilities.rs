@@ -0,0 +1,143 @@
+use chrono::Utc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager, State};
+
+use super::relay_stations::{
+    create_adapter, RelayStation, RelayStationManager, StationCapabilities,
+};
+
+const PROBE_TIMEOUT_SECS: u64 = 15;
+
+/// Run one read-only adapter call, recording why it failed
+async fn probe<T>(
+    errors: &mut HashMap<String, String>,
+    capability: &str,
+    call: impl Future<Output = anyhow::Result<T>>,
+) -> bool {
+    match tokio::time::timeout(Duration::from_secs(PROBE_TIMEOUT_SECS), call).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            errors.insert(capability.to_string(), e.to_string());
+            false
+        }
+        Err(_) => {
+            errors.insert(capability.to_string(), "Timed out".to_string());
+            false
+        }
+    }
+}
+
+/// Detect which adapter features a station supports. Token management can
+/// only be probed by listing, since creating a token would change the station.
+pub async fn probe_station(station: &RelayStation) -> Result<StationCapabilities, String> {
+    let adapter = create_adapter(&station.adapter);
+
+    // An unreachable station would look like it supports nothing
+    let test = adapter
+        .test_connection(station)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !test.success {
+        return Err(format!("Station is unreachable: {}", test.message));
+    }
+
+    let mut errors = HashMap::new();
+    let station_info = probe(
+        &mut errors,
+        "station_info",
+        adapter.get_station_info(station),
+    )
+    .await;
+    let user_info = probe(&mut errors, "user_info", adapter.get_user_info(station, "")).await;
+    let logs = probe(
+        &mut errors,
+        "logs",
+        adapter.get_logs(station, Some(1), Some(1), None),
+    )
+    .await;
+    let tokens = probe(
+        &mut errors,
+        "tokens",
+        adapter.list_tokens(station, Some(1), Some(1)),
+    )
+    .await;
+    let user_groups = probe(&mut errors, "user_groups", adapter.get_user_groups(station)).await;
+
+    Ok(StationCapabilities {
+        station_id: station.id.clone(),
+        station_info,
+        user_info,
+        logs,
+        tokens,
+        user_groups,
+        errors,
+        probed_at: Utc::now().timestamp(),
+    })
+}
+
+/// Probe a station and record what it supports
+#[command]
+pub async fn probe_relay_station_capabilities(
+    station_id: String,
+    app: AppHandle,
+) -> Result<StationCapabilities, String> {
+    let station = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state.lock().map_err(|e| e.to_string())?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or("Relay station manager not initialized")?;
+        manager
+            .get_station(&station_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Station not found: {}", station_id))?
+    };
+
+    let capabilities = probe_station(&station).await?;
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    if let Some(manager) = manager_lock.as_ref() {
+        manager
+            .record_station_capabilities(&capabilities)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(capabilities)
+}
+
+/// Get the recorded capabilities of every probed station
+#[command]
+pub async fn get_relay_station_capabilities(
+    app: AppHandle,
+) -> Result<Vec<StationCapabilities>, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    match manager_lock.as_ref() {
+        Some(manager) => manager
+            .list_station_capabilities()
+            .map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn probe_records_failed_capabilities() {
+        let mut errors = HashMap::new();
+        assert!(probe(&mut errors, "logs", async { Ok(()) }).await);
+        assert!(
+            !probe(&mut errors, "tokens", async {
+                Err::<(), _>(anyhow::anyhow!("Failed to list tokens: 403"))
+            })
+            .await
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors["tokens"], "Failed to list tokens: 403");
+    }
+}
@@ -0,0 +1,512 @@
+//! Shareable relay station export bundles.
+//!
+//! A bundle carries station tokens in clear text, replaces them with
+//! placeholders that are supplied again on import, or encrypts the whole
+//! station list with a password (Argon2id + AES-256-GCM). Each bundle has a
+//! checksum over its canonical JSON. Only the HMAC keyed from the password
+//! proves a bundle is unmodified; the plain SHA-256 written without a password
+//! catches accidental damage, and importing such a bundle needs the user's
+//! explicit consent.
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Manager, State};
+
+use super::relay_stations::{
+    ExportChecksum, ExportSecrets, RelayStation, RelayStationAdapter, RelayStationExport,
+    RelayStationExportItem, RelayStationManager,
+};
+use super::secrets::{decrypt, derive_key, encrypt, is_masked, is_secret_ref, random_salt, MASK};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const BUNDLE_VERSION: u32 = 2;
+// Associated data for the encrypted station list
+const EXPORT_AAD: &str = "relay-station-export";
+// Separates the checksum key from the encryption key
+const CHECKSUM_CONTEXT: &[u8] = b"relay-export-checksum";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportOptions {
+    /// Defaults to `redacted` so tokens are never shared by accident
+    pub secrets: Option<ExportSecrets>,
+    /// Encrypts (`encrypted`) or signs the bundle
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    /// Password HMAC matches
+    Valid,
+    /// Plain SHA-256 matches; anyone editing the bundle can recompute it
+    Unsigned,
+    /// The bundle was modified, or the password is wrong
+    Invalid,
+    /// Signed bundle opened without its password
+    Unverified,
+    /// Bundles written before checksums were added
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub exported_at: i64,
+    pub secrets: ExportSecrets,
+    pub checksum: ChecksumStatus,
+    /// The bundle is not verified and is only imported with the user's consent
+    pub requires_confirmation: bool,
+    pub stations: Vec<ImportPreviewStation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreviewStation {
+    pub name: String,
+    pub api_url: String,
+    pub adapter: RelayStationAdapter,
+    /// Station with the same name, skipped or overwritten by the import
+    pub existing_id: Option<String>,
+    /// Fields that differ from the existing station
+    pub changed_fields: Vec<String>,
+    /// The token is a placeholder and has to be supplied on import
+    pub needs_secret: bool,
+}
+
+/// Turn a plain export into a bundle that is safe to share
+pub fn seal_export(
+    mut export: RelayStationExport,
+    options: &ExportOptions,
+) -> Result<RelayStationExport, String> {
+    let mode = options.secrets.unwrap_or(ExportSecrets::Redacted);
+    export.version = BUNDLE_VERSION;
+    export.secrets = mode;
+    export.salt = None;
+    export.ciphertext = None;
+    export.checksum = None;
+
+    let key = match options.password.as_deref().filter(|p| !p.is_empty()) {
+        Some(password) => {
+            let salt = random_salt();
+            let key = derive_key(password, &salt)?;
+            export.salt = Some(salt);
+            Some(key)
+        }
+        None => None,
+    };
+
+    match mode {
+        ExportSecrets::Included => {}
+        ExportSecrets::Redacted => {
+            for station in &mut export.stations {
+                station.system_token = MASK.to_string();
+            }
+        }
+        ExportSecrets::Encrypted => {
+            let key = key.ok_or("A password is required to encrypt the export")?;
+            let plaintext = serde_json::to_vec(&export.stations).map_err(|e| e.to_string())?;
+            export.ciphertext = Some(encrypt(&key, EXPORT_AAD, &plaintext)?);
+            export.stations = Vec::new();
+        }
+    }
+
+    export.checksum = Some(compute_checksum(&export, key.as_ref())?);
+    Ok(export)
+}
+
+/// Decrypt a bundle if needed and check its checksum
+pub fn open_bundle(
+    bundle: &RelayStationExport,
+    password: Option<&str>,
+) -> Result<(Vec<RelayStationExportItem>, ChecksumStatus), String> {
+    let key = match (password.filter(|p| !p.is_empty()), &bundle.salt) {
+        (Some(password), Some(salt)) => Some(derive_key(password, salt)?),
+        _ => None,
+    };
+
+    let stations = match bundle.secrets {
+        ExportSecrets::Encrypted => {
+            let key = key.ok_or("This export is encrypted, enter its password")?;
+            let ciphertext = bundle
+                .ciphertext
+                .as_deref()
+                .ok_or("Encrypted export has no station data")?;
+            let plaintext = decrypt(&key, EXPORT_AAD, ciphertext)
+                .map_err(|_| "Wrong password or corrupted export".to_string())?;
+            serde_json::from_slice(&plaintext).map_err(|e| e.to_string())?
+        }
+        _ => bundle.stations.clone(),
+    };
+
+    Ok((stations, verify_checksum(bundle, key.as_ref())?))
+}
+
+/// Open a bundle for `import_stations`, filling placeholders from `secrets`
+/// (station name -> token). A bundle with a bad checksum is refused, and one
+/// that is not verified by its password only with `allow_unverified`.
+pub fn prepare_import(
+    bundle: RelayStationExport,
+    password: Option<&str>,
+    secrets: HashMap<String, String>,
+    allow_unverified: bool,
+) -> Result<RelayStationExport, String> {
+    let (mut stations, checksum) = open_bundle(&bundle, password)?;
+    match checksum {
+        ChecksumStatus::Valid => {}
+        ChecksumStatus::Invalid => {
+            return Err(
+                "Export checksum does not match, the file was modified or the password is wrong"
+                    .to_string(),
+            )
+        }
+        _ if allow_unverified => {}
+        _ => {
+            return Err(
+                "Export is not verified by a password, confirm that you trust its origin"
+                    .to_string(),
+            )
+        }
+    }
+
+    for station in &mut stations {
+        if let Some(token) = secrets.get(&station.name).filter(|t| !t.is_empty()) {
+            station.system_token = token.clone();
+        }
+        // A reference would resolve a locally stored credential for the bundle's URL
+        if is_secret_ref(&station.system_token) {
+            return Err(format!(
+                "Station '{}' has a secret reference as its token",
+                station.name
+            ));
+        }
+    }
+
+    Ok(RelayStationExport {
+        stations,
+        secrets: ExportSecrets::Included,
+        salt: None,
+        ciphertext: None,
+        checksum: None,
+        ..bundle
+    })
+}
+
+/// Compare a bundle with the stations already configured
+pub fn preview_import(
+    bundle: &RelayStationExport,
+    password: Option<&str>,
+    existing: &[RelayStation],
+) -> Result<ImportPreview, String> {
+    let (items, checksum) = open_bundle(bundle, password)?;
+
+    let stations = items
+        .into_iter()
+        .map(|item| {
+            let current = existing.iter().find(|s| s.name == item.name);
+            let placeholder = is_masked(&item.system_token);
+            ImportPreviewStation {
+                existing_id: current.map(|s| s.id.clone()),
+                changed_fields: current
+                    .map(|s| changed_fields(s, &item, placeholder))
+                    .unwrap_or_default(),
+                needs_secret: placeholder && current.is_none(),
+                name: item.name,
+                api_url: item.api_url,
+                adapter: item.adapter,
+            }
+        })
+        .collect();
+
+    Ok(ImportPreview {
+        exported_at: bundle.exported_at,
+        secrets: bundle.secrets,
+        requires_confirmation: checksum != ChecksumStatus::Valid,
+        checksum,
+        stations,
+    })
+}
+
+fn changed_fields(
+    station: &RelayStation,
+    item: &RelayStationExportItem,
+    placeholder: bool,
+) -> Vec<String> {
+    fn differs<T: Serialize>(a: &T, b: &T) -> bool {
+        serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+    }
+
+    let mut fields = Vec::new();
+    let mut check = |name: &str, changed: bool| {
+        if changed {
+            fields.push(name.to_string());
+        }
+    };
+    check("description", station.description != item.description);
+    check("api_url", station.api_url != item.api_url);
+    check("adapter", differs(&station.adapter, &item.adapter));
+    check(
+        "auth_method",
+        differs(&station.auth_method, &item.auth_method),
+    );
+    check(
+        "system_token",
+        !placeholder && station.system_token != item.system_token,
+    );
+    check("user_id", station.user_id != item.user_id);
+    check(
+        "adapter_config",
+        differs(&station.adapter_config, &item.adapter_config),
+    );
+    check("enabled", station.enabled != item.enabled);
+    fields
+}
+
+fn checksum_mac(key: &[u8; 32]) -> HmacSha256 {
+    let mut hasher = Sha256::new();
+    hasher.update(CHECKSUM_CONTEXT);
+    hasher.update(key);
+    <HmacSha256 as Mac>::new_from_slice(&hasher.finalize()).expect("HMAC accepts any key length")
+}
+
+fn compute_checksum(
+    export: &RelayStationExport,
+    key: Option<&[u8; 32]>,
+) -> Result<ExportChecksum, String> {
+    let canonical = checksum_payload(export)?;
+    Ok(match key {
+        Some(key) => {
+            let mut mac = checksum_mac(key);
+            mac.update(canonical.as_bytes());
+            ExportChecksum {
+                algorithm: "hmac-sha256".to_string(),
+                value: format!("{:x}", mac.finalize().into_bytes()),
+            }
+        }
+        None => ExportChecksum {
+            algorithm: "sha256".to_string(),
+            value: format!("{:x}", Sha256::digest(canonical.as_bytes())),
+        },
+    })
+}
+
+fn verify_checksum(
+    bundle: &RelayStationExport,
+    key: Option<&[u8; 32]>,
+) -> Result<ChecksumStatus, String> {
+    let Some(checksum) = &bundle.checksum else {
+        return Ok(ChecksumStatus::Missing);
+    };
+    let canonical = checksum_payload(bundle)?;
+
+    Ok(match (checksum.algorithm.as_str(), key) {
+        ("sha256", _) => {
+            if format!("{:x}", Sha256::digest(canonical.as_bytes())) == checksum.value {
+                ChecksumStatus::Unsigned
+            } else {
+                ChecksumStatus::Invalid
+            }
+        }
+        ("hmac-sha256", Some(key)) => {
+            let mut mac = checksum_mac(key);
+            mac.update(canonical.as_bytes());
+            match decode_hex(&checksum.value) {
+                Some(expected) if mac.verify_slice(&expected).is_ok() => ChecksumStatus::Valid,
+                _ => ChecksumStatus::Invalid,
+            }
+        }
+        ("hmac-sha256", None) => ChecksumStatus::Unverified,
+        _ => ChecksumStatus::Invalid,
+    })
+}
+
+/// The bundle without its checksum, as JSON with sorted keys
+fn checksum_payload(export: &RelayStationExport) -> Result<String, String> {
+    let mut unsigned = export.clone();
+    unsigned.checksum = None;
+    let value = serde_json::to_value(&unsigned).map_err(|e| e.to_string())?;
+    let mut out = String::new();
+    write_canonical(&value, &mut out);
+    Ok(out)
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Show what importing a bundle would change before anything is written
+#[command]
+pub async fn preview_relay_station_import(
+    export_data: RelayStationExport,
+    password: Option<String>,
+    app: AppHandle,
+) -> Result<ImportPreview, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or("Relay station manager not initialized")?;
+    let existing = manager.list_stations().map_err(|e| e.to_string())?;
+    preview_import(&export_data, password.as_deref(), &existing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::relay_stations::AuthMethod;
+
+    fn export() -> RelayStationExport {
+        RelayStationExport {
+            version: 1,
+            exported_at: 1_700_000_000,
+            stations: vec![RelayStationExportItem {
+                name: "team".to_string(),
+                description: None,
+                api_url: "https://relay.example.com".to_string(),
+                adapter: RelayStationAdapter::Newapi,
+                auth_method: AuthMethod::BearerToken,
+                system_token: "sk-team-token-1234567890".to_string(),
+                user_id: Some("42".to_string()),
+                adapter_config: Some(HashMap::from([
+                    ("b".to_string(), Value::from(1)),
+                    ("a".to_string(), Value::from("x")),
+                ])),
+                enabled: true,
+            }],
+            secrets: ExportSecrets::Included,
+            salt: None,
+            ciphertext: None,
+            checksum: None,
+        }
+    }
+
+    #[test]
+    fn redacted_export_drops_tokens() {
+        let bundle = seal_export(export(), &ExportOptions::default()).unwrap();
+        assert_eq!(bundle.secrets, ExportSecrets::Redacted);
+        assert_eq!(bundle.stations[0].system_token, MASK);
+
+        // Without a password the checksum only catches accidental damage
+        let (_, status) = open_bundle(&bundle, None).unwrap();
+        assert_eq!(status, ChecksumStatus::Unsigned);
+        let mut damaged = bundle.clone();
+        damaged.stations[0].api_url = "https://relay.example.org".to_string();
+        assert_eq!(
+            open_bundle(&damaged, None).unwrap().1,
+            ChecksumStatus::Invalid
+        );
+
+        assert!(prepare_import(bundle.clone(), None, HashMap::new(), false).is_err());
+        let mut unchecked = bundle.clone();
+        unchecked.checksum = None;
+        assert!(prepare_import(unchecked.clone(), None, HashMap::new(), false).is_err());
+        assert!(prepare_import(unchecked, None, HashMap::new(), true).is_ok());
+    }
+
+    #[test]
+    fn signed_export_needs_password() {
+        let options = ExportOptions {
+            secrets: Some(ExportSecrets::Redacted),
+            password: Some("correct horse".to_string()),
+        };
+        let bundle = seal_export(export(), &options).unwrap();
+        assert_eq!(
+            open_bundle(&bundle, None).unwrap().1,
+            ChecksumStatus::Unverified
+        );
+        assert!(prepare_import(bundle.clone(), None, HashMap::new(), false).is_err());
+        assert!(
+            prepare_import(bundle.clone(), Some("correct horse"), HashMap::new(), false).is_ok()
+        );
+
+        let mut tampered = bundle;
+        tampered.stations[0].api_url = "https://evil.example.com".to_string();
+        assert!(prepare_import(tampered, Some("correct horse"), HashMap::new(), true).is_err());
+    }
+
+    #[test]
+    fn secret_references_are_refused() {
+        let mut plain = export();
+        plain.stations[0].system_token = "secret://provider:p:api_key".to_string();
+        let bundle = seal_export(
+            plain,
+            &ExportOptions {
+                secrets: Some(ExportSecrets::Included),
+                password: Some("correct horse".to_string()),
+            },
+        )
+        .unwrap();
+        assert!(
+            prepare_import(bundle.clone(), Some("correct horse"), HashMap::new(), false).is_err()
+        );
+
+        let redacted = seal_export(export(), &ExportOptions::default()).unwrap();
+        let secrets = HashMap::from([(
+            "team".to_string(),
+            "secret://relay_station:other:system_token".to_string(),
+        )]);
+        assert!(prepare_import(redacted, None, secrets, true).is_err());
+    }
+
+    #[test]
+    fn encrypted_export_round_trips() {
+        let options = ExportOptions {
+            secrets: Some(ExportSecrets::Encrypted),
+            password: Some("correct horse".to_string()),
+        };
+        let bundle = seal_export(export(), &options).unwrap();
+        assert!(bundle.stations.is_empty());
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert!(!json.contains("sk-team-token"));
+
+        let bundle: RelayStationExport = serde_json::from_str(&json).unwrap();
+        assert!(open_bundle(&bundle, None).is_err());
+        assert!(open_bundle(&bundle, Some("wrong")).is_err());
+        let (stations, status) = open_bundle(&bundle, Some("correct horse")).unwrap();
+        assert_eq!(status, ChecksumStatus::Valid);
+        assert_eq!(stations[0].system_token, "sk-team-token-1234567890");
+
+        let mut tampered = bundle;
+        tampered.exported_at += 1;
+        assert!(prepare_import(tampered, Some("correct horse"), HashMap::new(), true).is_err());
+    }
+}
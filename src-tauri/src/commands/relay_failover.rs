@@ -0,0 +1,1039 @@
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use rusqlite::{params, Connection, Result as SqliteResult, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use super::agents::AgentDb;
+use super::http_client;
+use super::protocol_translation::StationTranslation;
+use super::provider::{
+    apply_provider_config, current_provider_config, find_provider_config, ProviderConfig,
+};
+use super::relay_stations::RelayStationManager;
+use super::secrets::is_store_locked;
+use crate::i18n;
+
+// How often the active failover group is evaluated
+const FAILOVER_CHECK_INTERVAL_SECS: u64 = 60;
+const PROVIDER_PROBE_TIMEOUT_SECS: u64 = 10;
+// Station health checks considered when computing a member's streak
+const STATION_HISTORY_HOURS: i64 = 24;
+
+fn default_failure_threshold() -> u32 {
+    2
+}
+
+fn default_error_threshold() -> u32 {
+    3
+}
+
+fn default_error_window_secs() -> u64 {
+    300
+}
+
+fn default_recovery_threshold() -> u32 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// What a failover group member refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailoverMemberKind {
+    /// A relay station, applied with its last used base URL and token
+    Station,
+    /// A provider preset from providers.json
+    Provider,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailoverMember {
+    pub kind: FailoverMemberKind,
+    pub id: String,
+}
+
+impl FailoverMemberKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            FailoverMemberKind::Station => "station",
+            FailoverMemberKind::Provider => "provider",
+        }
+    }
+}
+
+impl FailoverMember {
+    pub(crate) fn key(&self) -> String {
+        format!("{}:{}", self.kind.as_str(), self.id)
+    }
+}
+
+/// An ordered list of stations/providers to fail over between; the first
+/// member is the primary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailoverGroup {
+    pub id: Option<i64>,
+    pub name: String,
+    pub members: Vec<FailoverMember>,
+    /// Consecutive failed health checks before a member counts as down
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 5xx/overloaded errors reported by Claude runs within
+    /// `error_window_secs` that make the active member fail over
+    #[serde(default = "default_error_threshold")]
+    pub error_threshold: u32,
+    /// Also how long a member that failed over on stream errors is skipped
+    #[serde(default = "default_error_window_secs")]
+    pub error_window_secs: u64,
+    /// Consecutive successful health checks before the primary counts as recovered
+    #[serde(default = "default_recovery_threshold")]
+    pub recovery_threshold: u32,
+    /// Switch back to the primary once it recovers
+    #[serde(default = "default_true")]
+    pub auto_failback: bool,
+    /// Only one group can be enabled at a time
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Index of the member currently applied to settings.json
+    #[serde(default)]
+    pub active_index: usize,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// A recorded switch between members, also the payload of `relay-failover-switched`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailoverEvent {
+    pub id: Option<i64>,
+    pub group_id: i64,
+    pub group_name: String,
+    pub from_member: FailoverMember,
+    pub to_member: FailoverMember,
+    /// "health_check", "stream_errors" or "recovered"
+    pub reason: String,
+    pub message: String,
+    pub switched_at: Option<String>,
+}
+
+/// Runtime failover state that doesn't need to survive a restart
+#[derive(Default)]
+pub struct FailoverState {
+    runtime: Mutex<FailoverRuntime>,
+    /// Held while the enabled group is evaluated and switched, so the monitor
+    /// and concurrently reported stream errors act on its active member one
+    /// at a time
+    switching: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct FailoverRuntime {
+    /// Consecutive probe results of provider members: (last success, streak)
+    provider_checks: HashMap<String, (bool, u32)>,
+    /// Unix timestamps of upstream errors reported by Claude runs
+    stream_errors: VecDeque<i64>,
+    /// Members skipped until the given Unix timestamp, by member key
+    cooldowns: HashMap<String, i64>,
+}
+
+/// Health of a member: result of its latest check and how many checks in a
+/// row had that result
+#[derive(Debug, Clone, Copy, Default)]
+struct MemberStatus {
+    last_success: Option<bool>,
+    streak: u32,
+}
+
+/// A member resolved to the settings it would apply
+struct ResolvedMember {
+    config: Option<ProviderConfig>,
+    status: MemberStatus,
+    cooling_down: bool,
+}
+
+impl ResolvedMember {
+    fn is_down(&self, group: &FailoverGroup) -> bool {
+        self.status.last_success == Some(false) && self.status.streak >= group.failure_threshold
+    }
+
+    fn is_usable(&self, group: &FailoverGroup) -> bool {
+        self.config.is_some() && !self.cooling_down && !self.is_down(group)
+    }
+
+    /// Members without any health data recover once their cooldown is over
+    fn is_recovered(&self, group: &FailoverGroup) -> bool {
+        self.is_usable(group)
+            && match self.status.last_success {
+                Some(success) => success && self.status.streak >= group.recovery_threshold,
+                None => true,
+            }
+    }
+}
+
+/// Create the failover tables
+pub fn init_failover_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS relay_failover_groups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            members TEXT NOT NULL,
+            failure_threshold INTEGER NOT NULL DEFAULT 2,
+            error_threshold INTEGER NOT NULL DEFAULT 3,
+            error_window_secs INTEGER NOT NULL DEFAULT 300,
+            recovery_threshold INTEGER NOT NULL DEFAULT 3,
+            auto_failback BOOLEAN NOT NULL DEFAULT 1,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            active_index INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS relay_failover_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL,
+            group_name TEXT NOT NULL,
+            from_member TEXT NOT NULL,
+            to_member TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            message TEXT NOT NULL,
+            switched_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (group_id) REFERENCES relay_failover_groups(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS update_relay_failover_group_timestamp
+         AFTER UPDATE OF name, members, failure_threshold, error_threshold, error_window_secs,
+             recovery_threshold, auto_failback, enabled ON relay_failover_groups
+         FOR EACH ROW
+         BEGIN
+             UPDATE relay_failover_groups SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+         END",
+        [],
+    )?;
+
+    Ok(())
+}
+
+const GROUP_COLUMNS: &str =
+    "id, name, members, failure_threshold, error_threshold, error_window_secs,
+     recovery_threshold, auto_failback, enabled, active_index, created_at, updated_at";
+
+fn group_from_row(row: &Row) -> SqliteResult<FailoverGroup> {
+    let members: String = row.get(2)?;
+    Ok(FailoverGroup {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        members: serde_json::from_str(&members).unwrap_or_default(),
+        failure_threshold: row.get(3)?,
+        error_threshold: row.get(4)?,
+        error_window_secs: row.get(5)?,
+        recovery_threshold: row.get(6)?,
+        auto_failback: row.get(7)?,
+        enabled: row.get(8)?,
+        active_index: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
+
+fn load_group(conn: &Connection, id: i64) -> Result<FailoverGroup, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM relay_failover_groups WHERE id = ?1",
+            GROUP_COLUMNS
+        ),
+        params![id],
+        group_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn load_groups(conn: &Connection, enabled_only: bool) -> Result<Vec<FailoverGroup>, String> {
+    let sql = format!(
+        "SELECT {} FROM relay_failover_groups {} ORDER BY id",
+        GROUP_COLUMNS,
+        if enabled_only {
+            "WHERE enabled = 1"
+        } else {
+            ""
+        }
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let groups = stmt
+        .query_map([], group_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(groups)
+}
+
+fn validate_group(group: &FailoverGroup) -> Result<(), String> {
+    if group.name.trim().is_empty() {
+        return Err("Failover group name cannot be empty".to_string());
+    }
+    if group.members.len() < 2 {
+        return Err("A failover group needs at least two members".to_string());
+    }
+    for (i, member) in group.members.iter().enumerate() {
+        if group.members[..i].contains(member) {
+            return Err(format!("Duplicate failover group member: {}", member.key()));
+        }
+    }
+    if group.failure_threshold == 0 || group.error_threshold == 0 || group.recovery_threshold == 0 {
+        return Err("Failover thresholds must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+fn member_name(member: &FailoverMember, config: Option<&ProviderConfig>) -> String {
+    config
+        .map(|c| c.name.clone())
+        .unwrap_or_else(|| member.id.clone())
+}
+
+fn same_base_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/')
+        .eq_ignore_ascii_case(b.trim_end_matches('/'))
+}
+
+/// Whether a Claude API error is the upstream's fault (5xx or overloaded)
+/// rather than the request's
+fn is_upstream_failure(error: &str) -> bool {
+    if error.contains("overloaded") {
+        return true;
+    }
+    error
+        .split("API Error: ")
+        .nth(1)
+        .and_then(|rest| rest.get(..3))
+        .and_then(|code| code.parse::<u16>().ok())
+        .is_some_and(|code| (500..600).contains(&code))
+}
+
+/// Length of the run of identical results at the end of `results`
+fn trailing_status(results: &[bool]) -> MemberStatus {
+    match results.last() {
+        Some(&last) => MemberStatus {
+            last_success: Some(last),
+            streak: results.iter().rev().take_while(|&&r| r == last).count() as u32,
+        },
+        None => MemberStatus::default(),
+    }
+}
+
+/// Resolve a station member to the base URL and token it was last applied with
+fn resolve_station(manager: &RelayStationManager, station_id: &str) -> Option<ProviderConfig> {
+    let station = manager.get_station(station_id).ok().flatten()?;
+    if !station.enabled {
+        return None;
+    }
+    let usage = manager
+        .get_config_usage_status()
+        .ok()?
+        .into_iter()
+        .find(|u| u.station_id == station_id)?;
+    let model = manager
+        .get_station_config(station_id)
+        .ok()
+        .flatten()
+        .and_then(|c| c.model);
+
+    Some(ProviderConfig {
+        id: station.id,
+        name: station.name,
+        description: station.description.unwrap_or_default(),
+        base_url: usage.base_url,
+        auth_token: Some(usage.token),
+        api_key: None,
+        model,
+    })
+}
+
+/// Resolve a member to the settings it applies, or `None` if it cannot be used
+pub(crate) fn resolve_member(app: &AppHandle, member: &FailoverMember) -> Option<ProviderConfig> {
+    let config = match member.kind {
+        FailoverMemberKind::Provider => find_provider_config(&member.id).ok(),
+        FailoverMemberKind::Station => {
+            let relay_state: State<Mutex<Option<RelayStationManager>>> = app.state();
+            let relay_lock = relay_state.lock().ok()?;
+            resolve_station(relay_lock.as_ref()?, &member.id)
+        }
+    };
+    // Credentials locked in the secret store cannot be sent
+    config.filter(|c| !c.has_locked_secrets())
+}
+
+/// How requests to a member must be translated; only stations can speak the
+/// OpenAI chat format
+pub(crate) fn member_translation(app: &AppHandle, member: &FailoverMember) -> StationTranslation {
+    if member.kind != FailoverMemberKind::Station {
+        return StationTranslation::default();
+    }
+    let relay_state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let Ok(relay_lock) = relay_state.lock() else {
+        return StationTranslation::default();
+    };
+    relay_lock
+        .as_ref()
+        .and_then(|manager| manager.get_station(&member.id).ok().flatten())
+        .map(|station| StationTranslation::from_station(&station))
+        .unwrap_or_default()
+}
+
+/// Check that a provider endpoint answers; anything below 500 means the
+/// upstream is reachable. `None` when the probe could not be sent at all.
+async fn probe_provider(config: &ProviderConfig) -> Option<bool> {
+    let client = match http_client::client() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Cannot probe {}: {}", config.base_url, e);
+            return None;
+        }
+    };
+    let mut request = client
+        .get(format!(
+            "{}/v1/models",
+            config.base_url.trim_end_matches('/')
+        ))
+        .header("anthropic-version", "2023-06-01")
+        .timeout(std::time::Duration::from_secs(PROVIDER_PROBE_TIMEOUT_SECS));
+    if let Some(token) = &config.auth_token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(key) = &config.api_key {
+        request = request.header("x-api-key", key);
+    }
+
+    match request.send().await {
+        Ok(response) => Some(response.status().as_u16() < 500),
+        Err(e) => {
+            debug!("Provider probe of {} failed: {}", config.base_url, e);
+            Some(false)
+        }
+    }
+}
+
+/// Resolve every member of a group together with its current health
+fn resolve_members(app: &AppHandle, group: &FailoverGroup) -> Result<Vec<ResolvedMember>, String> {
+    let now = Utc::now().timestamp();
+    let runtime = app.state::<FailoverState>();
+    let runtime = runtime.runtime.lock().map_err(|e| e.to_string())?;
+    let relay_state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let relay_lock = relay_state.lock().map_err(|e| e.to_string())?;
+
+    let members = group
+        .members
+        .iter()
+        .map(|member| {
+            let (config, status) = match member.kind {
+                FailoverMemberKind::Provider => (
+                    find_provider_config(&member.id).ok(),
+                    runtime
+                        .provider_checks
+                        .get(&member.id)
+                        .map(|&(last_success, streak)| MemberStatus {
+                            last_success: Some(last_success),
+                            streak,
+                        })
+                        .unwrap_or_default(),
+                ),
+                FailoverMemberKind::Station => match relay_lock.as_ref() {
+                    Some(manager) => {
+                        let since = now - STATION_HISTORY_HOURS * 3600;
+                        let results: Vec<bool> = manager
+                            .list_station_health_checks(&member.id, since)
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|c| c.success)
+                            .collect();
+                        // The CLI cannot talk to an OpenAI-format station
+                        // directly, only through the routing proxy
+                        let config = resolve_station(manager, &member.id).filter(|_| {
+                            !manager
+                                .get_station(&member.id)
+                                .ok()
+                                .flatten()
+                                .is_some_and(|s| StationTranslation::from_station(&s).is_openai())
+                        });
+                        (config, trailing_status(&results))
+                    }
+                    None => (None, MemberStatus::default()),
+                },
+            };
+            ResolvedMember {
+                config: config.filter(|c| !c.has_locked_secrets()),
+                status,
+                cooling_down: runtime
+                    .cooldowns
+                    .get(&member.key())
+                    .is_some_and(|&until| until > now),
+            }
+        })
+        .collect();
+    Ok(members)
+}
+
+/// The next usable member after `from`, in group order
+fn next_usable(group: &FailoverGroup, members: &[ResolvedMember], from: usize) -> Option<usize> {
+    (1..members.len())
+        .map(|offset| (from + offset) % members.len())
+        .find(|&i| members[i].is_usable(group))
+}
+
+/// Whether settings.json, currently pointing at `current_base_url`, still
+/// uses the group's active member; if the user switched to something else by
+/// hand the group leaves it alone
+fn group_is_applied(
+    group: &FailoverGroup,
+    members: &[ResolvedMember],
+    current_base_url: Option<&str>,
+) -> bool {
+    match (
+        current_base_url,
+        members
+            .get(group.active_index)
+            .and_then(|m| m.config.as_ref()),
+    ) {
+        (Some(current), Some(active)) => same_base_url(current, &active.base_url),
+        _ => false,
+    }
+}
+
+fn notify_switch(app: &AppHandle, event: &FailoverEvent, from_name: &str, to_name: &str) {
+    let (title_key, body_key) = if event.reason == "recovered" {
+        ("relay.failback_title", "relay.failback_body")
+    } else {
+        ("relay.failover_title", "relay.failover_body")
+    };
+    let title = i18n::t_with_args(title_key, &[("group", event.group_name.as_str())]);
+    let body = i18n::t_with_args(
+        body_key,
+        &[
+            ("from", from_name),
+            ("to", to_name),
+            ("message", event.message.as_str()),
+        ],
+    );
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        error!("Failed to show failover notification: {}", e);
+    }
+    let _ = app.emit("relay-failover-switched", event);
+}
+
+/// Apply member `to` of the group to settings.json and record the switch
+async fn switch_member(
+    app: &AppHandle,
+    group: &FailoverGroup,
+    members: &[ResolvedMember],
+    to: usize,
+    reason: &str,
+    message: String,
+) -> Result<(), String> {
+    let group_id = group.id.ok_or("Failover group has no id")?;
+    let from = group.active_index.min(group.members.len() - 1);
+    let config = members[to].config.clone().ok_or_else(|| {
+        format!(
+            "Failover member {} cannot be applied",
+            group.members[to].key()
+        )
+    })?;
+
+    apply_provider_config(config.clone()).await?;
+
+    if group.members[to].kind == FailoverMemberKind::Station {
+        let relay_state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let relay_lock = relay_state.lock().map_err(|e| e.to_string())?;
+        if let Some(manager) = relay_lock.as_ref() {
+            if let Err(e) = manager.record_config_usage(
+                &config.id,
+                &config.base_url,
+                config.auth_token.as_deref().unwrap_or_default(),
+            ) {
+                warn!(
+                    "Failed to record config usage of station {}: {}",
+                    config.id, e
+                );
+            }
+        }
+    }
+
+    let mut event = FailoverEvent {
+        id: None,
+        group_id,
+        group_name: group.name.clone(),
+        from_member: group.members[from].clone(),
+        to_member: group.members[to].clone(),
+        reason: reason.to_string(),
+        message,
+        switched_at: None,
+    };
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE relay_failover_groups SET active_index = ?1 WHERE id = ?2",
+            params![to, group_id],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO relay_failover_events (group_id, group_name, from_member, to_member, reason, message)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                group_id,
+                event.group_name,
+                serde_json::to_string(&event.from_member).map_err(|e| e.to_string())?,
+                serde_json::to_string(&event.to_member).map_err(|e| e.to_string())?,
+                event.reason,
+                event.message,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        event.id = Some(conn.last_insert_rowid());
+    }
+
+    // Errors seen so far belong to the member we just left
+    if let Ok(mut runtime) = app.state::<FailoverState>().runtime.lock() {
+        runtime.stream_errors.clear();
+    }
+
+    let from_name = member_name(&group.members[from], members[from].config.as_ref());
+    let to_name = member_name(&group.members[to], Some(&config));
+    info!(
+        "Failover group '{}' switched from {} to {} ({})",
+        group.name, from_name, to_name, reason
+    );
+    notify_switch(app, &event, &from_name, &to_name);
+    Ok(())
+}
+
+fn load_enabled_group(app: &AppHandle) -> Result<Option<FailoverGroup>, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_groups(&conn, true)?.into_iter().next())
+}
+
+/// Probe the provider members, then fail over or back as their health requires
+async fn check_failover(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<FailoverState>();
+    let _switching = state.switching.lock().await;
+    let Some(group) = load_enabled_group(app)? else {
+        return Ok(());
+    };
+    // Probes without credentials would fail every member
+    if is_store_locked() {
+        info!("Secret store is locked, skipping failover checks");
+        return Ok(());
+    }
+
+    let providers: Vec<ProviderConfig> = group
+        .members
+        .iter()
+        .filter(|m| m.kind == FailoverMemberKind::Provider)
+        .filter_map(|m| find_provider_config(&m.id).ok())
+        .collect();
+    for provider in providers {
+        // A broken client configuration says nothing about the provider
+        let Some(success) = probe_provider(&provider).await else {
+            continue;
+        };
+        let mut runtime = state.runtime.lock().map_err(|e| e.to_string())?;
+        let entry = runtime
+            .provider_checks
+            .entry(provider.id)
+            .or_insert((success, 0));
+        if entry.0 == success {
+            entry.1 += 1;
+        } else {
+            *entry = (success, 1);
+        }
+    }
+
+    let members = resolve_members(app, &group)?;
+    let active = group.active_index.min(members.len() - 1);
+    let current = current_provider_config().anthropic_base_url;
+    if !group_is_applied(&group, &members, current.as_deref()) {
+        debug!("Failover group '{}' is not applied, skipping", group.name);
+        return Ok(());
+    }
+
+    if members[active].is_down(&group) {
+        match next_usable(&group, &members, active) {
+            Some(to) => {
+                let message = format!(
+                    "{} failed {} health checks in a row",
+                    member_name(&group.members[active], members[active].config.as_ref()),
+                    members[active].status.streak
+                );
+                switch_member(app, &group, &members, to, "health_check", message).await?;
+            }
+            None => warn!(
+                "Failover group '{}' has no healthy member to switch to",
+                group.name
+            ),
+        }
+    } else if group.auto_failback && active != 0 && members[0].is_recovered(&group) {
+        let message = format!(
+            "{} passed {} health checks in a row",
+            member_name(&group.members[0], members[0].config.as_ref()),
+            members[0].status.streak
+        );
+        switch_member(app, &group, &members, 0, "recovered", message).await?;
+    }
+    Ok(())
+}
+
+/// Count an upstream error and fail over once the enabled group's threshold is reached
+///
+/// Runs once per reported error; the group and its active member are loaded
+/// under the switching lock, so errors racing a switch count towards the
+/// member that was switched to instead of switching again.
+async fn check_stream_errors(app: AppHandle, error: String) -> Result<(), String> {
+    let state = app.state::<FailoverState>();
+    let _switching = state.switching.lock().await;
+    let Some(group) = load_enabled_group(&app)? else {
+        return Ok(());
+    };
+
+    let now = Utc::now().timestamp();
+    let active = group.active_index.min(group.members.len() - 1);
+    {
+        let mut runtime = state.runtime.lock().map_err(|e| e.to_string())?;
+        runtime.stream_errors.push_back(now);
+        let window_start = now - group.error_window_secs as i64;
+        while runtime
+            .stream_errors
+            .front()
+            .is_some_and(|&t| t < window_start)
+        {
+            runtime.stream_errors.pop_front();
+        }
+        if runtime.stream_errors.len() < group.error_threshold as usize {
+            return Ok(());
+        }
+    }
+
+    let members = resolve_members(&app, &group)?;
+    let current = current_provider_config().anthropic_base_url;
+    if !group_is_applied(&group, &members, current.as_deref()) {
+        return Ok(());
+    }
+
+    let Some(to) = next_usable(&group, &members, active) else {
+        warn!(
+            "Failover group '{}' has no healthy member to switch to",
+            group.name
+        );
+        return Ok(());
+    };
+    {
+        let mut runtime = state.runtime.lock().map_err(|e| e.to_string())?;
+        runtime.cooldowns.insert(
+            group.members[active].key(),
+            now + group.error_window_secs as i64,
+        );
+    }
+    let message = format!(
+        "{} upstream errors within {}s, last: {}",
+        group.error_threshold, group.error_window_secs, error
+    );
+    switch_member(&app, &group, &members, to, "stream_errors", message).await
+}
+
+/// Report an API error seen in a Claude run's output; 5xx and overloaded
+/// errors count towards failing over the enabled group
+pub fn report_stream_error(app: &AppHandle, error: &str) {
+    if !is_upstream_failure(error) {
+        return;
+    }
+    let app = app.clone();
+    let error = error.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = check_stream_errors(app, error).await {
+            error!("Failover on stream errors failed: {}", e);
+        }
+    });
+}
+
+/// Periodically evaluate the enabled failover group in the background
+pub fn start_failover_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(FAILOVER_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_failover(&app).await {
+                error!("Failover check failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Keep at most one group enabled
+fn disable_other_groups(conn: &Connection, id: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE relay_failover_groups SET enabled = 0 WHERE id != ?1 AND enabled = 1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// List all failover groups
+#[command]
+pub fn list_failover_groups(db: State<'_, AgentDb>) -> Result<Vec<FailoverGroup>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_groups(&conn, false)
+}
+
+/// Create a failover group; its first member is assumed to be the one applied
+#[command]
+pub fn create_failover_group(
+    db: State<'_, AgentDb>,
+    group: FailoverGroup,
+) -> Result<FailoverGroup, String> {
+    validate_group(&group)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO relay_failover_groups
+         (name, members, failure_threshold, error_threshold, error_window_secs, recovery_threshold, auto_failback, enabled)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            group.name,
+            serde_json::to_string(&group.members).map_err(|e| e.to_string())?,
+            group.failure_threshold,
+            group.error_threshold,
+            group.error_window_secs,
+            group.recovery_threshold,
+            group.auto_failback,
+            group.enabled,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let id = conn.last_insert_rowid();
+    if group.enabled {
+        disable_other_groups(&conn, id)?;
+    }
+    load_group(&conn, id)
+}
+
+/// Update a failover group
+#[command]
+pub fn update_failover_group(
+    db: State<'_, AgentDb>,
+    id: i64,
+    group: FailoverGroup,
+) -> Result<FailoverGroup, String> {
+    validate_group(&group)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let rows = conn
+        .execute(
+            "UPDATE relay_failover_groups
+             SET name = ?1, members = ?2, failure_threshold = ?3, error_threshold = ?4, error_window_secs = ?5,
+                 recovery_threshold = ?6, auto_failback = ?7, enabled = ?8, active_index = MIN(active_index, ?9)
+             WHERE id = ?10",
+            params![
+                group.name,
+                serde_json::to_string(&group.members).map_err(|e| e.to_string())?,
+                group.failure_threshold,
+                group.error_threshold,
+                group.error_window_secs,
+                group.recovery_threshold,
+                group.auto_failback,
+                group.enabled,
+                group.members.len() - 1,
+                id,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    if rows == 0 {
+        return Err(format!("Failover group {} not found", id));
+    }
+
+    if group.enabled {
+        disable_other_groups(&conn, id)?;
+    }
+    load_group(&conn, id)
+}
+
+/// Delete a failover group and its switch history
+#[command]
+pub fn delete_failover_group(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM relay_failover_events WHERE group_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM relay_failover_groups WHERE id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// List recorded switches, most recent first
+#[command]
+pub fn list_failover_events(
+    db: State<'_, AgentDb>,
+    group_id: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<FailoverEvent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, group_id, group_name, from_member, to_member, reason, message, switched_at
+             FROM relay_failover_events
+             WHERE ?1 IS NULL OR group_id = ?1
+             ORDER BY id DESC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let events = stmt
+        .query_map(params![group_id, limit.unwrap_or(100)], |row| {
+            let from_member: String = row.get(3)?;
+            let to_member: String = row.get(4)?;
+            let parse = |value: &str| -> SqliteResult<FailoverMember> {
+                serde_json::from_str(value).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        3,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })
+            };
+            Ok(FailoverEvent {
+                id: Some(row.get(0)?),
+                group_id: row.get(1)?,
+                group_name: row.get(2)?,
+                from_member: parse(&from_member)?,
+                to_member: parse(&to_member)?,
+                reason: row.get(5)?,
+                message: row.get(6)?,
+                switched_at: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(active_index: usize) -> FailoverGroup {
+        let mut group: FailoverGroup = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "main",
+            "members": [
+                { "kind": "station", "id": "a" },
+                { "kind": "provider", "id": "b" },
+                { "kind": "provider", "id": "c" }
+            ]
+        }))
+        .unwrap();
+        group.active_index = active_index;
+        group
+    }
+
+    fn member(base_url: Option<&str>, results: &[bool], cooling_down: bool) -> ResolvedMember {
+        ResolvedMember {
+            config: base_url.map(|base_url| ProviderConfig {
+                id: base_url.to_string(),
+                name: base_url.to_string(),
+                description: String::new(),
+                base_url: base_url.to_string(),
+                auth_token: Some("sk-test".to_string()),
+                api_key: None,
+                model: None,
+            }),
+            status: trailing_status(results),
+            cooling_down,
+        }
+    }
+
+    #[test]
+    fn upstream_failures_are_5xx_or_overloaded() {
+        assert!(is_upstream_failure(
+            "API Error: 529 {\"type\":\"overloaded_error\"}"
+        ));
+        assert!(is_upstream_failure("API Error: 502 Bad Gateway"));
+        assert!(is_upstream_failure("upstream overloaded"));
+        assert!(!is_upstream_failure("API Error: 400 invalid model"));
+        assert!(!is_upstream_failure("API Error: 429 rate limited"));
+        assert!(!is_upstream_failure("connection reset"));
+    }
+
+    #[test]
+    fn trailing_status_counts_the_last_run() {
+        let status = trailing_status(&[true, false, true, true]);
+        assert_eq!((status.last_success, status.streak), (Some(true), 2));
+        let status = trailing_status(&[true, false, false]);
+        assert_eq!((status.last_success, status.streak), (Some(false), 2));
+        let status = trailing_status(&[]);
+        assert_eq!((status.last_success, status.streak), (None, 0));
+    }
+
+    #[test]
+    fn next_usable_skips_down_cooling_and_unresolved_members() {
+        let group = group(0);
+        let members = vec![
+            member(Some("https://a.example"), &[false, false], false),
+            member(None, &[], false),
+            member(Some("https://c.example"), &[true], false),
+        ];
+        assert_eq!(next_usable(&group, &members, 0), Some(2));
+        // Wraps around to the primary, which is down
+        assert_eq!(next_usable(&group, &members, 2), None);
+
+        let members = vec![
+            member(Some("https://a.example"), &[true], false),
+            member(Some("https://b.example"), &[false], false),
+            member(Some("https://c.example"), &[true], true),
+        ];
+        // One failure is below the threshold, a cooling member is skipped
+        assert_eq!(next_usable(&group, &members, 0), Some(1));
+        assert_eq!(next_usable(&group, &members, 1), Some(0));
+    }
+
+    #[test]
+    fn group_is_applied_compares_the_active_base_url() {
+        let members = vec![
+            member(Some("https://a.example/"), &[], false),
+            member(Some("https://b.example"), &[], false),
+            member(None, &[], false),
+        ];
+        assert!(group_is_applied(
+            &group(0),
+            &members,
+            Some("https://A.example")
+        ));
+        assert!(!group_is_applied(
+            &group(1),
+            &members,
+            Some("https://a.example")
+        ));
+        assert!(!group_is_applied(&group(0), &members, None));
+        assert!(!group_is_applied(
+            &group(2),
+            &members,
+            Some("https://a.example")
+        ));
+    }
+}
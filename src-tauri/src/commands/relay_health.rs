@@ -0,0 +1,269 @@
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use super::relay_stations::{
+    create_adapter, ConnectionTestResult, RelayStation, RelayStationAdapter, RelayStationManager,
+    StationHealthCheck,
+};
+use super::secrets::is_store_locked;
+use super::usage_breakdown::percentile;
+use crate::i18n;
+
+const HEALTH_CHECK_INTERVAL_SECS: u64 = 300;
+// History kept for uptime and latency statistics
+const HEALTH_HISTORY_DAYS: i64 = 30;
+const DEFAULT_STATS_HOURS: u32 = 24;
+
+/// Emitted when a monitored station goes down or recovers
+#[derive(Debug, Clone, Serialize)]
+pub struct StationStatusChange {
+    pub station_id: String,
+    pub station_name: String,
+    pub up: bool,
+    pub status_code: Option<u16>,
+    pub message: String,
+}
+
+/// Uptime and latency of a station over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct StationHealthStats {
+    pub station_id: String,
+    pub station_name: String,
+    pub period_hours: u32,
+    pub check_count: u64,
+    /// Percentage of successful checks
+    pub uptime_percent: Option<f64>,
+    /// Response times in milliseconds of successful checks
+    pub latency_p50: Option<u64>,
+    pub latency_p95: Option<u64>,
+    pub last_check: Option<StationHealthCheck>,
+    /// Checks in the period, oldest first
+    pub history: Vec<StationHealthCheck>,
+}
+
+fn health_stats(
+    station: &RelayStation,
+    period_hours: u32,
+    history: Vec<StationHealthCheck>,
+) -> StationHealthStats {
+    let successes = history.iter().filter(|c| c.success).count();
+    let mut latencies: Vec<u64> = history
+        .iter()
+        .filter(|c| c.success)
+        .filter_map(|c| c.response_time)
+        .collect();
+    latencies.sort_unstable();
+
+    StationHealthStats {
+        station_id: station.id.clone(),
+        station_name: station.name.clone(),
+        period_hours,
+        check_count: history.len() as u64,
+        uptime_percent: (!history.is_empty())
+            .then(|| successes as f64 / history.len() as f64 * 100.0),
+        latency_p50: percentile(&latencies, 0.5),
+        latency_p95: percentile(&latencies, 0.95),
+        last_check: history.last().cloned(),
+        history,
+    }
+}
+
+fn notify_status_change(app: &AppHandle, change: &StationStatusChange) {
+    let (title_key, body_key) = if change.up {
+        (
+            "relay.station_recovered_title",
+            "relay.station_recovered_body",
+        )
+    } else {
+        ("relay.station_down_title", "relay.station_down_body")
+    };
+    let title = i18n::t_with_args(title_key, &[("name", change.station_name.as_str())]);
+    let body = i18n::t_with_args(body_key, &[("message", change.message.as_str())]);
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        error!("Failed to show station status notification: {}", e);
+    }
+    let _ = app.emit("relay-station-status-changed", change);
+}
+
+/// Test every enabled station once and report the ones whose status changed
+async fn check_stations(app: &AppHandle) -> Result<(), String> {
+    // Locked tokens would make every station look down
+    if is_store_locked() {
+        info!("Secret store is locked, skipping relay station health checks");
+        return Ok(());
+    }
+    let stations: Vec<RelayStation> = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state.lock().map_err(|e| e.to_string())?;
+        match manager_lock.as_ref() {
+            Some(manager) => manager.list_stations().map_err(|e| e.to_string())?,
+            None => return Ok(()),
+        }
+    };
+
+    for station in stations.into_iter().filter(|s| s.enabled) {
+        // Custom configurations have nothing to probe
+        if matches!(station.adapter, RelayStationAdapter::Custom) {
+            continue;
+        }
+
+        let adapter = create_adapter(&station.adapter);
+        let result =
+            adapter
+                .test_connection(&station)
+                .await
+                .unwrap_or_else(|e| ConnectionTestResult {
+                    success: false,
+                    response_time: None,
+                    message: e.to_string(),
+                    status_code: None,
+                    details: None,
+                });
+
+        let previous = {
+            let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+            let manager_lock = state.lock().map_err(|e| e.to_string())?;
+            match manager_lock.as_ref() {
+                Some(manager) => manager
+                    .record_station_health(&station.id, &result)
+                    .map_err(|e| e.to_string())?,
+                None => return Ok(()),
+            }
+        };
+
+        // The first check of a station sets its baseline
+        if previous.is_some_and(|was_up| was_up != result.success) {
+            let change = StationStatusChange {
+                station_id: station.id.clone(),
+                station_name: station.name.clone(),
+                up: result.success,
+                status_code: result.status_code,
+                message: result.message.clone(),
+            };
+            if change.up {
+                info!("Relay station '{}' recovered", station.name);
+            } else {
+                warn!(
+                    "Relay station '{}' is down: {}",
+                    station.name, result.message
+                );
+            }
+            notify_status_change(app, &change);
+        }
+    }
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    if let Some(manager) = manager_lock.as_ref() {
+        let before = Utc::now().timestamp() - HEALTH_HISTORY_DAYS * 24 * 3600;
+        manager
+            .prune_station_health_checks(before)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Periodically test the enabled relay stations in the background
+pub fn start_relay_health_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_stations(&app).await {
+                error!("Relay station health check failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Get uptime and p50/p95 latency of one station, or of every station, over
+/// the last `hours` hours (default 24)
+#[tauri::command]
+pub async fn get_relay_station_health_stats(
+    app: AppHandle,
+    station_id: Option<String>,
+    hours: Option<u32>,
+) -> Result<Vec<StationHealthStats>, String> {
+    let hours = hours.unwrap_or(DEFAULT_STATS_HOURS).max(1);
+    let since = Utc::now().timestamp() - hours as i64 * 3600;
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    let Some(manager) = manager_lock.as_ref() else {
+        return Ok(Vec::new());
+    };
+
+    let stations = match &station_id {
+        Some(id) => vec![manager
+            .get_station(id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Station not found: {}", id))?],
+        None => manager.list_stations().map_err(|e| e.to_string())?,
+    };
+
+    stations
+        .iter()
+        .map(|station| {
+            let history = manager
+                .list_station_health_checks(&station.id, since)
+                .map_err(|e| e.to_string())?;
+            Ok(health_stats(station, hours, history))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::relay_stations::AuthMethod;
+
+    fn check(success: bool, response_time: Option<u64>, checked_at: i64) -> StationHealthCheck {
+        StationHealthCheck {
+            success,
+            response_time,
+            status_code: None,
+            checked_at,
+        }
+    }
+
+    #[test]
+    fn health_stats_use_successful_latencies() {
+        let station = RelayStation {
+            id: "s1".to_string(),
+            name: "Relay".to_string(),
+            description: None,
+            api_url: "https://relay.example".to_string(),
+            adapter: RelayStationAdapter::Newapi,
+            auth_method: AuthMethod::BearerToken,
+            system_token: String::new(),
+            user_id: None,
+            adapter_config: None,
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        };
+        let mut history: Vec<StationHealthCheck> = (1..=10)
+            .map(|i| check(true, Some(i * 100), i as i64))
+            .collect();
+        // Failed checks count against uptime but not latency
+        history.push(check(false, Some(30_000), 11));
+        history.push(check(false, None, 12));
+
+        let stats = health_stats(&station, 24, history);
+        assert_eq!(stats.check_count, 12);
+        assert!((stats.uptime_percent.unwrap() - 1000.0 / 12.0).abs() < 1e-9);
+        assert_eq!(stats.latency_p50, Some(500));
+        assert_eq!(stats.latency_p95, Some(1000));
+        assert_eq!(stats.last_check.unwrap().checked_at, 12);
+
+        let empty = health_stats(&station, 24, Vec::new());
+        assert_eq!(empty.uptime_percent, None);
+        assert_eq!(empty.latency_p50, None);
+    }
+}
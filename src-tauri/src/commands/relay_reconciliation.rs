@@ -0,0 +1,653 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::pricing::load_pricing_config;
+use super::relay_stations::{create_adapter, RelayStation, RelayStationManager, StationLogEntry};
+use super::usage::parse_filter_date;
+use super::usage_index::lock_synced_index;
+
+// NewAPI's default quota units per USD, used when the station doesn't report its own
+const DEFAULT_QUOTA_PER_UNIT: f64 = 500_000.0;
+const LOG_PAGE_SIZE: usize = 100;
+// Stop paging after this many log entries
+const MAX_LOG_PAGES: usize = 100;
+// Slack (seconds) between a station log's time and the local entry's
+const DEFAULT_MATCH_WINDOW_SECS: i64 = 120;
+// Relative token and cost differences tolerated before flagging a request
+const DEFAULT_TOLERANCE: f64 = 0.05;
+// Token counts this close always match, however small
+const TOKEN_SLACK: i64 = 5;
+const MAX_DISCREPANCIES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// The station billed more than local pricing expects
+    Overcharge,
+    /// The station logged a different model than the response reported
+    ModelMismatch,
+    /// Billed by the station with no local usage to match
+    StationOnly,
+    /// Local usage the station has no log for
+    LocalOnly,
+}
+
+/// A request whose station log and local usage disagree
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationDiscrepancy {
+    pub kind: DiscrepancyKind,
+    /// Unix seconds of the station log, or of the local entry when unmatched
+    pub timestamp: i64,
+    pub station_log_id: Option<String>,
+    pub session_id: Option<String>,
+    pub station_model: Option<String>,
+    pub local_model: Option<String>,
+    pub station_prompt_tokens: Option<i64>,
+    pub station_completion_tokens: Option<i64>,
+    /// Input plus cache tokens
+    pub local_prompt_tokens: Option<i64>,
+    pub local_output_tokens: Option<i64>,
+    /// USD
+    pub station_cost: Option<f64>,
+    /// USD
+    pub local_cost: Option<f64>,
+}
+
+/// Station billing compared with local usage; costs are in USD
+#[derive(Debug, Clone, Serialize)]
+pub struct StationReconciliation {
+    pub station_id: String,
+    pub station_name: String,
+    /// Base URLs local usage was attributed to the station by
+    pub api_base_urls: Vec<String>,
+    pub quota_per_unit: f64,
+    pub station_requests: u64,
+    pub local_requests: u64,
+    pub matched_requests: u64,
+    pub station_quota: i64,
+    pub station_cost: f64,
+    pub local_cost: f64,
+    /// `station_cost - local_cost` over matched requests
+    pub matched_cost_difference: f64,
+    /// `matched_cost_difference` as a percentage of the matched local cost
+    pub matched_cost_difference_percent: f64,
+    pub overcharged_requests: u64,
+    pub model_mismatches: u64,
+    pub station_only_requests: u64,
+    pub local_only_requests: u64,
+    /// Station logs weren't fetched completely
+    pub truncated: bool,
+    /// Why the station couldn't be reconciled
+    pub error: Option<String>,
+    /// Most significant discrepancies first
+    pub discrepancies: Vec<ReconciliationDiscrepancy>,
+}
+
+/// One deduplicated local usage entry
+#[derive(Debug, Clone)]
+struct LocalRequest {
+    timestamp: i64,
+    session_id: String,
+    model: String,
+    input_tokens: i64,
+    output_tokens: i64,
+    prompt_tokens: i64,
+    cost: f64,
+}
+
+struct ReconcileSettings {
+    quota_per_unit: f64,
+    window_secs: i64,
+    tolerance: f64,
+}
+
+impl StationReconciliation {
+    fn new(station: &RelayStation, api_base_urls: Vec<String>) -> Self {
+        Self {
+            station_id: station.id.clone(),
+            station_name: station.name.clone(),
+            api_base_urls,
+            quota_per_unit: DEFAULT_QUOTA_PER_UNIT,
+            station_requests: 0,
+            local_requests: 0,
+            matched_requests: 0,
+            station_quota: 0,
+            station_cost: 0.0,
+            local_cost: 0.0,
+            matched_cost_difference: 0.0,
+            matched_cost_difference_percent: 0.0,
+            overcharged_requests: 0,
+            model_mismatches: 0,
+            station_only_requests: 0,
+            local_only_requests: 0,
+            truncated: false,
+            error: None,
+            discrepancies: Vec::new(),
+        }
+    }
+}
+
+/// Model name without provider prefix, date suffix or case, e.g.
+/// "anthropic/claude-sonnet-4-20250514" -> "claude-sonnet-4"
+fn normalize_model(model: &str) -> String {
+    let model = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    let model = model.strip_suffix("-latest").unwrap_or(&model);
+    match model.rsplit_once('-') {
+        Some((base, date)) if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) => {
+            base.to_string()
+        }
+        _ => model.to_string(),
+    }
+}
+
+fn same_model(a: &str, b: &str) -> bool {
+    normalize_model(a) == normalize_model(b)
+}
+
+fn tokens_match(station: i64, local: i64, tolerance: f64) -> bool {
+    let diff = (station - local).abs();
+    diff <= TOKEN_SLACK || diff as f64 <= local.max(station) as f64 * tolerance
+}
+
+/// How well a local request matches a station log; lower is better, None if it can't match
+fn match_score(log: &StationLogEntry, local: &LocalRequest, settings: &ReconcileSettings) -> Option<f64> {
+    // The log is written when the request finishes; `use_time` is its duration
+    let started = log.timestamp - log.use_time.unwrap_or(0).max(0);
+    if local.timestamp < started - settings.window_secs
+        || local.timestamp > log.timestamp + settings.window_secs
+    {
+        return None;
+    }
+
+    // Relays differ in whether prompt tokens include cached tokens
+    let completion = log.completion_tokens.unwrap_or(0);
+    let prompt = log.prompt_tokens.unwrap_or(0);
+    if !tokens_match(completion, local.output_tokens, settings.tolerance)
+        || !(tokens_match(prompt, local.input_tokens, settings.tolerance)
+            || tokens_match(prompt, local.prompt_tokens, settings.tolerance))
+    {
+        return None;
+    }
+
+    let mut score = (local.timestamp - log.timestamp).abs() as f64;
+    if !log.model_name.as_deref().is_some_and(|m| same_model(m, &local.model)) {
+        // Prefer a same-model candidate, but still pair swapped models
+        score += settings.window_secs as f64 * 10.0;
+    }
+    Some(score)
+}
+
+fn station_cost(log: &StationLogEntry, quota_per_unit: f64) -> f64 {
+    log.quota.unwrap_or(0) as f64 / quota_per_unit
+}
+
+/// Pair station logs with local requests and collect the discrepancies
+fn reconcile(
+    result: &mut StationReconciliation,
+    mut logs: Vec<StationLogEntry>,
+    locals: Vec<LocalRequest>,
+    settings: &ReconcileSettings,
+) {
+    logs.sort_by_key(|log| log.timestamp);
+    result.quota_per_unit = settings.quota_per_unit;
+    result.station_requests = logs.len() as u64;
+    result.local_requests = locals.len() as u64;
+    result.station_quota = logs.iter().filter_map(|log| log.quota).sum();
+    result.station_cost = result.station_quota as f64 / settings.quota_per_unit;
+    result.local_cost = locals.iter().map(|l| l.cost).sum();
+
+    let mut matched_local = vec![false; locals.len()];
+    let (mut matched_station_cost, mut matched_local_cost) = (0.0, 0.0);
+    let mut discrepancies = Vec::new();
+
+    for log in &logs {
+        let billed = station_cost(log, settings.quota_per_unit);
+        let best = locals
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !matched_local[*i])
+            .filter_map(|(i, local)| match_score(log, local, settings).map(|score| (i, score)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let discrepancy = |kind, local: Option<&LocalRequest>| ReconciliationDiscrepancy {
+            kind,
+            timestamp: log.timestamp,
+            station_log_id: Some(log.id.clone()),
+            session_id: local.map(|l| l.session_id.clone()),
+            station_model: log.model_name.clone(),
+            local_model: local.map(|l| l.model.clone()),
+            station_prompt_tokens: log.prompt_tokens,
+            station_completion_tokens: log.completion_tokens,
+            local_prompt_tokens: local.map(|l| l.prompt_tokens),
+            local_output_tokens: local.map(|l| l.output_tokens),
+            station_cost: Some(billed),
+            local_cost: local.map(|l| l.cost),
+        };
+
+        let Some((index, _)) = best else {
+            result.station_only_requests += 1;
+            discrepancies.push(discrepancy(DiscrepancyKind::StationOnly, None));
+            continue;
+        };
+        matched_local[index] = true;
+        let local = &locals[index];
+        result.matched_requests += 1;
+        matched_station_cost += billed;
+        matched_local_cost += local.cost;
+
+        if !log.model_name.as_deref().is_some_and(|m| same_model(m, &local.model)) {
+            result.model_mismatches += 1;
+            discrepancies.push(discrepancy(DiscrepancyKind::ModelMismatch, Some(local)));
+        }
+        if billed > local.cost * (1.0 + settings.tolerance) + f64::EPSILON {
+            result.overcharged_requests += 1;
+            discrepancies.push(discrepancy(DiscrepancyKind::Overcharge, Some(local)));
+        }
+    }
+
+    for (local, _) in locals.iter().zip(&matched_local).filter(|(_, matched)| !**matched) {
+        result.local_only_requests += 1;
+        discrepancies.push(ReconciliationDiscrepancy {
+            kind: DiscrepancyKind::LocalOnly,
+            timestamp: local.timestamp,
+            station_log_id: None,
+            session_id: Some(local.session_id.clone()),
+            station_model: None,
+            local_model: Some(local.model.clone()),
+            station_prompt_tokens: None,
+            station_completion_tokens: None,
+            local_prompt_tokens: Some(local.prompt_tokens),
+            local_output_tokens: Some(local.output_tokens),
+            station_cost: None,
+            local_cost: Some(local.cost),
+        });
+    }
+
+    result.matched_cost_difference = matched_station_cost - matched_local_cost;
+    result.matched_cost_difference_percent = if matched_local_cost > 0.0 {
+        result.matched_cost_difference / matched_local_cost * 100.0
+    } else {
+        0.0
+    };
+
+    // Billing problems first, then by the money involved
+    let rank = |kind: DiscrepancyKind| match kind {
+        DiscrepancyKind::Overcharge => 0,
+        DiscrepancyKind::ModelMismatch => 1,
+        DiscrepancyKind::StationOnly => 2,
+        DiscrepancyKind::LocalOnly => 3,
+    };
+    let amount = |d: &ReconciliationDiscrepancy| {
+        d.station_cost.unwrap_or(0.0) - d.local_cost.unwrap_or(0.0)
+    };
+    discrepancies.sort_by(|a, b| {
+        rank(a.kind)
+            .cmp(&rank(b.kind))
+            .then(amount(b).abs().total_cmp(&amount(a).abs()))
+    });
+    discrepancies.truncate(MAX_DISCREPANCIES);
+    result.discrepancies = discrepancies;
+}
+
+/// Local usage sent to any of `api_base_urls` between two Unix timestamps, costs in USD
+fn load_local_requests(
+    conn: &Connection,
+    api_base_urls: &[String],
+    start: i64,
+    end: i64,
+) -> Result<Vec<LocalRequest>, String> {
+    if api_base_urls.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let currency_multiplier = load_pricing_config().currency_multiplier;
+    let currency_multiplier = if currency_multiplier > 0.0 { currency_multiplier } else { 1.0 };
+
+    let placeholders: Vec<String> = (3..api_base_urls.len() + 3).map(|i| format!("?{}", i)).collect();
+    let mut values = vec![(start * 1000).to_string(), (end * 1000).to_string()];
+    values.extend(api_base_urls.iter().cloned());
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT timestamp_ms, session_id, model, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_read_tokens, cost
+             FROM usage_entries
+             WHERE timestamp_ms >= CAST(?1 AS INTEGER) AND timestamp_ms < CAST(?2 AS INTEGER)
+               AND RTRIM(LOWER(api_base_url), '/') IN ({})
+             ORDER BY timestamp_ms",
+            placeholders.join(", ")
+        ))
+        .map_err(|e| e.to_string())?;
+    let requests = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            let input_tokens: i64 = row.get(3)?;
+            let cache_tokens: i64 = row.get::<_, i64>(5)? + row.get::<_, i64>(6)?;
+            Ok(LocalRequest {
+                timestamp: row.get::<_, i64>(0)? / 1000,
+                session_id: row.get(1)?,
+                model: row.get(2)?,
+                input_tokens,
+                output_tokens: row.get(4)?,
+                prompt_tokens: input_tokens + cache_tokens,
+                cost: row.get::<_, f64>(7)? / currency_multiplier,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(requests)
+}
+
+/// Whether paging can stop: a page brought nothing new, or the station's
+/// reported total is reached
+fn logs_complete(before: usize, after: usize, total: i64) -> bool {
+    after == before || (total > 0 && after as i64 >= total)
+}
+
+/// Fetch the station's request logs between two Unix timestamps
+async fn fetch_station_logs(
+    station: &RelayStation,
+    start: i64,
+    end: i64,
+) -> Result<(Vec<StationLogEntry>, bool), String> {
+    let adapter = create_adapter(&station.adapter);
+    // Log filters are minutes in UTC
+    let format = |ts: i64| {
+        chrono::DateTime::from_timestamp(ts, 0)
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let filters = serde_json::json!({
+        "startTime": format(start),
+        "endTime": format(end),
+    });
+
+    let mut logs = Vec::new();
+    let mut seen = HashSet::new();
+    let mut truncated = true;
+    for page in 1..=MAX_LOG_PAGES {
+        let response = adapter
+            .get_logs(station, Some(page), Some(LOG_PAGE_SIZE), Some(filters.clone()))
+            .await
+            .map_err(|e| e.to_string())?;
+        // Some relays (OneAPI) serve a fixed page size whatever is requested,
+        // so a short page doesn't mean the last one
+        let before = logs.len();
+        logs.extend(response.items.into_iter().filter(|log| seen.insert(log.id.clone())));
+        if logs_complete(before, logs.len(), response.total) {
+            truncated = false;
+            break;
+        }
+    }
+
+    // Only model calls are billed
+    logs.retain(|log| {
+        log.model_name.as_deref().is_some_and(|m| !m.is_empty())
+            && (log.prompt_tokens.is_some() || log.completion_tokens.is_some())
+    });
+    Ok((logs, truncated))
+}
+
+async fn reconcile_station(
+    app: &AppHandle,
+    station: &RelayStation,
+    api_base_urls: Vec<String>,
+    start: i64,
+    end: i64,
+    window_secs: i64,
+    tolerance: f64,
+) -> StationReconciliation {
+    let mut result = StationReconciliation::new(station, api_base_urls);
+
+    let locals = {
+        let db = app.state::<AgentDb>();
+        match lock_synced_index(&db)
+            .and_then(|conn| load_local_requests(&conn, &result.api_base_urls, start, end))
+        {
+            Ok(locals) => locals,
+            Err(e) => {
+                result.error = Some(e);
+                return result;
+            }
+        }
+    };
+
+    let (logs, truncated) = match fetch_station_logs(station, start, end).await {
+        Ok(logs) => logs,
+        Err(e) => {
+            result.error = Some(format!("Failed to fetch station logs: {}", e));
+            return result;
+        }
+    };
+
+    let quota_per_unit = match create_adapter(&station.adapter).get_station_info(station).await {
+        Ok(info) => info
+            .quota_per_unit
+            .filter(|q| *q > 0)
+            .map(|q| q as f64)
+            .unwrap_or(DEFAULT_QUOTA_PER_UNIT),
+        Err(_) => DEFAULT_QUOTA_PER_UNIT,
+    };
+
+    let settings = ReconcileSettings {
+        quota_per_unit,
+        window_secs,
+        tolerance,
+    };
+    reconcile(&mut result, logs, locals, &settings);
+    result.truncated = truncated;
+    result
+}
+
+fn local_midnight(date: NaiveDate) -> Result<i64, String> {
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or("Invalid date")?;
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.timestamp())
+        .ok_or_else(|| format!("Invalid local date: {}", date))
+}
+
+/// Compare what relay stations billed (from their logs) with local usage,
+/// matching requests by time, tokens and model. Reconciles one station, or
+/// every enabled station when `station_id` is omitted.
+#[tauri::command]
+pub async fn reconcile_station_usage(
+    app: AppHandle,
+    station_id: Option<String>,
+    start_date: String,
+    end_date: Option<String>,
+    match_window_secs: Option<i64>,
+    tolerance: Option<f64>,
+) -> Result<Vec<StationReconciliation>, String> {
+    let start = local_midnight(parse_filter_date(&start_date, "start")?)?;
+    let end = match end_date {
+        Some(end_date) => {
+            let end_date = parse_filter_date(&end_date, "end")?;
+            local_midnight(end_date.succ_opt().ok_or("Invalid end date")?)?
+        }
+        None => chrono::Utc::now().timestamp(),
+    };
+    if end <= start {
+        return Err("End date must be after start date".to_string());
+    }
+    let window_secs = match_window_secs.unwrap_or(DEFAULT_MATCH_WINDOW_SECS).max(0);
+    let tolerance = tolerance.unwrap_or(DEFAULT_TOLERANCE).max(0.0);
+
+    let stations = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state.lock().map_err(|e| e.to_string())?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or("Relay station manager not initialized")?;
+        let stations = match &station_id {
+            Some(id) => vec![manager
+                .get_station(id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Station not found: {}", id))?],
+            None => manager
+                .list_stations()
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|s| s.enabled)
+                .collect(),
+        };
+        stations
+            .into_iter()
+            .map(|station| {
+                let urls = manager.station_base_urls(&station);
+                (station, urls)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut results = Vec::new();
+    for (station, api_base_urls) in stations {
+        results.push(
+            reconcile_station(&app, &station, api_base_urls, start, end, window_secs, tolerance).await,
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::relay_stations::{AuthMethod, RelayStationAdapter};
+
+    const SETTINGS: ReconcileSettings = ReconcileSettings {
+        quota_per_unit: 500_000.0,
+        window_secs: 120,
+        tolerance: 0.05,
+    };
+
+    fn log(id: &str, timestamp: i64, model: &str, prompt: i64, completion: i64, quota: i64) -> StationLogEntry {
+        StationLogEntry {
+            id: id.to_string(),
+            timestamp,
+            level: "info".to_string(),
+            message: String::new(),
+            user_id: None,
+            request_id: None,
+            metadata: None,
+            model_name: Some(model.to_string()),
+            prompt_tokens: Some(prompt),
+            completion_tokens: Some(completion),
+            quota: Some(quota),
+            token_name: None,
+            use_time: Some(5),
+            is_stream: Some(true),
+            channel: None,
+            group: None,
+        }
+    }
+
+    fn local(timestamp: i64, model: &str, input: i64, cache: i64, output: i64, cost: f64) -> LocalRequest {
+        LocalRequest {
+            timestamp,
+            session_id: format!("session-{}", timestamp),
+            model: model.to_string(),
+            input_tokens: input,
+            output_tokens: output,
+            prompt_tokens: input + cache,
+            cost,
+        }
+    }
+
+    fn run(logs: Vec<StationLogEntry>, locals: Vec<LocalRequest>) -> StationReconciliation {
+        let station = RelayStation {
+            id: "s1".to_string(),
+            name: "relay".to_string(),
+            description: None,
+            api_url: "https://relay.example.com".to_string(),
+            adapter: RelayStationAdapter::Newapi,
+            auth_method: AuthMethod::BearerToken,
+            system_token: String::new(),
+            user_id: None,
+            adapter_config: None,
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        };
+        let mut result = StationReconciliation::new(&station, Vec::new());
+        reconcile(&mut result, logs, locals, &SETTINGS);
+        result
+    }
+
+    #[test]
+    fn normalizes_models() {
+        assert_eq!(normalize_model("anthropic/claude-sonnet-4-20250514"), "claude-sonnet-4");
+        assert_eq!(normalize_model("Claude-3-5-Haiku-Latest"), "claude-3-5-haiku");
+        assert_eq!(normalize_model("claude-opus-4-1"), "claude-opus-4-1");
+        assert!(tokens_match(1000, 1040, 0.05));
+        assert!(tokens_match(3, 7, 0.0));
+        assert!(!tokens_match(1000, 1100, 0.05));
+    }
+
+    #[test]
+    fn matches_cache_inclusive_prompts() {
+        // The station counts cached tokens as prompt tokens
+        let log = log("1", 1_000, "claude-sonnet-4", 12_000, 300, 15_000);
+        let request = local(998, "claude-sonnet-4-20250514", 2_000, 10_000, 300, 0.03);
+        assert!(match_score(&log, &request, &SETTINGS).is_some());
+
+        let result = run(vec![log], vec![request]);
+        assert_eq!(result.matched_requests, 1);
+        assert!(result.discrepancies.is_empty());
+    }
+
+    #[test]
+    fn flags_swapped_models_and_overcharges() {
+        let logs = vec![
+            log("1", 1_000, "claude-3-5-haiku-20241022", 1_000, 200, 5_000),
+            log("2", 2_000, "claude-sonnet-4", 1_000, 200, 50_000),
+        ];
+        let locals = vec![
+            local(1_000, "claude-sonnet-4-20250514", 1_000, 0, 200, 0.01),
+            local(2_000, "claude-sonnet-4-20250514", 1_000, 0, 200, 0.01),
+        ];
+        let result = run(logs, locals);
+        assert_eq!(result.matched_requests, 2);
+        assert_eq!(result.model_mismatches, 1);
+        assert_eq!(result.overcharged_requests, 1);
+        // 50 000 quota is $0.10 against $0.01 expected
+        assert_eq!(result.discrepancies[0].kind, DiscrepancyKind::Overcharge);
+        assert_eq!(result.discrepancies[0].station_log_id.as_deref(), Some("2"));
+        assert_eq!(result.discrepancies[1].kind, DiscrepancyKind::ModelMismatch);
+    }
+
+    #[test]
+    fn reports_unmatched_entries_on_both_sides() {
+        let logs = vec![
+            log("1", 1_000, "claude-sonnet-4", 1_000, 200, 5_000),
+            // Outside the window of every local request
+            log("2", 9_000, "claude-sonnet-4", 1_000, 200, 5_000),
+        ];
+        let locals = vec![
+            local(1_010, "claude-sonnet-4", 1_000, 0, 200, 0.01),
+            // Token counts too far off to be the same request
+            local(1_020, "claude-sonnet-4", 50_000, 0, 4_000, 0.2),
+        ];
+        let result = run(logs, locals);
+        assert_eq!(result.matched_requests, 1);
+        assert_eq!(result.station_only_requests, 1);
+        assert_eq!(result.local_only_requests, 1);
+        let kinds: Vec<_> = result.discrepancies.iter().map(|d| d.kind).collect();
+        assert_eq!(kinds, vec![DiscrepancyKind::StationOnly, DiscrepancyKind::LocalOnly]);
+    }
+
+    #[test]
+    fn pages_until_empty_or_total() {
+        // A fixed 10-entry page out of 25 is not the end
+        assert!(!logs_complete(0, 10, 25));
+        assert!(logs_complete(20, 25, 25));
+        // No total reported: stop on a page with nothing new
+        assert!(!logs_complete(10, 20, 0));
+        assert!(logs_complete(20, 20, 0));
+    }
+}
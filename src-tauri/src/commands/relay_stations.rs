@@ -7,7 +7,11 @@ use anyhow::{Result, anyhow};
 use rusqlite::{params, Connection};
 use std::sync::Mutex;
 
-use super::relay_adapters::{NewApiAdapter, YourApiAdapter, CustomAdapter};
+use super::relay_adapters::{NewApiAdapter, OneApiAdapter, YourApiAdapter, CustomAdapter, DeclarativeAdapter};
+use super::model_catalog::validate_member_model;
+use super::relay_export::{prepare_import, seal_export, ExportOptions};
+use super::relay_failover::{FailoverMember, FailoverMemberKind};
+use super::secrets::{delete_secret, is_masked, is_secret_ref, mask_secret, put_secret, resolve_secret_or_keep};
 use crate::t;
 
 /// Relay station adapter type for different station implementations
@@ -18,6 +22,8 @@ pub enum RelayStationAdapter {
     Oneapi,
     Yourapi,
     Custom,
+    /// Driven by a definition file, see `relay_adapters::declarative`
+    Declarative,
 }
 
 /// Authentication method for relay stations
@@ -151,6 +157,73 @@ pub struct ConnectionTestResult {
     pub details: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Outcome of the last connection test of a station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationHealth {
+    pub station_id: String,
+    pub station_name: String,
+    pub enabled: bool,
+    pub success: bool,
+    pub response_time: Option<u64>,
+    pub status_code: Option<u16>,
+    pub message: String,
+    pub checked_at: i64,
+}
+
+/// One recorded connection test of a station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationHealthCheck {
+    pub success: bool,
+    pub response_time: Option<u64>,
+    pub status_code: Option<u16>,
+    pub checked_at: i64,
+}
+
+/// Adapter features a station was found to support when last probed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationCapabilities {
+    pub station_id: String,
+    pub station_info: bool,
+    pub user_info: bool,
+    pub logs: bool,
+    /// Listing tokens; creating and editing them are assumed to follow
+    pub tokens: bool,
+    pub user_groups: bool,
+    /// Why each unsupported feature failed, keyed by feature name
+    pub errors: HashMap<String, String>,
+    pub probed_at: i64,
+}
+
+/// Remaining balance of a station account, or of one of its tokens, at a
+/// point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaSnapshot {
+    pub station_id: String,
+    /// None for the account balance
+    pub token_id: Option<String>,
+    pub token_name: Option<String>,
+    /// Remaining quota in station units, when the station reports it
+    pub remain_quota: Option<i64>,
+    /// Remaining balance in currency
+    pub balance: f64,
+    /// Currency used so far; only reported for the account
+    pub amount_used: Option<f64>,
+    /// Quota units per currency unit the balance was converted with
+    pub quota_per_unit: Option<i64>,
+    pub captured_at: i64,
+}
+
+/// Low balance notification settings of a station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceAlertSettings {
+    pub station_id: String,
+    /// Notify when the account balance drops below this amount
+    pub min_balance: Option<f64>,
+    /// Notify when the balance is predicted to run out within this many days
+    pub min_days_left: Option<f64>,
+    pub enabled: bool,
+}
+
 /// Request structure for creating a new token
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateTokenRequest {
@@ -229,7 +302,40 @@ pub struct ConfigUsageStatus {
 pub struct RelayStationExport {
     pub version: u32,
     pub exported_at: i64,
+    /// Empty when the bundle is encrypted
+    #[serde(default)]
     pub stations: Vec<RelayStationExportItem>,
+    #[serde(default)]
+    pub secrets: ExportSecrets,
+    /// Argon2 salt for the export password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// Encrypted station list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ciphertext: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<ExportChecksum>,
+}
+
+/// How station tokens are carried in an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportSecrets {
+    /// Tokens in clear text
+    #[default]
+    Included,
+    /// Tokens replaced with placeholders, supplied again on import
+    Redacted,
+    /// Station list encrypted with a password
+    Encrypted,
+}
+
+/// Checksum over the canonical JSON of an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportChecksum {
+    /// `sha256`, or `hmac-sha256` when signed with the export password
+    pub algorithm: String,
+    pub value: String,
 }
 
 /// Individual station data for export
@@ -246,6 +352,34 @@ pub struct RelayStationExportItem {
     pub enabled: bool,
 }
 
+// Credentials are masked in command results unless explicitly revealed
+
+impl RelayStation {
+    pub fn masked(mut self) -> Self {
+        self.system_token = mask_secret(&self.system_token);
+        self
+    }
+
+    /// The token is still a reference because the secret store is locked
+    pub fn credentials_locked(&self) -> bool {
+        is_secret_ref(&self.system_token)
+    }
+}
+
+impl RelayStationToken {
+    pub fn masked(mut self) -> Self {
+        self.token = mask_secret(&self.token);
+        self
+    }
+}
+
+impl ConfigUsageStatus {
+    pub fn masked(mut self) -> Self {
+        self.token = mask_secret(&self.token);
+        self
+    }
+}
+
 /// Adapter trait for different relay station implementations
 #[async_trait::async_trait]
 pub trait StationAdapter: Send + Sync {
@@ -263,6 +397,9 @@ pub trait StationAdapter: Send + Sync {
     
     // User groups management
     async fn get_user_groups(&self, station: &RelayStation) -> Result<serde_json::Value>;
+
+    // Model ids the station's users may call
+    async fn list_models(&self, station: &RelayStation) -> Result<Vec<String>>;
 }
 
 
@@ -270,9 +407,10 @@ pub trait StationAdapter: Send + Sync {
 pub fn create_adapter(adapter_type: &RelayStationAdapter) -> Box<dyn StationAdapter> {
     match adapter_type {
         RelayStationAdapter::Newapi => Box::new(NewApiAdapter),
-        RelayStationAdapter::Oneapi => Box::new(NewApiAdapter), // OneAPI is compatible with NewAPI
+        RelayStationAdapter::Oneapi => Box::new(OneApiAdapter),
         RelayStationAdapter::Yourapi => Box::new(YourApiAdapter::new()),
         RelayStationAdapter::Custom => Box::new(CustomAdapter), // Custom adapter for simple configurations
+        RelayStationAdapter::Declarative => Box::new(DeclarativeAdapter),
     }
 }
 
@@ -281,17 +419,85 @@ pub struct RelayStationManager {
     db: Arc<Mutex<Connection>>,
 }
 
+// Tables whose rows belong to a station and are deleted with it
+const STATION_CHILD_TABLES: [&str; 6] = [
+    "relay_station_tokens",
+    "relay_station_health",
+    "relay_station_health_checks",
+    "relay_station_capabilities",
+    "relay_quota_snapshots",
+    "relay_balance_alerts",
+];
+
+// Ids of the credentials kept in the secret store
+fn station_secret_id(station_id: &str) -> String {
+    format!("relay_station:{}:system_token", station_id)
+}
+
+fn station_token_secret_id(token_id: &str) -> String {
+    format!("relay_station_token:{}:token", token_id)
+}
+
+fn usage_secret_id(station_id: &str) -> String {
+    format!("config_usage:{}:token", station_id)
+}
+
+/// Table, id column, credential column and secret id of a stored credential
+type SecretColumn = (&'static str, &'static str, &'static str, fn(&str) -> String);
+
 use std::sync::Arc;
 
 impl RelayStationManager {
     pub fn new(db: Arc<Mutex<Connection>>) -> Result<Self> {
         let manager = Self { db };
         manager.init_tables()?;
+        match manager.migrate_secrets() {
+            Ok(0) => {}
+            Ok(count) => log::info!("Moved {} relay station credentials into the secret store", count),
+            Err(e) => log::warn!("Failed to move relay station credentials into the secret store: {}", e),
+        }
         Ok(manager)
     }
 
+    /// Move credentials stored in plaintext by older versions into the secret store
+    fn migrate_secrets(&self) -> Result<usize> {
+        let conn = self.db.lock().unwrap();
+        let columns: [SecretColumn; 3] = [
+            ("relay_stations", "id", "system_token", station_secret_id),
+            ("relay_station_tokens", "id", "token", station_token_secret_id),
+            ("config_usage", "station_id", "token", usage_secret_id),
+        ];
+
+        let mut migrated = 0;
+        for (table, id_column, column, secret_id) in columns {
+            // config_usage only exists once a configuration was applied
+            let Ok(mut stmt) = conn.prepare(&format!(
+                "SELECT {id_column}, {column} FROM {table} WHERE {column} != '' AND {column} NOT LIKE 'secret://%'"
+            )) else {
+                continue;
+            };
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for (id, value) in rows {
+                let reference = put_secret(&secret_id(&id), &value).map_err(|e| anyhow!(e))?;
+                conn.execute(
+                    &format!("UPDATE {table} SET {column} = ?1 WHERE {id_column} = ?2"),
+                    params![reference, id],
+                )?;
+                migrated += 1;
+            }
+        }
+        Ok(migrated)
+    }
+
     fn init_tables(&self) -> Result<()> {
         let conn = self.db.lock().unwrap();
+
+        // SQLite leaves foreign keys off per connection; without them deleting a
+        // station would not cascade to its tokens, health, quota and alert rows
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
         
         // Create relay_stations table
         conn.execute(
@@ -335,10 +541,94 @@ impl RelayStationManager {
             [],
         )?;
 
+        // Create relay_station_health table
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS relay_station_health (
+                station_id TEXT PRIMARY KEY,
+                success INTEGER NOT NULL,
+                response_time INTEGER,
+                status_code INTEGER,
+                message TEXT NOT NULL,
+                checked_at INTEGER NOT NULL,
+                FOREIGN KEY (station_id) REFERENCES relay_stations (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Create relay_station_health_checks table (history of relay_station_health)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS relay_station_health_checks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                station_id TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                response_time INTEGER,
+                status_code INTEGER,
+                checked_at INTEGER NOT NULL,
+                FOREIGN KEY (station_id) REFERENCES relay_stations (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_station_health_checks_station ON relay_station_health_checks(station_id, checked_at)", [])?;
+
+        // Create relay_station_capabilities table
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS relay_station_capabilities (
+                station_id TEXT PRIMARY KEY,
+                station_info INTEGER NOT NULL,
+                user_info INTEGER NOT NULL,
+                logs INTEGER NOT NULL,
+                tokens INTEGER NOT NULL,
+                user_groups INTEGER NOT NULL,
+                errors TEXT,
+                probed_at INTEGER NOT NULL,
+                FOREIGN KEY (station_id) REFERENCES relay_stations (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Create relay_quota_snapshots table (balance history of accounts and tokens)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS relay_quota_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                station_id TEXT NOT NULL,
+                token_id TEXT,
+                token_name TEXT,
+                remain_quota INTEGER,
+                balance REAL NOT NULL,
+                amount_used REAL,
+                quota_per_unit INTEGER,
+                captured_at INTEGER NOT NULL,
+                FOREIGN KEY (station_id) REFERENCES relay_stations (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_quota_snapshots_station ON relay_quota_snapshots(station_id, captured_at)", [])?;
+
+        // Create relay_balance_alerts table; alerted is set while the station is below a threshold
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS relay_balance_alerts (
+                station_id TEXT PRIMARY KEY,
+                min_balance REAL,
+                min_days_left REAL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                alerted INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (station_id) REFERENCES relay_stations (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
         // Create indexes
         conn.execute("CREATE INDEX IF NOT EXISTS idx_station_tokens_station_id ON relay_station_tokens(station_id)", [])?;
         conn.execute("CREATE INDEX IF NOT EXISTS idx_station_tokens_enabled ON relay_station_tokens(enabled)", [])?;
 
+        // Remove rows that stations deleted before foreign keys were enabled left behind
+        for table in STATION_CHILD_TABLES {
+            conn.execute(
+                &format!("DELETE FROM {table} WHERE station_id NOT IN (SELECT id FROM relay_stations)"),
+                [],
+            )?;
+        }
+
         Ok(())
     }
 
@@ -364,6 +654,7 @@ impl RelayStationManager {
                     "oneapi" => RelayStationAdapter::Oneapi,
                     "yourapi" => RelayStationAdapter::Yourapi,
                     "custom" => RelayStationAdapter::Custom,
+                    "declarative" => RelayStationAdapter::Declarative,
                     _ => RelayStationAdapter::Newapi,
                 },
                 auth_method: match row.get::<_, String>("auth_method")?.as_str() {
@@ -372,7 +663,7 @@ impl RelayStationManager {
                     "custom" => AuthMethod::Custom,
                     _ => AuthMethod::BearerToken,
                 },
-                system_token: row.get("system_token")?,
+                system_token: resolve_secret_or_keep(row.get("system_token")?),
                 user_id: row.get("user_id")?,
                 adapter_config,
                 enabled: row.get::<_, i32>("enabled")? != 0,
@@ -385,6 +676,7 @@ impl RelayStationManager {
     }
 
     pub fn add_station(&self, station: &RelayStation) -> Result<()> {
+        let system_token = put_secret(&station_secret_id(&station.id), &station.system_token).map_err(|e| anyhow!(e))?;
         let conn = self.db.lock().unwrap();
         
         let adapter_config_str = if let Some(config) = &station.adapter_config {
@@ -406,13 +698,14 @@ impl RelayStationManager {
                     RelayStationAdapter::Oneapi => "oneapi",
                     RelayStationAdapter::Yourapi => "yourapi",
                     RelayStationAdapter::Custom => "custom",
+                    RelayStationAdapter::Declarative => "declarative",
                 },
                 match station.auth_method {
                     AuthMethod::BearerToken => "bearer_token",
                     AuthMethod::ApiKey => "api_key",
                     AuthMethod::Custom => "custom",
                 },
-                station.system_token,
+                system_token,
                 station.user_id,
                 adapter_config_str,
                 if station.enabled { 1 } else { 0 },
@@ -446,6 +739,7 @@ impl RelayStationManager {
                     "oneapi" => RelayStationAdapter::Oneapi,
                     "yourapi" => RelayStationAdapter::Yourapi,
                     "custom" => RelayStationAdapter::Custom,
+                    "declarative" => RelayStationAdapter::Declarative,
                     _ => RelayStationAdapter::Newapi,
                 },
                 auth_method: match row.get::<_, String>("auth_method")?.as_str() {
@@ -454,7 +748,7 @@ impl RelayStationManager {
                     "custom" => AuthMethod::Custom,
                     _ => AuthMethod::BearerToken,
                 },
-                system_token: row.get("system_token")?,
+                system_token: resolve_secret_or_keep(row.get("system_token")?),
                 user_id: row.get("user_id")?,
                 adapter_config,
                 enabled: row.get::<_, i32>("enabled")? != 0,
@@ -484,6 +778,7 @@ impl RelayStationManager {
                 "system_token" => query_parts.push("system_token = ?"),
                 "user_id" => query_parts.push("user_id = ?"),
                 "enabled" => query_parts.push("enabled = ?"),
+                "adapter_config" => query_parts.push("adapter_config = ?"),
                 _ => {}
             }
         }
@@ -518,7 +813,8 @@ impl RelayStationManager {
                         params_vec.push(rusqlite::types::Value::Text(value.as_str().unwrap_or("bearer_token").to_string()));
                     }
                     "system_token" => {
-                        params_vec.push(rusqlite::types::Value::Text(value.as_str().unwrap_or("").to_string()));
+                        let reference = put_secret(&station_secret_id(station_id), value.as_str().unwrap_or("")).map_err(|e| anyhow!(e))?;
+                        params_vec.push(rusqlite::types::Value::Text(reference));
                     }
                     "user_id" => {
                         if let Some(user_id) = value.as_str() {
@@ -531,6 +827,13 @@ impl RelayStationManager {
                         let enabled_val = if value.as_bool().unwrap_or(false) { 1i64 } else { 0i64 };
                         params_vec.push(rusqlite::types::Value::Integer(enabled_val));
                     }
+                    "adapter_config" => {
+                        if value.is_object() {
+                            params_vec.push(rusqlite::types::Value::Text(value.to_string()));
+                        } else {
+                            params_vec.push(rusqlite::types::Value::Null);
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -538,6 +841,11 @@ impl RelayStationManager {
             params_vec.push(rusqlite::types::Value::Text(station_id.to_string()));
 
             conn.execute(&query, rusqlite::params_from_iter(params_vec))?;
+
+            // Capabilities probed against the old settings no longer apply
+            if ["api_url", "adapter", "system_token", "adapter_config"].iter().any(|key| updates.contains_key(*key)) {
+                conn.execute("DELETE FROM relay_station_capabilities WHERE station_id = ?1", [station_id])?;
+            }
         }
 
         Ok(())
@@ -545,7 +853,22 @@ impl RelayStationManager {
 
     pub fn delete_station(&self, station_id: &str) -> Result<()> {
         let conn = self.db.lock().unwrap();
+        let token_ids = conn
+            .prepare("SELECT id FROM relay_station_tokens WHERE station_id = ?1")?
+            .query_map([station_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
         conn.execute("DELETE FROM relay_stations WHERE id = ?1", [station_id])?;
+        // config_usage only exists once a configuration was applied
+        let _ = conn.execute("DELETE FROM config_usage WHERE station_id = ?1", [station_id]);
+
+        let secret_ids = token_ids
+            .iter()
+            .map(|token_id| station_token_secret_id(token_id))
+            .chain([station_secret_id(station_id), usage_secret_id(station_id)]);
+        for secret_id in secret_ids {
+            delete_secret(&secret_id).map_err(|e| anyhow!(e))?;
+        }
         Ok(())
     }
 
@@ -760,12 +1083,309 @@ impl RelayStationManager {
         conn.execute(
             "INSERT OR REPLACE INTO config_usage (station_id, base_url, token, applied_at)
              VALUES (?1, ?2, ?3, ?4)",
-            params![station_id, base_url, token, now],
+            params![station_id, base_url, put_secret(&usage_secret_id(station_id), token).map_err(|e| anyhow!(e))?, now],
+        )?;
+
+        Ok(())
+    }
+
+    /// Record the outcome of a connection test, returning whether the station
+    /// was up at its previous test
+    pub fn record_station_health(&self, station_id: &str, result: &ConnectionTestResult) -> Result<Option<bool>> {
+        let conn = self.db.lock().unwrap();
+        let now = Utc::now().timestamp();
+
+        let previous: Option<bool> = conn
+            .query_row(
+                "SELECT success FROM relay_station_health WHERE station_id = ?1",
+                params![station_id],
+                |row| row.get::<_, i32>(0),
+            )
+            .ok()
+            .map(|success| success != 0);
+
+        conn.execute(
+            "INSERT OR REPLACE INTO relay_station_health
+             (station_id, success, response_time, status_code, message, checked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                station_id,
+                result.success,
+                result.response_time.map(|t| t as i64),
+                result.status_code,
+                result.message,
+                now
+            ],
+        )?;
+        conn.execute(
+            "INSERT INTO relay_station_health_checks (station_id, success, response_time, status_code, checked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                station_id,
+                result.success,
+                result.response_time.map(|t| t as i64),
+                result.status_code,
+                now
+            ],
+        )?;
+
+        Ok(previous)
+    }
+
+    /// Get a station's connection tests since `since` (Unix seconds), oldest first
+    pub fn list_station_health_checks(&self, station_id: &str, since: i64) -> Result<Vec<StationHealthCheck>> {
+        let conn = self.db.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT success, response_time, status_code, checked_at
+             FROM relay_station_health_checks
+             WHERE station_id = ?1 AND checked_at >= ?2
+             ORDER BY checked_at, id"
         )?;
 
+        let check_iter = stmt.query_map(params![station_id, since], |row| {
+            Ok(StationHealthCheck {
+                success: row.get::<_, i32>("success")? != 0,
+                response_time: row.get::<_, Option<i64>>("response_time")?.map(|t| t as u64),
+                status_code: row.get("status_code")?,
+                checked_at: row.get("checked_at")?,
+            })
+        })?;
+
+        check_iter.collect::<Result<Vec<_>, _>>().map_err(|e| anyhow!("Database error: {}", e))
+    }
+
+    /// Delete connection tests older than `before` (Unix seconds)
+    pub fn prune_station_health_checks(&self, before: i64) -> Result<usize> {
+        let conn = self.db.lock().unwrap();
+        Ok(conn.execute(
+            "DELETE FROM relay_station_health_checks WHERE checked_at < ?1",
+            params![before],
+        )?)
+    }
+
+    /// Get the last connection test of every tested station
+    pub fn list_station_health(&self) -> Result<Vec<StationHealth>> {
+        let conn = self.db.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT h.station_id, rs.name AS station_name, rs.enabled, h.success, h.response_time,
+                    h.status_code, h.message, h.checked_at
+             FROM relay_station_health h
+             JOIN relay_stations rs ON h.station_id = rs.id
+             ORDER BY rs.name"
+        )?;
+
+        let health_iter = stmt.query_map([], |row| {
+            Ok(StationHealth {
+                station_id: row.get("station_id")?,
+                station_name: row.get("station_name")?,
+                enabled: row.get::<_, i32>("enabled")? != 0,
+                success: row.get::<_, i32>("success")? != 0,
+                response_time: row.get::<_, Option<i64>>("response_time")?.map(|t| t as u64),
+                status_code: row.get("status_code")?,
+                message: row.get("message")?,
+                checked_at: row.get("checked_at")?,
+            })
+        })?;
+
+        health_iter.collect::<Result<Vec<_>, _>>().map_err(|e| anyhow!("Database error: {}", e))
+    }
+
+    /// Store the result of probing a station's capabilities
+    pub fn record_station_capabilities(&self, capabilities: &StationCapabilities) -> Result<()> {
+        let conn = self.db.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO relay_station_capabilities
+             (station_id, station_info, user_info, logs, tokens, user_groups, errors, probed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                capabilities.station_id,
+                capabilities.station_info,
+                capabilities.user_info,
+                capabilities.logs,
+                capabilities.tokens,
+                capabilities.user_groups,
+                serde_json::to_string(&capabilities.errors)?,
+                capabilities.probed_at
+            ],
+        )?;
         Ok(())
     }
 
+    /// Get the last probed capabilities of every probed station
+    pub fn list_station_capabilities(&self) -> Result<Vec<StationCapabilities>> {
+        let conn = self.db.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT c.* FROM relay_station_capabilities c
+             JOIN relay_stations rs ON c.station_id = rs.id
+             ORDER BY rs.name"
+        )?;
+
+        let capability_iter = stmt.query_map([], |row| {
+            let errors: Option<String> = row.get("errors")?;
+            Ok(StationCapabilities {
+                station_id: row.get("station_id")?,
+                station_info: row.get::<_, i32>("station_info")? != 0,
+                user_info: row.get::<_, i32>("user_info")? != 0,
+                logs: row.get::<_, i32>("logs")? != 0,
+                tokens: row.get::<_, i32>("tokens")? != 0,
+                user_groups: row.get::<_, i32>("user_groups")? != 0,
+                errors: errors.and_then(|e| serde_json::from_str(&e).ok()).unwrap_or_default(),
+                probed_at: row.get("probed_at")?,
+            })
+        })?;
+
+        capability_iter.collect::<Result<Vec<_>, _>>().map_err(|e| anyhow!("Database error: {}", e))
+    }
+
+    /// Store balance snapshots of a station's account and tokens
+    pub fn record_quota_snapshots(&self, snapshots: &[QuotaSnapshot]) -> Result<()> {
+        let conn = self.db.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "INSERT INTO relay_quota_snapshots
+             (station_id, token_id, token_name, remain_quota, balance, amount_used, quota_per_unit, captured_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+        )?;
+        for snapshot in snapshots {
+            stmt.execute(params![
+                snapshot.station_id,
+                snapshot.token_id,
+                snapshot.token_name,
+                snapshot.remain_quota,
+                snapshot.balance,
+                snapshot.amount_used,
+                snapshot.quota_per_unit,
+                snapshot.captured_at
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Get a station's balance snapshots since `since` (Unix seconds), oldest first
+    pub fn list_quota_snapshots(&self, station_id: &str, since: i64) -> Result<Vec<QuotaSnapshot>> {
+        let conn = self.db.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT * FROM relay_quota_snapshots
+             WHERE station_id = ?1 AND captured_at >= ?2
+             ORDER BY captured_at, id"
+        )?;
+
+        let snapshot_iter = stmt.query_map(params![station_id, since], |row| {
+            Ok(QuotaSnapshot {
+                station_id: row.get("station_id")?,
+                token_id: row.get("token_id")?,
+                token_name: row.get("token_name")?,
+                remain_quota: row.get("remain_quota")?,
+                balance: row.get("balance")?,
+                amount_used: row.get("amount_used")?,
+                quota_per_unit: row.get("quota_per_unit")?,
+                captured_at: row.get("captured_at")?,
+            })
+        })?;
+
+        snapshot_iter.collect::<Result<Vec<_>, _>>().map_err(|e| anyhow!("Database error: {}", e))
+    }
+
+    /// Delete balance snapshots older than `before` (Unix seconds)
+    pub fn prune_quota_snapshots(&self, before: i64) -> Result<usize> {
+        let conn = self.db.lock().unwrap();
+        Ok(conn.execute(
+            "DELETE FROM relay_quota_snapshots WHERE captured_at < ?1",
+            params![before],
+        )?)
+    }
+
+    /// Get the low balance notification settings of every configured station
+    pub fn list_balance_alerts(&self) -> Result<Vec<BalanceAlertSettings>> {
+        let conn = self.db.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT a.station_id, a.min_balance, a.min_days_left, a.enabled
+             FROM relay_balance_alerts a
+             JOIN relay_stations rs ON a.station_id = rs.id
+             ORDER BY rs.name"
+        )?;
+
+        let alert_iter = stmt.query_map([], |row| {
+            Ok(BalanceAlertSettings {
+                station_id: row.get("station_id")?,
+                min_balance: row.get("min_balance")?,
+                min_days_left: row.get("min_days_left")?,
+                enabled: row.get::<_, i32>("enabled")? != 0,
+            })
+        })?;
+
+        alert_iter.collect::<Result<Vec<_>, _>>().map_err(|e| anyhow!("Database error: {}", e))
+    }
+
+    /// Save a station's low balance notification settings; a station already
+    /// below the new thresholds is notified again
+    pub fn save_balance_alert(&self, settings: &BalanceAlertSettings) -> Result<()> {
+        let conn = self.db.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO relay_balance_alerts (station_id, min_balance, min_days_left, enabled, alerted)
+             VALUES (?1, ?2, ?3, ?4, 0)",
+            params![
+                settings.station_id,
+                settings.min_balance,
+                settings.min_days_left,
+                settings.enabled
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Mark whether a station is below its balance thresholds, returning
+    /// whether it already was
+    pub fn set_balance_alerted(&self, station_id: &str, alerted: bool) -> Result<bool> {
+        let conn = self.db.lock().unwrap();
+        let previous = conn
+            .query_row(
+                "SELECT alerted FROM relay_balance_alerts WHERE station_id = ?1",
+                params![station_id],
+                |row| row.get::<_, i32>(0),
+            )
+            .is_ok_and(|alerted| alerted != 0);
+        conn.execute(
+            "UPDATE relay_balance_alerts SET alerted = ?2 WHERE station_id = ?1",
+            params![station_id, alerted],
+        )?;
+        Ok(previous)
+    }
+
+    /// API base URLs Claude may have been pointed at for a station: its API
+    /// URL, the saved endpoint and the last applied configuration
+    pub fn station_base_urls(&self, station: &RelayStation) -> Vec<String> {
+        let mut urls = vec![station.api_url.clone()];
+
+        // The config tables are created on first use, so they may not exist yet
+        if let Ok(Some(config)) = self.get_station_config(&station.id) {
+            urls.push(config.custom_endpoint.filter(|e| !e.is_empty()).unwrap_or(config.api_endpoint));
+        }
+        {
+            let conn = self.db.lock().unwrap();
+            if let Ok(base_url) = conn.query_row(
+                "SELECT base_url FROM config_usage WHERE station_id = ?1",
+                params![station.id],
+                |row| row.get::<_, String>(0),
+            ) {
+                urls.push(base_url);
+            }
+        }
+
+        let mut normalized: Vec<String> = urls
+            .iter()
+            .map(|url| url.trim().trim_end_matches('/').to_lowercase())
+            .filter(|url| !url.is_empty())
+            .collect();
+        normalized.sort();
+        normalized.dedup();
+        normalized
+    }
+
     /// Export relay stations to JSON format
     pub fn export_stations(&self, station_ids: Option<Vec<String>>) -> Result<RelayStationExport> {
         let conn = self.db.lock().unwrap();
@@ -792,6 +1412,7 @@ impl RelayStationManager {
                             "oneapi" => RelayStationAdapter::Oneapi,
                             "yourapi" => RelayStationAdapter::Yourapi,
                             "custom" => RelayStationAdapter::Custom,
+                            "declarative" => RelayStationAdapter::Declarative,
                             _ => RelayStationAdapter::Newapi,
                         },
                         auth_method: match row.get::<_, String>("auth_method")?.as_str() {
@@ -800,7 +1421,7 @@ impl RelayStationManager {
                             "custom" => AuthMethod::Custom,
                             _ => AuthMethod::BearerToken,
                         },
-                        system_token: row.get("system_token")?,
+                        system_token: resolve_secret_or_keep(row.get("system_token")?),
                         user_id: row.get("user_id")?,
                         adapter_config,
                         enabled: row.get::<_, i32>("enabled")? != 0,
@@ -832,6 +1453,7 @@ impl RelayStationManager {
                         "oneapi" => RelayStationAdapter::Oneapi,
                         "yourapi" => RelayStationAdapter::Yourapi,
                         "custom" => RelayStationAdapter::Custom,
+                        "declarative" => RelayStationAdapter::Declarative,
                         _ => RelayStationAdapter::Newapi,
                     },
                     auth_method: match row.get::<_, String>("auth_method")?.as_str() {
@@ -840,7 +1462,7 @@ impl RelayStationManager {
                         "custom" => AuthMethod::Custom,
                         _ => AuthMethod::BearerToken,
                     },
-                    system_token: row.get("system_token")?,
+                    system_token: resolve_secret_or_keep(row.get("system_token")?),
                     user_id: row.get("user_id")?,
                     adapter_config,
                     enabled: row.get::<_, i32>("enabled")? != 0,
@@ -854,6 +1476,10 @@ impl RelayStationManager {
             version: 1,
             exported_at: Utc::now().timestamp(),
             stations,
+            secrets: ExportSecrets::Included,
+            salt: None,
+            ciphertext: None,
+            checksum: None,
         })
     }
 
@@ -861,6 +1487,18 @@ impl RelayStationManager {
     pub fn import_stations(&self, export_data: &RelayStationExport, overwrite_existing: bool) -> Result<Vec<String>> {
         let conn = self.db.lock().unwrap();
         let mut imported_stations = Vec::new();
+
+        // Redacted tokens can only be kept for stations that already exist
+        for station_data in &export_data.stations {
+            if is_masked(&station_data.system_token) {
+                let exists = conn
+                    .query_row("SELECT 1 FROM relay_stations WHERE name = ?1", [&station_data.name], |_| Ok(()))
+                    .is_ok();
+                if !exists {
+                    return Err(anyhow!("Station '{}' needs its token to be supplied", station_data.name));
+                }
+            }
+        }
         
         for station_data in &export_data.stations {
             // Check if station with same name already exists
@@ -890,6 +1528,12 @@ impl RelayStationManager {
             };
 
             let now = Utc::now().timestamp();
+            // A placeholder keeps the token the existing station already has
+            let system_token = if existing_station.is_some() && is_masked(&station_data.system_token) {
+                conn.query_row("SELECT system_token FROM relay_stations WHERE id = ?1", [&station_id], |row| row.get::<_, String>(0))?
+            } else {
+                put_secret(&station_secret_id(&station_id), &station_data.system_token).map_err(|e| anyhow!(e))?
+            };
 
             if existing_station.is_some() && overwrite_existing {
                 // Update existing station
@@ -904,13 +1548,14 @@ impl RelayStationManager {
                             RelayStationAdapter::Oneapi => "oneapi",
                             RelayStationAdapter::Yourapi => "yourapi",
                             RelayStationAdapter::Custom => "custom",
+                            RelayStationAdapter::Declarative => "declarative",
                         },
                         match station_data.auth_method {
                             AuthMethod::BearerToken => "bearer_token",
                             AuthMethod::ApiKey => "api_key",
                             AuthMethod::Custom => "custom",
                         },
-                        station_data.system_token,
+                        system_token,
                         station_data.user_id,
                         adapter_config_str,
                         if station_data.enabled { 1 } else { 0 },
@@ -933,13 +1578,14 @@ impl RelayStationManager {
                             RelayStationAdapter::Oneapi => "oneapi",
                             RelayStationAdapter::Yourapi => "yourapi",
                             RelayStationAdapter::Custom => "custom",
+                            RelayStationAdapter::Declarative => "declarative",
                         },
                         match station_data.auth_method {
                             AuthMethod::BearerToken => "bearer_token",
                             AuthMethod::ApiKey => "api_key",
                             AuthMethod::Custom => "custom",
                         },
-                        station_data.system_token,
+                        system_token,
                         station_data.user_id,
                         adapter_config_str,
                         if station_data.enabled { 1 } else { 0 },
@@ -971,7 +1617,7 @@ impl RelayStationManager {
                 station_id: row.get("station_id")?,
                 station_name: row.get::<_, Option<String>>("station_name")?.unwrap_or_else(|| "Unknown".to_string()),
                 base_url: row.get("base_url")?,
-                token: row.get("token")?,
+                token: resolve_secret_or_keep(row.get("token")?),
                 is_active: true, // Will be determined by comparing with current config
                 applied_at: Some(row.get("applied_at")?),
             })
@@ -984,24 +1630,34 @@ impl RelayStationManager {
 // Tauri command handlers
 
 #[tauri::command]
-pub async fn list_relay_stations(app: AppHandle) -> Result<Vec<RelayStation>, String> {
+pub async fn list_relay_stations(app: AppHandle, reveal: Option<bool>) -> Result<Vec<RelayStation>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
-        manager.list_stations().map_err(|e| t!("relay.failed_to_list_stations", "error" => &e.to_string()))
+        let stations = manager.list_stations().map_err(|e| t!("relay.failed_to_list_stations", "error" => &e.to_string()))?;
+        if reveal.unwrap_or(false) {
+            Ok(stations)
+        } else {
+            Ok(stations.into_iter().map(RelayStation::masked).collect())
+        }
     } else {
         Ok(Vec::new()) // Return empty list if manager not initialized
     }
 }
 
 #[tauri::command]
-pub async fn get_relay_station(station_id: String, app: AppHandle) -> Result<Option<RelayStation>, String> {
+pub async fn get_relay_station(station_id: String, app: AppHandle, reveal: Option<bool>) -> Result<Option<RelayStation>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
-        manager.get_station(&station_id).map_err(|e| t!("relay.failed_to_get_station", "error" => &e.to_string()))
+        let station = manager.get_station(&station_id).map_err(|e| t!("relay.failed_to_get_station", "error" => &e.to_string()))?;
+        if reveal.unwrap_or(false) {
+            Ok(station)
+        } else {
+            Ok(station.map(RelayStation::masked))
+        }
     } else {
         Ok(None)
     }
@@ -1091,7 +1747,7 @@ pub async fn get_station_info(station_id: String, app: AppHandle) -> Result<Stat
 }
 
 #[tauri::command]
-pub async fn list_station_tokens(station_id: String, page: Option<usize>, size: Option<usize>, app: AppHandle) -> Result<TokenPaginationResponse, String> {
+pub async fn list_station_tokens(station_id: String, page: Option<usize>, size: Option<usize>, app: AppHandle, reveal: Option<bool>) -> Result<TokenPaginationResponse, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     // Get the station first, releasing the lock before the async call
@@ -1111,7 +1767,11 @@ pub async fn list_station_tokens(station_id: String, page: Option<usize>, size:
     
     if let Some(station) = station {
         let adapter = create_adapter(&station.adapter);
-        adapter.list_tokens(&station, page, size).await.map_err(|e| t!("relay.failed_to_list_tokens", "error" => &e.to_string()))
+        let mut tokens = adapter.list_tokens(&station, page, size).await.map_err(|e| t!("relay.failed_to_list_tokens", "error" => &e.to_string()))?;
+        if !reveal.unwrap_or(false) {
+            tokens.items = tokens.items.into_iter().map(RelayStationToken::masked).collect();
+        }
+        Ok(tokens)
     } else {
         Ok(TokenPaginationResponse {
             items: Vec::new(),
@@ -1127,6 +1787,7 @@ pub async fn add_station_token(
     station_id: String,
     token_data: CreateTokenRequest,
     app: AppHandle,
+    reveal: Option<bool>,
 ) -> Result<RelayStationToken, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
@@ -1142,7 +1803,8 @@ pub async fn add_station_token(
     
     if let Some(station) = station {
         let adapter = create_adapter(&station.adapter);
-        adapter.create_token(&station, &token_data).await.map_err(|e| t!("relay.failed_to_create_token", "error" => &e.to_string()))
+        let token = adapter.create_token(&station, &token_data).await.map_err(|e| t!("relay.failed_to_create_token", "error" => &e.to_string()))?;
+        Ok(if reveal.unwrap_or(false) { token } else { token.masked() })
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1154,6 +1816,7 @@ pub async fn update_station_token(
     token_id: String,
     token_data: UpdateTokenRequest,
     app: AppHandle,
+    reveal: Option<bool>,
 ) -> Result<RelayStationToken, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
@@ -1169,7 +1832,8 @@ pub async fn update_station_token(
     
     if let Some(station) = station {
         let adapter = create_adapter(&station.adapter);
-        adapter.update_token(&station, &token_id, &token_data).await.map_err(|e| t!("relay.failed_to_update_token", "error" => &e.to_string()))
+        let token = adapter.update_token(&station, &token_id, &token_data).await.map_err(|e| t!("relay.failed_to_update_token", "error" => &e.to_string()))?;
+        Ok(if reveal.unwrap_or(false) { token } else { token.masked() })
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1273,12 +1937,36 @@ pub async fn test_station_connection(station_id: String, app: AppHandle) -> Resu
     
     if let Some(station) = station {
         let adapter = create_adapter(&station.adapter);
-        adapter.test_connection(&station).await.map_err(|e| t!("relay.failed_to_test_connection", "error" => &e.to_string()))
+        let result = adapter.test_connection(&station).await.map_err(|e| t!("relay.failed_to_test_connection", "error" => &e.to_string()))?;
+
+        // Keep the outcome for the health metrics
+        if let Ok(manager_lock) = state.lock() {
+            if let Some(manager) = manager_lock.as_ref() {
+                if let Err(e) = manager.record_station_health(&station_id, &result) {
+                    log::warn!("Failed to record health of station {}: {}", station_id, e);
+                }
+            }
+        }
+
+        Ok(result)
     } else {
         Err(t!("relay.station_not_found"))
     }
 }
 
+/// Get the last connection test result of each station
+#[tauri::command]
+pub async fn get_relay_station_health(app: AppHandle) -> Result<Vec<StationHealth>, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
+
+    if let Some(manager) = manager_lock.as_ref() {
+        manager.list_station_health().map_err(|e| e.to_string())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
 #[tauri::command]
 pub async fn api_user_self_groups(station_id: String, app: AppHandle) -> Result<serde_json::Value, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
@@ -1307,6 +1995,7 @@ pub async fn toggle_station_token(
     token_id: String,
     enabled: bool,
     app: AppHandle,
+    reveal: Option<bool>,
 ) -> Result<RelayStationToken, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
@@ -1322,7 +2011,8 @@ pub async fn toggle_station_token(
     
     if let Some(station) = station {
         let adapter = create_adapter(&station.adapter);
-        adapter.toggle_token(&station, &token_id, enabled).await.map_err(|e| t!("relay.failed_to_toggle_token", "error" => &e.to_string()))
+        let token = adapter.toggle_token(&station, &token_id, enabled).await.map_err(|e| t!("relay.failed_to_toggle_token", "error" => &e.to_string()))?;
+        Ok(if reveal.unwrap_or(false) { token } else { token.masked() })
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1419,6 +2109,15 @@ pub async fn save_station_config(
     
     if let Some(station) = station {
         let now = Utc::now().timestamp();
+
+        // Resolve aliases and catch model typos before they reach Claude
+        let model = match config_request.model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            Some(model) => {
+                let member = FailoverMember { kind: FailoverMemberKind::Station, id: station.id.clone() };
+                Some(validate_member_model(&app, &member, model).await?)
+            }
+            None => None,
+        };
         
         let config = RelayStationConfig {
             station_id: config_request.station_id.clone(),
@@ -1426,7 +2125,7 @@ pub async fn save_station_config(
             api_endpoint: config_request.api_endpoint,
             custom_endpoint: config_request.custom_endpoint,
             path: config_request.path,
-            model: config_request.model,
+            model,
             saved_settings: None,
             created_at: now,
             updated_at: now,
@@ -1464,12 +2163,17 @@ pub async fn get_station_config(
 
 /// Get configuration usage status for display
 #[tauri::command]
-pub async fn get_config_usage_status(app: AppHandle) -> Result<Vec<ConfigUsageStatus>, String> {
+pub async fn get_config_usage_status(app: AppHandle, reveal: Option<bool>) -> Result<Vec<ConfigUsageStatus>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     let manager_lock = state.lock().map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
     if let Some(manager) = manager_lock.as_ref() {
-        manager.get_config_usage_status().map_err(|e| t!("relay.failed_to_get_usage_status", "error" => &e.to_string()))
+        let status = manager.get_config_usage_status().map_err(|e| t!("relay.failed_to_get_usage_status", "error" => &e.to_string()))?;
+        if reveal.unwrap_or(false) {
+            Ok(status)
+        } else {
+            Ok(status.into_iter().map(ConfigUsageStatus::masked).collect())
+        }
     } else {
         Err(t!("relay.manager_not_initialized"))
     }
@@ -1498,13 +2202,15 @@ pub async fn record_config_usage(
 #[tauri::command]
 pub async fn export_relay_stations(
     station_ids: Option<Vec<String>>,
+    options: Option<ExportOptions>,
     app: AppHandle,
 ) -> Result<RelayStationExport, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
-        manager.export_stations(station_ids).map_err(|e| t!("relay.failed_to_export_stations", "error" => &e.to_string()))
+        let export = manager.export_stations(station_ids).map_err(|e| t!("relay.failed_to_export_stations", "error" => &e.to_string()))?;
+        seal_export(export, &options.unwrap_or_default()).map_err(|e| t!("relay.failed_to_export_stations", "error" => &e))
     } else {
         Err(t!("relay.manager_not_initialized"))
     }
@@ -1515,8 +2221,13 @@ pub async fn export_relay_stations(
 pub async fn import_relay_stations(
     export_data: RelayStationExport,
     overwrite_existing: bool,
+    password: Option<String>,
+    secrets: Option<HashMap<String, String>>,
+    allow_unverified: Option<bool>,
     app: AppHandle,
 ) -> Result<Vec<String>, String> {
+    let export_data = prepare_import(export_data, password.as_deref(), secrets.unwrap_or_default(), allow_unverified.unwrap_or(false))
+        .map_err(|e| t!("relay.failed_to_import_stations", "error" => &e))?;
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
     
@@ -1525,4 +2236,71 @@ pub async fn import_relay_stations(
     } else {
         Err(t!("relay.manager_not_initialized"))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_station(conn: &Connection, id: &str) {
+        conn.execute(
+            "INSERT INTO relay_stations
+                (id, name, api_url, adapter, auth_method, system_token, created_at, updated_at)
+             VALUES (?1, ?1, 'https://relay.example', 'newapi', 'bearer_token', '', 0, 0)",
+            [id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO relay_station_health (station_id, success, message, checked_at)
+             VALUES (?1, 1, 'ok', 0)",
+            [id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO relay_quota_snapshots (station_id, balance, captured_at) VALUES (?1, 5.0, 0)",
+            [id],
+        )
+        .unwrap();
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn deleting_a_station_cascades_to_its_rows() {
+        let db = Arc::new(Mutex::new(Connection::open_in_memory().unwrap()));
+        let _manager = RelayStationManager::new(db.clone()).unwrap();
+        let conn = db.lock().unwrap();
+        insert_station(&conn, "s1");
+        insert_station(&conn, "s2");
+
+        conn.execute("DELETE FROM relay_stations WHERE id = 's1'", []).unwrap();
+
+        assert_eq!(count(&conn, "relay_station_health"), 1);
+        assert_eq!(count(&conn, "relay_quota_snapshots"), 1);
+    }
+
+    #[test]
+    fn orphaned_rows_are_removed_on_start() {
+        let db = Arc::new(Mutex::new(Connection::open_in_memory().unwrap()));
+        RelayStationManager::new(db.clone()).unwrap();
+        {
+            let conn = db.lock().unwrap();
+            insert_station(&conn, "s1");
+            insert_station(&conn, "s2");
+            // As left behind by a delete without foreign keys
+            conn.execute_batch(
+                "PRAGMA foreign_keys = OFF; DELETE FROM relay_stations WHERE id = 's1';",
+            )
+            .unwrap();
+            assert_eq!(count(&conn, "relay_station_health"), 2);
+        }
+
+        RelayStationManager::new(db.clone()).unwrap();
+        let conn = db.lock().unwrap();
+        assert_eq!(count(&conn, "relay_station_health"), 1);
+        assert_eq!(count(&conn, "relay_quota_snapshots"), 1);
+    }
+}
@@ -0,0 +1,1097 @@
+use chrono::Utc;
+use log::{error, info, warn};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{command, AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::agents::AgentDb;
+use super::http_client;
+use super::protocol_translation::{
+    anthropic_to_openai_request, openai_to_anthropic_error, openai_to_anthropic_response,
+    StreamTranslator,
+};
+use super::provider::{apply_provider_config, ProviderConfig};
+use super::relay_failover::{member_translation, resolve_member, FailoverMember};
+use crate::claude_messages::Usage;
+
+const SETTINGS_KEY: &str = "routing_proxy";
+const DEFAULT_PORT: u16 = 8765;
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+// Requests carry whole conversations, images included
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+// Non-streaming responses are buffered up to this size to read their usage
+const MAX_USAGE_BUFFER_BYTES: usize = 4 * 1024 * 1024;
+// How long an upstream that failed a request is tried last
+const FAILURE_COOLDOWN_SECS: i64 = 30;
+// Hop-by-hop and credential headers that are never forwarded
+const SKIPPED_REQUEST_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "transfer-encoding",
+    "accept-encoding",
+    "authorization",
+    "x-api-key",
+    "proxy-connection",
+    "keep-alive",
+];
+const SKIPPED_RESPONSE_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "transfer-encoding",
+    "keep-alive",
+];
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+/// A relay station or provider requests are routed to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProxyUpstream {
+    #[serde(flatten)]
+    pub member: FailoverMember,
+    /// Relative share of requests
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+/// Settings of the local routing proxy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutingProxyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub upstreams: Vec<ProxyUpstream>,
+    /// Other upstreams tried after a failed attempt
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Token clients must send; the upstream credentials replace it
+    #[serde(default)]
+    pub access_token: String,
+}
+
+impl Default for RoutingProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            upstreams: Vec::new(),
+            max_retries: default_max_retries(),
+            access_token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingProxyStatus {
+    pub config: RoutingProxyConfig,
+    /// Base URL to point `ANTHROPIC_BASE_URL` at, when running
+    pub base_url: Option<String>,
+}
+
+/// Requests served by one upstream
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyUpstreamStats {
+    pub upstream_kind: String,
+    pub upstream_id: String,
+    pub upstream_name: String,
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    /// Mean time to the first response byte in milliseconds
+    pub avg_latency_ms: f64,
+    pub avg_duration_ms: f64,
+}
+
+struct RunningProxy {
+    port: u16,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct ProxyRuntime {
+    config: RoutingProxyConfig,
+    /// Smooth weighted round-robin state, by upstream key
+    current_weights: HashMap<String, i64>,
+    /// Upstreams tried last until the given Unix timestamp, by upstream key
+    cooldowns: HashMap<String, i64>,
+}
+
+/// The running proxy, if any, and the state its requests share
+#[derive(Default)]
+pub struct RoutingProxyState {
+    running: Mutex<Option<RunningProxy>>,
+    runtime: Mutex<ProxyRuntime>,
+}
+
+/// Create the proxy request log
+pub fn init_routing_proxy_tables(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp_ms INTEGER NOT NULL,
+            upstream_kind TEXT NOT NULL,
+            upstream_id TEXT NOT NULL,
+            upstream_name TEXT NOT NULL,
+            base_url TEXT NOT NULL,
+            path TEXT NOT NULL,
+            model TEXT,
+            status_code INTEGER,
+            success BOOLEAN NOT NULL,
+            attempt INTEGER NOT NULL,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            latency_ms INTEGER,
+            duration_ms INTEGER NOT NULL,
+            error TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_proxy_requests_timestamp ON proxy_requests(timestamp_ms)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn load_config(conn: &Connection) -> RoutingProxyConfig {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn store_config(conn: &Connection, config: &RoutingProxyConfig) -> Result<(), String> {
+    let value = serde_json::to_string(config).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2",
+        params![SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save routing proxy settings: {}", e))?;
+    Ok(())
+}
+
+/// A parsed client request
+struct ProxyRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl ProxyRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_authorized(&self, access_token: &str) -> bool {
+        let bearer = self
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        bearer == Some(access_token) || self.header("x-api-key") == Some(access_token)
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<ProxyRequest, String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err("Request headers too large".to_string());
+        }
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("Connection closed before the request was complete".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut request = ProxyRequest {
+        method,
+        path,
+        headers,
+        body: buffer[header_end + 4..].to_vec(),
+    };
+    if request
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        return Err("Chunked request bodies are not supported".to_string());
+    }
+    let content_length = request
+        .header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return Err("Request body too large".to_string());
+    }
+    while request.body.len() < content_length {
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("Connection closed before the request body was complete".to_string());
+        }
+        request.body.extend_from_slice(&chunk[..n]);
+    }
+    request.body.truncate(content_length);
+    Ok(request)
+}
+
+/// Write a complete response with an Anthropic-style error body
+async fn write_error(stream: &mut TcpStream, status: u16, error_type: &str, message: &str) {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": { "type": error_type, "message": message }
+    })
+    .to_string();
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Error");
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Token usage of one request, read from the response as it streams by
+#[derive(Default)]
+struct UsageTracker {
+    streaming: bool,
+    pending: Vec<u8>,
+    buffered: Vec<u8>,
+    model: Option<String>,
+    input: u64,
+    output: u64,
+    cache_creation: u64,
+    cache_read: u64,
+}
+
+impl UsageTracker {
+    fn add_usage(&mut self, usage: &serde_json::Value) {
+        if let Ok(usage) = serde_json::from_value::<Usage>(usage.clone()) {
+            // Streamed counts are cumulative, so keep the largest seen
+            self.input = self.input.max(usage.input());
+            self.output = self.output.max(usage.output());
+            self.cache_creation = self.cache_creation.max(usage.cache_creation());
+            self.cache_read = self.cache_read.max(usage.cache_read());
+        }
+    }
+
+    fn add_message(&mut self, message: &serde_json::Value) {
+        if let Some(model) = message.get("model").and_then(|m| m.as_str()) {
+            self.model = Some(model.to_string());
+        }
+        if let Some(usage) = message.get("usage") {
+            self.add_usage(usage);
+        }
+    }
+
+    fn add_event(&mut self, line: &[u8]) {
+        let Some(data) = line.strip_prefix(b"data:") else {
+            return;
+        };
+        let Ok(event) = serde_json::from_slice::<serde_json::Value>(data) else {
+            return;
+        };
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => {
+                if let Some(message) = event.get("message") {
+                    self.add_message(message);
+                }
+            }
+            Some("message_delta") => {
+                if let Some(usage) = event.get("usage") {
+                    self.add_usage(usage);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn observe(&mut self, chunk: &[u8]) {
+        if !self.streaming {
+            if self.buffered.len() + chunk.len() <= MAX_USAGE_BUFFER_BYTES {
+                self.buffered.extend_from_slice(chunk);
+            }
+            return;
+        }
+        self.pending.extend_from_slice(chunk);
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            self.add_event(line.trim_ascii());
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.streaming {
+            let line = std::mem::take(&mut self.pending);
+            self.add_event(line.trim_ascii());
+        } else if let Ok(message) = serde_json::from_slice::<serde_json::Value>(&self.buffered) {
+            self.add_message(&message);
+        }
+    }
+}
+
+/// One forwarded attempt, as stored in `proxy_requests`
+struct AttemptRecord<'a> {
+    upstream: &'a ProxyUpstream,
+    config: &'a ProviderConfig,
+    path: &'a str,
+    attempt: u32,
+    status_code: Option<u16>,
+    success: bool,
+    usage: Option<&'a UsageTracker>,
+    request_model: Option<&'a str>,
+    latency_ms: Option<u64>,
+    duration_ms: u64,
+    error: Option<String>,
+}
+
+fn record_attempt(app: &AppHandle, record: AttemptRecord) {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    let usage = record.usage;
+    let model = usage
+        .and_then(|u| u.model.as_deref())
+        .or(record.request_model);
+    let result = conn.execute(
+        "INSERT INTO proxy_requests
+         (timestamp_ms, upstream_kind, upstream_id, upstream_name, base_url, path, model, status_code,
+          success, attempt, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens,
+          latency_ms, duration_ms, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        params![
+            Utc::now().timestamp_millis(),
+            record.upstream.member.kind.as_str(),
+            record.upstream.member.id,
+            record.config.name,
+            record.config.base_url,
+            record.path,
+            model,
+            record.status_code,
+            record.success,
+            record.attempt,
+            usage.map_or(0, |u| u.input),
+            usage.map_or(0, |u| u.output),
+            usage.map_or(0, |u| u.cache_creation),
+            usage.map_or(0, |u| u.cache_read),
+            record.latency_ms,
+            record.duration_ms,
+            record.error,
+        ],
+    );
+    if let Err(e) = result {
+        warn!("Failed to record proxy request: {}", e);
+    }
+}
+
+/// Upstreams in the order they should be tried: the smooth weighted
+/// round-robin pick first, then the rest by weight, cooling down ones last
+fn upstream_order(runtime: &mut ProxyRuntime, now: i64) -> Vec<ProxyUpstream> {
+    let upstreams: Vec<ProxyUpstream> = runtime
+        .config
+        .upstreams
+        .iter()
+        .filter(|u| u.weight > 0)
+        .cloned()
+        .collect();
+    let cooling_down = |runtime: &ProxyRuntime, upstream: &ProxyUpstream| {
+        runtime
+            .cooldowns
+            .get(&upstream.member.key())
+            .is_some_and(|&until| until > now)
+    };
+    let (mut ready, mut cooling): (Vec<ProxyUpstream>, Vec<ProxyUpstream>) = upstreams
+        .into_iter()
+        .partition(|u| !cooling_down(runtime, u));
+    if ready.is_empty() {
+        std::mem::swap(&mut ready, &mut cooling);
+    }
+
+    let total: i64 = ready.iter().map(|u| u.weight as i64).sum();
+    let mut best: Option<(usize, i64)> = None;
+    for (i, upstream) in ready.iter().enumerate() {
+        let current = runtime
+            .current_weights
+            .entry(upstream.member.key())
+            .or_insert(0);
+        *current += upstream.weight as i64;
+        if best.is_none_or(|(_, weight)| *current > weight) {
+            best = Some((i, *current));
+        }
+    }
+
+    let mut order = Vec::with_capacity(ready.len() + cooling.len());
+    if let Some((i, _)) = best {
+        let picked = ready.remove(i);
+        if let Some(current) = runtime.current_weights.get_mut(&picked.member.key()) {
+            *current -= total;
+        }
+        order.push(picked);
+    }
+    ready.sort_by_key(|u| std::cmp::Reverse(u.weight));
+    cooling.sort_by_key(|u| std::cmp::Reverse(u.weight));
+    order.extend(ready);
+    order.extend(cooling);
+    order
+}
+
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Stream an upstream response back to the client, tracking its usage
+async fn relay_response(
+    stream: &mut TcpStream,
+    mut response: reqwest::Response,
+    usage: &mut UsageTracker,
+) -> Result<(), String> {
+    let status = response.status();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    );
+    for (name, value) in response.headers() {
+        if SKIPPED_RESPONSE_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    // The body is delimited by closing the connection
+    head.push_str("Connection: close\r\n\r\n");
+    usage.streaming = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    stream
+        .write_all(head.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        usage.observe(&chunk);
+        stream.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    usage.finish();
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+/// Relay an OpenAI chat completions response as a Messages API response
+async fn relay_translated_response(
+    stream: &mut TcpStream,
+    mut response: reqwest::Response,
+    usage: &mut UsageTracker,
+    model: &str,
+) -> Result<(), String> {
+    let status = response.status();
+    let streaming = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    if status.is_success() && streaming {
+        usage.streaming = true;
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+        stream
+            .write_all(head.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut translator = StreamTranslator::new(model);
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            let events = translator.push(&chunk);
+            usage.observe(&events);
+            stream.write_all(&events).await.map_err(|e| e.to_string())?;
+        }
+        let events = translator.finish();
+        usage.observe(&events);
+        stream.write_all(&events).await.map_err(|e| e.to_string())?;
+    } else {
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        let (status, converted) = if status.is_success() {
+            match serde_json::from_slice::<serde_json::Value>(&body) {
+                Ok(json) => (status, openai_to_anthropic_response(&json, model)),
+                Err(_) => (
+                    reqwest::StatusCode::BAD_GATEWAY,
+                    openai_to_anthropic_error(502, &body),
+                ),
+            }
+        } else {
+            (status, openai_to_anthropic_error(status.as_u16(), &body))
+        };
+        let converted = converted.to_string();
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or(""),
+            converted.len()
+        );
+        stream
+            .write_all(head.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        usage.observe(converted.as_bytes());
+        stream
+            .write_all(converted.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    usage.finish();
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+async fn handle_connection(app: AppHandle, mut stream: TcpStream) -> Result<(), String> {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            write_error(&mut stream, 400, "invalid_request_error", &e).await;
+            return Err(e);
+        }
+    };
+
+    let now = Utc::now().timestamp();
+    let (order, max_retries, access_token) = {
+        let state = app.state::<RoutingProxyState>();
+        let mut runtime = state.runtime.lock().map_err(|e| e.to_string())?;
+        (
+            upstream_order(&mut runtime, now),
+            runtime.config.max_retries,
+            runtime.config.access_token.clone(),
+        )
+    };
+
+    if !request.is_authorized(&access_token) {
+        write_error(
+            &mut stream,
+            401,
+            "authentication_error",
+            "Invalid proxy token",
+        )
+        .await;
+        return Ok(());
+    }
+    if !request.path.starts_with("/v1/") {
+        write_error(
+            &mut stream,
+            404,
+            "not_found_error",
+            "Only /v1/ endpoints are proxied",
+        )
+        .await;
+        return Ok(());
+    }
+
+    let request_json = serde_json::from_slice::<serde_json::Value>(&request.body).ok();
+    let request_model = request_json.as_ref().and_then(|body| {
+        body.get("model")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+    });
+    // Only the Messages endpoint can be translated for OpenAI-format stations
+    let is_messages = request.method == "POST"
+        && request.path.split('?').next() == Some("/v1/messages")
+        && request_json.is_some();
+    let method =
+        reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+    // Pooled connections, configured connect timeout, proxy and CA certificates
+    let client = match http_client::proxy_client() {
+        Ok(client) => client,
+        Err(e) => {
+            write_error(&mut stream, 502, "api_error", &e.to_string()).await;
+            return Ok(());
+        }
+    };
+    let mut attempt = 0;
+    let mut last_error = "No upstream configured".to_string();
+
+    let upstream_count = order.len();
+    for (i, upstream) in order.into_iter().enumerate() {
+        if attempt > max_retries {
+            break;
+        }
+        let Some(config) = resolve_member(&app, &upstream.member) else {
+            continue;
+        };
+        let translation = member_translation(&app, &upstream.member);
+        let translated = match &request_json {
+            Some(body) if translation.is_openai() && is_messages => {
+                match anthropic_to_openai_request(body, &translation) {
+                    Ok(body) => Some(body),
+                    Err(e) => {
+                        write_error(&mut stream, 400, "invalid_request_error", &e).await;
+                        return Ok(());
+                    }
+                }
+            }
+            _ if translation.is_openai() => continue,
+            _ => None,
+        };
+        attempt += 1;
+
+        let base_url = config.base_url.trim_end_matches('/');
+        let mut forward = match &translated {
+            Some(body) => client
+                .post(format!("{}{}", base_url, translation.openai_path))
+                .json(body),
+            None => client
+                .request(method.clone(), format!("{}{}", base_url, request.path))
+                .body(request.body.clone()),
+        };
+        for (name, value) in &request.headers {
+            let name_lower = name.to_ascii_lowercase();
+            if SKIPPED_REQUEST_HEADERS.contains(&name_lower.as_str())
+                || (translated.is_some()
+                    && (name_lower.starts_with("anthropic-") || name_lower == "content-type"))
+            {
+                continue;
+            }
+            forward = forward.header(name, value);
+        }
+        if let Some(token) = &config.auth_token {
+            forward = forward.bearer_auth(token);
+        }
+        if let Some(key) = &config.api_key {
+            forward = forward.header("x-api-key", key);
+        }
+
+        let started = Instant::now();
+        let result = forward.send().await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let failed = match &result {
+            Ok(response) => is_retryable(response.status()),
+            Err(_) => true,
+        };
+        if failed {
+            let state = app.state::<RoutingProxyState>();
+            if let Ok(mut runtime) = state.runtime.lock() {
+                runtime
+                    .cooldowns
+                    .insert(upstream.member.key(), now + FAILURE_COOLDOWN_SECS);
+            };
+        }
+
+        let last_attempt = attempt > max_retries || i + 1 == upstream_count;
+        match result {
+            // Out of upstreams to retry with: hand the failure to the client as is
+            Ok(response) if !failed || last_attempt => {
+                let status = response.status();
+                let mut usage = UsageTracker::default();
+                let relayed = match &translated {
+                    Some(_) => {
+                        relay_translated_response(
+                            &mut stream,
+                            response,
+                            &mut usage,
+                            request_model.as_deref().unwrap_or_default(),
+                        )
+                        .await
+                    }
+                    None => relay_response(&mut stream, response, &mut usage).await,
+                };
+                record_attempt(
+                    &app,
+                    AttemptRecord {
+                        upstream: &upstream,
+                        config: &config,
+                        path: &request.path,
+                        attempt,
+                        status_code: Some(status.as_u16()),
+                        success: status.is_success() && relayed.is_ok(),
+                        usage: Some(&usage),
+                        request_model: request_model.as_deref(),
+                        latency_ms: Some(latency_ms),
+                        duration_ms: started.elapsed().as_millis() as u64,
+                        error: relayed.as_ref().err().cloned(),
+                    },
+                );
+                return relayed;
+            }
+            Ok(response) => {
+                last_error = format!("{} returned HTTP {}", config.name, response.status());
+                record_attempt(
+                    &app,
+                    AttemptRecord {
+                        upstream: &upstream,
+                        config: &config,
+                        path: &request.path,
+                        attempt,
+                        status_code: Some(response.status().as_u16()),
+                        success: false,
+                        usage: None,
+                        request_model: request_model.as_deref(),
+                        latency_ms: Some(latency_ms),
+                        duration_ms: latency_ms,
+                        error: Some(last_error.clone()),
+                    },
+                );
+            }
+            Err(e) => {
+                last_error = format!("{}: {}", config.name, e);
+                record_attempt(
+                    &app,
+                    AttemptRecord {
+                        upstream: &upstream,
+                        config: &config,
+                        path: &request.path,
+                        attempt,
+                        status_code: None,
+                        success: false,
+                        usage: None,
+                        request_model: request_model.as_deref(),
+                        latency_ms: None,
+                        duration_ms: latency_ms,
+                        error: Some(e.to_string()),
+                    },
+                );
+                // Once a request has been sent the upstream may still be
+                // processing it, and sending it elsewhere could bill it twice
+                if !e.is_connect() && !http_client::is_idempotent(&method) {
+                    warn!(
+                        "Proxy attempt {} failed after sending: {}",
+                        attempt, last_error
+                    );
+                    break;
+                }
+            }
+        }
+        warn!("Proxy attempt {} failed: {}", attempt, last_error);
+    }
+
+    write_error(&mut stream, 502, "api_error", &last_error).await;
+    Ok(())
+}
+
+/// Update the shared config and start, restart or stop the listener as needed
+fn apply_config(app: &AppHandle, config: &RoutingProxyConfig) -> Result<(), String> {
+    let state = app.state::<RoutingProxyState>();
+    let mut running = state.running.lock().map_err(|e| e.to_string())?;
+
+    // Upstream changes take effect on the next request without a restart
+    let restart = config.enabled
+        && running
+            .as_ref()
+            .is_none_or(|proxy| proxy.port != config.port);
+    // Bind the new port before anything changes, so a taken port leaves the
+    // running proxy serving with its old config. Never listen beyond
+    // loopback: the proxy hands out upstream credentials
+    let address = format!("127.0.0.1:{}", config.port);
+    let listener = if restart {
+        let listener = std::net::TcpListener::bind(&address)
+            .map_err(|e| format!("Failed to bind {}: {}", address, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Some(listener)
+    } else {
+        None
+    };
+
+    {
+        let mut runtime = state.runtime.lock().map_err(|e| e.to_string())?;
+        if runtime.config.upstreams != config.upstreams {
+            runtime.current_weights.clear();
+            runtime.cooldowns.clear();
+        }
+        runtime.config = config.clone();
+    }
+
+    if config.enabled && !restart {
+        return Ok(());
+    }
+    if let Some(proxy) = running.take() {
+        proxy.task.abort();
+        info!("Stopped routing proxy on port {}", proxy.port);
+    }
+    let Some(listener) = listener else {
+        return Ok(());
+    };
+
+    let app_handle = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to start routing proxy: {}", e);
+                return;
+            }
+        };
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let app = app_handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = handle_connection(app, stream).await {
+                            warn!("Proxy request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept proxy connection: {}", e),
+            }
+        }
+    });
+
+    info!("Routing proxy listening on http://{}", address);
+    *running = Some(RunningProxy {
+        port: config.port,
+        task,
+    });
+    Ok(())
+}
+
+fn validate_config(config: &RoutingProxyConfig) -> Result<(), String> {
+    if config.port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    if config.enabled && !config.upstreams.iter().any(|u| u.weight > 0) {
+        return Err("The routing proxy needs at least one upstream with a weight".to_string());
+    }
+    Ok(())
+}
+
+/// Start the routing proxy if it is enabled in the settings
+pub fn start_routing_proxy(app: &AppHandle) {
+    let config = {
+        let db = app.state::<AgentDb>();
+        let conn = match db.0.lock() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Failed to load routing proxy settings: {}", e);
+                return;
+            }
+        };
+        load_config(&conn)
+    };
+    if let Err(e) = apply_config(app, &config) {
+        error!("{}", e);
+    }
+}
+
+/// Get the routing proxy settings and whether it is running
+#[command]
+pub fn get_routing_proxy_status(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<RoutingProxyStatus, String> {
+    let config = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_config(&conn)
+    };
+    let state = app.state::<RoutingProxyState>();
+    let running = state.running.lock().map_err(|e| e.to_string())?;
+    Ok(RoutingProxyStatus {
+        config,
+        base_url: running
+            .as_ref()
+            .map(|proxy| format!("http://127.0.0.1:{}", proxy.port)),
+    })
+}
+
+/// Save the routing proxy settings and start, update or stop it accordingly
+#[command]
+pub fn save_routing_proxy_config(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    mut config: RoutingProxyConfig,
+) -> Result<RoutingProxyConfig, String> {
+    validate_config(&config)?;
+    if config.access_token.trim().is_empty() {
+        config.access_token = format!("proxy-{}", uuid::Uuid::new_v4().simple());
+    }
+
+    apply_config(&app, &config)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store_config(&conn, &config)?;
+    Ok(config)
+}
+
+/// Point settings.json at the running proxy, so switching upstreams no
+/// longer needs a settings rewrite
+#[command]
+pub async fn use_routing_proxy(app: AppHandle) -> Result<String, String> {
+    let config = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_config(&conn)
+    };
+    let running = app
+        .state::<RoutingProxyState>()
+        .running
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if !running {
+        return Err("The routing proxy is not running".to_string());
+    }
+
+    apply_provider_config(ProviderConfig {
+        id: SETTINGS_KEY.to_string(),
+        name: "Local routing proxy".to_string(),
+        description: format!("127.0.0.1:{}", config.port),
+        base_url: format!("http://127.0.0.1:{}", config.port),
+        auth_token: Some(config.access_token),
+        api_key: None,
+        model: None,
+    })
+    .await
+}
+
+/// Per-upstream request counts, tokens and latency of the last `days` days (default 7)
+#[command]
+pub fn get_routing_proxy_stats(
+    db: State<'_, AgentDb>,
+    days: Option<u32>,
+) -> Result<Vec<ProxyUpstreamStats>, String> {
+    let since = Utc::now().timestamp_millis() - days.unwrap_or(7) as i64 * 86_400_000;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT upstream_kind, upstream_id, MAX(upstream_name), COUNT(*),
+                    SUM(CASE WHEN success THEN 0 ELSE 1 END),
+                    SUM(input_tokens), SUM(output_tokens), SUM(cache_creation_tokens), SUM(cache_read_tokens),
+                    COALESCE(AVG(latency_ms), 0), COALESCE(AVG(duration_ms), 0)
+             FROM proxy_requests
+             WHERE timestamp_ms >= ?1
+             GROUP BY upstream_kind, upstream_id
+             ORDER BY COUNT(*) DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let stats = stmt
+        .query_map(params![since], |row| {
+            Ok(ProxyUpstreamStats {
+                upstream_kind: row.get(0)?,
+                upstream_id: row.get(1)?,
+                upstream_name: row.get(2)?,
+                requests: row.get(3)?,
+                failed_requests: row.get(4)?,
+                input_tokens: row.get(5)?,
+                output_tokens: row.get(6)?,
+                cache_creation_tokens: row.get(7)?,
+                cache_read_tokens: row.get(8)?,
+                avg_latency_ms: row.get(9)?,
+                avg_duration_ms: row.get(10)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<SqliteResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::relay_failover::FailoverMemberKind;
+
+    fn upstream(id: &str, weight: u32) -> ProxyUpstream {
+        ProxyUpstream {
+            member: FailoverMember {
+                kind: FailoverMemberKind::Station,
+                id: id.to_string(),
+            },
+            weight,
+        }
+    }
+
+    fn runtime(upstreams: Vec<ProxyUpstream>) -> ProxyRuntime {
+        ProxyRuntime {
+            config: RoutingProxyConfig {
+                upstreams,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn order_ids(runtime: &mut ProxyRuntime, now: i64) -> Vec<String> {
+        upstream_order(runtime, now)
+            .into_iter()
+            .map(|u| u.member.id)
+            .collect()
+    }
+
+    #[test]
+    fn upstream_order_spreads_picks_by_weight() {
+        let mut runtime = runtime(vec![upstream("a", 3), upstream("b", 1)]);
+        let firsts: Vec<String> = (0..8)
+            .map(|_| order_ids(&mut runtime, 0).remove(0))
+            .collect();
+        assert_eq!(firsts, ["a", "a", "b", "a", "a", "a", "b", "a"]);
+    }
+
+    #[test]
+    fn upstream_order_skips_zero_weights() {
+        let mut runtime = runtime(vec![upstream("a", 0), upstream("b", 1), upstream("c", 2)]);
+        assert_eq!(order_ids(&mut runtime, 0), ["c", "b"]);
+    }
+
+    #[test]
+    fn upstream_order_tries_cooling_down_upstreams_last() {
+        let mut runtime = runtime(vec![upstream("a", 5), upstream("b", 1)]);
+        let key = runtime.config.upstreams[0].member.key();
+        runtime.cooldowns.insert(key, 100);
+        assert_eq!(order_ids(&mut runtime, 50), ["b", "a"]);
+        // An expired cooldown no longer demotes the upstream
+        assert_eq!(order_ids(&mut runtime, 100)[0], "a");
+    }
+
+    #[test]
+    fn upstream_order_picks_among_cooling_upstreams_when_all_are() {
+        let mut runtime = runtime(vec![upstream("a", 1), upstream("b", 2)]);
+        for upstream in runtime.config.upstreams.clone() {
+            runtime.cooldowns.insert(upstream.member.key(), 100);
+        }
+        assert_eq!(order_ids(&mut runtime, 50), ["b", "a"]);
+    }
+}
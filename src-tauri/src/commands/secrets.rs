@@ -0,0 +1,524 @@
+//! Encrypted store for relay station and provider credentials.
+//!
+//! Credential fields hold a `secret://<id>` reference instead of the value
+//! itself. Values are encrypted with AES-256-GCM under a key that comes from
+//! a local key file, or from a passphrase through Argon2id; a passphrase
+//! protected store stays locked until `unlock_secret_store` is called.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::command;
+
+const SECRET_REF_PREFIX: &str = "secret://";
+pub(crate) const STORE_LOCKED: &str = "Secret store is locked";
+pub(crate) const MASK: &str = "****";
+// Prefix the frontend adds to relay station token keys
+const KEY_PREFIX: &str = "sk-";
+// Values up to this length are masked completely
+const MASK_MIN_REVEAL_LEN: usize = 12;
+const STORE_FILE: &str = "store.json";
+const KEY_FILE: &str = "master.key";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+// Encrypted under the key so that a wrong passphrase is detected on unlock
+const CHECK_ID: &str = "check";
+const CHECK_VALUE: &[u8] = b"secret-store";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+enum KeySource {
+    KeyFile,
+    Passphrase { salt: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    key_source: KeySource,
+    check: Option<String>,
+    /// Secret id to base64 of nonce and ciphertext
+    entries: BTreeMap<String, String>,
+}
+
+impl Default for StoreFile {
+    fn default() -> Self {
+        StoreFile {
+            version: 1,
+            key_source: KeySource::KeyFile,
+            check: None,
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+struct SecretStore {
+    file: StoreFile,
+    key: Option<[u8; 32]>,
+}
+
+static STORE: Lazy<Mutex<Option<SecretStore>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretStoreStatus {
+    /// "key_file" or "passphrase"
+    pub mode: String,
+    pub locked: bool,
+    pub secret_count: usize,
+}
+
+fn store_dir() -> Result<PathBuf, String> {
+    let dir = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("secrets");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Replace a file with content only the current user can read
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp).map_err(|e| e.to_string())?;
+    file.write_all(content).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn load_or_create_key_file() -> Result<[u8; 32], String> {
+    let path = store_dir()?.join(KEY_FILE);
+    if path.exists() {
+        let encoded = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| format!("Invalid secret key file: {}", e))?;
+        return bytes
+            .try_into()
+            .map_err(|_| "Invalid secret key file: wrong key length".to_string());
+    }
+
+    let key: [u8; 32] = Aes256Gcm::generate_key(OsRng).into();
+    write_private(&path, BASE64.encode(key).as_bytes())?;
+    info!("Created secret store key file at {:?}", path);
+    Ok(key)
+}
+
+/// A fresh base64 salt for `derive_key`
+pub(crate) fn random_salt() -> String {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    BASE64.encode(salt)
+}
+
+pub(crate) fn derive_key(passphrase: &str, salt: &str) -> Result<[u8; 32], String> {
+    let salt = BASE64.decode(salt).map_err(|e| e.to_string())?;
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+/// Encrypt a value, binding it to its id so entries cannot be swapped
+pub(crate) fn encrypt(key: &[u8; 32], id: &str, value: &[u8]) -> Result<String, String> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: value,
+                aad: id.as_bytes(),
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    let mut out = nonce.to_vec();
+    out.extend(ciphertext);
+    Ok(BASE64.encode(out))
+}
+
+pub(crate) fn decrypt(key: &[u8; 32], id: &str, encoded: &str) -> Result<Vec<u8>, String> {
+    let bytes = BASE64.decode(encoded).map_err(|e| e.to_string())?;
+    if bytes.len() < NONCE_LEN {
+        return Err(format!("Secret '{}' is corrupt", id));
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: id.as_bytes(),
+            },
+        )
+        .map_err(|_| format!("Failed to decrypt secret '{}'", id))
+}
+
+impl SecretStore {
+    fn load() -> Result<Self, String> {
+        let path = store_dir()?.join(STORE_FILE);
+        let file: StoreFile = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            serde_json::from_str(&content).map_err(|e| format!("Invalid secret store: {}", e))?
+        } else {
+            StoreFile::default()
+        };
+        let key = match file.key_source {
+            KeySource::KeyFile => Some(load_or_create_key_file()?),
+            KeySource::Passphrase { .. } => None,
+        };
+        Ok(SecretStore { file, key })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_vec_pretty(&self.file).map_err(|e| e.to_string())?;
+        write_private(&store_dir()?.join(STORE_FILE), &content)
+    }
+
+    fn key(&self) -> Result<&[u8; 32], String> {
+        self.key.as_ref().ok_or_else(|| STORE_LOCKED.to_string())
+    }
+
+    /// Derive the key for a passphrase, checking it against the store
+    fn verify_passphrase(&self, passphrase: &str) -> Result<[u8; 32], String> {
+        let KeySource::Passphrase { salt } = &self.file.key_source else {
+            return Err("Secret store is not protected by a passphrase".to_string());
+        };
+        let key = derive_key(passphrase, salt)?;
+        let check = self
+            .file
+            .check
+            .as_deref()
+            .ok_or("Secret store has no check value")?;
+        match decrypt(&key, CHECK_ID, check) {
+            Ok(value) if value == CHECK_VALUE => Ok(key),
+            _ => Err("Incorrect passphrase".to_string()),
+        }
+    }
+
+    /// Encrypt `value` under `id`, returning whether the entry changed. A
+    /// masked value keeps the stored secret and is never stored itself.
+    fn put_entry(&mut self, id: &str, value: &str) -> Result<bool, String> {
+        if is_masked(value) {
+            return if self.file.entries.contains_key(id) {
+                Ok(false)
+            } else {
+                Err(format!(
+                    "No secret stored for '{}', enter the full value instead of a masked one",
+                    id
+                ))
+            };
+        }
+        let encrypted = encrypt(self.key()?, id, value.as_bytes())?;
+        self.file.entries.insert(id.to_string(), encrypted);
+        Ok(true)
+    }
+
+    fn status(&self) -> SecretStoreStatus {
+        SecretStoreStatus {
+            mode: match self.file.key_source {
+                KeySource::KeyFile => "key_file",
+                KeySource::Passphrase { .. } => "passphrase",
+            }
+            .to_string(),
+            locked: self.key.is_none(),
+            secret_count: self.file.entries.len(),
+        }
+    }
+}
+
+/// Run `f` on the store, loading it on first use
+fn with_store<T>(f: impl FnOnce(&mut SecretStore) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = STORE.lock().map_err(|e| e.to_string())?;
+    let store = match guard.take() {
+        Some(store) => store,
+        None => SecretStore::load()?,
+    };
+    f(guard.insert(store))
+}
+
+fn secret_ref(id: &str) -> String {
+    format!("{}{}", SECRET_REF_PREFIX, id)
+}
+
+pub fn is_secret_ref(value: &str) -> bool {
+    value.starts_with(SECRET_REF_PREFIX)
+}
+
+/// Whether a value is a masked secret handed back by the frontend
+pub fn is_masked(value: &str) -> bool {
+    value.contains(MASK)
+}
+
+/// Show only the first and last four characters of a secret. An `sk-`
+/// prefix is kept as is, so a key masks the same with or without it.
+pub fn mask_secret(value: &str) -> String {
+    if let Some(rest) = value.strip_prefix(KEY_PREFIX) {
+        return format!("{}{}", KEY_PREFIX, mask_secret(rest));
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.is_empty() {
+        return String::new();
+    }
+    if chars.len() <= MASK_MIN_REVEAL_LEN {
+        return MASK.to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}{}", head, MASK, tail)
+}
+
+pub fn mask_optional(value: &mut Option<String>) {
+    if let Some(value) = value {
+        *value = mask_secret(value);
+    }
+}
+
+/// Accept a reference only when it points at the field's own secret, so that
+/// a field cannot be made to resolve another credential
+fn own_secret_ref(id: &str, value: &str) -> Result<String, String> {
+    let own = secret_ref(id);
+    if value == own {
+        Ok(own)
+    } else {
+        Err(format!(
+            "Secret reference '{}' does not belong to this field",
+            value
+        ))
+    }
+}
+
+/// Encrypt `value` under `id` and return the reference to store in its field.
+/// Empty values are not stored, and a masked value keeps the stored secret;
+/// one without a stored secret is rejected.
+pub fn put_secret(id: &str, value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        delete_secret(id)?;
+        return Ok(String::new());
+    }
+    if is_secret_ref(value) {
+        return own_secret_ref(id, value);
+    }
+    with_store(|store| {
+        if store.put_entry(id, value)? {
+            store.save()?;
+        }
+        Ok(secret_ref(id))
+    })
+}
+
+/// Resolve a field value; anything that is not a reference is returned as is
+pub fn resolve_secret(value: &str) -> Result<String, String> {
+    let Some(id) = value.strip_prefix(SECRET_REF_PREFIX) else {
+        return Ok(value.to_string());
+    };
+    with_store(|store| {
+        let encoded = store
+            .file
+            .entries
+            .get(id)
+            .ok_or_else(|| format!("Secret '{}' not found", id))?;
+        let plain = decrypt(store.key()?, id, encoded)?;
+        String::from_utf8(plain).map_err(|e| e.to_string())
+    })
+}
+
+/// Like `resolve_secret`, but keeps the reference when it cannot be resolved,
+/// e.g. while the store is locked
+pub fn resolve_secret_or_keep(value: String) -> String {
+    match resolve_secret(&value) {
+        Ok(plain) => plain,
+        Err(e) => {
+            warn!("Could not resolve {}: {}", value, e);
+            value
+        }
+    }
+}
+
+/// Whether a passphrase protected store is waiting to be unlocked; stored
+/// references cannot be resolved until then
+pub fn is_store_locked() -> bool {
+    with_store(|store| Ok(store.key.is_none())).unwrap_or(false)
+}
+
+pub fn delete_secret(id: &str) -> Result<(), String> {
+    with_store(|store| {
+        if store.file.entries.remove(id).is_some() {
+            store.save()?;
+        }
+        Ok(())
+    })
+}
+
+#[command]
+pub fn get_secret_store_status() -> Result<SecretStoreStatus, String> {
+    with_store(|store| Ok(store.status()))
+}
+
+#[command]
+pub fn unlock_secret_store(passphrase: String) -> Result<SecretStoreStatus, String> {
+    with_store(|store| {
+        store.key = Some(store.verify_passphrase(&passphrase)?);
+        Ok(store.status())
+    })
+}
+
+#[command]
+pub fn lock_secret_store() -> Result<SecretStoreStatus, String> {
+    with_store(|store| {
+        if matches!(store.file.key_source, KeySource::KeyFile) {
+            return Err("Only a passphrase protected store can be locked".to_string());
+        }
+        store.key = None;
+        Ok(store.status())
+    })
+}
+
+/// Protect the store with a passphrase, change it, or go back to a key file
+/// when `passphrase` is `None`. Every secret is re-encrypted with the new key.
+#[command]
+pub fn set_secret_store_passphrase(
+    current_passphrase: Option<String>,
+    passphrase: Option<String>,
+) -> Result<SecretStoreStatus, String> {
+    with_store(|store| {
+        let old_key = match (&store.file.key_source, current_passphrase) {
+            (KeySource::KeyFile, _) => *store.key()?,
+            (KeySource::Passphrase { .. }, Some(current)) => store.verify_passphrase(&current)?,
+            (KeySource::Passphrase { .. }, None) => {
+                return Err("The current passphrase is required".to_string())
+            }
+        };
+        let key_file = store_dir()?.join(KEY_FILE);
+
+        let (key_source, new_key) = match passphrase.filter(|p| !p.is_empty()) {
+            Some(passphrase) => {
+                let salt = random_salt();
+                let key = derive_key(&passphrase, &salt)?;
+                (KeySource::Passphrase { salt }, key)
+            }
+            None if matches!(store.file.key_source, KeySource::KeyFile) => {
+                return Ok(store.status())
+            }
+            None => (KeySource::KeyFile, load_or_create_key_file()?),
+        };
+
+        let mut entries = BTreeMap::new();
+        for (id, encoded) in &store.file.entries {
+            let plain = decrypt(&old_key, id, encoded)?;
+            entries.insert(id.clone(), encrypt(&new_key, id, &plain)?);
+        }
+        let check = match key_source {
+            KeySource::Passphrase { .. } => Some(encrypt(&new_key, CHECK_ID, CHECK_VALUE)?),
+            KeySource::KeyFile => None,
+        };
+
+        store.file = StoreFile {
+            version: store.file.version,
+            key_source,
+            check,
+            entries,
+        };
+        store.key = Some(new_key);
+        store.save()?;
+
+        // A passphrase protected store must not leave a usable key on disk
+        if matches!(store.file.key_source, KeySource::Passphrase { .. }) && key_file.exists() {
+            fs::remove_file(&key_file).map_err(|e| e.to_string())?;
+        }
+        info!("Secret store re-encrypted ({})", store.status().mode);
+        Ok(store.status())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_secrets() {
+        assert_eq!(mask_secret(""), "");
+        assert_eq!(mask_secret("short-token"), MASK);
+        assert_eq!(mask_secret("sk-1234567890abcdef"), "sk-1234****cdef");
+        assert_eq!(
+            format!("sk-{}", mask_secret("1234567890abcdef")),
+            mask_secret("sk-1234567890abcdef")
+        );
+        assert_eq!(mask_secret("sk-short"), "sk-****");
+        assert!(is_masked(&mask_secret("sk-1234567890abcdef")));
+        assert!(!is_masked("sk-1234567890abcdef"));
+    }
+
+    #[test]
+    fn only_own_reference_is_accepted() {
+        assert_eq!(
+            own_secret_ref(
+                "relay_station:s1:system_token",
+                "secret://relay_station:s1:system_token"
+            )
+            .unwrap(),
+            "secret://relay_station:s1:system_token"
+        );
+        assert!(own_secret_ref(
+            "relay_station:s1:system_token",
+            "secret://provider:p:api_key"
+        )
+        .is_err());
+        assert!(own_secret_ref(
+            "relay_station:s1:system_token",
+            "secret://relay_station:s2:system_token"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn masked_values_only_keep_stored_secrets() {
+        let mut store = SecretStore {
+            file: StoreFile::default(),
+            key: Some([7u8; 32]),
+        };
+        let id = "relay_station:s1:system_token";
+        let masked = mask_secret("sk-1234567890abcdef");
+
+        assert!(store.put_entry(id, &masked).is_err());
+        assert!(store.file.entries.is_empty());
+
+        assert!(store.put_entry(id, "sk-1234567890abcdef").unwrap());
+        let stored = store.file.entries[id].clone();
+        assert!(!store.put_entry(id, &masked).unwrap());
+        assert_eq!(store.file.entries[id], stored);
+        assert_eq!(
+            decrypt(&[7u8; 32], id, &stored).unwrap(),
+            b"sk-1234567890abcdef"
+        );
+    }
+
+    #[test]
+    fn ciphertext_is_bound_to_key_and_id() {
+        let key = [7u8; 32];
+        let encrypted = encrypt(&key, "provider:a:api_key", b"sk-secret").unwrap();
+        assert_eq!(
+            decrypt(&key, "provider:a:api_key", &encrypted).unwrap(),
+            b"sk-secret"
+        );
+        assert!(decrypt(&key, "provider:b:api_key", &encrypted).is_err());
+        assert!(decrypt(&[8u8; 32], "provider:a:api_key", &encrypted).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::usage::{parse_filter_date, UsageFilter};
+use super::usage_index::lock_synced_index;
+
+/// Usage attributed to one tool or MCP server
+#[derive(Debug, Serialize)]
+pub struct ToolUsage {
+    /// Tool name, or the server name in `by_mcp_server`
+    name: String,
+    mcp_server: Option<String>,
+    call_count: u64,
+    /// Assistant turns that called the tool
+    turn_count: u64,
+    /// Tokens of those turns
+    turn_tokens: u64,
+    /// Full cost of those turns; a turn calling several tools counts for each
+    turn_cost: f64,
+    /// Turn cost split across the turn's tool calls
+    attributed_cost: f64,
+    /// `attributed_cost` as a percentage of all cost in the selection
+    cost_share: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolUsageReport {
+    total_cost: f64,
+    total_tokens: u64,
+    /// Turns that called at least one tool
+    tool_turns: u64,
+    tool_turn_cost: f64,
+    by_tool: Vec<ToolUsage>,
+    by_mcp_server: Vec<ToolUsage>,
+}
+
+/// Aggregate tool calls joined to their turns, grouped by `group_column`
+fn query_tool_usage(
+    conn: &Connection,
+    group_column: &str,
+    where_clause: &str,
+    values: &[String],
+    total_cost: f64,
+) -> Result<Vec<ToolUsage>, String> {
+    // One row per turn and group, so a turn counts once per tool or server
+    let sql = format!(
+        "WITH turn_calls AS (
+             SELECT dedup_key, {group} AS name, MAX(mcp_server) AS mcp_server, COUNT(*) AS calls
+             FROM usage_tool_calls WHERE {group} IS NOT NULL GROUP BY dedup_key, {group}
+         ),
+         turn_totals AS (
+             SELECT dedup_key, COUNT(*) AS calls FROM usage_tool_calls GROUP BY dedup_key
+         )
+         SELECT c.name, MAX(c.mcp_server), SUM(c.calls), COUNT(*),
+                SUM(e.input_tokens + e.output_tokens + e.cache_creation_tokens + e.cache_read_tokens),
+                SUM(e.cost), SUM(e.cost * c.calls / t.calls)
+         FROM turn_calls c
+         JOIN turn_totals t ON t.dedup_key = c.dedup_key
+         JOIN usage_entries e ON e.dedup_key = c.dedup_key
+         WHERE {}
+         GROUP BY c.name
+         ORDER BY 7 DESC",
+        where_clause,
+        group = group_column
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            let attributed_cost: f64 = row.get(6)?;
+            Ok(ToolUsage {
+                name: row.get(0)?,
+                mcp_server: row.get(1)?,
+                call_count: row.get::<_, i64>(2)? as u64,
+                turn_count: row.get::<_, i64>(3)? as u64,
+                turn_tokens: row.get::<_, i64>(4)? as u64,
+                turn_cost: row.get(5)?,
+                attributed_cost,
+                cost_share: if total_cost > 0.0 {
+                    attributed_cost / total_cost * 100.0
+                } else {
+                    0.0
+                },
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Get usage and cost attributed to tools and MCP servers, for one project or everything
+#[command]
+pub fn get_tool_usage(
+    db: State<'_, AgentDb>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    project_path: Option<String>,
+) -> Result<ToolUsageReport, String> {
+    let filter = UsageFilter {
+        start_date: start_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "start"))
+            .transpose()?,
+        end_date: end_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "end"))
+            .transpose()?,
+        project_path,
+        ..Default::default()
+    };
+
+    let conn = lock_synced_index(&db)?;
+    query_tool_usage_report(&conn, &filter)
+}
+
+fn query_tool_usage_report(conn: &Connection, filter: &UsageFilter) -> Result<ToolUsageReport, String> {
+    let (where_clause, values) = filter.where_clause();
+
+    let (total_cost, total_tokens): (f64, i64) = conn
+        .query_row(
+            &format!(
+                "SELECT COALESCE(SUM(cost), 0),
+                        COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0)
+                 FROM usage_entries WHERE {}",
+                where_clause
+            ),
+            params_from_iter(values.iter()),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let (tool_turns, tool_turn_cost): (i64, f64) = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(cost), 0) FROM usage_entries
+                 WHERE dedup_key IN (SELECT dedup_key FROM usage_tool_calls) AND {}",
+                where_clause
+            ),
+            params_from_iter(values.iter()),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // usage_entries columns are unambiguous in the joined queries, so the
+    // filter applies as is
+    let by_tool = query_tool_usage(conn, "tool_name", &where_clause, &values, total_cost)?;
+    let by_mcp_server = query_tool_usage(conn, "mcp_server", &where_clause, &values, total_cost)?;
+
+    Ok(ToolUsageReport {
+        total_cost,
+        total_tokens: total_tokens as u64,
+        tool_turns: tool_turns as u64,
+        tool_turn_cost,
+        by_tool,
+        by_mcp_server,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::{init_usage_index_tables, mcp_server_of};
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_message(conn: &Connection, dedup_key: &str, tokens: i64, cost: f64, tools: &[&str]) {
+        conn.execute(
+            "INSERT INTO usage_entries (file_path, timestamp, timestamp_ms, date, model,
+                input_tokens, cost, session_id, project_path, api_base_url, dedup_key)
+             VALUES ('a.jsonl', '', 0, '2026-03-01', 'claude-sonnet-4', ?1, ?2, 's1', '/work',
+                'https://api.anthropic.com', ?3)",
+            rusqlite::params![tokens, cost, dedup_key],
+        )
+        .unwrap();
+        for (i, tool) in tools.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO usage_tool_calls (tool_use_id, dedup_key, file_path, tool_name, mcp_server)
+                 VALUES (?1, ?2, 'a.jsonl', ?3, ?4)",
+                rusqlite::params![format!("{}-{}", dedup_key, i), dedup_key, tool, mcp_server_of(tool)],
+            )
+            .unwrap();
+        }
+    }
+
+    fn find<'a>(usage: &'a [ToolUsage], name: &str) -> &'a ToolUsage {
+        usage.iter().find(|u| u.name == name).unwrap()
+    }
+
+    #[test]
+    fn splits_a_turn_across_its_tools_and_mcp_servers() {
+        let conn = test_conn();
+        add_message(
+            &conn,
+            "m1",
+            1000,
+            8.0,
+            &[
+                "mcp__github__search",
+                "mcp__github__search",
+                "mcp__slack__post",
+                "Read",
+            ],
+        );
+        add_message(&conn, "m2", 500, 2.0, &[]);
+
+        let report = query_tool_usage_report(&conn, &UsageFilter::default()).unwrap();
+        assert_eq!(report.total_cost, 10.0);
+        assert_eq!(report.total_tokens, 1500);
+        assert_eq!(report.tool_turns, 1);
+        assert_eq!(report.tool_turn_cost, 8.0);
+
+        // Every tool call gets an equal share of the turn
+        let by_tool: f64 = report.by_tool.iter().map(|t| t.attributed_cost).sum();
+        assert!((by_tool - 8.0).abs() < 1e-9);
+        let github = find(&report.by_tool, "mcp__github__search");
+        assert_eq!((github.call_count, github.turn_count), (2, 1));
+        assert_eq!(github.attributed_cost, 4.0);
+        assert_eq!(github.cost_share, 40.0);
+        assert_eq!(github.mcp_server.as_deref(), Some("github"));
+        assert_eq!(find(&report.by_tool, "Read").attributed_cost, 2.0);
+
+        // Servers get their calls' shares; the rest belongs to built-in tools
+        let github = find(&report.by_mcp_server, "github");
+        let slack = find(&report.by_mcp_server, "slack");
+        assert_eq!(report.by_mcp_server.len(), 2);
+        assert_eq!(github.attributed_cost, 4.0);
+        assert_eq!(slack.attributed_cost, 2.0);
+        let by_server: f64 = report.by_mcp_server.iter().map(|s| s.attributed_cost).sum();
+        assert!((by_server + find(&report.by_tool, "Read").attributed_cost - 8.0).abs() < 1e-9);
+
+        // The turn counts once per server, with its full tokens and cost
+        for usage in report.by_mcp_server.iter().chain(&report.by_tool) {
+            assert_eq!(usage.turn_count, 1);
+            assert_eq!(usage.turn_tokens, 1000);
+            assert_eq!(usage.turn_cost, 8.0);
+        }
+        assert_eq!(github.call_count, 2);
+        assert_eq!(report.by_mcp_server[0].name, "github");
+    }
+}
@@ -0,0 +1,372 @@
+use chrono::{Datelike, Timelike};
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::usage::{parse_filter_date, query_daily_usage, DailyUsage, UsageFilter, UsageTimezone};
+use super::usage_index::lock_synced_index;
+
+// Upper bounds (minutes) of the session duration histogram; the last bucket is open-ended
+const SESSION_DURATION_BUCKETS: [(u64, &str); 6] = [
+    (5, "<5m"),
+    (15, "5-15m"),
+    (30, "15-30m"),
+    (60, "30-60m"),
+    (120, "1-2h"),
+    (300, "2-5h"),
+];
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct HourlyUsage {
+    hour: u32,
+    total_cost: f64,
+    total_tokens: u64,
+    entry_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionDurationBucket {
+    label: String,
+    min_minutes: u64,
+    max_minutes: Option<u64>,
+    session_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionDurationStats {
+    session_count: u64,
+    mean_minutes: f64,
+    median_minutes: f64,
+    p90_minutes: f64,
+    max_minutes: f64,
+    buckets: Vec<SessionDurationBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageTimeBreakdown {
+    timezone: String,
+    /// Usage per hour of day (0-23)
+    hourly: Vec<HourlyUsage>,
+    /// Usage per calendar day in `timezone`
+    by_date: Vec<DailyUsage>,
+    /// Tokens by weekday (Monday first) and hour of day
+    heatmap_tokens: Vec<Vec<u64>>,
+    /// Cost by weekday (Monday first) and hour of day
+    heatmap_cost: Vec<Vec<f64>>,
+    session_durations: SessionDurationStats,
+}
+
+/// Value at quantile `q` of `sorted` by the nearest-rank method, so it is
+/// always one of the observed values; shared by all percentile statistics
+pub(crate) fn percentile<T: Copy>(sorted: &[T], q: f64) -> Option<T> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn session_duration_stats(mut minutes: Vec<f64>) -> SessionDurationStats {
+    minutes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut buckets = Vec::new();
+    let mut lower = 0;
+    for (upper, label) in SESSION_DURATION_BUCKETS {
+        buckets.push(SessionDurationBucket {
+            label: label.to_string(),
+            min_minutes: lower,
+            max_minutes: Some(upper),
+            session_count: minutes
+                .iter()
+                .filter(|m| **m >= lower as f64 && **m < upper as f64)
+                .count() as u64,
+        });
+        lower = upper;
+    }
+    buckets.push(SessionDurationBucket {
+        label: format!(">={}h", lower / 60),
+        min_minutes: lower,
+        max_minutes: None,
+        session_count: minutes.iter().filter(|m| **m >= lower as f64).count() as u64,
+    });
+
+    SessionDurationStats {
+        session_count: minutes.len() as u64,
+        mean_minutes: if minutes.is_empty() {
+            0.0
+        } else {
+            minutes.iter().sum::<f64>() / minutes.len() as f64
+        },
+        median_minutes: percentile(&minutes, 0.5).unwrap_or(0.0),
+        p90_minutes: percentile(&minutes, 0.9).unwrap_or(0.0),
+        max_minutes: minutes.last().copied().unwrap_or(0.0),
+        buckets,
+    }
+}
+
+fn query_time_breakdown(conn: &Connection, filter: &UsageFilter) -> Result<UsageTimeBreakdown, String> {
+    let timezone = filter.timezone.as_ref().unwrap_or(&UsageTimezone::Local);
+    let (where_clause, values) = filter.where_clause();
+
+    let mut hourly: Vec<HourlyUsage> = (0..24)
+        .map(|hour| HourlyUsage { hour, ..Default::default() })
+        .collect();
+    let mut heatmap_tokens = vec![vec![0u64; 24]; 7];
+    let mut heatmap_cost = vec![vec![0f64; 24]; 7];
+
+    // Entries are streamed so long histories aren't held in memory
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT timestamp_ms,
+                    input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens,
+                    cost
+             FROM usage_entries WHERE {}",
+            where_clause
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query(params_from_iter(values.iter()))
+        .map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let timestamp_ms: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let tokens = row.get::<_, i64>(1).map_err(|e| e.to_string())? as u64;
+        let cost: f64 = row.get(2).map_err(|e| e.to_string())?;
+        let Some(local) = timezone.local_datetime(timestamp_ms) else {
+            continue;
+        };
+
+        let hour = local.hour() as usize;
+        let weekday = local.weekday().num_days_from_monday() as usize;
+
+        let bucket = &mut hourly[hour];
+        bucket.total_cost += cost;
+        bucket.total_tokens += tokens;
+        bucket.entry_count += 1;
+
+        heatmap_tokens[weekday][hour] += tokens;
+        heatmap_cost[weekday][hour] += cost;
+    }
+    drop(rows);
+    drop(stmt);
+
+    let by_date = query_daily_usage(conn, filter)?;
+
+    // Session length is the span between a session's first and last entry
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT MAX(timestamp_ms) - MIN(timestamp_ms) FROM usage_entries WHERE {} GROUP BY session_id",
+            where_clause
+        ))
+        .map_err(|e| e.to_string())?;
+    let durations = stmt
+        .query_map(params_from_iter(values.iter()), |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|ms| ms as f64 / 60_000.0)
+        .collect();
+
+    Ok(UsageTimeBreakdown {
+        timezone: timezone.name(),
+        hourly,
+        by_date,
+        heatmap_tokens,
+        heatmap_cost,
+        session_durations: session_duration_stats(durations),
+    })
+}
+
+/// Get hourly, weekday × hour and session duration breakdowns in an IANA timezone
+/// (e.g. "Asia/Shanghai"); the system timezone is used when none is given
+#[command]
+pub fn get_usage_time_breakdown(
+    db: State<'_, AgentDb>,
+    timezone: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    project_path: Option<String>,
+) -> Result<UsageTimeBreakdown, String> {
+    // Date bounds are calendar days in the requested timezone
+    let filter = UsageFilter {
+        start_date: start_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "start"))
+            .transpose()?,
+        end_date: end_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "end"))
+            .transpose()?,
+        project_path,
+        timezone: Some(UsageTimezone::parse(timezone.as_deref())?.unwrap_or(UsageTimezone::Local)),
+        ..Default::default()
+    };
+
+    let conn = lock_synced_index(&db)?;
+    query_time_breakdown(&conn, &filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::init_usage_index_tables;
+    use chrono::NaiveDate;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_entry(conn: &Connection, utc: &str, session_id: &str, tokens: i64, cost: f64) {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(utc).unwrap();
+        conn.execute(
+            "INSERT INTO usage_entries (file_path, timestamp, timestamp_ms, date, model,
+                input_tokens, cost, session_id, project_path, api_base_url)
+             VALUES ('a.jsonl', ?1, ?2, ?3, 'claude-sonnet-4', ?4, ?5, ?6, '/work', 'https://api.anthropic.com')",
+            rusqlite::params![
+                utc,
+                timestamp.timestamp_millis(),
+                timestamp.format("%Y-%m-%d").to_string(),
+                tokens,
+                cost,
+                session_id
+            ],
+        )
+        .unwrap();
+    }
+
+    fn new_york() -> Option<UsageTimezone> {
+        UsageTimezone::parse(Some("America/New_York")).unwrap()
+    }
+
+    // New York springs forward on 2026-03-08: 02:00 EST becomes 03:00 EDT
+    fn add_dst_entries(conn: &Connection) {
+        add_entry(conn, "2026-03-08T06:30:00Z", "s1", 100, 1.0); // Sun 01:30 EST
+        add_entry(conn, "2026-03-08T07:30:00Z", "s1", 200, 2.0); // Sun 03:30 EDT
+        add_entry(conn, "2026-03-09T03:30:00Z", "s2", 400, 4.0); // Sun 23:30 EDT
+        add_entry(conn, "2026-03-10T15:00:00Z", "s3", 800, 8.0); // Tue 11:00 EDT
+        add_entry(conn, "2026-03-10T15:10:00Z", "s3", 1600, 16.0); // Tue 11:10 EDT
+    }
+
+    #[test]
+    fn percentile_uses_the_nearest_rank() {
+        let sorted: Vec<u64> = (1..=10).map(|i| i * 100).collect();
+        assert_eq!(percentile(&sorted, 0.5), Some(500));
+        assert_eq!(percentile(&sorted, 0.95), Some(1000));
+        assert_eq!(percentile(&sorted, 0.0), Some(100));
+        assert_eq!(percentile(&[42], 0.95), Some(42));
+        assert_eq!(percentile::<u64>(&[], 0.5), None);
+        assert_eq!(percentile(&[1.5, 2.5, 9.0], 0.5), Some(2.5));
+    }
+
+    #[test]
+    fn buckets_hours_and_weekdays_in_the_requested_timezone() {
+        let conn = test_conn();
+        add_dst_entries(&conn);
+        let filter = UsageFilter {
+            timezone: new_york(),
+            ..Default::default()
+        };
+
+        let breakdown = query_time_breakdown(&conn, &filter).unwrap();
+        assert_eq!(breakdown.timezone, "America/New_York");
+
+        let active: Vec<(u32, u64, u64)> = breakdown
+            .hourly
+            .iter()
+            .filter(|h| h.entry_count > 0)
+            .map(|h| (h.hour, h.entry_count, h.total_tokens))
+            .collect();
+        // 02:00 doesn't exist on the DST day, so the hour after 01:30 is 03:30
+        assert_eq!(active, vec![(1, 1, 100), (3, 1, 200), (11, 2, 2400), (23, 1, 400)]);
+        assert_eq!(breakdown.hourly[2].entry_count, 0);
+
+        let sunday = 6;
+        let tuesday = 1;
+        assert_eq!(breakdown.heatmap_tokens[sunday][1], 100);
+        assert_eq!(breakdown.heatmap_tokens[sunday][3], 200);
+        // Monday in UTC, still Sunday evening in New York
+        assert_eq!(breakdown.heatmap_tokens[sunday][23], 400);
+        assert_eq!(breakdown.heatmap_tokens[0].iter().sum::<u64>(), 0);
+        assert_eq!(breakdown.heatmap_tokens[tuesday][11], 2400);
+        assert_eq!(breakdown.heatmap_cost[tuesday][11], 24.0);
+
+        let days: Vec<(&str, u64)> = breakdown
+            .by_date
+            .iter()
+            .map(|d| (d.date.as_str(), d.total_tokens))
+            .collect();
+        assert_eq!(days, vec![("2026-03-10", 2400), ("2026-03-08", 700)]);
+    }
+
+    #[test]
+    fn session_durations_use_elapsed_time_across_dst() {
+        let conn = test_conn();
+        add_dst_entries(&conn);
+        let filter = UsageFilter {
+            timezone: new_york(),
+            ..Default::default()
+        };
+
+        let stats = query_time_breakdown(&conn, &filter).unwrap().session_durations;
+        // s1 spans 01:30 to 03:30 on the wall clock but one hour elapsed
+        assert_eq!(stats.session_count, 3);
+        assert_eq!(stats.max_minutes, 60.0);
+        assert_eq!(stats.median_minutes, 10.0);
+        assert_eq!(stats.p90_minutes, 60.0);
+        assert!((stats.mean_minutes - 70.0 / 3.0).abs() < 1e-9);
+
+        let counts: Vec<(&str, u64)> = stats
+            .buckets
+            .iter()
+            .map(|b| (b.label.as_str(), b.session_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("<5m", 1),
+                ("5-15m", 1),
+                ("15-30m", 0),
+                ("30-60m", 0),
+                ("1-2h", 1),
+                ("2-5h", 0),
+                (">=5h", 0),
+            ]
+        );
+
+        let empty = session_duration_stats(Vec::new());
+        assert_eq!((empty.median_minutes, empty.p90_minutes), (0.0, 0.0));
+    }
+
+    #[test]
+    fn date_bounds_are_days_in_the_requested_timezone() {
+        let conn = test_conn();
+        add_dst_entries(&conn);
+        let day = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        let filter = UsageFilter {
+            start_date: Some(day),
+            end_date: Some(day),
+            timezone: new_york(),
+            ..Default::default()
+        };
+
+        let breakdown = query_time_breakdown(&conn, &filter).unwrap();
+        let hours: Vec<u32> = breakdown
+            .hourly
+            .iter()
+            .filter(|h| h.entry_count > 0)
+            .map(|h| h.hour)
+            .collect();
+        assert_eq!(hours, vec![1, 3, 23]);
+        assert_eq!(breakdown.by_date.len(), 1);
+        assert_eq!(breakdown.by_date[0].total_tokens, 700);
+        assert_eq!(breakdown.session_durations.session_count, 2);
+    }
+}
@@ -0,0 +1,674 @@
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use rusqlite::{params_from_iter, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::usage::{parse_filter_date, project_name_from_path, UsageFilter, USAGE_TOTALS_COLUMNS};
+use super::usage_index::sync_usage_index;
+
+// Rows buffered per Parquet row group
+const PARQUET_ROW_GROUP_SIZE: usize = 8192;
+
+// Entries read per database lock, so other commands aren't blocked for the
+// length of an export
+const EXPORT_BATCH_ROWS: usize = 2000;
+
+const TOTAL_TOKENS_COLUMN: &str =
+    "COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0)";
+
+#[derive(Debug, Clone, Copy)]
+enum ColumnKind {
+    Text,
+    Int,
+    Float,
+}
+
+// Column names and types of an exported dataset
+type ExportColumns = Vec<(&'static str, ColumnKind)>;
+
+// Rows of a batch and, for paged datasets, the (timestamp_ms, id) of its last row
+type ExportBatch = (Vec<Vec<ExportValue>>, Option<(i64, i64)>);
+
+#[derive(Debug)]
+enum ExportValue {
+    Text(String),
+    Int(i64),
+    Float(f64),
+}
+
+/// Export request
+#[derive(Debug, Deserialize)]
+pub struct UsageExportOptions {
+    /// Destination file
+    pub path: String,
+    /// "csv", "jsonl" or "parquet"
+    pub format: String,
+    /// "entries" (raw usage entries), "by_model", "by_date" or "by_project"
+    #[serde(default = "default_dataset")]
+    pub dataset: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub project_path: Option<String>,
+    pub model: Option<String>,
+    pub api_base_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageExportResult {
+    pub path: String,
+    pub format: String,
+    pub dataset: String,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+const EXPORT_FORMATS: [&str; 3] = ["csv", "jsonl", "parquet"];
+
+fn default_dataset() -> String {
+    "entries".to_string()
+}
+
+/// Reject an unknown format or dataset before any file is touched
+fn validate_options(options: &UsageExportOptions) -> Result<(), String> {
+    if !EXPORT_FORMATS.contains(&options.format.as_str()) {
+        return Err(format!("Unsupported export format: {}", options.format));
+    }
+    dataset_query(&options.dataset, "")?;
+    Ok(())
+}
+
+/// Hidden file next to `path`, renamed over it once the export succeeded
+fn temp_export_path(path: &Path) -> Result<PathBuf, String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid export path: {}", path.display()))?;
+    Ok(path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    )))
+}
+
+/// Columns and query of one exportable dataset
+fn dataset_query(dataset: &str, where_clause: &str) -> Result<(ExportColumns, String), String> {
+    use ColumnKind::*;
+
+    // Aggregations share the layout of `USAGE_TOTALS_COLUMNS` followed by the total token count
+    let totals = [
+        ("total_cost", Float),
+        ("input_tokens", Int),
+        ("output_tokens", Int),
+        ("cache_creation_tokens", Int),
+        ("cache_read_tokens", Int),
+        ("session_count", Int),
+        ("total_tokens", Int),
+    ];
+
+    match dataset {
+        "entries" => Ok((
+            vec![
+                ("timestamp", Text),
+                ("date", Text),
+                ("model", Text),
+                ("input_tokens", Int),
+                ("output_tokens", Int),
+                ("cache_creation_tokens", Int),
+                ("cache_read_tokens", Int),
+                ("cost", Float),
+                ("session_id", Text),
+                ("project_path", Text),
+                ("api_base_url", Text),
+                ("message_id", Text),
+                ("request_id", Text),
+            ],
+            // Trailing timestamp_ms and id are the position the next batch starts after
+            format!(
+                "SELECT timestamp, date, model, input_tokens, output_tokens, cache_creation_tokens,
+                        cache_read_tokens, cost, session_id, project_path, api_base_url, message_id, request_id,
+                        timestamp_ms, id
+                 FROM usage_entries WHERE {} ORDER BY timestamp_ms, id",
+                where_clause
+            ),
+        )),
+        "by_model" => Ok((
+            [("model", Text)].into_iter().chain(totals).collect(),
+            format!(
+                "SELECT model, {}, {} FROM usage_entries WHERE {} GROUP BY model ORDER BY 2 DESC",
+                USAGE_TOTALS_COLUMNS, TOTAL_TOKENS_COLUMN, where_clause
+            ),
+        )),
+        "by_date" => Ok((
+            [("date", Text)]
+                .into_iter()
+                .chain(totals)
+                .chain([("models_used", Text)])
+                .collect(),
+            format!(
+                "SELECT date, {}, {}, GROUP_CONCAT(DISTINCT model) FROM usage_entries WHERE {}
+                 GROUP BY date ORDER BY date",
+                USAGE_TOTALS_COLUMNS, TOTAL_TOKENS_COLUMN, where_clause
+            ),
+        )),
+        "by_project" => Ok((
+            [("project_path", Text)]
+                .into_iter()
+                .chain(totals)
+                .chain([("last_used", Text), ("project_name", Text)])
+                .collect(),
+            // project_name is derived from the path after reading the row
+            format!(
+                "SELECT project_path, {}, {}, MAX(timestamp) FROM usage_entries WHERE {}
+                 GROUP BY project_path ORDER BY 2 DESC",
+                USAGE_TOTALS_COLUMNS, TOTAL_TOKENS_COLUMN, where_clause
+            ),
+        )),
+        other => Err(format!("Unsupported export dataset: {}", other)),
+    }
+}
+
+fn read_row(row: &Row, columns: &[(&'static str, ColumnKind)]) -> rusqlite::Result<Vec<ExportValue>> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, (_, kind))| {
+            Ok(match kind {
+                ColumnKind::Text => ExportValue::Text(row.get::<_, Option<String>>(i)?.unwrap_or_default()),
+                ColumnKind::Int => ExportValue::Int(row.get::<_, Option<i64>>(i)?.unwrap_or_default()),
+                ColumnKind::Float => ExportValue::Float(row.get::<_, Option<f64>>(i)?.unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+/// Destination of exported rows
+trait ExportSink {
+    fn write_row(&mut self, row: Vec<ExportValue>) -> Result<(), String>;
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+struct CsvSink {
+    writer: BufWriter<File>,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl CsvSink {
+    fn new(file: File, columns: &[(&'static str, ColumnKind)]) -> Result<Self, String> {
+        let mut writer = BufWriter::new(file);
+        let header: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+        writeln!(writer, "{}", header.join(",")).map_err(|e| e.to_string())?;
+        Ok(Self { writer })
+    }
+}
+
+impl ExportSink for CsvSink {
+    fn write_row(&mut self, row: Vec<ExportValue>) -> Result<(), String> {
+        let fields: Vec<String> = row
+            .into_iter()
+            .map(|value| match value {
+                ExportValue::Text(s) => csv_field(&s),
+                ExportValue::Int(i) => i.to_string(),
+                ExportValue::Float(f) => f.to_string(),
+            })
+            .collect();
+        writeln!(self.writer, "{}", fields.join(",")).map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+}
+
+struct JsonlSink {
+    writer: BufWriter<File>,
+    names: Vec<&'static str>,
+}
+
+impl ExportSink for JsonlSink {
+    fn write_row(&mut self, row: Vec<ExportValue>) -> Result<(), String> {
+        let object: serde_json::Map<String, serde_json::Value> = self
+            .names
+            .iter()
+            .zip(row)
+            .map(|(name, value)| {
+                let value = match value {
+                    ExportValue::Text(s) => serde_json::Value::from(s),
+                    ExportValue::Int(i) => serde_json::Value::from(i),
+                    ExportValue::Float(f) => serde_json::Value::from(f),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        serde_json::to_writer(&mut self.writer, &object).map_err(|e| e.to_string())?;
+        self.writer.write_all(b"\n").map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+}
+
+enum ColumnBuffer {
+    Text(Vec<ByteArray>),
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+}
+
+/// Writes one row group per `PARQUET_ROW_GROUP_SIZE` rows
+struct ParquetSink {
+    writer: SerializedFileWriter<File>,
+    columns: Vec<ColumnBuffer>,
+    buffered: usize,
+}
+
+impl ParquetSink {
+    fn new(file: File, columns: &[(&'static str, ColumnKind)]) -> Result<Self, String> {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|(name, kind)| match kind {
+                ColumnKind::Text => format!("required binary {} (UTF8);", name),
+                ColumnKind::Int => format!("required int64 {};", name),
+                ColumnKind::Float => format!("required double {};", name),
+            })
+            .collect();
+        let schema = parse_message_type(&format!("message usage {{ {} }}", fields.join(" ")))
+            .map_err(|e| e.to_string())?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(props))
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            writer,
+            columns: columns
+                .iter()
+                .map(|(_, kind)| match kind {
+                    ColumnKind::Text => ColumnBuffer::Text(Vec::new()),
+                    ColumnKind::Int => ColumnBuffer::Int(Vec::new()),
+                    ColumnKind::Float => ColumnBuffer::Float(Vec::new()),
+                })
+                .collect(),
+            buffered: 0,
+        })
+    }
+
+    fn flush_row_group(&mut self) -> Result<(), String> {
+        if self.buffered == 0 {
+            return Ok(());
+        }
+
+        let mut row_group = self.writer.next_row_group().map_err(|e| e.to_string())?;
+        for buffer in &mut self.columns {
+            let mut column = row_group
+                .next_column()
+                .map_err(|e| e.to_string())?
+                .ok_or("Parquet schema has fewer columns than the export")?;
+            let written = match buffer {
+                ColumnBuffer::Text(values) => column.typed::<ByteArrayType>().write_batch(values, None, None),
+                ColumnBuffer::Int(values) => column.typed::<Int64Type>().write_batch(values, None, None),
+                ColumnBuffer::Float(values) => column.typed::<DoubleType>().write_batch(values, None, None),
+            };
+            written.map_err(|e| e.to_string())?;
+            column.close().map_err(|e| e.to_string())?;
+
+            match buffer {
+                ColumnBuffer::Text(values) => values.clear(),
+                ColumnBuffer::Int(values) => values.clear(),
+                ColumnBuffer::Float(values) => values.clear(),
+            }
+        }
+        row_group.close().map_err(|e| e.to_string())?;
+
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+impl ExportSink for ParquetSink {
+    fn write_row(&mut self, row: Vec<ExportValue>) -> Result<(), String> {
+        for (buffer, value) in self.columns.iter_mut().zip(row) {
+            match (buffer, value) {
+                (ColumnBuffer::Text(values), ExportValue::Text(s)) => values.push(ByteArray::from(s.into_bytes())),
+                (ColumnBuffer::Int(values), ExportValue::Int(i)) => values.push(i),
+                (ColumnBuffer::Float(values), ExportValue::Float(f)) => values.push(f),
+                _ => return Err("Export value does not match its column type".to_string()),
+            }
+        }
+
+        self.buffered += 1;
+        if self.buffered >= PARQUET_ROW_GROUP_SIZE {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.flush_row_group()?;
+        self.writer.close().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Read the next batch of rows, and for paged datasets the position after it
+fn read_batch(
+    conn: &Connection,
+    sql: &str,
+    values: &[String],
+    columns: &[(&'static str, ColumnKind)],
+    paged: bool,
+) -> rusqlite::Result<ExportBatch> {
+    let mut stmt = conn.prepare_cached(sql)?;
+    let mut rows = stmt.query(params_from_iter(values.iter()))?;
+    let mut batch = Vec::new();
+    let mut last = None;
+    while let Some(row) = rows.next()? {
+        batch.push(read_row(row, columns)?);
+        if paged {
+            last = Some((row.get(columns.len())?, row.get(columns.len() + 1)?));
+        }
+    }
+    Ok((batch, last))
+}
+
+/// Stream the rows of the requested dataset to `path`, returning the number of rows written
+///
+/// The database is only locked while a batch is read. Entries are paged by
+/// their position in time; aggregations are small and read at once.
+fn write_dataset(
+    db: &AgentDb,
+    options: &UsageExportOptions,
+    path: &Path,
+) -> Result<u64, String> {
+    let filter = UsageFilter {
+        start_date: options
+            .start_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "start"))
+            .transpose()?,
+        end_date: options
+            .end_date
+            .as_deref()
+            .map(|d| parse_filter_date(d, "end"))
+            .transpose()?,
+        project_path: options.project_path.clone(),
+        model: options.model.clone(),
+        api_base_url: options.api_base_url.clone(),
+        ..Default::default()
+    };
+    let (mut where_clause, mut values) = filter.where_clause();
+    let paged = options.dataset == "entries";
+    if paged {
+        where_clause.push_str(&format!(
+            " AND (timestamp_ms, id) > (CAST(?{} AS INTEGER), CAST(?{} AS INTEGER))",
+            values.len() + 1,
+            values.len() + 2
+        ));
+        values.extend([i64::MIN.to_string(), i64::MIN.to_string()]);
+    }
+    let (columns, mut sql) = dataset_query(&options.dataset, &where_clause)?;
+    if paged {
+        sql.push_str(&format!(" LIMIT {}", EXPORT_BATCH_ROWS));
+    }
+
+    let file = File::create(path).map_err(|e| format!("Failed to create export file: {}", e))?;
+    let mut sink: Box<dyn ExportSink> = match options.format.as_str() {
+        "csv" => Box::new(CsvSink::new(file, &columns)?),
+        "jsonl" => Box::new(JsonlSink {
+            writer: BufWriter::new(file),
+            names: columns.iter().map(|(name, _)| *name).collect(),
+        }),
+        "parquet" => Box::new(ParquetSink::new(file, &columns)?),
+        other => return Err(format!("Unsupported export format: {}", other)),
+    };
+
+    // by_project's last column isn't part of the query
+    let derive_project_name = options.dataset == "by_project";
+    let query_columns = if derive_project_name {
+        &columns[..columns.len() - 1]
+    } else {
+        &columns[..]
+    };
+
+    let mut count = 0u64;
+    loop {
+        let (batch, last) = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            read_batch(&conn, &sql, &values, query_columns, paged).map_err(|e| e.to_string())?
+        };
+        let done = !paged || batch.len() < EXPORT_BATCH_ROWS;
+
+        for mut row in batch {
+            if derive_project_name {
+                let name = match row.first() {
+                    Some(ExportValue::Text(path)) => project_name_from_path(path),
+                    _ => String::new(),
+                };
+                row.push(ExportValue::Text(name));
+            }
+            sink.write_row(row)?;
+            count += 1;
+        }
+
+        match last {
+            Some((timestamp_ms, id)) if !done => {
+                let position = values.len() - 2;
+                values[position] = timestamp_ms.to_string();
+                values[position + 1] = id.to_string();
+            }
+            _ => break,
+        }
+    }
+
+    sink.finish()?;
+    Ok(count)
+}
+
+/// Export usage entries or one of their aggregations to a file
+#[command]
+pub fn export_usage(
+    db: State<'_, AgentDb>,
+    options: UsageExportOptions,
+) -> Result<UsageExportResult, String> {
+    validate_options(&options)?;
+    let path = PathBuf::from(&options.path);
+    let temp_path = temp_export_path(&path)?;
+
+    sync_usage_index(&db)?;
+    // An existing file is only replaced once the export is complete
+    let written = write_dataset(&db, &options, &temp_path).and_then(|rows| {
+        fs::rename(&temp_path, &path)
+            .map(|_| rows)
+            .map_err(|e| format!("Failed to write export file: {}", e))
+    });
+    let rows = match written {
+        Ok(rows) => rows,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+    let bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+    Ok(UsageExportResult {
+        path: options.path,
+        format: options.format,
+        dataset: options.dataset,
+        rows,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::init_usage_index_tables;
+    use parquet::basic::Type as PhysicalType;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use std::sync::Mutex;
+
+    fn test_db() -> AgentDb {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        AgentDb(Mutex::new(conn))
+    }
+
+    fn insert_entry(conn: &Connection, timestamp_ms: i64, model: &str, project: &str, input: i64) {
+        let timestamp = chrono::DateTime::from_timestamp_millis(timestamp_ms)
+            .unwrap()
+            .to_rfc3339();
+        conn.execute(
+            "INSERT INTO usage_entries (file_path, timestamp, timestamp_ms, date, model, input_tokens,
+                output_tokens, cost, session_id, project_path, api_base_url)
+             VALUES ('a.jsonl', ?1, ?2, ?3, ?4, ?5, 1, 0.5, 's1', ?6, 'https://api.anthropic.com')",
+            rusqlite::params![timestamp, timestamp_ms, &timestamp[..10], model, input, project],
+        )
+        .unwrap();
+    }
+
+    fn options(path: &Path, format: &str, dataset: &str) -> UsageExportOptions {
+        UsageExportOptions {
+            path: path.to_string_lossy().to_string(),
+            format: format.to_string(),
+            dataset: dataset.to_string(),
+            start_date: None,
+            end_date: None,
+            project_path: None,
+            model: None,
+            api_base_url: None,
+        }
+    }
+
+    #[test]
+    fn datasets_select_their_columns() {
+        let db = test_db();
+        let conn = db.0.lock().unwrap();
+        for dataset in ["entries", "by_model", "by_date", "by_project"] {
+            let (columns, sql) = dataset_query(dataset, "1 = 1").unwrap();
+            let selected = conn.prepare(&sql).unwrap().column_count();
+            // Entries add their paging position, by_project derives its project name
+            let expected = match dataset {
+                "entries" => columns.len() + 2,
+                "by_project" => columns.len() - 1,
+                _ => columns.len(),
+            };
+            assert_eq!(selected, expected, "{}", dataset);
+        }
+        assert!(dataset_query("by_hour", "1 = 1").is_err());
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        insert_entry(&db.0.lock().unwrap(), 1_767_225_600_000, "claude-sonnet-4", "/work/a,\"b\"", 10);
+        let path = dir.path().join("usage.csv");
+        assert_eq!(write_dataset(&db, &options(&path, "csv", "by_project"), &path).unwrap(), 1);
+        let csv = fs::read_to_string(&path).unwrap();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.starts_with("\"/work/a,\"\"b\"\"\",0.5,10,1,0,0,1,11,"), "{}", row);
+        assert!(row.ends_with(",\"a,\"\"b\"\"\""), "{}", row);
+    }
+
+    #[test]
+    fn entries_are_read_in_batches_in_time_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        let total = EXPORT_BATCH_ROWS * 2 + 1;
+        {
+            let conn = db.0.lock().unwrap();
+            let tx = conn.unchecked_transaction().unwrap();
+            // Inserted newest first, with timestamps shared across batch boundaries
+            for i in (0..total).rev() {
+                insert_entry(&tx, 1_767_225_600_000 + (i / 3) as i64, "claude-sonnet-4", "/work", i as i64);
+            }
+            tx.commit().unwrap();
+        }
+
+        let path = dir.path().join("usage.jsonl");
+        assert_eq!(write_dataset(&db, &options(&path, "jsonl", "entries"), &path).unwrap(), total as u64);
+        let rows: Vec<serde_json::Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), total);
+        let mut inputs: Vec<i64> = rows.iter().map(|r| r["input_tokens"].as_i64().unwrap()).collect();
+        assert!(rows.windows(2).all(|w| w[0]["timestamp"].as_str() <= w[1]["timestamp"].as_str()));
+        inputs.sort_unstable();
+        inputs.dedup();
+        assert_eq!(inputs.len(), total);
+    }
+
+    #[test]
+    fn parquet_schema_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        {
+            let conn = db.0.lock().unwrap();
+            insert_entry(&conn, 1_767_225_600_000, "claude-sonnet-4", "/work/app", 10);
+            insert_entry(&conn, 1_767_312_000_000, "claude-opus-4", "/work/app", 20);
+        }
+
+        let path = dir.path().join("usage.parquet");
+        assert_eq!(write_dataset(&db, &options(&path, "parquet", "by_date"), &path).unwrap(), 2);
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let (columns, _) = dataset_query("by_date", "").unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr_ptr();
+        let fields: Vec<(String, PhysicalType)> = schema
+            .columns()
+            .iter()
+            .map(|c| (c.name().to_string(), c.physical_type()))
+            .collect();
+        let expected: Vec<(String, PhysicalType)> = columns
+            .iter()
+            .map(|(name, kind)| {
+                let physical = match kind {
+                    ColumnKind::Text => PhysicalType::BYTE_ARRAY,
+                    ColumnKind::Int => PhysicalType::INT64,
+                    ColumnKind::Float => PhysicalType::DOUBLE,
+                };
+                (name.to_string(), physical)
+            })
+            .collect();
+        assert_eq!(fields, expected);
+
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_string(0).unwrap(), "2026-01-01");
+        assert_eq!(rows[0].get_double(1).unwrap(), 0.5);
+        assert_eq!(rows[0].get_long(2).unwrap(), 10);
+        assert_eq!(rows[1].get_string(8).unwrap(), "claude-opus-4");
+    }
+}
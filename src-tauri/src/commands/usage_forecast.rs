@@ -0,0 +1,617 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::usage::{project_name_from_path, query_daily_usage, UsageFilter};
+use super::usage_index::lock_synced_index;
+
+const DEFAULT_HISTORY_DAYS: u32 = 90;
+// z-score of the two-sided 95% interval
+const CONFIDENCE_Z: f64 = 1.96;
+
+const DEFAULT_ANOMALY_SIGMA: f64 = 3.0;
+const DEFAULT_ANOMALY_LOOKBACK_DAYS: u32 = 30;
+// Days a day's cost is compared against
+const BASELINE_DAYS: i64 = 28;
+// Fewer baseline samples than this give no meaningful deviation
+const MIN_BASELINE_SAMPLES: usize = 7;
+// Items cheaper than this are never flagged, whatever their deviation
+const DEFAULT_MIN_ANOMALY_COST: f64 = 1.0;
+
+/// Month-end projection of one model
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastModel {
+    /// "linear" or "weekday"
+    pub model: String,
+    /// Projected spend of the remaining days of the month
+    pub projected_remaining: f64,
+    /// Month-to-date spend plus `projected_remaining`
+    pub projected_total: f64,
+    /// 95% confidence band of `projected_total`
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+}
+
+/// Projected spend of one remaining day
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastDay {
+    pub date: String,
+    pub linear: f64,
+    pub weekday: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpendForecast {
+    /// Month being forecast (YYYY-MM)
+    pub month: String,
+    pub month_to_date_cost: f64,
+    pub days_elapsed: u32,
+    pub days_in_month: u32,
+    /// Days of history the models were fitted on
+    pub history_days: u32,
+    pub linear: ForecastModel,
+    pub weekday: ForecastModel,
+    pub daily: Vec<ForecastDay>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyKind {
+    Day,
+    Session,
+    Project,
+}
+
+/// An item whose cost is well above its baseline
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageAnomaly {
+    pub kind: AnomalyKind,
+    /// Date, session id or project path
+    pub key: String,
+    /// Human readable name of `key`
+    pub label: String,
+    /// Day the cost fell on (first day of a session)
+    pub date: String,
+    pub project_path: Option<String>,
+    pub cost: f64,
+    pub baseline_mean: f64,
+    pub baseline_std_dev: f64,
+    /// Standard deviations above the baseline mean
+    pub z_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageAnomalyReport {
+    pub threshold_sigma: f64,
+    pub lookback_days: u32,
+    /// Anomalies ordered by z-score, highest first
+    pub anomalies: Vec<UsageAnomaly>,
+}
+
+/// Mean and sample standard deviation
+fn mean_std_dev(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+/// Standard deviations `value` lies above the baseline, if the baseline varies at all
+fn z_score(value: f64, mean: f64, std_dev: f64) -> Option<f64> {
+    (std_dev > 0.0).then(|| (value - mean) / std_dev)
+}
+
+/// Daily cost from `start` to `end` inclusive, with days without usage as zero
+fn daily_cost_series(
+    conn: &Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<(NaiveDate, f64)>, String> {
+    let filter = UsageFilter {
+        start_date: Some(start),
+        end_date: Some(end),
+        ..Default::default()
+    };
+    let costs: HashMap<String, f64> = query_daily_usage(conn, &filter)?
+        .into_iter()
+        .map(|day| (day.date, day.total_cost))
+        .collect();
+
+    Ok(start
+        .iter_days()
+        .take_while(|d| *d <= end)
+        .map(|d| {
+            let cost = costs
+                .get(&d.format("%Y-%m-%d").to_string())
+                .copied()
+                .unwrap_or(0.0);
+            (d, cost)
+        })
+        .collect())
+}
+
+/// Least squares fit of `values` against their index, as (intercept, slope)
+fn linear_fit(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    if values.len() < 2 {
+        return (values.first().copied().unwrap_or(0.0), 0.0);
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (i, y) in values.iter().enumerate() {
+        let dx = i as f64 - mean_x;
+        covariance += dx * (y - mean_y);
+        variance += dx * dx;
+    }
+    let slope = covariance / variance;
+    (mean_y - slope * mean_x, slope)
+}
+
+/// Sum the per-day predictions into a month-end projection; the band assumes
+/// independent daily errors with the model's residual deviation
+fn project(model: &str, month_to_date: f64, predictions: &[f64], residual_std_dev: f64) -> ForecastModel {
+    let projected_remaining: f64 = predictions.iter().sum();
+    let margin = CONFIDENCE_Z * residual_std_dev * (predictions.len() as f64).sqrt();
+    let projected_total = month_to_date + projected_remaining;
+    ForecastModel {
+        model: model.to_string(),
+        projected_remaining,
+        projected_total,
+        lower_bound: (projected_total - margin).max(month_to_date),
+        upper_bound: projected_total + margin,
+    }
+}
+
+/// Forecast month-end spend from the last `history_days` complete days
+///
+/// Today counts as spent so far; projections cover the days after it.
+pub(crate) fn compute_spend_forecast(
+    conn: &Connection,
+    today: NaiveDate,
+    history_days: u32,
+) -> Result<SpendForecast, String> {
+    let history_days = history_days.max(MIN_BASELINE_SAMPLES as u32);
+    let month_start = today.with_day(1).ok_or("Invalid date")?;
+    let next_month = if today.month() == 12 {
+        NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(today.year(), today.month() + 1, 1)
+    }
+    .ok_or("Invalid date")?;
+    let days_in_month = (next_month - month_start).num_days() as u32;
+
+    let month_to_date_cost: f64 = daily_cost_series(conn, month_start, today)?
+        .iter()
+        .map(|(_, cost)| cost)
+        .sum();
+
+    let history_end = today - Duration::days(1);
+    let history = daily_cost_series(
+        conn,
+        today - Duration::days(history_days as i64),
+        history_end,
+    )?;
+    let costs: Vec<f64> = history.iter().map(|(_, cost)| *cost).collect();
+
+    // Linear trend over the history
+    let (intercept, slope) = linear_fit(&costs);
+    let linear_residuals: Vec<f64> = costs
+        .iter()
+        .enumerate()
+        .map(|(i, cost)| cost - (intercept + slope * i as f64))
+        .collect();
+
+    // Average cost per weekday
+    let mut by_weekday: HashMap<Weekday, Vec<f64>> = HashMap::new();
+    for (date, cost) in &history {
+        by_weekday.entry(date.weekday()).or_default().push(*cost);
+    }
+    let weekday_means: HashMap<Weekday, f64> = by_weekday
+        .iter()
+        .map(|(weekday, costs)| (*weekday, mean_std_dev(costs).0))
+        .collect();
+    let weekday_residuals: Vec<f64> = history
+        .iter()
+        .map(|(date, cost)| cost - weekday_means.get(&date.weekday()).copied().unwrap_or(0.0))
+        .collect();
+
+    let daily: Vec<ForecastDay> = (today + Duration::days(1))
+        .iter_days()
+        .take_while(|d| *d < next_month)
+        .map(|date| {
+            let index = (date - history_end).num_days() + costs.len() as i64 - 1;
+            ForecastDay {
+                date: date.format("%Y-%m-%d").to_string(),
+                linear: (intercept + slope * index as f64).max(0.0),
+                weekday: weekday_means.get(&date.weekday()).copied().unwrap_or(0.0),
+            }
+        })
+        .collect();
+
+    let linear_predictions: Vec<f64> = daily.iter().map(|d| d.linear).collect();
+    let weekday_predictions: Vec<f64> = daily.iter().map(|d| d.weekday).collect();
+
+    Ok(SpendForecast {
+        month: today.format("%Y-%m").to_string(),
+        month_to_date_cost,
+        days_elapsed: today.day(),
+        days_in_month,
+        history_days,
+        linear: project(
+            "linear",
+            month_to_date_cost,
+            &linear_predictions,
+            mean_std_dev(&linear_residuals).1,
+        ),
+        weekday: project(
+            "weekday",
+            month_to_date_cost,
+            &weekday_predictions,
+            mean_std_dev(&weekday_residuals).1,
+        ),
+        daily,
+    })
+}
+
+/// Flag `series` days in the lookback whose cost deviates from the preceding
+/// `BASELINE_DAYS` days
+fn daily_anomalies(
+    series: &[(NaiveDate, f64)],
+    lookback_start: NaiveDate,
+    sigma: f64,
+    min_cost: f64,
+    mut make: impl FnMut(NaiveDate, f64, f64, f64, f64) -> UsageAnomaly,
+) -> Vec<UsageAnomaly> {
+    let mut anomalies = Vec::new();
+    for (i, (date, cost)) in series.iter().enumerate() {
+        if *date < lookback_start || *cost < min_cost {
+            continue;
+        }
+        let baseline_start = i.saturating_sub(BASELINE_DAYS as usize);
+        let baseline: Vec<f64> = series[baseline_start..i].iter().map(|(_, c)| *c).collect();
+        if baseline.len() < MIN_BASELINE_SAMPLES {
+            continue;
+        }
+        let (mean, std_dev) = mean_std_dev(&baseline);
+        if let Some(z) = z_score(*cost, mean, std_dev).filter(|z| *z >= sigma) {
+            anomalies.push(make(*date, *cost, mean, std_dev, z));
+        }
+    }
+    anomalies
+}
+
+/// Find days, projects and sessions in the last `lookback_days` whose cost is
+/// at least `sigma` standard deviations above their baseline
+pub(crate) fn compute_usage_anomalies(
+    conn: &Connection,
+    today: NaiveDate,
+    sigma: f64,
+    lookback_days: u32,
+    min_cost: f64,
+) -> Result<UsageAnomalyReport, String> {
+    let lookback_start = today - Duration::days(lookback_days.max(1) as i64 - 1);
+    let history_start = lookback_start - Duration::days(BASELINE_DAYS);
+    let mut anomalies = Vec::new();
+
+    // Days against the preceding days
+    let series = daily_cost_series(conn, history_start, today)?;
+    anomalies.extend(daily_anomalies(
+        &series,
+        lookback_start,
+        sigma,
+        min_cost,
+        |date, cost, mean, std_dev, z| {
+            let date = date.format("%Y-%m-%d").to_string();
+            UsageAnomaly {
+                kind: AnomalyKind::Day,
+                key: date.clone(),
+                label: date.clone(),
+                date,
+                project_path: None,
+                cost,
+                baseline_mean: mean,
+                baseline_std_dev: std_dev,
+                z_score: z,
+            }
+        },
+    ));
+
+    let filter = UsageFilter {
+        start_date: Some(history_start),
+        end_date: Some(today),
+        ..Default::default()
+    };
+    let (where_clause, values) = filter.where_clause();
+
+    // Project days against the project's preceding days
+    let mut project_costs: HashMap<String, HashMap<String, f64>> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT project_path, date, SUM(cost) FROM usage_entries WHERE {}
+                 GROUP BY project_path, date",
+                where_clause
+            ))
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt
+            .query(params_from_iter(values.iter()))
+            .map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let project_path: String = row.get(0).map_err(|e| e.to_string())?;
+            let date: String = row.get(1).map_err(|e| e.to_string())?;
+            let cost: f64 = row.get(2).map_err(|e| e.to_string())?;
+            project_costs.entry(project_path).or_default().insert(date, cost);
+        }
+    }
+    for (project_path, costs) in &project_costs {
+        let project_series: Vec<(NaiveDate, f64)> = series
+            .iter()
+            .map(|(date, _)| {
+                let cost = costs
+                    .get(&date.format("%Y-%m-%d").to_string())
+                    .copied()
+                    .unwrap_or(0.0);
+                (*date, cost)
+            })
+            .collect();
+        anomalies.extend(daily_anomalies(
+            &project_series,
+            lookback_start,
+            sigma,
+            min_cost,
+            |date, cost, mean, std_dev, z| UsageAnomaly {
+                kind: AnomalyKind::Project,
+                key: project_path.clone(),
+                label: project_name_from_path(project_path),
+                date: date.format("%Y-%m-%d").to_string(),
+                project_path: Some(project_path.clone()),
+                cost,
+                baseline_mean: mean,
+                baseline_std_dev: std_dev,
+                z_score: z,
+            },
+        ));
+    }
+
+    // Sessions against every other session in the window
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT session_id, MAX(project_path), MIN(date), SUM(cost) FROM usage_entries
+             WHERE {} GROUP BY session_id",
+            where_clause
+        ))
+        .map_err(|e| e.to_string())?;
+    let sessions = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    if sessions.len() > MIN_BASELINE_SAMPLES {
+        let lookback_start = lookback_start.format("%Y-%m-%d").to_string();
+        let n = sessions.len() as f64;
+        let sum: f64 = sessions.iter().map(|s| s.3).sum();
+        let sum_squares: f64 = sessions.iter().map(|s| s.3 * s.3).sum();
+        for (session_id, project_path, date, cost) in sessions {
+            if date < lookback_start || cost < min_cost {
+                continue;
+            }
+            // Leave the session itself out of its baseline
+            let mean = (sum - cost) / (n - 1.0);
+            let variance = ((sum_squares - cost * cost) - (n - 1.0) * mean * mean) / (n - 2.0);
+            let std_dev = variance.max(0.0).sqrt();
+            if let Some(z) = z_score(cost, mean, std_dev).filter(|z| *z >= sigma) {
+                anomalies.push(UsageAnomaly {
+                    kind: AnomalyKind::Session,
+                    label: format!("{} ({})", project_name_from_path(&project_path), date),
+                    key: session_id,
+                    date,
+                    project_path: Some(project_path),
+                    cost,
+                    baseline_mean: mean,
+                    baseline_std_dev: std_dev,
+                    z_score: z,
+                });
+            }
+        }
+    }
+
+    anomalies.sort_by(|a, b| b.z_score.total_cmp(&a.z_score));
+
+    Ok(UsageAnomalyReport {
+        threshold_sigma: sigma,
+        lookback_days,
+        anomalies,
+    })
+}
+
+/// Forecast this month's spend with a linear trend and a weekday model
+#[command]
+pub fn get_spend_forecast(
+    db: State<'_, AgentDb>,
+    history_days: Option<u32>,
+) -> Result<SpendForecast, String> {
+    let conn = lock_synced_index(&db)?;
+    compute_spend_forecast(
+        &conn,
+        Local::now().date_naive(),
+        history_days.unwrap_or(DEFAULT_HISTORY_DAYS),
+    )
+}
+
+/// Find days, projects and sessions whose cost is `threshold_sigma` (default 3)
+/// standard deviations above their baseline
+#[command]
+pub fn get_usage_anomalies(
+    db: State<'_, AgentDb>,
+    threshold_sigma: Option<f64>,
+    lookback_days: Option<u32>,
+    min_cost: Option<f64>,
+) -> Result<UsageAnomalyReport, String> {
+    let sigma = threshold_sigma.unwrap_or(DEFAULT_ANOMALY_SIGMA);
+    if sigma <= 0.0 {
+        return Err("threshold_sigma must be positive".to_string());
+    }
+
+    let conn = lock_synced_index(&db)?;
+    compute_usage_anomalies(
+        &conn,
+        Local::now().date_naive(),
+        sigma,
+        lookback_days.unwrap_or(DEFAULT_ANOMALY_LOOKBACK_DAYS),
+        min_cost.unwrap_or(DEFAULT_MIN_ANOMALY_COST),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usage_index::init_usage_index_tables;
+    use rusqlite::params;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        conn
+    }
+
+    fn insert(conn: &Connection, date: NaiveDate, session_id: &str, cost: f64) {
+        let timestamp = date.and_hms_opt(12, 0, 0).unwrap().and_utc();
+        conn.execute(
+            "INSERT INTO usage_entries (file_path, timestamp, timestamp_ms, date, model, cost,
+                                        session_id, project_path, api_base_url)
+             VALUES ('f', ?1, ?2, ?3, 'claude-sonnet-4', ?4, ?5, '/work/app', '')",
+            params![
+                timestamp.to_rfc3339(),
+                timestamp.timestamp_millis(),
+                date.format("%Y-%m-%d").to_string(),
+                cost,
+                session_id
+            ],
+        )
+        .unwrap();
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn linear_fit_recovers_a_line() {
+        let (intercept, slope) = linear_fit(&[2.0, 5.0, 8.0, 11.0]);
+        assert!((intercept - 2.0).abs() < 1e-9);
+        assert!((slope - 3.0).abs() < 1e-9);
+
+        assert_eq!(linear_fit(&[4.0]), (4.0, 0.0));
+        assert_eq!(linear_fit(&[]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn weekday_model_follows_the_weekly_pattern() {
+        let conn = test_db();
+        let today = date("2026-02-10");
+        for day in (today - Duration::days(60)).iter_days().take_while(|d| *d <= today) {
+            let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+            if !weekend {
+                insert(&conn, day, &day.to_string(), 10.0);
+            }
+        }
+
+        let forecast = compute_spend_forecast(&conn, today, 28).unwrap();
+        assert_eq!((forecast.days_elapsed, forecast.days_in_month), (10, 28));
+        // 2026-02-01 is a Sunday, so seven weekdays so far
+        assert!((forecast.month_to_date_cost - 70.0).abs() < 1e-9);
+        assert_eq!(forecast.daily.len(), 18);
+        for day in &forecast.daily {
+            let weekend = matches!(date(&day.date).weekday(), Weekday::Sat | Weekday::Sun);
+            assert_eq!(day.weekday, if weekend { 0.0 } else { 10.0 }, "{}", day.date);
+        }
+
+        // The weekday model explains the history exactly, the linear one doesn't
+        let weekday = &forecast.weekday;
+        assert!((weekday.projected_total - 200.0).abs() < 1e-9);
+        assert_eq!(weekday.lower_bound, weekday.upper_bound);
+        assert!(forecast.linear.upper_bound > forecast.linear.lower_bound);
+    }
+
+    #[test]
+    fn daily_anomalies_need_a_baseline() {
+        let start = date("2026-01-01");
+        let mut series: Vec<(NaiveDate, f64)> = start
+            .iter_days()
+            .take(20)
+            .enumerate()
+            .map(|(i, d)| (d, if i % 2 == 0 { 1.0 } else { 1.2 }))
+            .collect();
+        series[3].1 = 10.0;
+        series[15].1 = 10.0;
+
+        let make = |date, cost, mean, std_dev, z_score| UsageAnomaly {
+            kind: AnomalyKind::Day,
+            key: String::new(),
+            label: String::new(),
+            date: format!("{}", date),
+            project_path: None,
+            cost,
+            baseline_mean: mean,
+            baseline_std_dev: std_dev,
+            z_score,
+        };
+
+        // Day 3 has too few preceding days to judge
+        let anomalies = daily_anomalies(&series, start, 3.0, 1.0, make);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].date, "2026-01-16");
+
+        // Too cheap to matter, or before the lookback
+        assert!(daily_anomalies(&series, start, 3.0, 20.0, make).is_empty());
+        assert!(daily_anomalies(&series, date("2026-01-17"), 3.0, 1.0, make).is_empty());
+    }
+
+    #[test]
+    fn sessions_are_compared_without_themselves() {
+        let conn = test_db();
+        let today = date("2026-03-20");
+        let costs = [1.0, 1.1, 0.9, 1.0, 1.2, 0.8, 1.0, 1.1, 0.9, 1.0];
+        for (i, cost) in costs.iter().enumerate() {
+            insert(&conn, today - Duration::days(i as i64), &format!("s{}", i), *cost);
+        }
+        insert(&conn, today, "spike", 20.0);
+
+        let report = compute_usage_anomalies(&conn, today, 3.0, 30, 0.5).unwrap();
+        let sessions: Vec<&UsageAnomaly> = report
+            .anomalies
+            .iter()
+            .filter(|a| a.kind == AnomalyKind::Session)
+            .collect();
+        assert_eq!(sessions.len(), 1);
+        let spike = sessions[0];
+        assert_eq!(spike.key, "spike");
+
+        let (mean, std_dev) = mean_std_dev(&costs);
+        assert!((spike.baseline_mean - mean).abs() < 1e-9);
+        assert!((spike.baseline_std_dev - std_dev).abs() < 1e-9);
+    }
+}
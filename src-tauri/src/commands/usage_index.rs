@@ -0,0 +1,1087 @@
+use chrono::{DateTime, NaiveDate};
+use log::{info, warn};
+use rusqlite::{params, Connection, Result as SqliteResult};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Instant, UNIX_EPOCH};
+use tauri::{command, State};
+
+use super::agents::AgentDb;
+use super::pricing::{load_pricing_config, PricingConfig};
+use super::usage::get_api_base_url;
+use crate::claude_messages::{ClaudeMessage, Usage};
+
+/// Bumped whenever the index layout or dedup rules change
+const USAGE_INDEX_VERSION: &str = "5";
+
+/// Bytes hashed at each end of the indexed part of a file to detect rewrites
+const FINGERPRINT_BYTES: u64 = 4096;
+
+/// Parsed entries buffered before they are written in one transaction
+const WRITE_BATCH_ENTRIES: usize = 5000;
+
+/// Serialises syncs, so two queries never index the same tail twice
+static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+/// Result of bringing the usage index up to date
+#[derive(Debug, Default, Serialize)]
+pub struct UsageIndexSyncResult {
+    pub files_scanned: u64,
+    pub files_updated: u64,
+    pub entries_added: u64,
+    pub duplicates_skipped: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Indexing state of a JSONL file from the last sync
+struct IndexedFile {
+    size: u64,
+    mtime: i64,
+    byte_offset: u64,
+    fingerprint: String,
+    project_path: Option<String>,
+}
+
+/// A JSONL file with content that still has to be indexed
+struct PendingFile {
+    path: PathBuf,
+    project_name: String,
+    size: u64,
+    mtime: i64,
+    start_offset: u64,
+    project_path: Option<String>,
+}
+
+/// The newly read tail of a file, parsed without holding the database lock
+struct ParsedFile {
+    entries: Vec<ParsedEntry>,
+    byte_offset: u64,
+    fingerprint: String,
+    project_path: Option<String>,
+}
+
+/// One assistant message with token usage
+struct ParsedEntry {
+    timestamp: String,
+    timestamp_ms: i64,
+    date: String,
+    model: String,
+    usage: Usage,
+    cost: f64,
+    reported_cost: Option<f64>,
+    session_id: String,
+    project_path: String,
+    message_id: Option<String>,
+    request_id: Option<String>,
+    dedup_key: Option<String>,
+    /// (tool_use_id, tool name) of the tool_use blocks in this copy
+    tool_calls: Vec<(String, String)>,
+}
+
+/// Create the usage index tables
+///
+/// `usage_files` remembers how far each JSONL file under `~/.claude/projects`
+/// has been read, `usage_entries` holds one row per assistant message with
+/// token usage and `usage_tool_calls` the tool_use blocks of those messages.
+pub fn init_usage_index_tables(conn: &Connection) -> SqliteResult<()> {
+    // The index is derived data, so a schema or dedup change simply rebuilds it
+    let version: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = 'usage_index_version'",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
+    if version.as_deref() != Some(USAGE_INDEX_VERSION) {
+        conn.execute("DROP TABLE IF EXISTS usage_entries", [])?;
+        conn.execute("DROP TABLE IF EXISTS usage_duplicates", [])?;
+        conn.execute("DROP TABLE IF EXISTS usage_tool_calls", [])?;
+        conn.execute("DROP TABLE IF EXISTS usage_files", [])?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('usage_index_version', ?1)",
+            params![USAGE_INDEX_VERSION],
+        )?;
+    }
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_files (
+            path TEXT PRIMARY KEY,
+            project_name TEXT NOT NULL,
+            project_path TEXT,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL,
+            byte_offset INTEGER NOT NULL,
+            fingerprint TEXT NOT NULL DEFAULT '',
+            indexed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            file_path TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            timestamp_ms INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            model TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cost REAL NOT NULL DEFAULT 0,
+            reported_cost REAL,
+            session_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            api_base_url TEXT NOT NULL,
+            message_id TEXT,
+            request_id TEXT,
+            dedup_key TEXT UNIQUE
+        )",
+        [],
+    )?;
+
+    // Copies of already counted messages, kept for the dedup statistics
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_duplicates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            dedup_key TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            total_tokens INTEGER NOT NULL DEFAULT 0,
+            cost REAL NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Tool calls made by a counted message, joined to it by dedup_key
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_tool_calls (
+            tool_use_id TEXT PRIMARY KEY,
+            dedup_key TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            tool_name TEXT NOT NULL,
+            mcp_server TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_timestamp ON usage_entries(timestamp_ms)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_date ON usage_entries(date)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_session ON usage_entries(session_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_entries_file ON usage_entries(file_path)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_duplicates_key ON usage_duplicates(dedup_key)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_tool_calls_key ON usage_tool_calls(dedup_key)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Bring the usage index up to date, then lock the database for querying it
+pub fn lock_synced_index(db: &AgentDb) -> Result<MutexGuard<'_, Connection>, String> {
+    sync_usage_index(db)?;
+    db.0.lock().map_err(|e| e.to_string())
+}
+
+/// Index everything appended to the session JSONL files since the last sync
+///
+/// Unchanged files are skipped by size and mtime, grown files are read from
+/// the stored byte offset, and files that shrank or whose indexed part no
+/// longer matches its fingerprint (rewritten) are re-indexed from the start.
+/// Rows of files that were deleted from disk are kept, so history outlives
+/// Claude's own session cleanup.
+///
+/// The files are read and parsed without holding the database lock; it is
+/// only taken to load the file state and to write each batch of entries.
+pub fn sync_usage_index(db: &AgentDb) -> Result<UsageIndexSyncResult, String> {
+    let projects_dir = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude")
+        .join("projects");
+
+    sync_projects_dir(db, &projects_dir, &load_pricing_config(), &get_api_base_url())
+}
+
+fn sync_projects_dir(
+    db: &AgentDb,
+    projects_dir: &Path,
+    pricing: &PricingConfig,
+    api_base_url: &str,
+) -> Result<UsageIndexSyncResult, String> {
+    let _sync = SYNC_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let started = Instant::now();
+    let mut result = UsageIndexSyncResult::default();
+
+    let indexed = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        recompute_costs_if_changed(&conn, pricing)?;
+        load_indexed_files(&conn).map_err(|e| e.to_string())?
+    };
+
+    let mut pending = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut rewritten = Vec::new();
+    for (path, project_name) in collect_jsonl_files(projects_dir) {
+        result.files_scanned += 1;
+
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Failed to stat {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let size = metadata.len();
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+
+        let key = path.to_string_lossy().to_string();
+        let mut file = PendingFile {
+            path,
+            project_name,
+            size,
+            mtime,
+            start_offset: 0,
+            project_path: None,
+        };
+        match indexed.get(&key) {
+            Some(indexed) if indexed.size == size && indexed.mtime == mtime => {
+                file.start_offset = indexed.byte_offset;
+                file.project_path = indexed.project_path.clone();
+                unchanged.push(file);
+            }
+            Some(indexed)
+                if size >= indexed.byte_offset
+                    && fingerprint(&file.path, indexed.byte_offset).ok().as_deref()
+                        == Some(indexed.fingerprint.as_str()) =>
+            {
+                file.start_offset = indexed.byte_offset;
+                file.project_path = indexed.project_path.clone();
+                pending.push(file);
+            }
+            // File shrank or its indexed part changed - it was rewritten, so start over
+            Some(_) => {
+                rewritten.push(key);
+                pending.push(file);
+            }
+            None => pending.push(file),
+        }
+    }
+
+    if !rewritten.is_empty() {
+        let dropped = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            drop_indexed_files(&conn, rewritten).map_err(|e| e.to_string())?
+        };
+        for file in pending.iter_mut() {
+            if dropped.contains(file.path.to_string_lossy().as_ref()) {
+                file.start_offset = 0;
+                file.project_path = None;
+            }
+        }
+        for mut file in unchanged {
+            if dropped.contains(file.path.to_string_lossy().as_ref()) {
+                file.start_offset = 0;
+                file.project_path = None;
+                pending.push(file);
+            }
+        }
+    }
+
+    if pending.is_empty() {
+        return Ok(result);
+    }
+
+    // Index new files in chronological order so that duplicated messages are
+    // attributed to the session they first appeared in
+    pending.sort_by_cached_key(|file| {
+        if file.start_offset == 0 {
+            get_earliest_timestamp(&file.path)
+        } else {
+            None
+        }
+    });
+
+    let mut batch = Vec::new();
+    let mut batch_entries = 0;
+    for file in pending {
+        match parse_file(&file, api_base_url, pricing) {
+            Ok(parsed) => {
+                batch_entries += parsed.entries.len();
+                batch.push((file, parsed));
+            }
+            Err(e) => warn!("Failed to index {:?}: {}", file.path, e),
+        }
+        if batch_entries >= WRITE_BATCH_ENTRIES {
+            write_batch(db, &batch, api_base_url, &mut result)?;
+            batch.clear();
+            batch_entries = 0;
+        }
+    }
+    write_batch(db, &batch, api_base_url, &mut result)?;
+
+    result.elapsed_ms = started.elapsed().as_millis() as u64;
+    if result.entries_added > 0 {
+        info!(
+            "Usage index updated: {} files, {} new entries, {} duplicates skipped in {}ms",
+            result.files_updated, result.entries_added, result.duplicates_skipped, result.elapsed_ms
+        );
+    }
+
+    Ok(result)
+}
+
+/// Drop the usage index and rebuild it from the JSONL files
+#[command]
+pub fn rebuild_usage_index(db: State<'_, AgentDb>) -> Result<UsageIndexSyncResult, String> {
+    {
+        let _sync = SYNC_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+        for table in ["usage_entries", "usage_duplicates", "usage_tool_calls", "usage_files"] {
+            conn.execute(&format!("DROP TABLE IF EXISTS {}", table), [])
+                .map_err(|e| format!("Failed to drop {} table: {}", table, e))?;
+        }
+        init_usage_index_tables(&conn).map_err(|e| e.to_string())?;
+    }
+
+    sync_usage_index(&db)
+}
+
+/// How much double counting the dedup removed
+#[derive(Debug, Default, Serialize)]
+pub struct UsageDedupStats {
+    pub counted_entries: u64,
+    pub counted_tokens: u64,
+    pub counted_cost: f64,
+    pub duplicate_entries: u64,
+    pub duplicate_tokens: u64,
+    pub duplicate_cost: f64,
+    /// Copies within the same file (streamed content blocks)
+    pub same_file_duplicates: u64,
+    /// Copies in another file (resumed or forked sessions)
+    pub cross_file_duplicates: u64,
+    /// Sessions that contained at least one duplicate
+    pub affected_sessions: u64,
+}
+
+/// Get statistics about the entries removed by deduplication
+#[command]
+pub fn get_usage_dedup_stats(db: State<'_, AgentDb>) -> Result<UsageDedupStats, String> {
+    let conn = lock_synced_index(&db)?;
+
+    let (counted_entries, counted_tokens, counted_cost): (i64, i64, f64) = conn
+        .query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(input_tokens + output_tokens + cache_creation_tokens + cache_read_tokens), 0),
+                    COALESCE(SUM(cost), 0)
+             FROM usage_entries",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let (duplicate_entries, duplicate_tokens, duplicate_cost, same_file, affected_sessions): (
+        i64,
+        i64,
+        f64,
+        i64,
+        i64,
+    ) = conn
+        .query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(d.total_tokens), 0),
+                    COALESCE(SUM(d.cost), 0),
+                    COALESCE(SUM(CASE WHEN d.file_path = e.file_path THEN 1 ELSE 0 END), 0),
+                    COUNT(DISTINCT d.session_id)
+             FROM usage_duplicates d
+             LEFT JOIN usage_entries e ON e.dedup_key = d.dedup_key",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    Ok(UsageDedupStats {
+        counted_entries: counted_entries as u64,
+        counted_tokens: counted_tokens as u64,
+        counted_cost,
+        duplicate_entries: duplicate_entries as u64,
+        duplicate_tokens: duplicate_tokens as u64,
+        duplicate_cost,
+        same_file_duplicates: same_file as u64,
+        cross_file_duplicates: (duplicate_entries - same_file) as u64,
+        affected_sessions: affected_sessions as u64,
+    })
+}
+
+/// Recompute all indexed costs when the pricing config differs from the one
+/// they were calculated with
+fn recompute_costs_if_changed(conn: &Connection, pricing: &PricingConfig) -> Result<(), String> {
+    let fingerprint = pricing.fingerprint();
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = 'usage_pricing_fingerprint'",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
+    if stored.as_deref() == Some(fingerprint.as_str()) {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    {
+        let mut select = tx
+            .prepare(
+                "SELECT id, model, api_base_url, date, input_tokens, output_tokens,
+                        cache_creation_tokens, cache_read_tokens, reported_cost
+                 FROM usage_entries",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut update = tx
+            .prepare("UPDATE usage_entries SET cost = ?1 WHERE id = ?2")
+            .map_err(|e| e.to_string())?;
+
+        let rows = select
+            .query_map([], |row| {
+                let usage = Usage {
+                    input_tokens: Some(row.get::<_, i64>(4)? as u64),
+                    output_tokens: Some(row.get::<_, i64>(5)? as u64),
+                    cache_creation_input_tokens: Some(row.get::<_, i64>(6)? as u64),
+                    cache_read_input_tokens: Some(row.get::<_, i64>(7)? as u64),
+                };
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    usage,
+                    row.get::<_, Option<f64>>(8)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        for row in rows {
+            let (id, model, api_base_url, date, usage, reported_cost) =
+                row.map_err(|e| e.to_string())?;
+            let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok();
+            let cost = pricing.cost(&model, &api_base_url, date, &usage, reported_cost);
+            update
+                .execute(params![cost, id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        // A duplicate costs the same as the entry it copies
+        tx.execute(
+            "UPDATE usage_duplicates SET cost = COALESCE(
+                (SELECT e.cost FROM usage_entries e WHERE e.dedup_key = usage_duplicates.dedup_key),
+                cost)",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('usage_pricing_fingerprint', ?1)",
+            params![fingerprint],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    info!("Pricing changed, recomputed usage costs");
+    Ok(())
+}
+
+fn load_indexed_files(conn: &Connection) -> SqliteResult<HashMap<String, IndexedFile>> {
+    let mut stmt =
+        conn.prepare("SELECT path, size, mtime, byte_offset, fingerprint, project_path FROM usage_files")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            IndexedFile {
+                size: row.get::<_, i64>(1)? as u64,
+                mtime: row.get(2)?,
+                byte_offset: row.get::<_, i64>(3)? as u64,
+                fingerprint: row.get(4)?,
+                project_path: row.get(5)?,
+            },
+        ))
+    })?;
+    rows.collect()
+}
+
+/// All `.jsonl` files below the projects directory with their encoded project name
+fn collect_jsonl_files(projects_dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+
+    if let Ok(projects) = fs::read_dir(projects_dir) {
+        for project in projects.flatten() {
+            if project.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                let project_name = project.file_name().to_string_lossy().to_string();
+
+                walkdir::WalkDir::new(project.path())
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
+                    .for_each(|entry| {
+                        files.push((entry.path().to_path_buf(), project_name.clone()));
+                    });
+            }
+        }
+    }
+
+    files
+}
+
+/// MCP server of a tool named `mcp__<server>__<tool>`
+pub(crate) fn mcp_server_of(tool_name: &str) -> Option<&str> {
+    tool_name
+        .strip_prefix("mcp__")
+        .and_then(|rest| rest.split_once("__"))
+        .map(|(server, _)| server)
+}
+
+fn get_earliest_timestamp(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .filter_map(ClaudeMessage::parse)
+        .filter_map(|msg| msg.timestamp().map(|t| t.to_string()))
+        .min()
+}
+
+/// Hash of the first and last few KiB of the indexed part of a file
+///
+/// Appending leaves both ends untouched, while a rewrite (compaction, an
+/// edited or replaced session) changes at least one of them even when the
+/// new file is larger than the indexed offset.
+fn fingerprint(path: &Path, indexed_len: u64) -> std::io::Result<String> {
+    let mut handle = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+
+    let head_len = indexed_len.min(FINGERPRINT_BYTES);
+    let mut head = vec![0; head_len as usize];
+    handle.read_exact(&mut head)?;
+    hasher.update(&head);
+
+    let tail_start = indexed_len.saturating_sub(FINGERPRINT_BYTES).max(head_len);
+    let mut tail = vec![0; (indexed_len - tail_start) as usize];
+    handle.seek(SeekFrom::Start(tail_start))?;
+    handle.read_exact(&mut tail)?;
+    hasher.update(&tail);
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Parse the not yet indexed tail of a file
+fn parse_file(
+    file: &PendingFile,
+    api_base_url: &str,
+    pricing: &PricingConfig,
+) -> Result<ParsedFile, String> {
+    let buffer = read_from(&file.path, file.start_offset).map_err(|e| e.to_string())?;
+
+    // Only consume complete lines; a trailing line that is still being
+    // written is picked up on the next sync
+    let mut consumed = buffer
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    if consumed < buffer.len()
+        && ClaudeMessage::parse(&String::from_utf8_lossy(&buffer[consumed..])).is_some()
+    {
+        consumed = buffer.len();
+    }
+
+    let session_dir = file
+        .path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    let mut project_path = file.project_path.clone();
+    let mut entries = Vec::new();
+
+    for raw_line in buffer[..consumed].split(|b| *b == b'\n') {
+        let line = String::from_utf8_lossy(raw_line);
+        let msg = match ClaudeMessage::parse(&line) {
+            Some(msg) => msg,
+            None => continue,
+        };
+
+        // Extract the actual project path from cwd if we haven't already
+        if project_path.is_none() {
+            project_path = msg.cwd().map(|cwd| cwd.to_string());
+        }
+
+        let entry = match &msg {
+            ClaudeMessage::User(entry) | ClaudeMessage::Assistant(entry) => entry,
+            _ => continue,
+        };
+        let (message, timestamp) = match (&entry.message, &entry.timestamp) {
+            (Some(message), Some(timestamp)) => (message, timestamp),
+            _ => continue,
+        };
+        let usage = match &message.usage {
+            Some(usage) if !usage.is_empty() => usage,
+            _ => continue,
+        };
+
+        // The same assistant message is written once per content block while
+        // streaming and copied again into resumed or forked sessions, so count
+        // each message id + request id only once across all files
+        let dedup_key = message.id.as_ref().map(|msg_id| match &entry.request_id {
+            Some(req_id) => format!("{}:{}", msg_id, req_id),
+            None => msg_id.clone(),
+        });
+
+        let parsed_time = DateTime::parse_from_rfc3339(timestamp).ok();
+        let timestamp_ms = parsed_time.map(|dt| dt.timestamp_millis()).unwrap_or(0);
+        let date = parsed_time
+            .map(|dt| dt.naive_local().date().format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| timestamp.split('T').next().unwrap_or(timestamp).to_string());
+
+        let model = message
+            .model
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string());
+        let cost = pricing.cost(
+            &model,
+            api_base_url,
+            parsed_time.map(|dt| dt.date_naive()),
+            usage,
+            entry.cost_usd,
+        );
+
+        let tool_calls = match &dedup_key {
+            Some(dedup_key) => message
+                .content
+                .tool_uses()
+                .iter()
+                .enumerate()
+                .map(|(index, tool_use)| {
+                    let tool_use_id = if tool_use.id.is_empty() {
+                        format!("{}:{}", dedup_key, index)
+                    } else {
+                        tool_use.id.to_string()
+                    };
+                    (tool_use_id, tool_use.name.to_string())
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        entries.push(ParsedEntry {
+            timestamp: timestamp.clone(),
+            timestamp_ms,
+            date,
+            model,
+            usage: usage.clone(),
+            cost,
+            reported_cost: entry.cost_usd,
+            session_id: entry
+                .session_id
+                .clone()
+                .unwrap_or_else(|| session_dir.clone()),
+            project_path: project_path
+                .clone()
+                .unwrap_or_else(|| file.project_name.clone()),
+            message_id: message.id.clone(),
+            request_id: entry.request_id.clone(),
+            dedup_key,
+            tool_calls,
+        });
+    }
+
+    let byte_offset = file.start_offset + consumed as u64;
+    let fingerprint = fingerprint(&file.path, byte_offset).map_err(|e| e.to_string())?;
+
+    Ok(ParsedFile {
+        entries,
+        byte_offset,
+        fingerprint,
+        project_path,
+    })
+}
+
+fn read_from(path: &Path, offset: u64) -> std::io::Result<Vec<u8>> {
+    let mut handle = fs::File::open(path)?;
+    handle.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    handle.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Write parsed files in one transaction and record their new offsets
+fn write_batch(
+    db: &AgentDb,
+    batch: &[(PendingFile, ParsedFile)],
+    api_base_url: &str,
+    result: &mut UsageIndexSyncResult,
+) -> Result<(), String> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    for (file, parsed) in batch {
+        store_file(&tx, file, parsed, api_base_url, result).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Drop the rows of rewritten files so they are indexed again from the start
+///
+/// A file may hold the counted copy of a message, or the only recorded copy
+/// of its tool calls, while other files hold duplicates that were never
+/// stored in full. Every file sharing a message with a dropped file is
+/// dropped as well, so re-parsing them restores the counted copy. Returns the
+/// paths of all dropped files.
+fn drop_indexed_files(conn: &Connection, rewritten: Vec<String>) -> SqliteResult<HashSet<String>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut dropped = HashSet::new();
+    {
+        let mut sharing = tx.prepare(
+            "WITH keys AS (
+                SELECT dedup_key FROM usage_entries WHERE file_path = ?1 AND dedup_key IS NOT NULL
+                UNION SELECT dedup_key FROM usage_duplicates WHERE file_path = ?1
+                UNION SELECT dedup_key FROM usage_tool_calls WHERE file_path = ?1
+             )
+             SELECT file_path FROM usage_entries WHERE dedup_key IN (SELECT dedup_key FROM keys)
+             UNION SELECT file_path FROM usage_duplicates WHERE dedup_key IN (SELECT dedup_key FROM keys)
+             UNION SELECT file_path FROM usage_tool_calls WHERE dedup_key IN (SELECT dedup_key FROM keys)",
+        )?;
+        let mut queue = rewritten;
+        while let Some(path) = queue.pop() {
+            if !dropped.insert(path.clone()) {
+                continue;
+            }
+            let shared = sharing
+                .query_map(params![path], |row| row.get::<_, String>(0))?
+                .collect::<SqliteResult<Vec<_>>>()?;
+            queue.extend(shared.into_iter().filter(|p| !dropped.contains(p)));
+        }
+    }
+
+    for path in &dropped {
+        for table in ["usage_entries", "usage_duplicates", "usage_tool_calls"] {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE file_path = ?1", table),
+                params![path],
+            )?;
+        }
+        tx.execute("DELETE FROM usage_files WHERE path = ?1", params![path])?;
+    }
+    tx.commit()?;
+    Ok(dropped)
+}
+
+fn store_file(
+    conn: &Connection,
+    file: &PendingFile,
+    parsed: &ParsedFile,
+    api_base_url: &str,
+    result: &mut UsageIndexSyncResult,
+) -> SqliteResult<()> {
+    let file_path = file.path.to_string_lossy().to_string();
+
+    let mut insert = conn.prepare_cached(
+        "INSERT OR IGNORE INTO usage_entries (
+            file_path, timestamp, timestamp_ms, date, model,
+            input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens,
+            cost, reported_cost, session_id, project_path, api_base_url,
+            message_id, request_id, dedup_key
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+    )?;
+    let mut record_duplicate = conn.prepare_cached(
+        "INSERT INTO usage_duplicates (dedup_key, file_path, session_id, timestamp, total_tokens, cost)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut record_tool_call = conn.prepare_cached(
+        "INSERT OR IGNORE INTO usage_tool_calls (tool_use_id, dedup_key, file_path, tool_name, mcp_server)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+
+    for entry in &parsed.entries {
+        let inserted = insert.execute(params![
+            file_path,
+            entry.timestamp,
+            entry.timestamp_ms,
+            entry.date,
+            entry.model,
+            entry.usage.input() as i64,
+            entry.usage.output() as i64,
+            entry.usage.cache_creation() as i64,
+            entry.usage.cache_read() as i64,
+            entry.cost,
+            entry.reported_cost,
+            entry.session_id,
+            entry.project_path,
+            api_base_url,
+            entry.message_id,
+            entry.request_id,
+            entry.dedup_key,
+        ])?;
+
+        // Tool calls arrive on the streamed copies of a message, so they are
+        // recorded whether or not this copy was counted
+        if let Some(dedup_key) = &entry.dedup_key {
+            for (tool_use_id, tool_name) in &entry.tool_calls {
+                record_tool_call.execute(params![
+                    tool_use_id,
+                    dedup_key,
+                    file_path,
+                    tool_name,
+                    mcp_server_of(tool_name),
+                ])?;
+            }
+        }
+
+        if inserted > 0 {
+            result.entries_added += 1;
+        } else {
+            record_duplicate.execute(params![
+                entry.dedup_key,
+                file_path,
+                entry.session_id,
+                entry.timestamp,
+                entry.usage.total() as i64,
+                entry.cost,
+            ])?;
+            result.duplicates_skipped += 1;
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO usage_files (path, project_name, project_path, size, mtime, byte_offset, fingerprint, indexed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, CURRENT_TIMESTAMP)
+         ON CONFLICT(path) DO UPDATE SET
+            project_path = excluded.project_path,
+            size = excluded.size,
+            mtime = excluded.mtime,
+            byte_offset = excluded.byte_offset,
+            fingerprint = excluded.fingerprint,
+            indexed_at = excluded.indexed_at",
+        params![
+            file_path,
+            file.project_name,
+            parsed.project_path,
+            file.size as i64,
+            file.mtime,
+            parsed.byte_offset as i64,
+            parsed.fingerprint,
+        ],
+    )?;
+
+    result.files_updated += 1;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn test_db() -> AgentDb {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        init_usage_index_tables(&conn).unwrap();
+        AgentDb(Mutex::new(conn))
+    }
+
+    fn line(msg_id: &str, req_id: &str, input: u64) -> String {
+        format!(
+            r#"{{"type":"assistant","sessionId":"s1","requestId":"{}","timestamp":"2026-01-02T03:04:05Z","message":{{"id":"{}","model":"claude-sonnet-4","content":[],"usage":{{"input_tokens":{},"output_tokens":1}}}}}}"#,
+            req_id, msg_id, input
+        ) + "\n"
+    }
+
+    fn sync(db: &AgentDb, dir: &Path) -> UsageIndexSyncResult {
+        sync_projects_dir(db, dir, &PricingConfig::default(), "https://api.anthropic.com").unwrap()
+    }
+
+    fn count(db: &AgentDb, sql: &str) -> i64 {
+        db.0.lock().unwrap().query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn session_file(dir: &Path, name: &str) -> PathBuf {
+        let project = dir.join("-home-me-project");
+        fs::create_dir_all(&project).unwrap();
+        project.join(name)
+    }
+
+    #[test]
+    fn reads_only_appended_complete_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        let path = session_file(dir.path(), "a.jsonl");
+        fs::write(&path, line("m1", "r1", 10) + &line("m2", "r2", 20)).unwrap();
+
+        assert_eq!(sync(&db, dir.path()).entries_added, 2);
+
+        // A line that is still being written is left for the next sync
+        let partial = line("m4", "r4", 40);
+        let (head, rest) = partial.split_at(30);
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all((line("m3", "r3", 30) + head).as_bytes()).unwrap();
+        let result = sync(&db, dir.path());
+        assert_eq!((result.entries_added, result.duplicates_skipped), (1, 0));
+
+        file.write_all(rest.as_bytes()).unwrap();
+        assert_eq!(sync(&db, dir.path()).entries_added, 1);
+        assert_eq!(sync(&db, dir.path()).files_updated, 0);
+        assert_eq!(count(&db, "SELECT SUM(input_tokens) FROM usage_entries"), 100);
+        assert_eq!(
+            count(&db, "SELECT byte_offset FROM usage_files"),
+            fs::metadata(&path).unwrap().len() as i64
+        );
+    }
+
+    #[test]
+    fn counts_each_message_and_request_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        // Streamed copies of m1 and m1 retried under a new request id, then a
+        // resumed session that copies m1 into another file
+        fs::write(
+            session_file(dir.path(), "a.jsonl"),
+            line("m1", "r1", 10) + &line("m1", "r1", 10) + &line("m1", "r2", 10),
+        )
+        .unwrap();
+        let result = sync(&db, dir.path());
+        assert_eq!((result.entries_added, result.duplicates_skipped), (2, 1));
+
+        fs::write(session_file(dir.path(), "b.jsonl"), line("m1", "r1", 10)).unwrap();
+        let result = sync(&db, dir.path());
+        assert_eq!((result.entries_added, result.duplicates_skipped), (0, 1));
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM usage_entries WHERE dedup_key = 'm1:r2'"),
+            1
+        );
+
+        let same_file = count(
+            &db,
+            "SELECT COUNT(*) FROM usage_duplicates d
+             JOIN usage_entries e ON e.dedup_key = d.dedup_key AND e.file_path = d.file_path",
+        );
+        assert_eq!(same_file, 1);
+    }
+
+    #[test]
+    fn reindexes_rewritten_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        let path = session_file(dir.path(), "a.jsonl");
+        fs::write(&path, line("m1", "r1", 10) + &line("m2", "r2", 20)).unwrap();
+        sync(&db, dir.path());
+
+        // Truncated
+        fs::write(&path, line("m3", "r3", 30)).unwrap();
+        sync(&db, dir.path());
+        assert_eq!(count(&db, "SELECT SUM(input_tokens) FROM usage_entries"), 30);
+
+        // Rewritten to a larger size
+        fs::write(
+            &path,
+            line("m5", "r5", 50) + &line("m6", "r6", 60) + &line("m7", "r7", 70),
+        )
+        .unwrap();
+        let result = sync(&db, dir.path());
+        assert_eq!((result.entries_added, result.duplicates_skipped), (3, 0));
+        assert_eq!(count(&db, "SELECT SUM(input_tokens) FROM usage_entries"), 180);
+    }
+
+    #[test]
+    fn rewriting_the_counting_file_keeps_shared_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = test_db();
+        let with_tool = |msg_id: &str, req_id: &str, input: u64| {
+            line(msg_id, req_id, input).replace(
+                r#""content":[]"#,
+                r#""content":[{"type":"tool_use","id":"t1","name":"mcp__github__search","input":{}}]"#,
+            )
+        };
+        let a = session_file(dir.path(), "a.jsonl");
+        fs::write(&a, with_tool("m1", "r1", 10) + &line("m2", "r2", 20)).unwrap();
+        sync(&db, dir.path());
+        // A resumed session copies m1, which stays counted in a.jsonl
+        fs::write(session_file(dir.path(), "b.jsonl"), with_tool("m1", "r1", 10)).unwrap();
+        assert_eq!(sync(&db, dir.path()).duplicates_skipped, 1);
+
+        // a.jsonl is compacted and loses its copy of m1
+        fs::write(&a, line("m2", "r2", 20)).unwrap();
+        sync(&db, dir.path());
+        assert_eq!(count(&db, "SELECT SUM(input_tokens) FROM usage_entries"), 30);
+        assert_eq!(
+            count(
+                &db,
+                "SELECT COUNT(*) FROM usage_entries
+                 WHERE dedup_key = 'm1:r1' AND file_path LIKE '%b.jsonl'",
+            ),
+            1
+        );
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM usage_duplicates"), 0);
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM usage_tool_calls WHERE dedup_key = 'm1:r1'"),
+            1
+        );
+        assert_eq!(sync(&db, dir.path()).files_updated, 0);
+    }
+
+    #[test]
+    fn version_change_rebuilds_index() {
+        let db = test_db();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(session_file(dir.path(), "a.jsonl"), line("m1", "r1", 10)).unwrap();
+        sync(&db, dir.path());
+
+        {
+            let conn = db.0.lock().unwrap();
+            conn.execute(
+                "UPDATE app_settings SET value = 'old' WHERE key = 'usage_index_version'",
+                [],
+            )
+            .unwrap();
+            init_usage_index_tables(&conn).unwrap();
+        }
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM usage_entries"), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM usage_files"), 0);
+
+        // Unchanged files are indexed again from the start
+        assert_eq!(sync(&db, dir.path()).entries_added, 1);
+    }
+}
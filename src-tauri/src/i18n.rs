@@ -118,7 +118,13 @@ impl I18n {
                 let file_path = translations_dir.join(format!("backend_{}.json", language.to_string()));
                 if let Ok(content) = fs::read_to_string(&file_path) {
                     if let Ok(translation_file) = serde_json::from_str::<TranslationFile>(&content) {
-                        self.messages.insert(language, translation_file.messages);
+                        // 以内置翻译为基础，避免旧翻译文件缺少新增的键
+                        let mut messages = match language {
+                            Language::Zh => self.get_default_zh_translations(),
+                            Language::En => self.get_default_en_translations(),
+                        };
+                        messages.extend(translation_file.messages);
+                        self.messages.insert(language, messages);
                     }
                 }
             }
@@ -171,6 +177,7 @@ impl I18n {
         translations.insert("provider.switch_success".to_string(), "已成功切换到 {name} ({description})，配置已保存到 Raw Settings".to_string());
         translations.insert("provider.clear_success".to_string(), "已清理所有 ANTHROPIC 环境变量在 Raw Settings 中".to_string());
         translations.insert("provider.connection_test_complete".to_string(), "连接测试完成：{url}".to_string());
+        translations.insert("provider.secret_store_locked".to_string(), "密钥存储已锁定，请先输入密码解锁".to_string());
         
         // Process termination messages
         translations.insert("process.terminating_claude_processes".to_string(), "正在终止所有Claude进程以应用新的代理商配置...".to_string());
@@ -217,6 +224,19 @@ impl I18n {
         // Usage messages
         translations.insert("usage.stats_load_failed".to_string(), "加载使用统计失败: {error}".to_string());
         translations.insert("usage.stats_save_failed".to_string(), "保存使用统计失败: {error}".to_string());
+        translations.insert("budget.threshold_title".to_string(), "预算提醒: {name}".to_string());
+        translations.insert("budget.threshold_body".to_string(), "已使用 {threshold}% 预算 ({spent} / {amount})".to_string());
+        translations.insert("relay.station_down_title".to_string(), "中转站不可用: {name}".to_string());
+        translations.insert("relay.station_down_body".to_string(), "连接测试失败: {message}".to_string());
+        translations.insert("relay.station_recovered_title".to_string(), "中转站已恢复: {name}".to_string());
+        translations.insert("relay.station_recovered_body".to_string(), "连接测试成功: {message}".to_string());
+        translations.insert("relay.balance_low_title".to_string(), "中转站余额不足: {name}".to_string());
+        translations.insert("relay.balance_low_body".to_string(), "剩余余额: {balance}".to_string());
+        translations.insert("relay.balance_depleting_body".to_string(), "剩余余额: {balance}，预计 {days} 天后用完".to_string());
+        translations.insert("relay.failover_title".to_string(), "中转故障转移: {group}".to_string());
+        translations.insert("relay.failover_body".to_string(), "已从 {from} 切换到 {to} ({message})".to_string());
+        translations.insert("relay.failback_title".to_string(), "中转已切回主线路: {group}".to_string());
+        translations.insert("relay.failback_body".to_string(), "已从 {from} 切回 {to} ({message})".to_string());
         
         // Slash commands messages
         translations.insert("slash.command_not_found".to_string(), "斜杠命令未找到: {command}".to_string());
@@ -282,6 +302,7 @@ impl I18n {
         translations.insert("provider.switch_success".to_string(), "Successfully switched to {name} ({description}), config saved to Raw Settings".to_string());
         translations.insert("provider.clear_success".to_string(), "Cleared all ANTHROPIC environment variables in Raw Settings".to_string());
         translations.insert("provider.connection_test_complete".to_string(), "Connection test completed: {url}".to_string());
+        translations.insert("provider.secret_store_locked".to_string(), "The secret store is locked, unlock it with its passphrase first".to_string());
         
         // Process termination messages
         translations.insert("process.terminating_claude_processes".to_string(), "Terminating all Claude processes to apply new provider configuration...".to_string());
@@ -328,6 +349,19 @@ impl I18n {
         // Usage messages
         translations.insert("usage.stats_load_failed".to_string(), "Failed to load usage statistics: {error}".to_string());
         translations.insert("usage.stats_save_failed".to_string(), "Failed to save usage statistics: {error}".to_string());
+        translations.insert("budget.threshold_title".to_string(), "Budget alert: {name}".to_string());
+        translations.insert("budget.threshold_body".to_string(), "{threshold}% of the budget used ({spent} / {amount})".to_string());
+        translations.insert("relay.station_down_title".to_string(), "Relay station down: {name}".to_string());
+        translations.insert("relay.station_down_body".to_string(), "Connection test failed: {message}".to_string());
+        translations.insert("relay.station_recovered_title".to_string(), "Relay station recovered: {name}".to_string());
+        translations.insert("relay.station_recovered_body".to_string(), "Connection test succeeded: {message}".to_string());
+        translations.insert("relay.balance_low_title".to_string(), "Relay station balance low: {name}".to_string());
+        translations.insert("relay.balance_low_body".to_string(), "Remaining balance: {balance}".to_string());
+        translations.insert("relay.balance_depleting_body".to_string(), "Remaining balance: {balance}, runs out in about {days} days".to_string());
+        translations.insert("relay.failover_title".to_string(), "Relay failover: {group}".to_string());
+        translations.insert("relay.failover_body".to_string(), "Switched from {from} to {to} ({message})".to_string());
+        translations.insert("relay.failback_title".to_string(), "Relay switched back: {group}".to_string());
+        translations.insert("relay.failback_body".to_string(), "Switched from {from} back to {to} ({message})".to_string());
         
         // Slash commands messages
         translations.insert("slash.command_not_found".to_string(), "Slash command not found: {command}".to_string());
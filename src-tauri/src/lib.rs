@@ -7,6 +7,7 @@ pub mod claude_binary;
 #[cfg(not(target_os = "windows"))]
 pub mod claude_binary_unix;
 pub mod claude_binary_common;
+pub mod claude_messages;
 pub mod commands;
 pub mod process;
 pub mod i18n;
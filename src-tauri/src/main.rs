@@ -7,6 +7,7 @@ mod claude_binary;
 #[cfg(not(target_os = "windows"))]
 mod claude_binary_unix;
 mod claude_binary_common;
+mod claude_messages;
 mod commands;
 mod process;
 mod i18n;
@@ -15,11 +16,13 @@ use checkpoint::state::CheckpointState;
 use commands::agents::{
     cleanup_finished_processes, create_agent, delete_agent, execute_agent, export_agent,
     export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
+    get_agent_cost_trends, get_agent_leaderboard, get_agent_model_comparison,
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
     get_live_session_output, get_session_output, get_session_status, import_agent,
     import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
     list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
-    list_running_sessions, load_agent_session_history, set_claude_binary_path, stream_session_output, update_agent, AgentDb,
+    list_running_sessions, load_agent_session_history, set_claude_binary_path, start_run_metrics_backfill,
+    stream_session_output, update_agent, AgentDb,
 };
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
@@ -46,6 +49,22 @@ use commands::usage::{
     get_session_stats, get_usage_by_date_range, get_usage_details, get_usage_stats,
     get_today_usage_stats, get_usage_by_api_base_url, get_active_sessions, get_burn_rate_analysis,
 };
+use commands::usage_index::{get_usage_dedup_stats, rebuild_usage_index};
+use commands::usage_export::export_usage;
+use commands::usage_breakdown::get_usage_time_breakdown;
+use commands::cache_analytics::get_cache_efficiency;
+use commands::tool_usage::get_tool_usage;
+use commands::usage_forecast::{get_spend_forecast, get_usage_anomalies};
+use commands::pricing::{get_pricing_config, reset_pricing_config, save_pricing_config};
+use commands::plan_limits::{get_plan_limits_config, get_plan_usage, save_plan_limits_config};
+use commands::metrics_exporter::{
+    get_metrics_exporter_status, save_metrics_exporter_config, start_metrics_exporter,
+    MetricsExporterState,
+};
+use commands::budgets::{
+    create_budget, delete_budget, get_budget_status, list_budgets, start_budget_monitor,
+    update_budget,
+};
 use commands::about::{
     get_app_version, get_database_path, get_app_info, check_for_updates,
 };
@@ -66,10 +85,36 @@ use commands::relay_stations::{
     list_relay_stations, get_relay_station, add_relay_station, update_relay_station,
     delete_relay_station, get_station_info, list_station_tokens, add_station_token,
     update_station_token, delete_station_token, get_token_user_info, get_station_logs,
-    test_station_connection, api_user_self_groups, toggle_station_token, RelayStationManager,
+    test_station_connection, get_relay_station_health, api_user_self_groups, toggle_station_token, RelayStationManager,
     load_station_api_endpoints, save_station_config, get_station_config,
     get_config_usage_status, record_config_usage, export_relay_stations, import_relay_stations,
 };
+use commands::relay_reconciliation::reconcile_station_usage;
+use commands::relay_health::{get_relay_station_health_stats, start_relay_health_monitor};
+use commands::relay_balance::{
+    get_relay_balance_alerts, get_relay_balance_trends, save_relay_balance_alert,
+    snapshot_relay_balance, start_relay_balance_monitor,
+};
+use commands::relay_failover::{
+    create_failover_group, delete_failover_group, list_failover_events, list_failover_groups,
+    start_failover_monitor, update_failover_group, FailoverState,
+};
+use commands::routing_proxy::{
+    get_routing_proxy_stats, get_routing_proxy_status, save_routing_proxy_config,
+    start_routing_proxy, use_routing_proxy, RoutingProxyState,
+};
+use commands::relay_adapters::declarative::list_adapter_definitions;
+use commands::relay_capabilities::{
+    get_relay_station_capabilities, probe_relay_station_capabilities,
+};
+use commands::relay_export::preview_relay_station_import;
+use commands::http_client::{get_http_client_config, init_http_client, save_http_client_config};
+use commands::model_catalog::{
+    get_model_catalogs, refresh_model_catalog, save_model_aliases, validate_model,
+};
+use commands::secrets::{
+    get_secret_store_status, lock_secret_store, set_secret_store_passphrase, unlock_secret_store,
+};
 use process::ProcessRegistryState;
 use std::sync::Mutex;
 use tauri::Manager;
@@ -83,12 +128,22 @@ fn main() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .setup(|app| {
             // Initialize agents database
             let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
             app.manage(AgentDb(Mutex::new(conn)));
 
+            // Record metrics of runs that finished before they were stored
+            start_run_metrics_backfill(app.handle().clone());
+
+            // Apply the saved timeout, retry and proxy settings before any monitor sends requests
+            init_http_client(app.handle());
+
+            // Check spend budgets as new usage arrives
+            start_budget_monitor(app.handle().clone());
+
             // Initialize relay station manager using the same database connection
             let db_path = app
                 .path()
@@ -100,6 +155,16 @@ fn main() {
                 .expect("Failed to initialize relay station manager");
             app.manage(Mutex::new(Some(relay_manager)) as Mutex<Option<RelayStationManager>>);
 
+            // Periodically test enabled relay stations
+            start_relay_health_monitor(app.handle().clone());
+
+            // Periodically record relay station balances
+            start_relay_balance_monitor(app.handle().clone());
+
+            // Fail over between relay stations/providers when the active one goes down
+            app.manage(FailoverState::default());
+            start_failover_monitor(app.handle().clone());
+
             // Initialize checkpoint state
             let checkpoint_state = CheckpointState::new();
 
@@ -127,6 +192,14 @@ fn main() {
             // Initialize Claude process state
             app.manage(ClaudeProcessState::default());
 
+            // Serve OpenMetrics locally if enabled
+            app.manage(MetricsExporterState::default());
+            start_metrics_exporter(app.handle());
+
+            // Route Claude requests through the local proxy if enabled
+            app.manage(RoutingProxyState::default());
+            start_routing_proxy(app.handle());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -189,6 +262,9 @@ fn main() {
             get_agent_run,
             list_agent_runs_with_metrics,
             get_agent_run_with_real_time_metrics,
+            get_agent_leaderboard,
+            get_agent_cost_trends,
+            get_agent_model_comparison,
             list_running_sessions,
             kill_agent_session,
             get_session_status,
@@ -217,6 +293,27 @@ fn main() {
             get_session_stats,
             get_active_sessions,
             get_burn_rate_analysis,
+            rebuild_usage_index,
+            get_usage_dedup_stats,
+            export_usage,
+            get_usage_time_breakdown,
+            get_cache_efficiency,
+            get_tool_usage,
+            get_spend_forecast,
+            get_usage_anomalies,
+            get_pricing_config,
+            save_pricing_config,
+            reset_pricing_config,
+            list_budgets,
+            create_budget,
+            update_budget,
+            delete_budget,
+            get_budget_status,
+            get_plan_limits_config,
+            save_plan_limits_config,
+            get_plan_usage,
+            get_metrics_exporter_status,
+            save_metrics_exporter_config,
             
             // MCP (Model Context Protocol)
             mcp_add,
@@ -278,6 +375,26 @@ fn main() {
             get_token_user_info,
             get_station_logs,
             test_station_connection,
+            get_relay_station_health,
+            get_relay_station_health_stats,
+            snapshot_relay_balance,
+            get_relay_balance_trends,
+            get_relay_balance_alerts,
+            save_relay_balance_alert,
+            list_failover_groups,
+            create_failover_group,
+            update_failover_group,
+            delete_failover_group,
+            list_failover_events,
+            get_routing_proxy_status,
+            save_routing_proxy_config,
+            use_routing_proxy,
+            get_routing_proxy_stats,
+            get_secret_store_status,
+            unlock_secret_store,
+            lock_secret_store,
+            set_secret_store_passphrase,
+            reconcile_station_usage,
             api_user_self_groups,
             toggle_station_token,
             load_station_api_endpoints,
@@ -287,6 +404,18 @@ fn main() {
             record_config_usage,
             export_relay_stations,
             import_relay_stations,
+            preview_relay_station_import,
+            list_adapter_definitions,
+            probe_relay_station_capabilities,
+            get_relay_station_capabilities,
+            // HTTP Client
+            get_http_client_config,
+            save_http_client_config,
+            // Model Catalogs
+            get_model_catalogs,
+            refresh_model_catalog,
+            save_model_aliases,
+            validate_model,
             
             // About / App Information
             get_app_version,
@@ -27,7 +27,7 @@ import {
   Loader2
 } from 'lucide-react';
 import { Badge } from '@/components/ui/badge';
-import { api, type RelayStation } from '@/lib/api';
+import { api, type RelayStation, type ModelCatalog } from '@/lib/api';
 //import { useTranslation } from '@/hooks/useTranslation';
 
 // API端点信息接口 (从api_status.har中解析)
@@ -73,6 +73,8 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
   const [apiEndpoints, setApiEndpoints] = useState<ApiEndpoint[]>([]);
   const [selectedToken, setSelectedToken] = useState<string>('');
   const [availableTokens, setAvailableTokens] = useState<any[]>([]);
+  const [modelCatalog, setModelCatalog] = useState<ModelCatalog | null>(null);
+  const [modelError, setModelError] = useState<string | null>(null);
   
   const [formData, setFormData] = useState<RelayStationConfig>({
     stationId: station.id,
@@ -91,6 +93,7 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
       loadCurrentConfig();
       loadApiEndpoints();
       loadAvailableTokens();
+      loadModelCatalog();
     }
   }, [open, station.id, preselectedToken]);
 
@@ -142,6 +145,21 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
     }
   };
 
+  // 加载中转站的模型列表和别名，用于模型输入提示
+  const loadModelCatalog = async () => {
+    setModelError(null);
+    try {
+      const catalogs = await api.getModelCatalogs();
+      const cached = catalogs.find(c => c.kind === 'station' && c.id === station.id) || null;
+      setModelCatalog(cached);
+      if (!cached || cached.models.length === 0) {
+        setModelCatalog(await api.refreshModelCatalog({ kind: 'station', id: station.id }));
+      }
+    } catch (error) {
+      console.warn('Failed to load model catalog:', error);
+    }
+  };
+
   const loadApiEndpoints = async () => {
     setLoadingEndpoints(true);
     try {
@@ -247,11 +265,41 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
     }
   };
 
+  // 界面上的令牌均已脱敏，应用配置时才获取明文
+  const revealToken = async (): Promise<string> => {
+    if (!formData.token.includes('****')) {
+      return formData.token;
+    }
+    if (station.adapter === 'custom') {
+      const saved = await api.getRelayStation(station.id, true);
+      return saved?.system_token || formData.token;
+    }
+    if (selectedToken) {
+      return `sk-${await api.revealStationToken(station.id, selectedToken)}`;
+    }
+    const currentConfig = await api.getCurrentProviderConfig(true);
+    return currentConfig.anthropic_auth_token || formData.token;
+  };
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     setLoading(true);
+    setModelError(null);
 
     try {
+      // 解析模型别名并校验中转站是否提供该模型
+      let model = formData.model?.trim() || undefined;
+      if (model) {
+        try {
+          model = await api.validateModel({ kind: 'station', id: station.id }, model);
+        } catch (error) {
+          setModelError(String(error));
+          return;
+        }
+      }
+
+      const token = await revealToken();
+
       // 构建最终的API端点URL
       let finalApiUrl = formData.apiEndpoint;
       if (formData.apiEndpoint === 'custom' && formData.customEndpoint) {
@@ -271,8 +319,8 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
         name: `${station.name} - 配置`,
         description: `从中转站 ${station.name} 应用的配置`,
         base_url: finalApiUrl,
-        auth_token: formData.token,
-        model: formData.model || undefined
+        auth_token: token,
+        model
       };
 
       // 应用配置
@@ -285,14 +333,14 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
           api_endpoint: formData.apiEndpoint === 'custom' ? formData.customEndpoint || '' : formData.apiEndpoint,
           custom_endpoint: formData.apiEndpoint === 'custom' ? formData.customEndpoint : undefined,
           path: formData.path || undefined,
-          model: formData.model || undefined
+          model
         };
         
         await api.saveStationConfig(configRequest);
       }
 
       // 记录配置使用状态
-      await api.recordConfigUsage(station.id, finalApiUrl, formData.token);
+      await api.recordConfigUsage(station.id, finalApiUrl, token);
 
       onConfigApplied();
       onOpenChange(false);
@@ -437,11 +485,27 @@ const RelayStationConfigDialog: React.FC<RelayStationConfigDialogProps> = ({
                 <Input
                   id="model"
                   value={formData.model}
-                  onChange={(e) => setFormData(prev => ({ ...prev, model: e.target.value }))}
+                  onChange={(e) => {
+                    setModelError(null);
+                    setFormData(prev => ({ ...prev, model: e.target.value }));
+                  }}
                   placeholder="claude-3-5-sonnet-20241022"
+                  list="relay-station-models"
                 />
+                <datalist id="relay-station-models">
+                  {modelCatalog && Object.entries(modelCatalog.aliases).map(([alias, target]) => (
+                    <option key={`alias-${alias}`} value={alias}>{`${alias} → ${target}`}</option>
+                  ))}
+                  {modelCatalog?.models.map(model => (
+                    <option key={model} value={model} />
+                  ))}
+                </datalist>
+                {modelError && (
+                  <p className="text-xs text-destructive">{modelError}</p>
+                )}
                 <p className="text-xs text-muted-foreground">
                   将设置为 ANTHROPIC_MODEL 环境变量
+                  {modelCatalog && modelCatalog.models.length > 0 && `，该中转站提供 ${modelCatalog.models.length} 个模型`}
                 </p>
               </div>
             </CardContent>
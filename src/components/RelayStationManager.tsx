@@ -49,9 +49,10 @@ import { Label } from '@/components/ui/label';
 import { Textarea } from '@/components/ui/textarea';
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '@/components/ui/select';
 import { Switch } from '@/components/ui/switch';
-import { api, type RelayStation, type RelayStationAdapter, type CreateRelayStationRequest, type RelayStationToken, type StationInfo, type UserInfo, type StationLogEntry, type LogPaginationResponse, type ConnectionTestResult, type CreateTokenRequest, type UpdateTokenRequest, type TokenGroup, type ConfigUsageStatus, type RelayStationExport } from '@/lib/api';
+import { api, type RelayStation, type RelayStationAdapter, type CreateRelayStationRequest, type RelayStationToken, type StationInfo, type UserInfo, type StationLogEntry, type LogPaginationResponse, type ConnectionTestResult, type CreateTokenRequest, type UpdateTokenRequest, type TokenGroup, type ConfigUsageStatus, type RelayStationExport, type ImportPreview, type AdapterDefinitionSummary, type StationCapabilities } from '@/lib/api';
 import { Toast } from '@/components/ui/toast';
 import RelayStationConfigDialog from './RelayStationConfigDialog';
+import SecretStorePanel from './SecretStorePanel';
 
 interface RelayStationManagerProps {
   onBack: () => void;
@@ -309,6 +310,7 @@ const AddStationDialog: React.FC<AddStationDialogProps> = ({
 }) => {
   const [loading, setLoading] = useState(false);
   const [showPassword, setShowPassword] = useState(false);
+  const [definitions, setDefinitions] = useState<AdapterDefinitionSummary[]>([]);
   const [formData, setFormData] = useState({
     name: editMode && editStation ? editStation.name : '',
     description: editMode && editStation ? editStation.description || '' : '',
@@ -318,6 +320,7 @@ const AddStationDialog: React.FC<AddStationDialogProps> = ({
     system_token: editMode && editStation ? editStation.system_token : '',
     user_id: editMode && editStation ? editStation.user_id || '' : '',
     enabled: editMode && editStation ? editStation.enabled : true,
+    definition: editMode && editStation ? editStation.adapter_config?.definition || '' : '',
   });
 
   // Reset form data when opening/closing or switching edit mode
@@ -333,15 +336,30 @@ const AddStationDialog: React.FC<AddStationDialogProps> = ({
         system_token: editMode && editStation ? editStation.system_token : '',
         user_id: editMode && editStation ? editStation.user_id || '' : '',
         enabled: editMode && editStation ? editStation.enabled : true,
+        definition: editMode && editStation ? editStation.adapter_config?.definition || '' : '',
       });
     }
   }, [open, editMode, editStation]);
 
+  // Load declarative adapter definitions when that adapter type is selected
+  useEffect(() => {
+    if (open && formData.adapter === 'declarative') {
+      api.listAdapterDefinitions()
+        .then(setDefinitions)
+        .catch((error) => console.error('Failed to load adapter definitions:', error));
+    }
+  }, [open, formData.adapter]);
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     setLoading(true);
 
     try {
+      const { definition, ...stationData } = formData;
+      const adapterConfig = formData.adapter === 'declarative'
+        ? { ...(editStation?.adapter_config || {}), definition }
+        : editStation?.adapter_config;
+
       if (editMode && editStation) {
         // Update existing station
         const updates = {
@@ -353,16 +371,17 @@ const AddStationDialog: React.FC<AddStationDialogProps> = ({
           system_token: formData.system_token,
           user_id: formData.user_id || undefined,
           enabled: formData.enabled,
+          adapter_config: adapterConfig,
         };
 
         await api.updateRelayStation(editStation.id, updates);
       } else {
         // Create new station
         const stationRequest: CreateRelayStationRequest = {
-          ...formData,
+          ...stationData,
           adapter: formData.adapter as any,
           auth_method: formData.auth_method as any,
-          adapter_config: undefined,
+          adapter_config: adapterConfig,
         };
 
         await api.addRelayStation(stationRequest);
@@ -383,6 +402,7 @@ const AddStationDialog: React.FC<AddStationDialogProps> = ({
           system_token: '',
           user_id: '',
           enabled: true,
+          definition: '',
         });
       }
     } catch (error) {
@@ -453,9 +473,29 @@ const AddStationDialog: React.FC<AddStationDialogProps> = ({
                   <SelectItem value="oneapi">OneAPI (完整功能)</SelectItem>
                   <SelectItem value="yourapi">YourAPI (完整功能)</SelectItem>
                   <SelectItem value="custom">自定义 (仅配置切换)</SelectItem>
+                  <SelectItem value="declarative">声明式适配器 (定义文件)</SelectItem>
                 </SelectContent>
               </Select>
             </div>
+            {formData.adapter === 'declarative' && (
+              <div className="grid grid-cols-4 items-center gap-4">
+                <Label htmlFor="definition" className="text-right">
+                  适配器定义
+                </Label>
+                <Select value={formData.definition} onValueChange={(value) => setFormData({ ...formData, definition: value })}>
+                  <SelectTrigger className="col-span-3">
+                    <SelectValue placeholder="~/.claude/relay_adapters 中的定义" />
+                  </SelectTrigger>
+                  <SelectContent>
+                    {definitions.map((definition) => (
+                      <SelectItem key={definition.path} value={definition.id} disabled={!!definition.error}>
+                        {definition.name}{definition.error ? ` (解析失败: ${definition.error})` : ''}
+                      </SelectItem>
+                    ))}
+                  </SelectContent>
+                </Select>
+              </div>
+            )}
             <div className="grid grid-cols-4 items-center gap-4">
               <Label htmlFor="system_token" className="text-right">
                 {formData.adapter === 'custom' ? 'API密钥' : '系统令牌'}
@@ -611,8 +651,16 @@ const TokenDetailView: React.FC<TokenDetailViewProps> = ({ token, station, onBac
   };
 
   const handleCopyToken = async () => {
+    // 令牌默认脱敏，复制时才获取明文
+    let value: string;
+    try {
+      value = `sk-${await api.revealStationToken(station.id, token.id)}`;
+    } catch (error) {
+      setToastMessage({ message: `获取令牌失败：${error}`, type: 'error' });
+      return;
+    }
     try {
-      await navigator.clipboard.writeText(`sk-${token.token}`);
+      await navigator.clipboard.writeText(value);
       setToastMessage({ 
         message: `令牌 "${token.name}" 已复制到剪贴板！`, 
         type: 'success' 
@@ -621,7 +669,7 @@ const TokenDetailView: React.FC<TokenDetailViewProps> = ({ token, station, onBac
       console.error('Failed to copy to clipboard:', error);
       // Fallback: create a temporary textarea
       const textarea = document.createElement('textarea');
-      textarea.value = `sk-${token.token}`;
+      textarea.value = value;
       document.body.appendChild(textarea);
       textarea.select();
       document.execCommand('copy');
@@ -840,6 +888,11 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
   const [currentProviderConfig, setCurrentProviderConfig] = useState<any>(null);
   const [showConfigDialog, setShowConfigDialog] = useState(false);
   const [configUsageStatus, setConfigUsageStatus] = useState<ConfigUsageStatus[]>([]);
+  const [capabilities, setCapabilities] = useState<StationCapabilities | null>(null);
+
+  // Hide actions the station was found not to support; unprobed stations show everything
+  const supportsTokens = station.adapter !== 'custom' && capabilities?.tokens !== false;
+  const supportsLogs = station.adapter !== 'custom' && capabilities?.logs !== false;
   
   // Log filtering state
   const [logFilters, setLogFilters] = useState({
@@ -1006,6 +1059,17 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
     return baseUrlMatches && authTokenMatches;
   };
 
+  useEffect(() => {
+    if (station.adapter === 'custom') {
+      return;
+    }
+    // Use recorded capabilities, probing the station the first time it is opened
+    api.getRelayStationCapabilities()
+      .then((all) => all.find((c) => c.station_id === station.id) || api.probeRelayStationCapabilities(station.id))
+      .then(setCapabilities)
+      .catch((error) => console.error('Failed to load station capabilities:', error));
+  }, [station.id, station.adapter]);
+
   useEffect(() => {
     // Load data when tab changes, but skip if it's the initial 'info' tab since loadBasicData already handles it
     if (activeTab !== 'info') {
@@ -1097,8 +1161,8 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
       return; // Data already loaded
     }
 
-    // Skip loading data for unsupported tabs on custom adapters
-    if (station.adapter === 'custom' && (tabValue === 'tokens' || tabValue === 'logs')) {
+    // Skip loading data for unsupported tabs
+    if ((tabValue === 'tokens' && !supportsTokens) || (tabValue === 'logs' && !supportsLogs)) {
       return;
     }
 
@@ -1481,9 +1545,18 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
     });
   };
 
-  const handleCopyToken = async (token: string, tokenName: string) => {
+  const handleCopyToken = async (token: RelayStationToken) => {
+    const tokenName = token.name;
+    // 令牌默认脱敏，复制时才获取明文
+    let value: string;
     try {
-      await navigator.clipboard.writeText(`sk-${token}`);
+      value = `sk-${await api.revealStationToken(station.id, token.id)}`;
+    } catch (error) {
+      setToastMessage({ message: `获取令牌失败：${error}`, type: 'error' });
+      return;
+    }
+    try {
+      await navigator.clipboard.writeText(value);
       setToastMessage({ 
         message: `令牌 "${tokenName}" 已复制到剪贴板！`, 
         type: 'success' 
@@ -1492,7 +1565,7 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
       console.error('Failed to copy to clipboard:', error);
       // Fallback: create a temporary textarea
       const textarea = document.createElement('textarea');
-      textarea.value = `sk-${token}`;
+      textarea.value = value;
       document.body.appendChild(textarea);
       textarea.select();
       document.execCommand('copy');
@@ -1659,10 +1732,10 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
           {/* Tabs for detailed info */}
           <div className="lg:col-span-3">
             <Tabs value={activeTab} onValueChange={setActiveTab} className="w-full">
-              <TabsList className={`grid w-full ${station.adapter === 'custom' ? 'grid-cols-2' : 'grid-cols-4'}`}>
+              <TabsList className={`grid w-full ${['grid-cols-2', 'grid-cols-3', 'grid-cols-4'][Number(supportsTokens) + Number(supportsLogs)]}`}>
                 <TabsTrigger value="info">站点信息</TabsTrigger>
-                {station.adapter !== 'custom' && <TabsTrigger value="tokens">令牌管理</TabsTrigger>}
-                {station.adapter !== 'custom' && <TabsTrigger value="logs">使用日志</TabsTrigger>}
+                {supportsTokens && <TabsTrigger value="tokens">令牌管理</TabsTrigger>}
+                {supportsLogs && <TabsTrigger value="logs">使用日志</TabsTrigger>}
                 <TabsTrigger value="settings">设置</TabsTrigger>
               </TabsList>
               
@@ -1832,13 +1905,13 @@ const StationDetailView: React.FC<DetailViewProps> = ({ station, onBack, onStati
                               </div>
                               <div className="mt-2 flex items-center gap-4 text-sm text-muted-foreground">
                                 <div className="flex items-center gap-2">
-                                  <span className="font-mono">sk-{token.token.substring(0, 20)}...</span>
+                                  <span className="font-mono">sk-{token.token}</span>
                                   <Button
                                     variant="ghost"
                                     size="sm"
                                     onClick={(e) => {
                                       e.stopPropagation();
-                                      handleCopyToken(token.token, token.name);
+                                      handleCopyToken(token);
                                     }}
                                     className="h-6 w-6 p-0 hover:bg-muted"
                                     title="复制完整令牌"
@@ -2597,13 +2670,17 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
   const [showImportDialog, setShowImportDialog] = useState(false);
   const [importData, setImportData] = useState<RelayStationExport | null>(null);
   const [overwriteExisting, setOverwriteExisting] = useState(false);
+  const [importPassword, setImportPassword] = useState('');
+  const [importPreview, setImportPreview] = useState<ImportPreview | null>(null);
+  const [importPreviewError, setImportPreviewError] = useState<string | null>(null);
+  const [trustImport, setTrustImport] = useState(false);
   const [isExporting, setIsExporting] = useState(false);
   const [isImporting, setIsImporting] = useState(false);
 
   const handleExportStations = async (selectedStationIds?: string[]) => {
     setIsExporting(true);
     try {
-      const exportData = await api.exportRelayStations(selectedStationIds);
+      const exportData = await api.exportRelayStations(selectedStationIds, { secrets: 'included' });
       
       // Format timestamp for filename
       const timestamp = new Date().toISOString().slice(0, 19).replace(/[:-]/g, '').replace('T', '_');
@@ -2664,6 +2741,9 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
         }
         
         setImportData(importData);
+        setImportPassword('');
+        setTrustImport(false);
+        await loadImportPreview(importData);
         setShowImportDialog(true);
       }
     } catch (error) {
@@ -2675,12 +2755,39 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
     }
   };
 
+  // 校验导入文件：只有密码签名的文件才算已验证，其余需要用户确认信任
+  const loadImportPreview = async (data: RelayStationExport, password?: string) => {
+    try {
+      setImportPreview(await api.previewRelayStationImport(data, password || undefined));
+      setImportPreviewError(null);
+    } catch (error) {
+      setImportPreview(null);
+      setImportPreviewError(String(error));
+    }
+  };
+
+  const resetImportDialog = () => {
+    setShowImportDialog(false);
+    setImportData(null);
+    setOverwriteExisting(false);
+    setImportPassword('');
+    setImportPreview(null);
+    setImportPreviewError(null);
+    setTrustImport(false);
+  };
+
   const confirmImportStations = async () => {
     if (!importData) return;
     
     setIsImporting(true);
     try {
-      const importedStations = await api.importRelayStations(importData, overwriteExisting);
+      const importedStations = await api.importRelayStations(
+        importData,
+        overwriteExisting,
+        importPassword || undefined,
+        undefined,
+        trustImport
+      );
       
       // Reload stations list
       await loadStations();
@@ -2690,9 +2797,7 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
         type: 'success'
       });
       
-      setShowImportDialog(false);
-      setImportData(null);
-      setOverwriteExisting(false);
+      resetImportDialog();
     } catch (error) {
       console.error('Import failed:', error);
       setToastMessage({
@@ -2853,9 +2958,7 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
       return {
         station: matchedStation,
         baseUrl: currentProviderConfig.anthropic_base_url,
-        partialKey: currentProviderConfig.anthropic_auth_token ? 
-          `${currentProviderConfig.anthropic_auth_token.substring(0, 8)}...${currentProviderConfig.anthropic_auth_token.substring(currentProviderConfig.anthropic_auth_token.length - 4)}` : 
-          undefined
+        partialKey: currentProviderConfig.anthropic_auth_token || undefined
       };
     }
 
@@ -2870,9 +2973,7 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
       return {
         station: appliedCustomStation,
         baseUrl: currentProviderConfig.anthropic_base_url,
-        partialKey: currentProviderConfig.anthropic_auth_token ? 
-          `${currentProviderConfig.anthropic_auth_token.substring(0, 8)}...${currentProviderConfig.anthropic_auth_token.substring(currentProviderConfig.anthropic_auth_token.length - 4)}` : 
-          undefined
+        partialKey: currentProviderConfig.anthropic_auth_token || undefined
       };
     }
 
@@ -2880,9 +2981,7 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
     if (currentProviderConfig.anthropic_base_url) {
       return {
         baseUrl: currentProviderConfig.anthropic_base_url,
-        partialKey: currentProviderConfig.anthropic_auth_token ? 
-          `${currentProviderConfig.anthropic_auth_token.substring(0, 8)}...${currentProviderConfig.anthropic_auth_token.substring(currentProviderConfig.anthropic_auth_token.length - 4)}` : 
-          undefined
+        partialKey: currentProviderConfig.anthropic_auth_token || undefined
       };
     }
 
@@ -2976,6 +3075,14 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
         </div>
       </div>
 
+      {/* Secret Store */}
+      <SecretStorePanel
+        onChanged={() => {
+          loadStations();
+          loadCurrentProviderConfig();
+        }}
+      />
+
       {/* Current Applied Configuration Status */}
       {(() => {
         const appliedConfig = getAppliedConfigInfo();
@@ -3254,24 +3361,75 @@ const RelayStationManager: React.FC<RelayStationManagerProps> = ({ onBack }) =>
                   如果存在同名中转站，将跳过导入。启用"覆盖现有中转站"选项将更新现有配置。
                 </div>
               )}
+
+              {(importData.salt || importPreview?.checksum === 'unverified') && (
+                <div className="space-y-2">
+                  <Label htmlFor="import-password" className="text-sm">导出密码</Label>
+                  <Input
+                    id="import-password"
+                    type="password"
+                    value={importPassword}
+                    onChange={(e) => setImportPassword(e.target.value)}
+                    onBlur={() => loadImportPreview(importData, importPassword)}
+                    placeholder="输入导出时设置的密码以校验文件"
+                  />
+                </div>
+              )}
+
+              {importPreviewError && (
+                <div className="text-xs bg-red-500/10 text-red-600 p-2 rounded border border-red-500/20">
+                  {importPreviewError}
+                </div>
+              )}
+
+              {importPreview?.checksum === 'valid' && (
+                <div className="text-xs bg-green-500/10 text-green-600 p-2 rounded border border-green-500/20">
+                  文件已通过密码校验，未被修改。
+                </div>
+              )}
+
+              {importPreview?.checksum === 'invalid' && (
+                <div className="text-xs bg-red-500/10 text-red-600 p-2 rounded border border-red-500/20">
+                  文件校验失败：文件已被修改或密码错误，无法导入。
+                </div>
+              )}
+
+              {importPreview?.requires_confirmation && importPreview.checksum !== 'invalid' && (
+                <div className="space-y-2 text-xs bg-yellow-500/10 text-yellow-700 p-2 rounded border border-yellow-500/20">
+                  <p>
+                    此文件未经密码签名校验，可能已被他人修改（例如将中转站地址指向其他服务器）。请仅导入来源可信的文件。
+                  </p>
+                  <div className="flex items-center space-x-2">
+                    <Switch
+                      id="trust-import"
+                      checked={trustImport}
+                      onCheckedChange={setTrustImport}
+                    />
+                    <Label htmlFor="trust-import" className="text-xs">
+                      我信任此文件的来源
+                    </Label>
+                  </div>
+                </div>
+              )}
             </div>
           )}
 
           <DialogFooter className="gap-2">
             <Button
               variant="outline"
-              onClick={() => {
-                setShowImportDialog(false);
-                setImportData(null);
-                setOverwriteExisting(false);
-              }}
+              onClick={resetImportDialog}
               disabled={isImporting}
             >
               取消
             </Button>
             <Button
               onClick={confirmImportStations}
-              disabled={isImporting || !importData}
+              disabled={
+                isImporting ||
+                !importData ||
+                importPreview?.checksum === 'invalid' ||
+                ((!importPreview || importPreview.requires_confirmation) && !trustImport)
+              }
             >
               {isImporting ? (
                 <Loader2 className="h-4 w-4 mr-2 animate-spin" />
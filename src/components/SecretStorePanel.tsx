@@ -0,0 +1,188 @@
+import React, { useState, useEffect } from 'react';
+import { Lock, Unlock, KeyRound, Loader2 } from 'lucide-react';
+import { Button } from '@/components/ui/button';
+import { Card } from '@/components/ui/card';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import { api, type SecretStoreStatus } from '@/lib/api';
+
+interface SecretStorePanelProps {
+  /** 解锁、锁定或修改密码后调用，用于重新加载令牌 */
+  onChanged?: () => void;
+}
+
+/**
+ * 密钥存储状态：锁定时提供解锁入口，解锁后可设置、修改或移除密码
+ */
+const SecretStorePanel: React.FC<SecretStorePanelProps> = ({ onChanged }) => {
+  const [status, setStatus] = useState<SecretStoreStatus | null>(null);
+  const [passphrase, setPassphrase] = useState('');
+  const [editing, setEditing] = useState(false);
+  const [currentPassphrase, setCurrentPassphrase] = useState('');
+  const [newPassphrase, setNewPassphrase] = useState('');
+  const [confirmPassphrase, setConfirmPassphrase] = useState('');
+  const [error, setError] = useState<string | null>(null);
+  const [busy, setBusy] = useState(false);
+
+  useEffect(() => {
+    api.getSecretStoreStatus()
+      .then(setStatus)
+      .catch((e) => console.error('Failed to load secret store status:', e));
+  }, []);
+
+  const run = async (action: () => Promise<SecretStoreStatus>) => {
+    setBusy(true);
+    setError(null);
+    try {
+      setStatus(await action());
+      setPassphrase('');
+      setEditing(false);
+      setCurrentPassphrase('');
+      setNewPassphrase('');
+      setConfirmPassphrase('');
+      onChanged?.();
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const handleSavePassphrase = (remove: boolean) => {
+    if (!remove && newPassphrase !== confirmPassphrase) {
+      setError('两次输入的密码不一致');
+      return;
+    }
+    run(() => api.setSecretStorePassphrase(
+      status?.mode === 'passphrase' ? currentPassphrase : undefined,
+      remove ? undefined : newPassphrase
+    ));
+  };
+
+  if (!status) return null;
+
+  if (status.locked) {
+    return (
+      <Card className="bg-yellow-50 border-yellow-200 dark:bg-yellow-500/10 dark:border-yellow-500/20">
+        <div className="p-4 space-y-3">
+          <div className="flex items-center gap-2">
+            <Lock className="h-4 w-4 text-yellow-700" />
+            <span className="font-medium text-yellow-800 dark:text-yellow-600">密钥存储已锁定</span>
+          </div>
+          <p className="text-sm text-yellow-700 dark:text-yellow-600">
+            中转站和代理商的令牌暂时无法使用，后台健康检测、余额记录和故障转移已暂停。
+          </p>
+          <form
+            className="flex items-center gap-2"
+            onSubmit={(e) => {
+              e.preventDefault();
+              run(() => api.unlockSecretStore(passphrase));
+            }}
+          >
+            <Input
+              type="password"
+              value={passphrase}
+              onChange={(e) => setPassphrase(e.target.value)}
+              placeholder="输入密钥存储密码"
+              className="max-w-xs bg-background"
+            />
+            <Button type="submit" disabled={busy || !passphrase}>
+              {busy ? <Loader2 className="h-4 w-4 mr-2 animate-spin" /> : <Unlock className="h-4 w-4 mr-2" />}
+              解锁
+            </Button>
+          </form>
+          {error && <p className="text-xs text-red-600">{error}</p>}
+        </div>
+      </Card>
+    );
+  }
+
+  return (
+    <Card>
+      <div className="p-4 space-y-3">
+        <div className="flex items-center justify-between">
+          <div className="flex items-center gap-2 text-sm">
+            <KeyRound className="h-4 w-4 text-muted-foreground" />
+            <span>
+              {status.mode === 'passphrase' ? '令牌已加密保存，并受密码保护' : '令牌已使用本地密钥文件加密保存'}
+            </span>
+            <span className="text-muted-foreground">（{status.secret_count} 项）</span>
+          </div>
+          <div className="flex items-center gap-2">
+            <Button variant="outline" size="sm" onClick={() => setEditing(!editing)} disabled={busy}>
+              {status.mode === 'passphrase' ? '修改密码' : '设置密码'}
+            </Button>
+            {status.mode === 'passphrase' && (
+              <Button variant="outline" size="sm" onClick={() => run(() => api.lockSecretStore())} disabled={busy}>
+                <Lock className="h-3 w-3 mr-1" />
+                锁定
+              </Button>
+            )}
+          </div>
+        </div>
+
+        {editing && (
+          <div className="grid gap-3 sm:grid-cols-3">
+            {status.mode === 'passphrase' && (
+              <div className="space-y-1">
+                <Label htmlFor="secret-current" className="text-xs">当前密码</Label>
+                <Input
+                  id="secret-current"
+                  type="password"
+                  value={currentPassphrase}
+                  onChange={(e) => setCurrentPassphrase(e.target.value)}
+                />
+              </div>
+            )}
+            <div className="space-y-1">
+              <Label htmlFor="secret-new" className="text-xs">新密码</Label>
+              <Input
+                id="secret-new"
+                type="password"
+                value={newPassphrase}
+                onChange={(e) => setNewPassphrase(e.target.value)}
+              />
+            </div>
+            <div className="space-y-1">
+              <Label htmlFor="secret-confirm" className="text-xs">确认新密码</Label>
+              <Input
+                id="secret-confirm"
+                type="password"
+                value={confirmPassphrase}
+                onChange={(e) => setConfirmPassphrase(e.target.value)}
+              />
+            </div>
+            <div className="sm:col-span-3 flex items-center justify-between">
+              <p className="text-xs text-muted-foreground">
+                设置密码后，每次启动应用都需要先解锁才能使用令牌。
+              </p>
+              <div className="flex gap-2">
+                {status.mode === 'passphrase' && (
+                  <Button
+                    variant="outline"
+                    size="sm"
+                    onClick={() => handleSavePassphrase(true)}
+                    disabled={busy || !currentPassphrase}
+                  >
+                    移除密码
+                  </Button>
+                )}
+                <Button
+                  size="sm"
+                  onClick={() => handleSavePassphrase(false)}
+                  disabled={busy || !newPassphrase || (status.mode === 'passphrase' && !currentPassphrase)}
+                >
+                  {busy && <Loader2 className="h-3 w-3 mr-1 animate-spin" />}
+                  保存密码
+                </Button>
+              </div>
+            </div>
+          </div>
+        )}
+        {error && <p className="text-xs text-red-600">{error}</p>}
+      </div>
+    </Card>
+  );
+};
+
+export default SecretStorePanel;
@@ -54,13 +54,15 @@ export const UsageDashboard: React.FC<UsageDashboardProps> = ({ onBack }) => {
       let statsData: UsageStats;
       let sessionData: ProjectUsage[];
       let apiBaseUrlData: ApiBaseUrlUsage[];
+      // Count days in the user's own timezone
+      const timezone = Intl.DateTimeFormat().resolvedOptions().timeZone;
       
       if (selectedDateRange === "today") {
-        statsData = await api.getTodayUsageStats();
+        statsData = await api.getTodayUsageStats(timezone);
         sessionData = await api.getSessionStats();
         apiBaseUrlData = await api.getUsageByApiBaseUrl();
       } else if (selectedDateRange === "all") {
-        statsData = await api.getUsageStats();
+        statsData = await api.getUsageStats(timezone);
         sessionData = await api.getSessionStats();
         apiBaseUrlData = await api.getUsageByApiBaseUrl();
       } else {
@@ -76,9 +78,17 @@ export const UsageDashboard: React.FC<UsageDashboardProps> = ({ onBack }) => {
             return `${year}${month}${day}`;
         }
 
+        const formatLocalDate = (date: Date) => {
+            const year = date.getFullYear();
+            const month = String(date.getMonth() + 1).padStart(2, '0');
+            const day = String(date.getDate()).padStart(2, '0');
+            return `${year}-${month}-${day}`;
+        }
+
         statsData = await api.getUsageByDateRange(
-          startDate.toISOString(),
-          endDate.toISOString()
+          formatLocalDate(startDate),
+          formatLocalDate(endDate),
+          timezone
         );
         sessionData = await api.getSessionStats(
             formatDateForApi(startDate),
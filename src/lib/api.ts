@@ -492,7 +492,109 @@ export interface ImportServerResult {
 /**
  * Relay station adapter type for different station implementations
  */
-export type RelayStationAdapter = 'newapi' | 'oneapi' | 'yourapi' | 'custom';
+export type RelayStationAdapter = 'newapi' | 'oneapi' | 'yourapi' | 'custom' | 'declarative';
+
+/**
+ * Adapter features a station was found to support when last probed
+ */
+export interface StationCapabilities {
+  station_id: string;
+  station_info: boolean;
+  user_info: boolean;
+  logs: boolean;
+  /** Token listing; creating and editing tokens are assumed to follow */
+  tokens: boolean;
+  user_groups: boolean;
+  /** Why each unsupported feature failed, keyed by feature name */
+  errors: Record<string, string>;
+  probed_at: number;
+}
+
+/**
+ * Balance of a station account, or one of its tokens, at a point in time
+ */
+export interface QuotaSnapshot {
+  station_id: string;
+  /** Null for the account balance */
+  token_id?: string | null;
+  token_name?: string | null;
+  /** Remaining quota in station units */
+  remain_quota?: number | null;
+  /** Remaining balance in currency */
+  balance: number;
+  amount_used?: number | null;
+  quota_per_unit?: number | null;
+  captured_at: number;
+}
+
+/**
+ * Balance change of one local day
+ */
+export interface BalanceDay {
+  date: string;
+  consumed: number;
+  topped_up: number;
+  balance: number;
+}
+
+/**
+ * Balance history and predicted run-out date of an account or token
+ */
+export interface BalanceTrend {
+  station_id: string;
+  station_name: string;
+  token_id?: string | null;
+  token_name?: string | null;
+  balance: number;
+  /** Mean consumption per day, top-ups excluded */
+  consumption_per_day?: number | null;
+  days_left?: number | null;
+  depletion_at?: number | null;
+  daily: BalanceDay[];
+  history: QuotaSnapshot[];
+}
+
+/**
+ * Low balance notification settings of a station
+ */
+export interface BalanceAlertSettings {
+  station_id: string;
+  min_balance?: number | null;
+  min_days_left?: number | null;
+  enabled: boolean;
+}
+
+/**
+ * A relay station or provider preset whose models are catalogued
+ */
+export interface ModelCatalogMember {
+  kind: "station" | "provider";
+  id: string;
+}
+
+/**
+ * Models offered by a station or provider, with user defined aliases
+ */
+export interface ModelCatalog extends ModelCatalogMember {
+  models: string[];
+  /** Alias -> model id */
+  aliases: Record<string, string>;
+  fetched_at?: number | null;
+}
+
+/**
+ * Declarative adapter definition found in ~/.claude/relay_adapters
+ */
+export interface AdapterDefinitionSummary {
+  id: string;
+  name: string;
+  description?: string;
+  path: string;
+  /** Adapter methods the definition implements */
+  methods: string[];
+  /** Parse error; the definition cannot be used */
+  error?: string;
+}
 
 /**
  * Authentication method for relay stations
@@ -645,6 +747,56 @@ export interface RelayStationExport {
   version: number;
   exported_at: number;
   stations: RelayStationExportItem[];
+  secrets?: ExportSecrets;
+  salt?: string;
+  ciphertext?: string;
+  checksum?: { algorithm: 'sha256' | 'hmac-sha256'; value: string };
+}
+
+/**
+ * How station tokens are carried in an export bundle
+ */
+export type ExportSecrets = 'included' | 'redacted' | 'encrypted';
+
+export interface ExportOptions {
+  /** Defaults to 'redacted' */
+  secrets?: ExportSecrets;
+  /** Required for 'encrypted'; signs the checksum otherwise */
+  password?: string;
+}
+
+/**
+ * 'valid': verified by the password HMAC; 'unsigned': plain SHA-256 matches,
+ * which only rules out accidental damage
+ */
+export type ChecksumStatus = 'valid' | 'unsigned' | 'invalid' | 'unverified' | 'missing';
+
+export interface ImportPreviewStation {
+  name: string;
+  api_url: string;
+  adapter: RelayStationAdapter;
+  existing_id?: string;
+  changed_fields: string[];
+  needs_secret: boolean;
+}
+
+/**
+ * State of the encrypted store holding station and provider credentials
+ */
+export interface SecretStoreStatus {
+  mode: 'key_file' | 'passphrase';
+  /** A passphrase protected store stays locked until it is unlocked */
+  locked: boolean;
+  secret_count: number;
+}
+
+export interface ImportPreview {
+  exported_at: number;
+  secrets: ExportSecrets;
+  checksum: ChecksumStatus;
+  /** The bundle is not verified; importing it needs allowUnverified */
+  requires_confirmation: boolean;
+  stations: ImportPreviewStation[];
 }
 
 export interface RelayStationExportItem {
@@ -872,6 +1024,27 @@ export interface UpdateInfo {
   release_notes?: string;
 }
 
+/**
+ * Timeout, retry and proxy settings for relay station and GitHub requests
+ */
+export interface HttpClientConfig {
+  connect_timeout_secs: number;
+  /** Longest wait for the next chunk of a response */
+  read_timeout_secs: number;
+  /** Extra attempts for idempotent requests */
+  max_retries: number;
+  /** Delay before the first retry, doubled for every further one */
+  retry_backoff_ms: number;
+  /** http(s):// or socks5(h):// proxy; the system proxy is used when unset */
+  proxy_url?: string | null;
+  /** Comma separated hosts that bypass the proxy */
+  no_proxy?: string | null;
+  /** PEM files with extra trusted root certificates */
+  ca_cert_paths: string[];
+  /** Sent unless a station sets adapter_config.user_agent */
+  user_agent: string;
+}
+
 /**
  * API client for interacting with the Rust backend
  */
@@ -1509,11 +1682,12 @@ export const api = {
 
   /**
    * Gets overall usage statistics
+   * @param timezone - Optional IANA timezone the daily buckets are counted in
    * @returns Promise resolving to usage statistics
    */
-  async getUsageStats(): Promise<UsageStats> {
+  async getUsageStats(timezone?: string): Promise<UsageStats> {
     try {
-      return await invoke<UsageStats>("get_usage_stats");
+      return await invoke<UsageStats>("get_usage_stats", { timezone });
     } catch (error) {
       console.error("Failed to get usage stats:", error);
       throw error;
@@ -1524,11 +1698,12 @@ export const api = {
    * Gets usage statistics filtered by date range
    * @param startDate - Start date (ISO format)
    * @param endDate - End date (ISO format)
+   * @param timezone - Optional IANA timezone the dates and daily buckets are in
    * @returns Promise resolving to usage statistics
    */
-  async getUsageByDateRange(startDate: string, endDate: string): Promise<UsageStats> {
+  async getUsageByDateRange(startDate: string, endDate: string, timezone?: string): Promise<UsageStats> {
     try {
-      return await invoke<UsageStats>("get_usage_by_date_range", { startDate, endDate });
+      return await invoke<UsageStats>("get_usage_by_date_range", { startDate, endDate, timezone });
     } catch (error) {
       console.error("Failed to get usage by date range:", error);
       throw error;
@@ -1561,11 +1736,12 @@ export const api = {
 
   /**
    * Gets usage statistics for today only
+   * @param timezone - Optional IANA timezone "today" is taken in
    * @returns Promise resolving to today's usage statistics
    */
-  async getTodayUsageStats(): Promise<UsageStats> {
+  async getTodayUsageStats(timezone?: string): Promise<UsageStats> {
     try {
-      return await invoke<UsageStats>("get_today_usage_stats");
+      return await invoke<UsageStats>("get_today_usage_stats", { timezone });
     } catch (error) {
       console.error("Failed to get today's usage stats:", error);
       throw error;
@@ -2396,9 +2572,9 @@ export const api = {
    * Gets the list of preset provider configurations
    * @returns Promise resolving to array of provider configurations
    */
-  async getProviderPresets(): Promise<ProviderConfig[]> {
+  async getProviderPresets(reveal?: boolean): Promise<ProviderConfig[]> {
     try {
-      return await invoke<ProviderConfig[]>("get_provider_presets");
+      return await invoke<ProviderConfig[]>("get_provider_presets", { reveal });
     } catch (error) {
       console.error("Failed to get provider presets:", error);
       throw error;
@@ -2409,9 +2585,9 @@ export const api = {
    * Gets the current provider configuration from environment variables
    * @returns Promise resolving to current configuration
    */
-  async getCurrentProviderConfig(): Promise<CurrentProviderConfig> {
+  async getCurrentProviderConfig(reveal?: boolean): Promise<CurrentProviderConfig> {
     try {
-      return await invoke<CurrentProviderConfig>("get_current_provider_config");
+      return await invoke<CurrentProviderConfig>("get_current_provider_config", { reveal });
     } catch (error) {
       console.error("Failed to get current provider config:", error);
       throw error;
@@ -2518,9 +2694,9 @@ export const api = {
    * @param id - The ID of the provider configuration to get
    * @returns Promise resolving to provider configuration
    */
-  async getProviderConfig(id: string): Promise<ProviderConfig> {
+  async getProviderConfig(id: string, reveal?: boolean): Promise<ProviderConfig> {
     try {
-      return await invoke<ProviderConfig>("get_provider_config", { id });
+      return await invoke<ProviderConfig>("get_provider_config", { id, reveal });
     } catch (error) {
       console.error("Failed to get provider config:", error);
       throw error;
@@ -2584,13 +2760,166 @@ export const api = {
 
   // Relay Station Management API methods
 
+  /**
+   * Probes which adapter features a station supports and records the result
+   * @param stationId - The ID of the relay station
+   * @returns Promise resolving to the detected capabilities
+   */
+  async probeRelayStationCapabilities(stationId: string): Promise<StationCapabilities> {
+    try {
+      return await invoke<StationCapabilities>("probe_relay_station_capabilities", { stationId });
+    } catch (error) {
+      console.error("Failed to probe relay station capabilities:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the recorded capabilities of every probed station
+   * @returns Promise resolving to the capabilities
+   */
+  async getRelayStationCapabilities(): Promise<StationCapabilities[]> {
+    try {
+      return await invoke<StationCapabilities[]>("get_relay_station_capabilities");
+    } catch (error) {
+      console.error("Failed to get relay station capabilities:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Records the current balance of a station's account and tokens
+   * @param stationId - The ID of the relay station
+   * @returns Promise resolving to the recorded snapshots
+   */
+  async snapshotRelayBalance(stationId: string): Promise<QuotaSnapshot[]> {
+    try {
+      return await invoke<QuotaSnapshot[]>("snapshot_relay_balance", { stationId });
+    } catch (error) {
+      console.error("Failed to snapshot relay balance:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets balance history and consumption forecasts
+   * @param stationId - Optional station to limit the result to
+   * @param days - Days of history (default 30)
+   * @returns Promise resolving to one trend per account and token
+   */
+  async getRelayBalanceTrends(stationId?: string, days?: number): Promise<BalanceTrend[]> {
+    try {
+      return await invoke<BalanceTrend[]>("get_relay_balance_trends", { stationId, days });
+    } catch (error) {
+      console.error("Failed to get relay balance trends:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the low balance notification settings of every configured station
+   * @returns Promise resolving to the settings
+   */
+  async getRelayBalanceAlerts(): Promise<BalanceAlertSettings[]> {
+    try {
+      return await invoke<BalanceAlertSettings[]>("get_relay_balance_alerts");
+    } catch (error) {
+      console.error("Failed to get relay balance alerts:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the low balance notification settings of a station
+   * @param settings - The new settings
+   * @returns Promise resolving to the saved settings
+   */
+  async saveRelayBalanceAlert(settings: BalanceAlertSettings): Promise<BalanceAlertSettings> {
+    try {
+      return await invoke<BalanceAlertSettings>("save_relay_balance_alert", { settings });
+    } catch (error) {
+      console.error("Failed to save relay balance alert:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets every cached model catalog
+   * @returns Promise resolving to the catalogs
+   */
+  async getModelCatalogs(): Promise<ModelCatalog[]> {
+    try {
+      return await invoke<ModelCatalog[]>("get_model_catalogs");
+    } catch (error) {
+      console.error("Failed to get model catalogs:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Fetches the models of a station or provider now
+   * @param member - The station or provider
+   * @returns Promise resolving to the refreshed catalog
+   */
+  async refreshModelCatalog(member: ModelCatalogMember): Promise<ModelCatalog> {
+    try {
+      return await invoke<ModelCatalog>("refresh_model_catalog", { member });
+    } catch (error) {
+      console.error("Failed to refresh model catalog:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Replaces the model aliases of a station or provider
+   * @param member - The station or provider
+   * @param aliases - Alias -> model id
+   * @returns Promise resolving to the updated catalog
+   */
+  async saveModelAliases(member: ModelCatalogMember, aliases: Record<string, string>): Promise<ModelCatalog> {
+    try {
+      return await invoke<ModelCatalog>("save_model_aliases", { member, aliases });
+    } catch (error) {
+      console.error("Failed to save model aliases:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Resolves an alias and checks that a station or provider offers the model
+   * @param member - The station or provider
+   * @param model - Model id or alias
+   * @returns Promise resolving to the model id to use
+   */
+  async validateModel(member: ModelCatalogMember, model: string): Promise<string> {
+    try {
+      return await invoke<string>("validate_model", { member, model });
+    } catch (error) {
+      console.error("Failed to validate model:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists the declarative adapter definitions in the user directory
+   * @returns Promise resolving to the definitions, including ones that failed to parse
+   */
+  async listAdapterDefinitions(): Promise<AdapterDefinitionSummary[]> {
+    try {
+      return await invoke<AdapterDefinitionSummary[]>("list_adapter_definitions");
+    } catch (error) {
+      console.error("Failed to list adapter definitions:", error);
+      throw error;
+    }
+  },
+
   /**
    * Lists all configured relay stations
    * @returns Promise resolving to array of relay stations
    */
-  async listRelayStations(): Promise<RelayStation[]> {
+  async listRelayStations(reveal?: boolean): Promise<RelayStation[]> {
     try {
-      return await invoke<RelayStation[]>("list_relay_stations");
+      return await invoke<RelayStation[]>("list_relay_stations", { reveal });
     } catch (error) {
       console.error("Failed to list relay stations:", error);
       throw error;
@@ -2602,9 +2931,9 @@ export const api = {
    * @param stationId - The ID of the relay station
    * @returns Promise resolving to relay station details
    */
-  async getRelayStation(stationId: string): Promise<RelayStation> {
+  async getRelayStation(stationId: string, reveal?: boolean): Promise<RelayStation> {
     try {
-      return await invoke<RelayStation>("get_relay_station", { stationId });
+      return await invoke<RelayStation>("get_relay_station", { stationId, reveal });
     } catch (error) {
       console.error("Failed to get relay station:", error);
       throw error;
@@ -2675,15 +3004,35 @@ export const api = {
    * @param size - Optional page size for pagination
    * @returns Promise resolving to paginated tokens response
    */
-  async listStationTokens(stationId: string, page?: number, size?: number): Promise<TokenPaginationResponse> {
+  async listStationTokens(stationId: string, page?: number, size?: number, reveal?: boolean): Promise<TokenPaginationResponse> {
     try {
-      return await invoke<TokenPaginationResponse>("list_station_tokens", { stationId, page, size });
+      return await invoke<TokenPaginationResponse>("list_station_tokens", { stationId, page, size, reveal });
     } catch (error) {
       console.error("Failed to list station tokens:", error);
       throw error;
     }
   },
 
+  /**
+   * Gets the clear text value of one station token, for an explicit copy or apply
+   * @param stationId - The ID of the relay station
+   * @param tokenId - The ID of the token
+   * @returns Promise resolving to the token value
+   */
+  async revealStationToken(stationId: string, tokenId: string): Promise<string> {
+    const size = 100;
+    for (let page = 1; ; page++) {
+      const response = await this.listStationTokens(stationId, page, size, true);
+      const token = response.items.find((item) => item.id === tokenId);
+      if (token) {
+        return token.token;
+      }
+      if (response.items.length === 0 || page * response.page_size >= response.total) {
+        throw new Error(`Token ${tokenId} not found`);
+      }
+    }
+  },
+
   /**
    * Adds a new token to a relay station
    * @param stationId - The ID of the relay station
@@ -2862,9 +3211,9 @@ export const api = {
    * Get configuration usage status for display
    * @returns Promise resolving to array of configuration usage status
    */
-  async getConfigUsageStatus(): Promise<ConfigUsageStatus[]> {
+  async getConfigUsageStatus(reveal?: boolean): Promise<ConfigUsageStatus[]> {
     try {
-      return await invoke<ConfigUsageStatus[]>("get_config_usage_status");
+      return await invoke<ConfigUsageStatus[]>("get_config_usage_status", { reveal });
     } catch (error) {
       console.error("Failed to get config usage status:", error);
       throw error;
@@ -2890,12 +3239,14 @@ export const api = {
   /**
    * Exports relay stations to JSON format
    * @param stationIds - Optional array of station IDs to export (exports all if not provided)
+   * @param options - How tokens are exported; they are redacted by default
    * @returns Promise resolving to export data
    */
-  async exportRelayStations(stationIds?: string[]): Promise<RelayStationExport> {
+  async exportRelayStations(stationIds?: string[], options?: ExportOptions): Promise<RelayStationExport> {
     try {
       return await invoke<RelayStationExport>("export_relay_stations", { 
-        stationIds: stationIds || null 
+        stationIds: stationIds || null,
+        options
       });
     } catch (error) {
       console.error("Failed to export relay stations:", error);
@@ -2907,13 +3258,25 @@ export const api = {
    * Imports relay stations from JSON format
    * @param exportData - The export data to import
    * @param overwriteExisting - Whether to overwrite existing stations with same names
+   * @param password - Password of an encrypted or signed export
+   * @param secrets - Tokens for redacted stations, keyed by station name
+   * @param allowUnverified - Import a bundle that its password does not verify
    * @returns Promise resolving to array of imported station names
    */
-  async importRelayStations(exportData: RelayStationExport, overwriteExisting: boolean = false): Promise<string[]> {
+  async importRelayStations(
+    exportData: RelayStationExport,
+    overwriteExisting: boolean = false,
+    password?: string,
+    secrets?: Record<string, string>,
+    allowUnverified: boolean = false
+  ): Promise<string[]> {
     try {
       return await invoke<string[]>("import_relay_stations", { 
         exportData, 
-        overwriteExisting 
+        overwriteExisting,
+        password,
+        secrets,
+        allowUnverified
       });
     } catch (error) {
       console.error("Failed to import relay stations:", error);
@@ -2921,6 +3284,76 @@ export const api = {
     }
   },
 
+  /**
+   * Shows which stations an import would add or overwrite, without writing anything
+   * @param exportData - The export data to inspect
+   * @param password - Password of an encrypted or signed export
+   * @returns Promise resolving to the import preview
+   */
+  async previewRelayStationImport(exportData: RelayStationExport, password?: string): Promise<ImportPreview> {
+    try {
+      return await invoke<ImportPreview>("preview_relay_station_import", { exportData, password });
+    } catch (error) {
+      console.error("Failed to preview relay station import:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the state of the credential store
+   * @returns Promise resolving to the store status
+   */
+  async getSecretStoreStatus(): Promise<SecretStoreStatus> {
+    try {
+      return await invoke<SecretStoreStatus>("get_secret_store_status");
+    } catch (error) {
+      console.error("Failed to get secret store status:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Unlocks a passphrase protected credential store
+   * @param passphrase - The store passphrase
+   * @returns Promise resolving to the store status
+   */
+  async unlockSecretStore(passphrase: string): Promise<SecretStoreStatus> {
+    try {
+      return await invoke<SecretStoreStatus>("unlock_secret_store", { passphrase });
+    } catch (error) {
+      console.error("Failed to unlock secret store:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Locks a passphrase protected credential store
+   * @returns Promise resolving to the store status
+   */
+  async lockSecretStore(): Promise<SecretStoreStatus> {
+    try {
+      return await invoke<SecretStoreStatus>("lock_secret_store");
+    } catch (error) {
+      console.error("Failed to lock secret store:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Protects the credential store with a passphrase, changes it, or removes it
+   * @param currentPassphrase - The current passphrase, if one is set
+   * @param passphrase - The new passphrase; empty goes back to a local key file
+   * @returns Promise resolving to the store status
+   */
+  async setSecretStorePassphrase(currentPassphrase?: string, passphrase?: string): Promise<SecretStoreStatus> {
+    try {
+      return await invoke<SecretStoreStatus>("set_secret_store_passphrase", { currentPassphrase, passphrase });
+    } catch (error) {
+      console.error("Failed to set secret store passphrase:", error);
+      throw error;
+    }
+  },
+
   // About / App Information methods
 
   /**
@@ -2973,5 +3406,32 @@ export const api = {
       console.error("Failed to check for updates:", error);
       throw error;
     }
+  },
+
+  /**
+   * Gets the HTTP client settings
+   * @returns Promise resolving to the settings
+   */
+  async getHttpClientConfig(): Promise<HttpClientConfig> {
+    try {
+      return await invoke<HttpClientConfig>("get_http_client_config");
+    } catch (error) {
+      console.error("Failed to get HTTP client settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the HTTP client settings; invalid proxies or certificates are rejected
+   * @param config - The new settings
+   * @returns Promise resolving to the saved settings
+   */
+  async saveHttpClientConfig(config: HttpClientConfig): Promise<HttpClientConfig> {
+    try {
+      return await invoke<HttpClientConfig>("save_http_client_config", { config });
+    } catch (error) {
+      console.error("Failed to save HTTP client settings:", error);
+      throw error;
+    }
   }
 };